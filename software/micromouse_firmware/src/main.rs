@@ -55,7 +55,6 @@ use crate::motors::{Encoder, Motor};
 use micromouse_logic::config::{mouse_2019, mouse_2020};
 
 use micromouse_logic::comms::{DebugMsg, DebugPacket};
-use micromouse_logic::fast::{Orientation, Vector, DIRECTION_PI_2};
 use micromouse_logic::mouse::Mouse;
 
 use crate::motors::left::{LeftEncoder, LeftMotor};
@@ -102,18 +101,6 @@ where
 {
     let config = mouse_2020::MOUSE;
 
-    let initial_orientation = Orientation {
-        position: Vector {
-            //x: 1260.0,
-            //y: 1170.0,
-            //x: 1250.0,
-            //y: 1350.0,
-            x: 90.0,
-            y: 90.0,
-        },
-        direction: DIRECTION_PI_2,
-    };
-
     let mut last_time: u32 = time.now();
 
     let mut mouse: Option<Mouse> = None;
@@ -173,6 +160,12 @@ where
                 let front_distance_range = front_distance.range();
                 let right_distance_range = right_distance.range();
 
+                // The sensors take turns updating, so not all of them have a new range each time.
+                // The mouse keeps the last range from the ones that don't.
+                let distances_fresh = left_distance_range.is_some()
+                    || front_distance_range.is_some()
+                    || right_distance_range.is_some();
+
                 let (left_power, right_power, debug) = mouse.update(
                     &config,
                     now,
@@ -182,6 +175,7 @@ where
                     left_distance_range,
                     front_distance_range,
                     right_distance_range,
+                    distances_fresh,
                 );
 
                 right_motor.change_power((right_power) as i32);
@@ -243,10 +237,10 @@ where
                     //orange_led.set_low().ok();
                 }
 
-                if now - start_time > 1000 && mouse.is_none() {
-                    mouse = Some(Mouse::new(
+                // The mouse holds still for the start delay in its config on its own
+                if mouse.is_none() {
+                    mouse = Some(Mouse::at_start(
                         &config,
-                        initial_orientation,
                         last_time,
                         left_encoder.count(),
                         right_encoder.count(),
@@ -0,0 +1,54 @@
+//! Compare the time to evaluate a `Bezier3` in Bernstein form against the polynomial form
+//!
+//! Run with `cargo run --release --features std --bin curve_bench`
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use micromouse_logic::fast::curve::{Bezier3, Curve};
+use micromouse_logic::fast::Vector;
+
+const ITERATIONS: u32 = 100;
+const SAMPLES: u32 = 10_000;
+
+/// Time `f` over all the samples, and print the average time per evaluation
+fn bench(name: &str, f: impl Fn(f32) -> Vector) {
+    let start = Instant::now();
+
+    for _ in 0..ITERATIONS {
+        for i in 0..SAMPLES {
+            black_box(f(black_box(i as f32 / SAMPLES as f32)));
+        }
+    }
+
+    let elapsed = start.elapsed();
+    println!(
+        "{}: {:?} per evaluation",
+        name,
+        elapsed / (ITERATIONS * SAMPLES)
+    );
+}
+
+fn main() {
+    let b = Bezier3::new(
+        Vector {
+            x: 1260.0,
+            y: 1170.0,
+        },
+        Vector {
+            x: 1350.0,
+            y: 1170.0,
+        },
+        Vector {
+            x: 1440.0,
+            y: 1260.0,
+        },
+        Vector {
+            x: 1440.0,
+            y: 1350.0,
+        },
+    );
+
+    bench("bernstein", |t| b.bernstein_at(t));
+    bench("horner", |t| b.at(t));
+}
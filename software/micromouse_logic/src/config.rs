@@ -1,5 +1,6 @@
 use core::f32;
 
+use libm::F32Ext;
 use serde::Deserialize;
 use serde::Serialize;
 
@@ -7,11 +8,20 @@ use crate::slow::map::MapConfig;
 use crate::slow::maze::MazeConfig;
 
 use crate::fast::localize::{LocalizeConfig, SideDistanceFilterConfig};
+use crate::fast::motor_control::FULL_POWER;
+use crate::fast::{
+    Orientation, Vector, DIRECTION_0, DIRECTION_3_PI_2, DIRECTION_PI, DIRECTION_PI_2,
+};
 use crate::slow::motion_plan::MotionPlanConfig;
+use crate::slow::navigate::NavigateConfig;
+use crate::slow::{MazeDirection, MazePosition};
 
 pub const MAZE: MazeConfig = MazeConfig {
-    cell_width: 180.0,
+    cell_width_x: 180.0,
+    cell_width_y: 180.0,
     wall_width: 12.0,
+    start_cell: MazePosition { x: 0, y: 0 },
+    start_facing: MazeDirection::North,
 };
 
 pub const SIDE_FILTER: SideDistanceFilterConfig = SideDistanceFilterConfig {
@@ -24,23 +34,47 @@ pub const LOCALIZE: LocalizeConfig = LocalizeConfig {
     left_side_filter: SIDE_FILTER,
     right_side_filter: SIDE_FILTER,
     front_max_range: 150.0,
+    front_filter_alpha: 1.0,
     use_sensors: true,
+    wall_trust: 1.0,
+    divergence_threshold: 40.0,
+    divergence_count: 5,
+    blind_trust_growth: 0.0,
+    sensor_latency: 0,
+    heading_fusion: 0.0,
+    heading_fusion_baseline: 0.0,
+    heading_fusion_max_correction: 0.0,
 };
 
 pub const MAP: MapConfig = MapConfig {
     front_threhold: 150.0,
     left_threshold: 100.0,
     right_threshold: 100.0,
+    wall_confirm_count: 2,
+};
+
+pub const MOTION_PLAN: MotionPlanConfig = MotionPlanConfig {
+    move_offset: 12.0,
+    stop_at_intersections: false,
+    intersection_stop_updates: 0,
+    max_curvature: 0.0,
 };
 
-pub const MOTION_PLAN: MotionPlanConfig = MotionPlanConfig { move_offset: 12.0 };
+pub const NAVIGATE: NavigateConfig = NavigateConfig {
+    bias_turns: true,
+    loop_window: 8,
+    orthogonal_cost: 1.0,
+    diagonal_cost: 1.5,
+    turn_cost: 0.5,
+};
 
 pub mod sim {
-    use crate::fast::motion_control::MotionControlConfig;
+    use crate::fast::motion_control::{MotionControlConfig, GAIN_SCHEDULE_SIZE};
     use crate::fast::motor_control::{MotorControlConfig, PidfConfig};
     use crate::fast::path::PathHandlerConfig;
     use crate::fast::turn::TurnHandlerConfig;
-    use crate::mouse::MouseConfig;
+    use crate::mouse::{MouseConfig, UnreachableGoal};
+    use crate::slow::MazePosition;
     use core::f32::consts::FRAC_PI_8;
 
     pub const PIDF: PidfConfig = PidfConfig {
@@ -57,6 +91,10 @@ pub mod sim {
             d: 0.0,
             offset_p: 0.02,
             velocity: 0.5,
+            curvature_deadband: 0.0,
+            warm_start: true,
+            max_offset_curvature: 0.1,
+            completion_hysteresis: 0.0,
         },
         turn: TurnHandlerConfig {
             rad_per_sec: 0.1,
@@ -64,6 +102,9 @@ pub mod sim {
             i: 0.0,
             d: 0.0,
             tolerance: 0.02,
+            settle_updates: 3,
+            max_angular_velocity: 0.0,
+            angular_acceleration: 0.0,
         },
         motor_control: MotorControlConfig {
             left_pidf: PIDF,
@@ -72,6 +113,17 @@ pub mod sim {
             right_reverse: false,
         },
         stop_distance: 90.0,
+        min_velocity: 0.0,
+        max_velocity: 1.0,
+        curvature_slew_rate: 0.0,
+        power_time_constant: 0.0,
+        max_acceleration: 0.005,
+        look_behind: 2,
+        wall_centering_p: 0.0,
+        wall_centering_weight: 0.5,
+        gain_schedule: [None; GAIN_SCHEDULE_SIZE],
+        coast_velocity: 0.0,
+        wall_follow: None,
     };
 
     pub const MOUSE_2020: MouseConfig = MouseConfig {
@@ -79,11 +131,19 @@ pub mod sim {
         maze: super::MAZE,
         map: super::MAP,
         motion_plan: super::MOTION_PLAN,
+        navigate: super::NAVIGATE,
         localize: super::LOCALIZE,
         motion_control: MOTION_CONTROL,
         front_sensor_abort: 50.0,
         left_sensor_abort: 20.0,
         right_sensor_abort: 20.0,
+        start_delay: 0,
+        replan_on_blocked: true,
+        slow_plan_budget: 0,
+        route_goal: MazePosition { x: 7, y: 7 },
+        unreachable_goal: UnreachableGoal::Explore,
+        trail_length: 0,
+        max_explore_steps: 0,
     };
 
     pub const MOUSE_2019: MouseConfig = MouseConfig {
@@ -91,21 +151,30 @@ pub mod sim {
         maze: super::MAZE,
         map: super::MAP,
         motion_plan: super::MOTION_PLAN,
+        navigate: super::NAVIGATE,
         localize: super::LOCALIZE,
         motion_control: MOTION_CONTROL,
         front_sensor_abort: 50.0,
         left_sensor_abort: 20.0,
         right_sensor_abort: 20.0,
+        start_delay: 0,
+        replan_on_blocked: true,
+        slow_plan_budget: 0,
+        route_goal: MazePosition { x: 7, y: 7 },
+        unreachable_goal: UnreachableGoal::Explore,
+        trail_length: 0,
+        max_explore_steps: 0,
     };
 }
 
 pub mod mouse_2020 {
-    use crate::config::MechanicalConfig;
-    use crate::fast::motion_control::MotionControlConfig;
+    use crate::config::{MechanicalConfig, EXTRA_DISTANCE_SENSORS};
+    use crate::fast::motion_control::{MotionControlConfig, GAIN_SCHEDULE_SIZE};
     use crate::fast::motor_control::{MotorControlConfig, PidfConfig};
     use crate::fast::path::PathHandlerConfig;
     use crate::fast::turn::TurnHandlerConfig;
-    use crate::mouse::MouseConfig;
+    use crate::mouse::{MouseConfig, UnreachableGoal};
+    use crate::slow::MazePosition;
 
     pub const MECH: MechanicalConfig = MechanicalConfig {
         wheel_diameter: 32.0,
@@ -125,6 +194,14 @@ pub mod mouse_2020 {
         front_sensor_limit: 200.0,
         left_sensor_limit: 100.0,
         right_sensor_limit: 100.0,
+        left_power_trim: 1.0,
+        right_power_trim: 1.0,
+        left_encoder_sign: 1,
+        right_encoder_sign: 1,
+        extra_sensors: [None; EXTRA_DISTANCE_SENSORS],
+        static_friction_power: 0.0,
+        max_wheel_velocity: 0.0,
+        power_quantization: 0,
     };
 
     pub const PIDF: PidfConfig = PidfConfig {
@@ -139,6 +216,7 @@ pub mod mouse_2020 {
         maze: super::MAZE,
         map: super::MAP,
         motion_plan: super::MOTION_PLAN,
+        navigate: super::NAVIGATE,
         localize: super::LOCALIZE,
         motion_control: MotionControlConfig {
             path: PathHandlerConfig {
@@ -147,6 +225,10 @@ pub mod mouse_2020 {
                 d: 0.0,
                 offset_p: 0.008,
                 velocity: 0.3,
+                curvature_deadband: 0.0,
+                warm_start: true,
+                max_offset_curvature: 0.1,
+                completion_hysteresis: 0.0,
             },
             turn: TurnHandlerConfig {
                 rad_per_sec: 0.05,
@@ -154,6 +236,9 @@ pub mod mouse_2020 {
                 i: 0.0,
                 d: 0.0,
                 tolerance: 0.02,
+                settle_updates: 3,
+                max_angular_velocity: 0.0,
+                angular_acceleration: 0.0,
             },
             motor_control: MotorControlConfig {
                 left_pidf: PIDF,
@@ -162,20 +247,39 @@ pub mod mouse_2020 {
                 right_reverse: false,
             },
             stop_distance: 90.0,
+            min_velocity: 0.0,
+            max_velocity: 1.0,
+            curvature_slew_rate: 0.0,
+            power_time_constant: 0.0,
+            max_acceleration: 0.005,
+            look_behind: 2,
+            wall_centering_p: 0.0,
+            wall_centering_weight: 0.5,
+            gain_schedule: [None; GAIN_SCHEDULE_SIZE],
+            coast_velocity: 0.0,
+            wall_follow: None,
         },
         front_sensor_abort: 50.0,
         left_sensor_abort: 10.0,
         right_sensor_abort: 10.0,
+        start_delay: 1000,
+        replan_on_blocked: true,
+        slow_plan_budget: 0,
+        route_goal: MazePosition { x: 7, y: 7 },
+        unreachable_goal: UnreachableGoal::Explore,
+        trail_length: 0,
+        max_explore_steps: 0,
     };
 }
 
 pub mod mouse_2019 {
-    use crate::config::MechanicalConfig;
-    use crate::fast::motion_control::MotionControlConfig;
+    use crate::config::{MechanicalConfig, EXTRA_DISTANCE_SENSORS};
+    use crate::fast::motion_control::{MotionControlConfig, GAIN_SCHEDULE_SIZE};
     use crate::fast::motor_control::{MotorControlConfig, PidfConfig};
     use crate::fast::path::PathHandlerConfig;
     use crate::fast::turn::TurnHandlerConfig;
-    use crate::mouse::MouseConfig;
+    use crate::mouse::{MouseConfig, UnreachableGoal};
+    use crate::slow::MazePosition;
 
     pub const MECH: MechanicalConfig = MechanicalConfig {
         wheel_diameter: 32.0,
@@ -195,6 +299,14 @@ pub mod mouse_2019 {
         front_sensor_limit: 200.0,
         left_sensor_limit: 150.0,
         right_sensor_limit: 150.0,
+        left_power_trim: 1.0,
+        right_power_trim: 1.0,
+        left_encoder_sign: 1,
+        right_encoder_sign: 1,
+        extra_sensors: [None; EXTRA_DISTANCE_SENSORS],
+        static_friction_power: 0.0,
+        max_wheel_velocity: 0.0,
+        power_quantization: 0,
     };
 
     pub const PIDF: PidfConfig = PidfConfig {
@@ -209,6 +321,7 @@ pub mod mouse_2019 {
         maze: super::MAZE,
         map: super::MAP,
         motion_plan: super::MOTION_PLAN,
+        navigate: super::NAVIGATE,
         localize: super::LOCALIZE,
         motion_control: MotionControlConfig {
             path: PathHandlerConfig {
@@ -217,6 +330,10 @@ pub mod mouse_2019 {
                 d: 0.0,
                 offset_p: 0.01,
                 velocity: 0.2,
+                curvature_deadband: 0.0,
+                warm_start: true,
+                max_offset_curvature: 0.1,
+                completion_hysteresis: 0.0,
             },
             turn: TurnHandlerConfig {
                 rad_per_sec: 0.05,
@@ -224,6 +341,9 @@ pub mod mouse_2019 {
                 i: 0.0,
                 d: 0.0,
                 tolerance: 0.02,
+                settle_updates: 3,
+                max_angular_velocity: 0.0,
+                angular_acceleration: 0.0,
             },
             motor_control: MotorControlConfig {
                 left_pidf: PIDF,
@@ -232,10 +352,28 @@ pub mod mouse_2019 {
                 right_reverse: false,
             },
             stop_distance: 90.0,
+            min_velocity: 0.0,
+            max_velocity: 1.0,
+            curvature_slew_rate: 0.0,
+            power_time_constant: 0.0,
+            max_acceleration: 0.005,
+            look_behind: 2,
+            wall_centering_p: 0.0,
+            wall_centering_weight: 0.5,
+            gain_schedule: [None; GAIN_SCHEDULE_SIZE],
+            coast_velocity: 0.0,
+            wall_follow: None,
         },
         front_sensor_abort: 50.0,
         left_sensor_abort: 20.0,
         right_sensor_abort: 20.0,
+        start_delay: 1000,
+        replan_on_blocked: true,
+        slow_plan_budget: 0,
+        route_goal: MazePosition { x: 7, y: 7 },
+        unreachable_goal: UnreachableGoal::Explore,
+        trail_length: 0,
+        max_explore_steps: 0,
     };
 }
 
@@ -279,9 +417,166 @@ pub struct MechanicalConfig {
     pub front_sensor_limit: f32,
     pub left_sensor_limit: f32,
     pub right_sensor_limit: f32,
+
+    /// Multipliers for the final left and right motor powers, to trim out drift from the motors
+    /// not being the same. 1.0 is no trim
+    pub left_power_trim: f32,
+    pub right_power_trim: f32,
+
+    /// Which way each encoder counts when the wheel goes forward, 1 or -1, depending on how it
+    /// is wired
+    pub left_encoder_sign: i32,
+    pub right_encoder_sign: i32,
+
+    /// Any distance sensors besides the left, front, and right ones, like diagonal sensors
+    pub extra_sensors: [Option<DistanceSensorConfig>; EXTRA_DISTANCE_SENSORS],
+
+    /// The fraction of full power it takes to get a wheel moving at all
+    pub static_friction_power: f32,
+
+    /// How fast a wheel goes at full power, in mm/ms. This is used for the motor model, which
+    /// replaces the `f` gains of the wheel velocity loops, see
+    /// [MechanicalConfig::power_for_wheel_velocity]. Path motions are also followed with the
+    /// curvature limited to keep the outside wheel under it, see
+    /// [MechanicalConfig::max_curvature_at]. 0 turns off both.
+    pub max_wheel_velocity: f32,
+
+    /// How many steps the motor driver can output between stopped and full power in each
+    /// direction, like 256 for 8 bit PWM. The final motor powers are rounded to the nearest
+    /// step. 0 turns off quantization
+    pub power_quantization: u32,
+}
+
+/// How many distance sensors there can be besides the left, front, and right ones
+pub const EXTRA_DISTANCE_SENSORS: usize = 2;
+
+/// How many distance sensors there can be in total
+pub const MAX_DISTANCE_SENSORS: usize = 3 + EXTRA_DISTANCE_SENSORS;
+
+/// Where the left, front, and right sensors are in [MechanicalConfig::distance_sensors] and in
+/// the readings that go with it. Any extra sensors come after these.
+pub const LEFT_SENSOR: usize = 0;
+pub const FRONT_SENSOR: usize = 1;
+pub const RIGHT_SENSOR: usize = 2;
+
+/// Which walls a distance sensor can see while the mouse is lined up with the maze
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SensorFacing {
+    Front,
+    Left,
+    Right,
+    Back,
+}
+
+/// Where a distance sensor is on the mouse
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DistanceSensorConfig {
+    /// Where the sensor is and which way it points, relative to the center of the mouse with x
+    /// forward and y to the left
+    pub mount: Orientation,
+
+    /// Readings past this are out of range
+    pub limit: f32,
+}
+
+impl DistanceSensorConfig {
+    /// Sensors pointing within this many radians of straight ahead or behind see the walls in
+    /// front or behind, and anything else sees the walls to the side
+    const FACING_WITHIN: f32 = f32::consts::FRAC_PI_8;
+
+    pub fn facing(&self) -> SensorFacing {
+        let direction = self.mount.direction;
+        if direction.within(DIRECTION_0, Self::FACING_WITHIN) {
+            SensorFacing::Front
+        } else if direction.within(DIRECTION_PI, Self::FACING_WITHIN) {
+            SensorFacing::Back
+        } else if direction.into_unit_vector().y > 0.0 {
+            SensorFacing::Left
+        } else {
+            SensorFacing::Right
+        }
+    }
+
+    /// Where a reading of `distance` hits, relative to the center of the mouse
+    pub fn project(&self, distance: f32) -> Vector {
+        self.mount.position + distance * self.mount.direction.into_unit_vector()
+    }
 }
 
 impl MechanicalConfig {
+    /// All of the distance sensors, in the order given by [LEFT_SENSOR], [FRONT_SENSOR], and
+    /// [RIGHT_SENSOR], followed by the extra sensors
+    pub fn distance_sensors(
+        &self,
+    ) -> [Option<DistanceSensorConfig>; MAX_DISTANCE_SENSORS] {
+        let mut sensors = [None; MAX_DISTANCE_SENSORS];
+
+        sensors[LEFT_SENSOR] = Some(DistanceSensorConfig {
+            mount: Orientation {
+                position: Vector {
+                    x: self.left_sensor_offset_x,
+                    y: self.left_sensor_offset_y,
+                },
+                direction: DIRECTION_PI_2,
+            },
+            limit: self.left_sensor_limit,
+        });
+
+        sensors[FRONT_SENSOR] = Some(DistanceSensorConfig {
+            mount: Orientation {
+                position: Vector {
+                    x: self.front_sensor_offset_x,
+                    y: 0.0,
+                },
+                direction: DIRECTION_0,
+            },
+            limit: self.front_sensor_limit,
+        });
+
+        sensors[RIGHT_SENSOR] = Some(DistanceSensorConfig {
+            mount: Orientation {
+                position: Vector {
+                    x: self.right_sensor_offset_x,
+                    y: -self.right_sensor_offset_y,
+                },
+                direction: DIRECTION_3_PI_2,
+            },
+            limit: self.right_sensor_limit,
+        });
+
+        sensors[RIGHT_SENSOR + 1..].copy_from_slice(&self.extra_sensors);
+
+        sensors
+    }
+
+    pub fn trim_powers(&self, left_power: i32, right_power: i32) -> (i32, i32) {
+        (
+            (left_power as f32 * self.left_power_trim) as i32,
+            (right_power as f32 * self.right_power_trim) as i32,
+        )
+    }
+
+    /// Round the motor powers to the nearest step the motor driver can output, see
+    /// [MechanicalConfig::power_quantization]
+    pub fn quantize_powers(&self, left_power: i32, right_power: i32) -> (i32, i32) {
+        if self.power_quantization == 0 {
+            return (left_power, right_power);
+        }
+
+        let step = FULL_POWER as f32 / self.power_quantization as f32;
+        let quantize = |power: i32| (F32Ext::round(power as f32 / step) * step) as i32;
+
+        (quantize(left_power), quantize(right_power))
+    }
+
+    /// Flip the encoder deltas so that going forward always counts up
+    pub fn signed_encoder_deltas(&self, delta_left: i32, delta_right: i32) -> (i32, i32) {
+        (
+            delta_left * self.left_encoder_sign,
+            delta_right * self.right_encoder_sign,
+        )
+    }
+
     pub fn ticks_per_mm(&self) -> f32 {
         (self.ticks_per_rev * self.gearbox_ratio)
             / (self.wheel_diameter * f32::consts::PI)
@@ -318,4 +613,178 @@ impl MechanicalConfig {
     pub fn rads_to_mm(&self, rads: f32) -> f32 {
         rads * self.mm_per_rad()
     }
+
+    /// The fraction of full power, from -1 to 1, to drive a wheel at `velocity` mm/ms. This is
+    /// linear in the velocity, plus enough to overcome static friction. Always 0 if there is no
+    /// `max_wheel_velocity`.
+    pub fn power_for_wheel_velocity(&self, velocity: f32) -> f32 {
+        if velocity == 0.0 || self.max_wheel_velocity <= 0.0 {
+            return 0.0;
+        }
+
+        let power = self.static_friction_power
+            + (1.0 - self.static_friction_power) * velocity.abs()
+                / self.max_wheel_velocity;
+        let power = if power > 1.0 { 1.0 } else { power };
+
+        if velocity > 0.0 {
+            power
+        } else {
+            -power
+        }
+    }
+
+    /// The tightest curvature, in 1/mm, the mouse can follow going `velocity` mm/ms without
+    /// the outside wheel going faster than `max_wheel_velocity`. A `max_wheel_velocity` of 0 is
+    /// no limit, so this is infinite.
+    pub fn max_curvature_at(&self, velocity: f32) -> f32 {
+        let max_wheel_velocity = self.max_wheel_velocity;
+        if max_wheel_velocity <= 0.0 {
+            return f32::INFINITY;
+        }
+
+        // The outside wheel goes velocity * (1 + curvature * wheelbase / 2)
+        let velocity = velocity.abs();
+        if velocity >= max_wheel_velocity {
+            0.0
+        } else {
+            (max_wheel_velocity - velocity) / (velocity * self.mm_per_rad())
+        }
+    }
+}
+
+#[cfg(test)]
+mod power_for_wheel_velocity_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{mouse_2019, MechanicalConfig};
+
+    const MECH: MechanicalConfig = MechanicalConfig {
+        static_friction_power: 0.1,
+        max_wheel_velocity: 2.0,
+        ..mouse_2019::MECH
+    };
+
+    #[test]
+    fn stopped_is_zero() {
+        assert_close(MECH.power_for_wheel_velocity(0.0), 0.0);
+    }
+
+    #[test]
+    fn small_overcomes_friction() {
+        assert!(MECH.power_for_wheel_velocity(0.001) >= 0.1);
+        assert!(MECH.power_for_wheel_velocity(-0.001) <= -0.1);
+    }
+
+    #[test]
+    fn linear() {
+        assert_close(MECH.power_for_wheel_velocity(1.0), 0.55);
+        assert_close(MECH.power_for_wheel_velocity(-1.0), -0.55);
+    }
+
+    #[test]
+    fn max_is_full_power() {
+        assert_close(MECH.power_for_wheel_velocity(2.0), 1.0);
+        assert_close(MECH.power_for_wheel_velocity(-2.0), -1.0);
+        assert_close(MECH.power_for_wheel_velocity(3.0), 1.0);
+    }
+
+    #[test]
+    fn no_model() {
+        assert_close(mouse_2019::MECH.power_for_wheel_velocity(1.0), 0.0);
+    }
+}
+
+#[cfg(test)]
+mod max_curvature_at_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{mouse_2019, MechanicalConfig};
+
+    const MECH: MechanicalConfig = MechanicalConfig {
+        max_wheel_velocity: 1.0,
+        ..mouse_2019::MECH
+    };
+
+    #[test]
+    fn slow_is_high() {
+        assert!(MECH.max_curvature_at(0.01) > 1.0);
+    }
+
+    #[test]
+    fn slower_is_higher() {
+        assert!(MECH.max_curvature_at(0.2) > MECH.max_curvature_at(0.5));
+    }
+
+    #[test]
+    fn at_max_wheel_velocity_is_strait() {
+        assert_close(MECH.max_curvature_at(1.0), 0.0);
+        assert_close(MECH.max_curvature_at(1.5), 0.0);
+    }
+
+    #[test]
+    fn zero_is_no_limit() {
+        assert_eq!(mouse_2019::MECH.max_curvature_at(0.5), f32::INFINITY);
+        assert_eq!(mouse_2019::MECH.max_curvature_at(1.5), f32::INFINITY);
+    }
+
+    #[test]
+    fn outside_wheel_at_max() {
+        let curvature = MECH.max_curvature_at(0.5);
+        let outside = 0.5 * (1.0 + curvature * MECH.wheelbase / 2.0);
+        assert_close(outside, 1.0);
+    }
+}
+
+#[cfg(test)]
+mod quantize_powers_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{mouse_2019, MechanicalConfig};
+    use crate::fast::motor_control::FULL_POWER;
+
+    const MECH: MechanicalConfig = MechanicalConfig {
+        power_quantization: 256,
+        ..mouse_2019::MECH
+    };
+
+    const STEP: f32 = FULL_POWER as f32 / 256.0;
+
+    fn power(fraction: f32) -> i32 {
+        (fraction * FULL_POWER as f32) as i32
+    }
+
+    #[test]
+    fn half_is_on_a_step() {
+        assert_eq!(MECH.quantize_powers(power(0.5), -power(0.5)), (5000, -5000));
+    }
+
+    #[test]
+    fn rounds_to_the_nearest_step() {
+        let (left, right) = MECH.quantize_powers(power(0.3), -power(0.3));
+
+        // 3000 is 76.8 steps
+        assert_eq!(left, (77.0 * STEP) as i32);
+        assert_eq!(right, (-77.0 * STEP) as i32);
+    }
+
+    #[test]
+    fn full_power() {
+        assert_eq!(
+            MECH.quantize_powers(FULL_POWER, -FULL_POWER),
+            (FULL_POWER, -FULL_POWER)
+        );
+    }
+
+    #[test]
+    fn no_quantization() {
+        assert_eq!(
+            mouse_2019::MECH.quantize_powers(power(0.5), power(0.3)),
+            (power(0.5), power(0.3))
+        );
+        assert_eq!(mouse_2019::MECH.quantize_powers(5001, -2999), (5001, -2999));
+    }
 }
@@ -46,6 +46,24 @@ pub trait Curve {
     /// By default, it does a binary search with default parameters,
     /// but can be overridden if there is a better method
     fn closest_point(&self, m: Vector) -> (f32, Vector) {
+        self.closest_point_past_ends(m)
+            .unwrap_or_else(|| self.closest_point_by_binary_search(m, 32, 0.000001))
+    }
+
+    /// Like [Curve::closest_point], but search outward from `guess` instead of across the whole
+    /// curve. When `m` has only moved a little since `guess` was found, this is quicker and
+    /// stays on the same part of a curve that comes back close to itself.
+    fn closest_point_near(&self, m: Vector, guess: f32) -> (f32, Vector) {
+        self.closest_point_past_ends(m).unwrap_or_else(|| {
+            let t = clamp(guess, 0.0, 1.0);
+            let p = self.at(t);
+            self.closest_point_by_local_search(m, (t, p), 1.0 / 32.0, 0.000001)
+        })
+    }
+
+    /// If `m` is past either end of the curve, find the closest point on the line extended from
+    /// that end. See [Curve::closest_point]
+    fn closest_point_past_ends(&self, m: Vector) -> Option<(f32, Vector)> {
         // Check if the point is before the start
         let start_point = self.at(0.0);
         let start_tangent = self.derivative().at(0.0);
@@ -59,7 +77,7 @@ pub trait Curve {
                 end: start_point,
             };
             let (_, p) = line.closest_point(m);
-            return (-0.1, p);
+            return Some((-0.1, p));
         }
 
         // Check if the point is after the end
@@ -75,10 +93,10 @@ pub trait Curve {
                 end: end_point,
             };
             let (_, p) = line.closest_point(m);
-            return (1.1, p);
+            return Some((1.1, p));
         }
 
-        self.closest_point_by_binary_search(m, 32, 0.000001)
+        None
     }
 
     /// Do a binary search to find the closest point on the curve.
@@ -100,7 +118,7 @@ pub trait Curve {
         epsilon: f32,
     ) -> (f32, Vector) {
         // Do a coarse linear search to get a good starting point for the binary search
-        let mut current = (0..steps)
+        let (t, p, _) = (0..steps)
             // Compute the point and distance at each t
             .map(|i| {
                 let t = i as f32 / steps as f32;
@@ -121,7 +139,23 @@ pub trait Curve {
             // If steps was 0 and no point was found, start in the middle
             .unwrap_or((0.5, self.at(0.5), (m - self.at(0.5)).magnitude()));
 
-        let mut h = 1.0 / steps as f32;
+        self.closest_point_by_local_search(m, (t, p), 1.0 / steps as f32, epsilon)
+    }
+
+    /// Starting at `start`, walk along the curve in steps of `h` while it gets closer to `m`,
+    /// halving `h` whenever it can't until it is smaller than `epsilon`. This finds the closest
+    /// point nearby, which may not be the closest point on the whole curve.
+    ///
+    /// Returns a tuple of `(t, point)` for the closest point
+    fn closest_point_by_local_search(
+        &self,
+        m: Vector,
+        start: (f32, Vector),
+        h: f32,
+        epsilon: f32,
+    ) -> (f32, Vector) {
+        let mut current = (start.0, start.1, (m - start.1).magnitude());
+        let mut h = h;
 
         loop {
             let (t, p, d) = current;
@@ -342,10 +376,13 @@ mod line_tests {
     }
 }
 
+/// A quadratic bezier curve. Build it with [Bezier2::new] so the polynomial form is worked out
+/// once up front.
 pub struct Bezier2 {
     pub start: Vector,
     pub ctrl0: Vector,
     pub end: Vector,
+    polynomial: Quadratic,
 }
 
 impl Curve for Bezier2 {
@@ -353,15 +390,7 @@ impl Curve for Bezier2 {
 
     /// Evaluate the curve at `t`
     fn at(&self, t: f32) -> Vector {
-        Vector {
-            x: self.start.x * (1.0 - t) * (1.0 - t)
-                + 2.0 * self.ctrl0.x * (1.0 - t) * t
-                + self.end.x * t * t,
-
-            y: self.start.y * (1.0 - t) * (1.0 - t)
-                + 2.0 * self.ctrl0.y * (1.0 - t) * t
-                + self.end.y * t * t,
-        }
+        self.polynomial.at(t)
     }
 
     fn derivative(&self) -> Self::Derivative {
@@ -372,6 +401,52 @@ impl Curve for Bezier2 {
     }
 }
 
+impl Bezier2 {
+    pub fn new(start: Vector, ctrl0: Vector, end: Vector) -> Bezier2 {
+        Bezier2 {
+            start,
+            ctrl0,
+            end,
+            polynomial: Quadratic {
+                a: start - 2.0 * ctrl0 + end,
+                b: 2.0 * (ctrl0 - start),
+                c: start,
+            },
+        }
+    }
+
+    /// The polynomial form `a*t^2 + b*t + c`
+    pub fn polynomial(&self) -> Quadratic {
+        self.polynomial
+    }
+}
+
+/// A quadratic polynomial `a*t^2 + b*t + c`, evaluated with Horner's method
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Quadratic {
+    pub a: Vector,
+    pub b: Vector,
+    pub c: Vector,
+}
+
+impl Curve for Quadratic {
+    type Derivative = Line;
+
+    fn at(&self, t: f32) -> Vector {
+        Vector {
+            x: (self.a.x * t + self.b.x) * t + self.c.x,
+            y: (self.a.y * t + self.b.y) * t + self.c.y,
+        }
+    }
+
+    fn derivative(&self) -> Self::Derivative {
+        Line {
+            start: self.b,
+            end: 2.0 * self.a + self.b,
+        }
+    }
+}
+
 #[cfg(test)]
 mod bezier2_tests {
     #[allow(unused_imports)]
@@ -380,63 +455,69 @@ mod bezier2_tests {
     use super::super::Vector;
     use super::{Bezier2, Curve};
 
-    const B: Bezier2 = Bezier2 {
-        start: Vector { x: 0.0, y: 0.0 },
-        ctrl0: Vector { x: 0.75, y: 0.25 },
-        end: Vector { x: 1.0, y: 1.0 },
-    };
+    fn b() -> Bezier2 {
+        Bezier2::new(
+            Vector { x: 0.0, y: 0.0 },
+            Vector { x: 0.75, y: 0.25 },
+            Vector { x: 1.0, y: 1.0 },
+        )
+    }
 
     #[test]
     fn start_is_t0() {
-        assert_close2(B.at(0.0), Vector { x: 0.0, y: 0.0 });
+        assert_close2(b().at(0.0), Vector { x: 0.0, y: 0.0 });
     }
 
     #[test]
     fn end_is_t1() {
-        assert_close2(B.at(1.0), Vector { x: 1.0, y: 1.0 });
+        assert_close2(b().at(1.0), Vector { x: 1.0, y: 1.0 });
     }
 
     #[test]
     fn mid() {
-        assert_close2(B.at(0.5), Vector { x: 0.625, y: 0.375 });
+        assert_close2(b().at(0.5), Vector { x: 0.625, y: 0.375 });
     }
 
     #[test]
     fn derivative() {
-        let d = B.derivative();
+        let d = b().derivative();
         assert_close2(d.start, Vector { x: 1.5, y: 0.5 });
         assert_close2(d.end, Vector { x: 0.5, y: 1.5 });
     }
 
     #[test]
     fn closest_point() {
-        let (t, p) = B.closest_point(Vector { x: 0.75, y: 0.25 });
+        let (t, p) = b().closest_point(Vector { x: 0.75, y: 0.25 });
         assert_close(t, 0.5);
         assert_close2(p, Vector { x: 0.625, y: 0.375 });
     }
 
     #[test]
     fn start_curvature() {
-        assert_close(B.curvature(0.0), 0.50596446);
+        assert_close(b().curvature(0.0), 0.50596446);
     }
 
     #[test]
     fn mid_curvature() {
-        assert_close(B.curvature(0.5), core::f32::consts::FRAC_1_SQRT_2);
+        assert_close(b().curvature(0.5), core::f32::consts::FRAC_1_SQRT_2);
     }
 
     #[test]
     fn end_curvature() {
-        assert_close(B.curvature(1.0), 0.50596446);
+        assert_close(b().curvature(1.0), 0.50596446);
     }
 }
 
+/// A cubic bezier curve. Build it with [Bezier3::new] so the polynomial form is worked out
+/// once up front. Only the control points are serialized.
 #[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(from = "[Vector; 4]", into = "[Vector; 4]")]
 pub struct Bezier3 {
     pub start: Vector,
     pub ctrl0: Vector,
     pub ctrl1: Vector,
     pub end: Vector,
+    polynomial: Cubic,
 }
 
 impl Curve for Bezier3 {
@@ -444,6 +525,46 @@ impl Curve for Bezier3 {
 
     /// Evaluate the curve at `t`
     fn at(&self, t: f32) -> Vector {
+        self.polynomial.at(t)
+    }
+
+    fn derivative(&self) -> Self::Derivative {
+        Bezier2::new(
+            3.0 * (self.ctrl0 - self.start),
+            3.0 * (self.ctrl1 - self.ctrl0),
+            3.0 * (self.end - self.ctrl1),
+        )
+    }
+
+    fn curvature(&self, t: f32) -> f32 {
+        self.polynomial.curvature(t)
+    }
+}
+
+impl Bezier3 {
+    pub fn new(start: Vector, ctrl0: Vector, ctrl1: Vector, end: Vector) -> Bezier3 {
+        Bezier3 {
+            start,
+            ctrl0,
+            ctrl1,
+            end,
+            polynomial: Cubic {
+                a: end - start + 3.0 * (ctrl0 - ctrl1),
+                b: 3.0 * (start - 2.0 * ctrl0 + ctrl1),
+                c: 3.0 * (ctrl0 - start),
+                d: start,
+            },
+        }
+    }
+
+    /// The polynomial form `a*t^3 + b*t^2 + c*t + d`
+    pub fn polynomial(&self) -> Cubic {
+        self.polynomial
+    }
+
+    /// Evaluate the curve at `t` in the Bernstein form, which is slower than [Curve::at]. This
+    /// is for checking and benchmarking the polynomial form against.
+    pub fn bernstein_at(&self, t: f32) -> Vector {
         Vector {
             x: self.start.x * (1.0 - t) * (1.0 - t) * (1.0 - t)
                 + 3.0 * self.ctrl0.x * (1.0 - t) * (1.0 - t) * t
@@ -456,12 +577,47 @@ impl Curve for Bezier3 {
                 + self.end.y * t * t * t,
         }
     }
+}
+
+impl From<[Vector; 4]> for Bezier3 {
+    fn from(points: [Vector; 4]) -> Bezier3 {
+        Bezier3::new(points[0], points[1], points[2], points[3])
+    }
+}
+
+impl From<Bezier3> for [Vector; 4] {
+    fn from(b: Bezier3) -> [Vector; 4] {
+        [b.start, b.ctrl0, b.ctrl1, b.end]
+    }
+}
+
+/// A cubic polynomial `a*t^3 + b*t^2 + c*t + d`, evaluated with Horner's method
+///
+/// This takes 3 multiplies per coordinate to evaluate, compared to 10 for the Bernstein form
+/// of a `Bezier3`
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Cubic {
+    pub a: Vector,
+    pub b: Vector,
+    pub c: Vector,
+    pub d: Vector,
+}
+
+impl Curve for Cubic {
+    type Derivative = Quadratic;
+
+    fn at(&self, t: f32) -> Vector {
+        Vector {
+            x: ((self.a.x * t + self.b.x) * t + self.c.x) * t + self.d.x,
+            y: ((self.a.y * t + self.b.y) * t + self.c.y) * t + self.d.y,
+        }
+    }
 
     fn derivative(&self) -> Self::Derivative {
-        Bezier2 {
-            start: 3.0 * (self.ctrl0 - self.start),
-            ctrl0: 3.0 * (self.ctrl1 - self.ctrl0),
-            end: 3.0 * (self.end - self.ctrl1),
+        Quadratic {
+            a: 3.0 * self.a,
+            b: 2.0 * self.b,
+            c: self.c,
         }
     }
 }
@@ -474,27 +630,29 @@ mod bezier3_tests {
     use super::super::Vector;
     use super::{Bezier3, Curve};
 
-    const B: Bezier3 = Bezier3 {
-        start: Vector { x: 0.0, y: 0.0 },
-        ctrl0: Vector { x: 0.5, y: 0.0 },
-        ctrl1: Vector { x: 1.0, y: 0.5 },
-        end: Vector { x: 1.0, y: 1.0 },
-    };
+    fn b() -> Bezier3 {
+        Bezier3::new(
+            Vector { x: 0.0, y: 0.0 },
+            Vector { x: 0.5, y: 0.0 },
+            Vector { x: 1.0, y: 0.5 },
+            Vector { x: 1.0, y: 1.0 },
+        )
+    }
 
     #[test]
     fn start_is_t0() {
-        assert_close2(B.at(0.0), Vector { x: 0.0, y: 0.0 });
+        assert_close2(b().at(0.0), Vector { x: 0.0, y: 0.0 });
     }
 
     #[test]
     fn end_is_t1() {
-        assert_close2(B.at(1.0), Vector { x: 1.0, y: 1.0 });
+        assert_close2(b().at(1.0), Vector { x: 1.0, y: 1.0 });
     }
 
     #[test]
     fn mid() {
         assert_close2(
-            B.at(0.5),
+            b().at(0.5),
             Vector {
                 x: 0.6875,
                 y: 0.3125,
@@ -504,7 +662,7 @@ mod bezier3_tests {
 
     #[test]
     fn derivative() {
-        let d = B.derivative();
+        let d = b().derivative();
         assert_close2(d.start, Vector { x: 1.5, y: 0.0 });
         assert_close2(d.ctrl0, Vector { x: 1.5, y: 1.5 });
         assert_close2(d.end, Vector { x: 0.0, y: 1.5 });
@@ -512,7 +670,7 @@ mod bezier3_tests {
 
     #[test]
     fn closest_point_outside() {
-        let (t, p) = B.closest_point(Vector { x: 0.75, y: 0.25 });
+        let (t, p) = b().closest_point(Vector { x: 0.75, y: 0.25 });
         assert_close(t, 0.5);
         assert_close2(
             p,
@@ -525,7 +683,7 @@ mod bezier3_tests {
 
     #[test]
     fn closest_point_inside() {
-        let (t, p) = B.closest_point(Vector { x: 0.5, y: 0.5 });
+        let (t, p) = b().closest_point(Vector { x: 0.5, y: 0.5 });
         assert_close(t, 0.5);
         assert_close2(
             p,
@@ -538,28 +696,28 @@ mod bezier3_tests {
 
     #[test]
     fn closest_point_before_outside() {
-        let (t, p) = B.closest_point(Vector { x: -1.0, y: -0.25 });
+        let (t, p) = b().closest_point(Vector { x: -1.0, y: -0.25 });
         assert!(t < 0.0);
         assert_close2(p, Vector { x: -1.0, y: 0.0 });
     }
 
     #[test]
     fn closest_point_before_inside() {
-        let (t, p) = B.closest_point(Vector { x: -1.0, y: 0.25 });
+        let (t, p) = b().closest_point(Vector { x: -1.0, y: 0.25 });
         assert!(t < 0.0);
         assert_close2(p, Vector { x: -1.0, y: 0.0 });
     }
 
     #[test]
     fn closest_point_after_outside() {
-        let (t, p) = B.closest_point(Vector { x: 1.25, y: 2.0 });
+        let (t, p) = b().closest_point(Vector { x: 1.25, y: 2.0 });
         assert!(t > 1.0);
         assert_close2(p, Vector { x: 1.0, y: 2.0 });
     }
 
     #[test]
     fn closest_point_after_inside() {
-        let (t, p) = B.closest_point(Vector { x: 0.75, y: 2.0 });
+        let (t, p) = b().closest_point(Vector { x: 0.75, y: 2.0 });
         assert!(t > 1.0);
         assert_close2(p, Vector { x: 1.0, y: 2.0 });
     }
@@ -567,24 +725,24 @@ mod bezier3_tests {
     // Observed in simulator when first testing
     #[test]
     fn closest_point_after_from_sim() {
-        let b = Bezier3 {
-            start: Vector {
+        let b = Bezier3::new(
+            Vector {
                 x: 1260.0,
                 y: 1170.0,
             },
-            ctrl0: Vector {
+            Vector {
                 x: 1440.0,
                 y: 1170.0,
             },
-            ctrl1: Vector {
+            Vector {
                 x: 1440.0,
                 y: 1170.0,
             },
-            end: Vector {
+            Vector {
                 x: 1620.0,
                 y: 1170.0,
             },
-        };
+        );
         let (t, p) = b.closest_point(Vector {
             x: 1861.0,
             y: 1170.0,
@@ -601,17 +759,69 @@ mod bezier3_tests {
 
     #[test]
     fn start_curvature() {
-        assert_close(B.curvature(0.0), 1.333333);
+        assert_close(b().curvature(0.0), 1.333333);
     }
 
     #[test]
     fn mid_curvature() {
-        assert_close(B.curvature(0.5), 0.8380524);
+        assert_close(b().curvature(0.5), 0.8380524);
     }
 
     #[test]
     fn end_curvature() {
-        assert_close(B.curvature(1.0), 1.3333333);
+        assert_close(b().curvature(1.0), 1.3333333);
+    }
+
+    fn assert_same_as_bernstein(b: &Bezier3) {
+        let scale = 1.0f32
+            .max(b.start.magnitude())
+            .max(b.ctrl0.magnitude())
+            .max(b.ctrl1.magnitude())
+            .max(b.end.magnitude());
+
+        for i in -100..=1100 {
+            let t = i as f32 / 1000.0;
+            let horner = b.at(t);
+            let bernstein = b.bernstein_at(t);
+            assert_close2(horner * (1.0 / scale), bernstein * (1.0 / scale));
+        }
+    }
+
+    #[test]
+    fn horner_same_as_bernstein() {
+        assert_same_as_bernstein(&b());
+    }
+
+    #[test]
+    fn horner_same_as_bernstein_in_mm() {
+        assert_same_as_bernstein(&Bezier3::new(
+            Vector {
+                x: 1260.0,
+                y: 1170.0,
+            },
+            Vector {
+                x: 1350.0,
+                y: 1170.0,
+            },
+            Vector {
+                x: 1440.0,
+                y: 1260.0,
+            },
+            Vector {
+                x: 1440.0,
+                y: 1350.0,
+            },
+        ));
+    }
+
+    #[test]
+    fn polynomial_derivative_same_as_bezier_derivative() {
+        let p = b().polynomial().derivative();
+        let d = b().derivative();
+        for i in 0..=100 {
+            let t = i as f32 / 100.0;
+            assert_close2(p.at(t), d.at(t));
+        }
     }
 }
 
@@ -643,16 +853,50 @@ impl Curve for Bezier4 {
     }
 
     fn derivative(&self) -> Self::Derivative {
-        Bezier3 {
-            start: 4.0 * (self.ctrl0 - self.start),
-            ctrl0: 4.0 * (self.ctrl1 - self.ctrl0),
-            ctrl1: 4.0 * (self.ctrl2 - self.ctrl1),
-            end: 4.0 * (self.end - self.ctrl2),
+        Bezier3::new(
+            4.0 * (self.ctrl0 - self.start),
+            4.0 * (self.ctrl1 - self.ctrl0),
+            4.0 * (self.ctrl2 - self.ctrl1),
+            4.0 * (self.end - self.ctrl2),
+        )
+    }
+}
+
+/// A quartic polynomial `a*t^4 + b*t^3 + c*t^2 + d*t + e`, evaluated with Horner's method.
+/// This is the derivative of a [Quintic].
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Quartic {
+    pub a: Vector,
+    pub b: Vector,
+    pub c: Vector,
+    pub d: Vector,
+    pub e: Vector,
+}
+
+impl Curve for Quartic {
+    type Derivative = Cubic;
+
+    fn at(&self, t: f32) -> Vector {
+        Vector {
+            x: (((self.a.x * t + self.b.x) * t + self.c.x) * t + self.d.x) * t + self.e.x,
+            y: (((self.a.y * t + self.b.y) * t + self.c.y) * t + self.d.y) * t + self.e.y,
+        }
+    }
+
+    fn derivative(&self) -> Self::Derivative {
+        Cubic {
+            a: 4.0 * self.a,
+            b: 3.0 * self.b,
+            c: 2.0 * self.c,
+            d: self.d,
         }
     }
 }
 
+/// A quintic bezier curve. Build it with [Bezier5::new] so the polynomial form is worked out
+/// once up front. Only the control points are serialized.
 #[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(from = "[Vector; 6]", into = "[Vector; 6]")]
 pub struct Bezier5 {
     pub start: Vector,
     pub ctrl0: Vector,
@@ -660,25 +904,21 @@ pub struct Bezier5 {
     pub ctrl2: Vector,
     pub ctrl3: Vector,
     pub end: Vector,
+    polynomial: Quintic,
 }
 
 impl Curve for Bezier5 {
     type Derivative = Bezier4;
 
+    /// Evaluate the curve at `t`
+    ///
+    /// The polynomial form can round a little away from the end at `t = 1`, so the end is
+    /// given exactly there to keep curves that end on a boundary inside it
     fn at(&self, t: f32) -> Vector {
-        Vector {
-            x: self.start.x * (1.0 - t) * (1.0 - t) * (1.0 - t) * (1.0 - t) * (1.0 - t)
-                + 5.0 * self.ctrl0.x * (1.0 - t) * (1.0 - t) * (1.0 - t) * (1.0 - t) * t
-                + 10.0 * self.ctrl1.x * (1.0 - t) * (1.0 - t) * (1.0 - t) * t * t
-                + 10.0 * self.ctrl2.x * (1.0 - t) * (1.0 - t) * t * t * t
-                + 5.0 * self.ctrl3.x * (1.0 - t) * t * t * t * t
-                + self.end.x * t * t * t * t * t,
-            y: self.start.y * (1.0 - t) * (1.0 - t) * (1.0 - t) * (1.0 - t) * (1.0 - t)
-                + 5.0 * self.ctrl0.y * (1.0 - t) * (1.0 - t) * (1.0 - t) * (1.0 - t) * t
-                + 10.0 * self.ctrl1.y * (1.0 - t) * (1.0 - t) * (1.0 - t) * t * t
-                + 10.0 * self.ctrl2.y * (1.0 - t) * (1.0 - t) * t * t * t
-                + 5.0 * self.ctrl3.y * (1.0 - t) * t * t * t * t
-                + self.end.y * t * t * t * t * t,
+        if t == 1.0 {
+            self.end
+        } else {
+            self.polynomial.at(t)
         }
     }
 
@@ -691,4 +931,271 @@ impl Curve for Bezier5 {
             end: 5.0 * (self.end - self.ctrl3),
         }
     }
+
+    fn curvature(&self, t: f32) -> f32 {
+        self.polynomial.curvature(t)
+    }
+}
+
+impl Bezier5 {
+    pub fn new(
+        start: Vector,
+        ctrl0: Vector,
+        ctrl1: Vector,
+        ctrl2: Vector,
+        ctrl3: Vector,
+        end: Vector,
+    ) -> Bezier5 {
+        Bezier5 {
+            start,
+            ctrl0,
+            ctrl1,
+            ctrl2,
+            ctrl3,
+            end,
+            polynomial: Quintic {
+                a: end - 5.0 * ctrl3 + 10.0 * ctrl2 - 10.0 * ctrl1 + 5.0 * ctrl0 - start,
+                b: 5.0 * (ctrl3 - 4.0 * ctrl2 + 6.0 * ctrl1 - 4.0 * ctrl0 + start),
+                c: 10.0 * (ctrl2 - 3.0 * ctrl1 + 3.0 * ctrl0 - start),
+                d: 10.0 * (ctrl1 - 2.0 * ctrl0 + start),
+                e: 5.0 * (ctrl0 - start),
+                f: start,
+            },
+        }
+    }
+
+    /// The polynomial form `a*t^5 + b*t^4 + c*t^3 + d*t^2 + e*t + f`
+    pub fn polynomial(&self) -> Quintic {
+        self.polynomial
+    }
+
+    /// Split the curve at `t` into the part before and the part after, which together follow
+    /// the same path
+    pub fn split(&self, t: f32) -> (Bezier5, Bezier5) {
+        let mut points: [Vector; 6] = (*self).into();
+        let mut first = points;
+        let mut second = points;
+
+        // Keep interpolating between the points. The first point of each round is on the first
+        // part, and the last is on the second part.
+        for n in 1..6 {
+            for i in 0..6 - n {
+                points[i] = points[i] + (points[i + 1] - points[i]) * t;
+            }
+            first[n] = points[0];
+            second[5 - n] = points[5 - n];
+        }
+
+        (Bezier5::from(first), Bezier5::from(second))
+    }
+}
+
+impl From<[Vector; 6]> for Bezier5 {
+    fn from(points: [Vector; 6]) -> Bezier5 {
+        Bezier5::new(
+            points[0], points[1], points[2], points[3], points[4], points[5],
+        )
+    }
+}
+
+impl From<Bezier5> for [Vector; 6] {
+    fn from(b: Bezier5) -> [Vector; 6] {
+        [b.start, b.ctrl0, b.ctrl1, b.ctrl2, b.ctrl3, b.end]
+    }
+}
+
+/// A quintic polynomial `a*t^5 + b*t^4 + c*t^3 + d*t^2 + e*t + f`, evaluated with Horner's
+/// method
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Quintic {
+    pub a: Vector,
+    pub b: Vector,
+    pub c: Vector,
+    pub d: Vector,
+    pub e: Vector,
+    pub f: Vector,
+}
+
+impl Curve for Quintic {
+    type Derivative = Quartic;
+
+    fn at(&self, t: f32) -> Vector {
+        Vector {
+            x: ((((self.a.x * t + self.b.x) * t + self.c.x) * t + self.d.x) * t
+                + self.e.x)
+                * t
+                + self.f.x,
+            y: ((((self.a.y * t + self.b.y) * t + self.c.y) * t + self.d.y) * t
+                + self.e.y)
+                * t
+                + self.f.y,
+        }
+    }
+
+    fn derivative(&self) -> Self::Derivative {
+        Quartic {
+            a: 5.0 * self.a,
+            b: 4.0 * self.b,
+            c: 3.0 * self.c,
+            d: 2.0 * self.d,
+            e: self.e,
+        }
+    }
+}
+
+#[cfg(test)]
+mod bezier5_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{Bezier5, Curve};
+    use crate::fast::Vector;
+
+    fn b() -> Bezier5 {
+        Bezier5::new(
+            Vector { x: 0.0, y: 0.0 },
+            Vector { x: 20.0, y: 80.0 },
+            Vector { x: 40.0, y: 80.0 },
+            Vector { x: 60.0, y: -20.0 },
+            Vector { x: 80.0, y: 80.0 },
+            Vector { x: 100.0, y: 0.0 },
+        )
+    }
+
+    /// Evaluate a bezier curve of up to degree 5 by repeatedly interpolating between the
+    /// points
+    fn de_casteljau(points: &[Vector], t: f32) -> Vector {
+        let mut p = [Vector { x: 0.0, y: 0.0 }; 6];
+        p[..points.len()].copy_from_slice(points);
+        for n in (1..points.len()).rev() {
+            for i in 0..n {
+                p[i] = (1.0 - t) * p[i] + t * p[i + 1];
+            }
+        }
+        p[0]
+    }
+
+    #[test]
+    fn start_is_t0() {
+        assert_close2(b().at(0.0), b().start);
+    }
+
+    #[test]
+    fn end_is_t1() {
+        assert_close2(b().at(1.0), b().end);
+    }
+
+    #[test]
+    fn same_as_de_casteljau() {
+        let b = b();
+        let points = [b.start, b.ctrl0, b.ctrl1, b.ctrl2, b.ctrl3, b.end];
+        for i in 0..=100 {
+            let t = i as f32 / 100.0;
+            assert_close2(b.at(t) * 0.01, de_casteljau(&points, t) * 0.01);
+        }
+    }
+
+    #[test]
+    fn derivative_same_as_de_casteljau() {
+        let d = b().derivative();
+        let points = [d.start, d.ctrl0, d.ctrl1, d.ctrl2, d.end];
+        for i in 0..=100 {
+            let t = i as f32 / 100.0;
+            assert_close2(d.at(t) * 0.01, de_casteljau(&points, t) * 0.01);
+        }
+    }
+
+    #[test]
+    fn curvature_same_as_from_derivatives() {
+        let b = b();
+        for i in 0..=100 {
+            let t = i as f32 / 100.0;
+            let d1 = b.derivative().at(t);
+            let d2 = b.derivative().derivative().at(t);
+            let expected = (d1.x * d2.y - d2.x * d1.y)
+                / (d1.magnitude() * d1.magnitude() * d1.magnitude());
+            assert_close(b.curvature(t) * 100.0, expected * 100.0);
+        }
+    }
+
+    #[test]
+    fn split_follows_the_same_path() {
+        let b = b();
+        for &t in [0.5, 0.3].iter() {
+            let (first, second) = b.split(t);
+            for i in 0..=10 {
+                let u = i as f32 / 10.0;
+                assert_close2(first.at(u) * 0.01, b.at(u * t) * 0.01);
+                assert_close2(second.at(u) * 0.01, b.at(t + u * (1.0 - t)) * 0.01);
+            }
+        }
+    }
+
+    #[test]
+    fn round_trips_through_points() {
+        let points: [Vector; 6] = b().into();
+        assert_eq!(Bezier5::from(points), b());
+    }
+}
+
+#[cfg(test)]
+mod closest_point_near_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{Bezier5, Curve};
+    use crate::fast::Vector;
+
+    // Up the y axis, around a hairpin, and back down 20mm over
+    fn hairpin() -> Bezier5 {
+        Bezier5::new(
+            Vector { x: 0.0, y: 0.0 },
+            Vector { x: 0.0, y: 100.0 },
+            Vector { x: 0.0, y: 160.0 },
+            Vector { x: 20.0, y: 160.0 },
+            Vector { x: 20.0, y: 100.0 },
+            Vector { x: 20.0, y: 0.0 },
+        )
+    }
+
+    #[test]
+    fn matches_cold_start() {
+        let m = Vector { x: 2.0, y: 50.0 };
+        let (t, p) = hairpin().closest_point(m);
+        let (near_t, near_p) = hairpin().closest_point_near(m, t + 0.05);
+
+        // Both searches stop within epsilon in t, which is a bit more than that in mm
+        assert!((near_t - t).abs() < 0.0001);
+        assert!(near_p.approx_eq(p, 0.01));
+    }
+
+    #[test]
+    fn stays_on_the_same_side_of_the_hairpin() {
+        // On the way up, the closest point is on the first side
+        let (t, p) = hairpin().closest_point(Vector { x: 8.0, y: 50.0 });
+        assert!(t < 0.5);
+        assert!(p.x < 10.0);
+
+        // Drifting past the middle, a cold start jumps to the way back down
+        let m = Vector { x: 11.0, y: 50.0 };
+        let (cold_t, _) = hairpin().closest_point(m);
+        assert!(cold_t > 0.5);
+
+        // But a warm start stays on the way up
+        let (warm_t, warm_p) = hairpin().closest_point_near(m, t);
+        assert!(warm_t < 0.5);
+        assert!(warm_p.x < 10.0);
+    }
+
+    #[test]
+    fn past_the_ends() {
+        assert_eq!(
+            hairpin().closest_point_near(Vector { x: 0.0, y: -10.0 }, 0.5),
+            hairpin().closest_point(Vector { x: 0.0, y: -10.0 }),
+        );
+        assert_eq!(
+            hairpin().closest_point_near(Vector { x: 20.0, y: -10.0 }, 0.5),
+            hairpin().closest_point(Vector { x: 20.0, y: -10.0 }),
+        );
+    }
 }
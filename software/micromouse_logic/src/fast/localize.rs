@@ -11,10 +11,14 @@ use heapless::{ArrayLength, Vec};
 
 use typenum::U8;
 
-use crate::config::MechanicalConfig;
+use crate::config::{
+    MechanicalConfig, SensorFacing, FRONT_SENSOR, LEFT_SENSOR, MAX_DISTANCE_SENSORS,
+    RIGHT_SENSOR,
+};
 use crate::mouse::ContainsDistanceReading;
-use crate::mouse::DistanceReading;
+use crate::mouse::{three_distance_readings, DistanceReading, DistanceReadings};
 use crate::slow::maze::MazeConfig;
+use crate::slow::{MazeDirection, MazePosition};
 
 use super::{
     Direction, Orientation, Vector, DIRECTION_0, DIRECTION_3_PI_2, DIRECTION_PI,
@@ -93,6 +97,66 @@ mod test_average_filter {
     }
 }
 
+/// Smooths readings by going part of the way from the last filtered value to each new one
+#[derive(Default)]
+pub struct ExponentialFilter {
+    last: Option<f32>,
+}
+
+impl ExponentialFilter {
+    pub fn new() -> ExponentialFilter {
+        ExponentialFilter { last: None }
+    }
+
+    /// Take `alpha` of `value` and the rest from the last filtered value. An `alpha` of 0 or 1
+    /// passes the readings through as they are
+    pub fn filter(&mut self, alpha: f32, value: f32) -> f32 {
+        let filtered = match self.last {
+            Some(last) if alpha > 0.0 && alpha < 1.0 => last + alpha * (value - last),
+            _ => value,
+        };
+
+        self.last = Some(filtered);
+        filtered
+    }
+}
+
+#[cfg(test)]
+mod test_exponential_filter {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::ExponentialFilter;
+
+    #[test]
+    fn first_is_raw() {
+        let mut filter = ExponentialFilter::new();
+        assert_close(filter.filter(0.25, 10.0), 10.0);
+    }
+
+    #[test]
+    fn smooths() {
+        let mut filter = ExponentialFilter::new();
+        filter.filter(0.25, 10.0);
+        assert_close(filter.filter(0.25, 18.0), 12.0);
+        assert_close(filter.filter(0.25, 12.0), 12.0);
+    }
+
+    #[test]
+    fn one_is_raw() {
+        let mut filter = ExponentialFilter::new();
+        filter.filter(1.0, 10.0);
+        assert_close(filter.filter(1.0, 18.0), 18.0);
+    }
+
+    #[test]
+    fn zero_is_raw() {
+        let mut filter = ExponentialFilter::new();
+        filter.filter(0.0, 10.0);
+        assert_close(filter.filter(0.0, 18.0), 18.0);
+    }
+}
+
 /// Configuration for a [SideDistanceFilter]
 #[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct SideDistanceFilterConfig {
@@ -175,6 +239,12 @@ impl SideDistanceFilter {
     }
 }
 
+impl Default for SideDistanceFilter {
+    fn default() -> SideDistanceFilter {
+        SideDistanceFilter::new()
+    }
+}
+
 #[cfg(test)]
 mod side_distance_filter_test {
     #[allow(unused_imports)]
@@ -268,13 +338,134 @@ pub struct LocalizeConfig {
     pub left_side_filter: SideDistanceFilterConfig,
     pub right_side_filter: SideDistanceFilterConfig,
     pub front_max_range: f32,
+
+    /// How much of each new front distance reading to take, from 0 to 1, with the rest from the
+    /// readings before it. See [ExponentialFilter]. 0 or 1 uses the raw readings.
+    pub front_filter_alpha: f32,
+
+    /// How much to trust the front wall distance over the encoders for the along-track
+    /// position, from 0 (ignore the front wall) to 1 (only use the front wall)
+    pub wall_trust: f32,
+
+    /// How far the sensor position can be from the encoder position before the update counts
+    /// as diverging
+    pub divergence_threshold: f32,
+
+    /// How many diverging updates in a row before the localization is considered diverged. 0
+    /// disables divergence detection. After diverging, it is recovering until the mouse comes
+    /// to rest somewhere the sensors agree with.
+    pub divergence_count: u32,
+
+    /// How much more to trust the front wall for each cell moved since the last front wall
+    /// correction, on top of `wall_trust`, up to fully trusting it. The longer the mouse has
+    /// gone on just the encoders, the less the along-track position can be trusted. 0 always
+    /// uses `wall_trust`.
+    pub blind_trust_growth: f32,
+
+    /// How many updates old the distance readings are by the time they get here. The sensor
+    /// correction is worked out from where the mouse was then, and moved forward by how far the
+    /// encoders say it has gone since. Up to [LatencyHistorySize], 0 uses the readings as if
+    /// they were from now.
+    pub sensor_latency: u8,
+
+    /// How much of the way to move the heading towards the direction the mouse actually moved,
+    /// from 0 to 1, each time it has gone [LocalizeConfig::heading_fusion_baseline] straight.
+    /// The side walls keep the position honest, so this pulls out drift in the encoder heading.
+    /// 0 disables it.
+    pub heading_fusion: f32,
+
+    /// How far the mouse has to go straight, in mm, before the direction it went is used as a
+    /// heading. Any shorter and a single snap from the side walls swamps the direction.
+    pub heading_fusion_baseline: f32,
+
+    /// The most the heading can be moved each update, in radians. 0 doesn't limit it.
+    pub heading_fusion_max_correction: f32,
+}
+
+/// The most the encoders can say the mouse turned in one update, in radians, for it to count as
+/// going straight for [LocalizeConfig::heading_fusion]
+const FUSION_MAX_TURN: f32 = 0.002;
+
+/// The direction the mouse went moving from `from` to `to`, or None if it moved less than
+/// `min_distance` mm, which is too little for the direction to mean anything
+pub fn heading_from_positions(
+    from: Vector,
+    to: Vector,
+    min_distance: f32,
+) -> Option<Direction> {
+    let moved = to - from;
+    if moved.magnitude() < min_distance || moved.magnitude() == 0.0 {
+        None
+    } else {
+        Some(moved.direction())
+    }
 }
 
+#[cfg(test)]
+mod heading_from_positions_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use pretty_assertions::assert_eq;
+
+    use super::heading_from_positions;
+    use crate::fast::{Vector, DIRECTION_PI_2};
+
+    #[test]
+    fn north() {
+        let heading = heading_from_positions(
+            Vector { x: 90.0, y: 90.0 },
+            Vector { x: 90.0, y: 95.0 },
+            1.0,
+        );
+        assert_close(f32::from(heading.unwrap()), f32::from(DIRECTION_PI_2));
+    }
+
+    #[test]
+    fn too_short() {
+        let heading = heading_from_positions(
+            Vector { x: 90.0, y: 90.0 },
+            Vector { x: 90.0, y: 90.5 },
+            1.0,
+        );
+        assert_eq!(heading, None);
+    }
+
+    #[test]
+    fn still() {
+        let still = Vector { x: 90.0, y: 90.0 };
+        assert_eq!(heading_from_positions(still, still, 0.0), None);
+    }
+}
+
+/// How many updates of movement are kept to compensate for [LocalizeConfig::sensor_latency]
+pub type LatencyHistorySize = U8;
+
 #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct LocalizeDebug {
     //pub maze: Maze,
     pub encoder_orientation: Orientation,
     pub sensor: Option<SensorDebug>,
+    pub diverged: bool,
+
+    /// Whether the localization diverged, and the mouse hasn't come to rest where the sensors
+    /// agree with it since
+    pub recovering: bool,
+
+    /// The left, front, and right distance readings after filtering, but before offsetting to
+    /// the center of the mouse, to compare with the raw readings. `None` if there was no reading
+    /// or the filter rejected it
+    pub filtered_left: Option<f32>,
+    pub filtered_front: Option<f32>,
+    pub filtered_right: Option<f32>,
+
+    /// How far each distance sensor says the wall it sees is from the center of the mouse,
+    /// forward for front sensors and to the side for side sensors. In the same order as
+    /// [MechanicalConfig::distance_sensors].
+    pub projected: [Option<f32>; MAX_DISTANCE_SENSORS],
+
+    /// How many cells the mouse has moved since the last front wall correction
+    pub blind_cells: u32,
 }
 
 #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
@@ -286,17 +477,28 @@ pub struct SensorDebug {
     pub center_offset: Option<f32>,
     pub maybe_x: Option<f32>,
     pub maybe_y: Option<f32>,
+    pub innovation: f32,
 }
 
 pub struct Localize {
     orientation: Orientation,
     left_encoder: i32,
     right_encoder: i32,
-    raw_left_distance: Option<DistanceReading>,
-    raw_right_distance: Option<DistanceReading>,
-    left_filter: SideDistanceFilter,
-    right_filter: SideDistanceFilter,
+    raw_side_distances: DistanceReadings,
+    side_filters: [SideDistanceFilter; MAX_DISTANCE_SENSORS],
+    front_filters: [ExponentialFilter; MAX_DISTANCE_SENSORS],
     last_direction_moved: Direction,
+    diverging_count: u32,
+    recovering: bool,
+    last_cell: Option<MazePosition>,
+    blind_cells: u32,
+
+    /// Where the mouse was when it started going straight, to measure the direction it went
+    /// for [LocalizeConfig::heading_fusion]
+    fusion_start: Option<Vector>,
+
+    /// How far the encoders moved the mouse each update, oldest first
+    movement_history: Vec<Vector, LatencyHistorySize>,
 }
 
 impl Localize {
@@ -309,11 +511,16 @@ impl Localize {
             orientation,
             left_encoder,
             right_encoder,
-            raw_left_distance: None,
-            raw_right_distance: None,
-            left_filter: SideDistanceFilter::new(),
-            right_filter: SideDistanceFilter::new(),
+            raw_side_distances: [None; MAX_DISTANCE_SENSORS],
+            side_filters: Default::default(),
+            front_filters: Default::default(),
             last_direction_moved: orientation.direction,
+            diverging_count: 0,
+            recovering: false,
+            last_cell: None,
+            blind_cells: 0,
+            fusion_start: None,
+            movement_history: Vec::new(),
         }
     }
 
@@ -327,16 +534,93 @@ impl Localize {
         raw_left_distance: Option<DistanceReading>,
         raw_front_distance: Option<DistanceReading>,
         raw_right_distance: Option<DistanceReading>,
+        distances_fresh: bool,
         motion: Option<Motion>,
         moves_completed: usize,
     ) -> (Orientation, LocalizeDebug) {
-        let delta_left = left_encoder - self.left_encoder;
-        let delta_right = right_encoder - self.right_encoder;
+        self.update_sensors(
+            mech,
+            maze,
+            config,
+            left_encoder,
+            right_encoder,
+            three_distance_readings(
+                raw_left_distance,
+                raw_front_distance,
+                raw_right_distance,
+            ),
+            distances_fresh,
+            motion,
+            moves_completed,
+        )
+    }
+
+    /// Like [Localize::update], but with a reading from every distance sensor in
+    /// [MechanicalConfig::distance_sensors]. Each reading is projected to how far the wall it
+    /// sees is from the center of the mouse, and the readings of sensors that see the same
+    /// wall are averaged together.
+    pub fn update_sensors(
+        &mut self,
+        mech: &MechanicalConfig,
+        maze: &MazeConfig,
+        config: &LocalizeConfig,
+        left_encoder: i32,
+        right_encoder: i32,
+        raw_distances: DistanceReadings,
+        distances_fresh: bool,
+        motion: Option<Motion>,
+        moves_completed: usize,
+    ) -> (Orientation, LocalizeDebug) {
+        // Old distance readings would pull the position back to where the mouse used to be, so
+        // only use the encoders until there are new ones
+        let raw_distances = if distances_fresh {
+            raw_distances
+        } else {
+            [None; MAX_DISTANCE_SENSORS]
+        };
+
+        let sensors = mech.distance_sensors();
+
+        let (delta_left, delta_right) = mech.signed_encoder_deltas(
+            left_encoder - self.left_encoder,
+            right_encoder - self.right_encoder,
+        );
 
         let encoder_orientation =
             self.orientation
                 .update_from_encoders(&mech, delta_left, delta_right);
 
+        if self.movement_history.is_full() {
+            self.movement_history.rotate_left(1);
+            self.movement_history.pop();
+        }
+        self.movement_history
+            .push(encoder_orientation.position - self.orientation.position)
+            .ok();
+
+        // Where the mouse was when the distance readings were taken
+        let latency_movement = self
+            .movement_history
+            .iter()
+            .rev()
+            .take(config.sensor_latency as usize)
+            .fold(Vector { x: 0.0, y: 0.0 }, |sum, &movement| sum + movement);
+        let sensed_orientation = Orientation {
+            position: encoder_orientation.position - latency_movement,
+            direction: encoder_orientation.direction,
+        };
+
+        let cell = encoder_orientation.to_maze_orientation(maze).position;
+        if self.last_cell != Some(cell) {
+            if self.last_cell.is_some() {
+                self.blind_cells = self.blind_cells.saturating_add(1);
+            }
+            self.last_cell = Some(cell);
+        }
+
+        let mut filtered = [None; MAX_DISTANCE_SENSORS];
+        let mut projected = [None; MAX_DISTANCE_SENSORS];
+
         let (orientation, sensor_debug) = if let Some(Motion::Path(motion)) = motion {
             let (t, _) = motion.closest_point(encoder_orientation.position);
             let path_direction = motion.derivative(t).direction();
@@ -353,88 +637,167 @@ impl Localize {
             {
                 // Filter distance values
 
-                // Make sure that there are both left and right readings
-                if let Some(reading) = raw_left_distance {
-                    self.raw_left_distance = Some(reading);
-                }
-
-                if let Some(reading) = raw_right_distance {
-                    self.raw_right_distance = Some(reading);
+                // Make sure that there are readings from all of the side sensors
+                for (i, sensor) in sensors.iter().enumerate() {
+                    if let Some(sensor) = sensor {
+                        match sensor.facing() {
+                            SensorFacing::Left | SensorFacing::Right => {
+                                if let Some(reading) = raw_distances[i] {
+                                    self.raw_side_distances[i] = Some(reading);
+                                }
+                            }
+                            SensorFacing::Front | SensorFacing::Back => {}
+                        }
+                    }
                 }
 
-                let (left_distance, right_distance) =
-                    if let (Some(raw_left_distance), Some(raw_right_distance)) =
-                        (self.raw_left_distance, self.raw_right_distance)
-                    {
-                        self.raw_left_distance = None;
-                        self.raw_right_distance = None;
+                let all_sides_read = sensors
+                    .iter()
+                    .zip(self.raw_side_distances.iter())
+                    .all(|(sensor, raw)| match sensor.map(|sensor| sensor.facing()) {
+                        Some(SensorFacing::Left) | Some(SensorFacing::Right) => {
+                            raw.is_some()
+                        }
+                        _ => true,
+                    });
 
-                        let left_distance = self
-                            .left_filter
-                            .filter(&config.left_side_filter, raw_left_distance)
-                            .map(|d| d + mech.left_sensor_offset_y);
+                for (i, sensor) in sensors.iter().enumerate() {
+                    if let Some(sensor) = sensor {
+                        let (reading, projection) = match sensor.facing() {
+                            SensorFacing::Left if all_sides_read => {
+                                let reading =
+                                    self.raw_side_distances[i].and_then(|raw| {
+                                        self.side_filters[i]
+                                            .filter(&config.left_side_filter, raw)
+                                    });
+                                (reading, reading.map(|d| sensor.project(d).y))
+                            }
+                            SensorFacing::Right if all_sides_read => {
+                                let reading =
+                                    self.raw_side_distances[i].and_then(|raw| {
+                                        self.side_filters[i]
+                                            .filter(&config.right_side_filter, raw)
+                                    });
+                                (reading, reading.map(|d| -sensor.project(d).y))
+                            }
+                            SensorFacing::Front => {
+                                let in_range = raw_distances[i].value().filter(|&d| {
+                                    sensor.project(d).x < config.front_max_range
+                                });
+
+                                // Start over on the next wall seen
+                                if raw_distances[i].is_some() && in_range.is_none() {
+                                    self.front_filters[i] = ExponentialFilter::new();
+                                }
+
+                                let reading = in_range.map(|d| {
+                                    self.front_filters[i]
+                                        .filter(config.front_filter_alpha, d)
+                                });
+                                (reading, reading.map(|d| sensor.project(d).x))
+                            }
+                            _ => (None, None),
+                        };
+
+                        filtered[i] = reading;
+                        projected[i] = projection;
+                    }
+                }
 
-                        let right_distance = self
-                            .right_filter
-                            .filter(&config.right_side_filter, raw_right_distance)
-                            .map(|d| d + mech.left_sensor_offset_y);
+                if all_sides_read {
+                    self.raw_side_distances = [None; MAX_DISTANCE_SENSORS];
+                }
 
-                        (left_distance, right_distance)
+                // Average together the sensors that see the same wall
+                let fused = |facing: SensorFacing| {
+                    let (sum, count) = sensors
+                        .iter()
+                        .zip(projected.iter())
+                        .filter(|(sensor, _)| {
+                            sensor.map(|sensor| sensor.facing()) == Some(facing)
+                        })
+                        .filter_map(|(_, &projection)| projection)
+                        .fold((0.0, 0), |(sum, count), d| (sum + d, count + 1));
+
+                    if count > 0 {
+                        Some(sum / count as f32)
                     } else {
-                        (None, None)
-                    };
+                        None
+                    }
+                };
 
-                let front_distance = raw_front_distance
-                    .value()
-                    .map(|d| d + mech.front_sensor_offset_x)
-                    .filter(|&d| d < config.front_max_range);
+                let left_distance = fused(SensorFacing::Left);
+                let front_distance = fused(SensorFacing::Front);
+                let right_distance = fused(SensorFacing::Right);
 
                 // Calculate maze 'constants' for this location
-                let cell_center_x = (encoder_orientation.position.x / maze.cell_width)
+                let cell_center_x = (sensed_orientation.position.x / maze.cell_width_x)
                     .floor()
-                    * maze.cell_width
-                    + maze.cell_width / 2.0;
+                    * maze.cell_width_x
+                    + maze.cell_width_x / 2.0;
 
-                let cell_center_y = (encoder_orientation.position.y / maze.cell_width)
+                let cell_center_y = (sensed_orientation.position.y / maze.cell_width_y)
                     .floor()
-                    * maze.cell_width
-                    + maze.cell_width / 2.0;
+                    * maze.cell_width_y
+                    + maze.cell_width_y / 2.0;
 
                 // Where the front sensor is
-                let front_sensor_position = encoder_orientation.position
+                let front_sensor_position = sensed_orientation.position
                     + mech.front_sensor_offset_x
-                        * encoder_orientation.direction.into_unit_vector();
+                        * sensed_orientation.direction.into_unit_vector();
 
                 let front_sensor_cell_center_x =
-                    (front_sensor_position.x / maze.cell_width).floor() * maze.cell_width
-                        + maze.cell_width / 2.0;
+                    (front_sensor_position.x / maze.cell_width_x).floor()
+                        * maze.cell_width_x
+                        + maze.cell_width_x / 2.0;
 
                 let front_sensor_cell_center_y =
-                    (front_sensor_position.y / maze.cell_width).floor() * maze.cell_width
-                        + maze.cell_width / 2.0;
+                    (front_sensor_position.y / maze.cell_width_y).floor()
+                        * maze.cell_width_y
+                        + maze.cell_width_y / 2.0;
+
+                // The side walls are across the way the mouse is going
+                let across = if within_east || within_west {
+                    MazeDirection::North
+                } else {
+                    MazeDirection::East
+                };
+                let side_center_to_wall = maze.center_to_wall(across);
 
                 // Where are we left/right within the cell?
                 let center_offset = match (left_distance, right_distance) {
                     (Some(left), Some(right)) => {
-                        if left + right <= maze.cell_width {
+                        if left + right <= maze.cell_width(across) {
                             Some((right - left) / 2.0)
                         } else if left < right {
-                            Some(maze.center_to_wall() - left)
+                            Some(side_center_to_wall - left)
                         } else {
-                            Some(right - maze.center_to_wall())
+                            Some(right - side_center_to_wall)
                         }
                     }
-                    (None, Some(right)) => Some(right - maze.center_to_wall()),
-                    (Some(left), None) => Some(maze.center_to_wall() - left),
+                    (None, Some(right)) => Some(right - side_center_to_wall),
+                    (Some(left), None) => Some(side_center_to_wall - left),
                     _ => None,
                 };
 
+                // Move the along-track position towards where the front wall says it is,
+                // more so the longer it has been since the last time
+                let wall_trust = (config.wall_trust
+                    + config.blind_trust_growth * self.blind_cells as f32)
+                    .min(1.0);
+                let along_track =
+                    |encoder: f32, front: f32| encoder + wall_trust * (front - encoder);
+
                 let (maybe_x, maybe_y) = if within_east {
                     let y =
                         center_offset.map(|center_offset| cell_center_y + center_offset);
                     let x = front_distance.map(|front_distance| {
-                        front_sensor_cell_center_x + maze.center_to_wall()
-                            - front_distance
+                        along_track(
+                            sensed_orientation.position.x,
+                            front_sensor_cell_center_x
+                                + maze.center_to_wall(MazeDirection::East)
+                                - front_distance,
+                        )
                     });
 
                     (x, y)
@@ -442,8 +805,12 @@ impl Localize {
                     let y =
                         center_offset.map(|center_offset| cell_center_y - center_offset);
                     let x = front_distance.map(|front_distance| {
-                        front_sensor_cell_center_x - maze.center_to_wall()
-                            + front_distance
+                        along_track(
+                            sensed_orientation.position.x,
+                            front_sensor_cell_center_x
+                                - maze.center_to_wall(MazeDirection::West)
+                                + front_distance,
+                        )
                     });
 
                     (x, y)
@@ -451,8 +818,12 @@ impl Localize {
                     let x =
                         center_offset.map(|center_offset| cell_center_x - center_offset);
                     let y = front_distance.map(|front_distance| {
-                        front_sensor_cell_center_y + maze.center_to_wall()
-                            - front_distance
+                        along_track(
+                            sensed_orientation.position.y,
+                            front_sensor_cell_center_y
+                                + maze.center_to_wall(MazeDirection::North)
+                                - front_distance,
+                        )
                     });
 
                     (x, y)
@@ -460,8 +831,12 @@ impl Localize {
                     let x =
                         center_offset.map(|center_offset| cell_center_x + center_offset);
                     let y = front_distance.map(|front_distance| {
-                        front_sensor_cell_center_y - maze.center_to_wall()
-                            + front_distance
+                        along_track(
+                            sensed_orientation.position.y,
+                            front_sensor_cell_center_y
+                                - maze.center_to_wall(MazeDirection::South)
+                                + front_distance,
+                        )
                     });
 
                     (x, y)
@@ -469,10 +844,24 @@ impl Localize {
                     (None, None)
                 };
 
+                // Move the correction forward to where the mouse is now
                 let position = Vector {
-                    x: maybe_x.unwrap_or(encoder_orientation.position.x),
-                    y: maybe_y.unwrap_or(encoder_orientation.position.y),
-                };
+                    x: maybe_x.unwrap_or(sensed_orientation.position.x),
+                    y: maybe_y.unwrap_or(sensed_orientation.position.y),
+                } + latency_movement;
+
+                if front_distance.is_some() {
+                    self.blind_cells = 0;
+                }
+
+                // How far the sensors moved the position from what the encoders said
+                let innovation = (position - encoder_orientation.position).magnitude();
+
+                if innovation > config.divergence_threshold {
+                    self.diverging_count += 1;
+                } else {
+                    self.diverging_count = 0;
+                }
 
                 let direction_moved = (position - self.orientation.position).direction();
 
@@ -512,6 +901,7 @@ impl Localize {
                     center_offset,
                     maybe_x,
                     maybe_y,
+                    innovation,
                 };
 
                 (orientation, Some(sensor_debug))
@@ -519,15 +909,102 @@ impl Localize {
                 (encoder_orientation, None)
             }
         } else {
-            self.left_filter = SideDistanceFilter::new();
-            self.right_filter = SideDistanceFilter::new();
+            self.side_filters = Default::default();
+            self.front_filters = Default::default();
             (encoder_orientation, None)
         };
 
+        // Pull the heading towards the way the mouse moved since it started going straight
+        let turned = (encoder_orientation
+            .direction
+            .centered_at(self.orientation.direction)
+            - f32::from(self.orientation.direction))
+        .abs();
+        self.fusion_start = if config.heading_fusion > 0.0 && turned < FUSION_MAX_TURN {
+            self.fusion_start.or(Some(self.orientation.position))
+        } else {
+            None
+        };
+
+        // The walls just moved the position, so wait for the next update to see which way the
+        // mouse is going
+        let wall_corrected = orientation.position != encoder_orientation.position;
+
+        let moved = self
+            .fusion_start
+            .filter(|_| !wall_corrected)
+            .and_then(|start| {
+                heading_from_positions(
+                    start,
+                    orientation.position,
+                    config.heading_fusion_baseline,
+                )
+            });
+        let orientation = match moved {
+            Some(moved) => {
+                let error = moved.centered_at(orientation.direction)
+                    - f32::from(orientation.direction);
+                let correction = if config.heading_fusion_max_correction > 0.0 {
+                    (config.heading_fusion * error)
+                        .max(-config.heading_fusion_max_correction)
+                        .min(config.heading_fusion_max_correction)
+                } else {
+                    config.heading_fusion * error
+                };
+
+                // Measure again from here
+                self.fusion_start = Some(orientation.position);
+
+                Orientation {
+                    position: orientation.position,
+                    direction: orientation.direction + correction,
+                }
+            }
+            None => orientation,
+        };
+
+        let diverged = config.divergence_count > 0
+            && self.diverging_count >= config.divergence_count;
+
+        // Start over from the sensor position, and assume that the mouse is still lined up
+        // with the maze
+        let orientation = if diverged {
+            self.diverging_count = 0;
+            self.side_filters = Default::default();
+            self.front_filters = Default::default();
+            self.fusion_start = None;
+
+            Orientation {
+                position: orientation.position,
+                direction: orientation
+                    .to_maze_orientation(maze)
+                    .direction
+                    .into_direction(),
+            }
+        } else {
+            orientation
+        };
+
+        // Recovered once the mouse has come to rest where the sensors agree with it
+        let still =
+            left_encoder == self.left_encoder && right_encoder == self.right_encoder;
+        if diverged {
+            self.recovering = true;
+        } else if still && self.diverging_count == 0 {
+            self.recovering = false;
+        }
+
         let debug = LocalizeDebug {
             //maze: self.maze.clone(),
             encoder_orientation,
             sensor: sensor_debug,
+            diverged,
+            recovering: self.recovering,
+            filtered_left: filtered[LEFT_SENSOR],
+            filtered_front: filtered[FRONT_SENSOR],
+            filtered_right: filtered[RIGHT_SENSOR],
+            projected,
+            blind_cells: self.blind_cells,
         };
 
         self.left_encoder = left_encoder;
@@ -537,3 +1014,994 @@ impl Localize {
         (self.orientation, debug)
     }
 }
+
+#[cfg(test)]
+mod localize_divergence_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{Localize, LocalizeConfig, LocalizeDebug};
+    use crate::config::{mouse_2019, LOCALIZE, MAZE};
+    use crate::fast::motion_queue::Motion;
+    use crate::fast::path::PathMotion;
+    use crate::fast::{Orientation, Vector, DIRECTION_0};
+    use crate::mouse::DistanceReading;
+
+    const CONFIG: LocalizeConfig = LocalizeConfig {
+        divergence_threshold: 20.0,
+        divergence_count: 3,
+        ..LOCALIZE
+    };
+
+    const START: Orientation = Orientation {
+        position: Vector { x: 90.0, y: 90.0 },
+        direction: DIRECTION_0,
+    };
+
+    fn motion() -> Option<Motion> {
+        Some(Motion::Path(PathMotion::line(
+            Vector { x: 90.0, y: 90.0 },
+            Vector { x: 270.0, y: 90.0 },
+        )))
+    }
+
+    fn update(localize: &mut Localize, front: f32) -> bool {
+        update_debug(localize, front).diverged
+    }
+
+    fn update_debug(localize: &mut Localize, front: f32) -> LocalizeDebug {
+        let (_, debug) = localize.update(
+            &mouse_2019::MECH,
+            &MAZE,
+            &CONFIG,
+            0,
+            0,
+            None,
+            Some(DistanceReading::InRange(front)),
+            None,
+            true,
+            motion(),
+            0,
+        );
+
+        debug
+    }
+
+    #[test]
+    fn consistent_readings_do_not_diverge() {
+        let mut localize = Localize::new(START, 0, 0);
+
+        // The front wall should be 84mm from the center, minus the sensor offset
+        for _ in 0..10 {
+            assert!(!update(
+                &mut localize,
+                84.0 - mouse_2019::MECH.front_sensor_offset_x
+            ));
+        }
+    }
+
+    #[test]
+    fn pose_jumps_diverge() {
+        let mut localize = Localize::new(START, 0, 0);
+
+        // The position jumps back and forth by more than the threshold each update
+        assert!(!update(&mut localize, 10.0));
+        assert!(!update(&mut localize, 60.0));
+        assert!(update(&mut localize, 10.0));
+
+        // And is reset after recovering
+        assert!(!update(&mut localize, 10.0));
+    }
+
+    #[test]
+    fn recovering_until_consistent() {
+        let mut localize = Localize::new(START, 0, 0);
+
+        update(&mut localize, 10.0);
+        update(&mut localize, 60.0);
+        let debug = update_debug(&mut localize, 10.0);
+        assert!(debug.diverged);
+        assert!(debug.recovering);
+
+        // Still jumping around
+        let debug = update_debug(&mut localize, 60.0);
+        assert!(!debug.diverged);
+        assert!(debug.recovering);
+
+        // The same reading again agrees with where the last one put it
+        assert!(!update_debug(&mut localize, 60.0).recovering);
+    }
+
+    #[test]
+    fn recovering_until_still() {
+        let mut localize = Localize::new(START, 0, 0);
+
+        update(&mut localize, 10.0);
+        update(&mut localize, 60.0);
+        assert!(update(&mut localize, 10.0));
+
+        // Still rolling to a stop, with nothing to check against
+        let mut update_encoders = |encoder: i32| {
+            let (_, debug) = localize.update(
+                &mouse_2019::MECH,
+                &MAZE,
+                &CONFIG,
+                encoder,
+                encoder,
+                None,
+                None,
+                None,
+                true,
+                None,
+                0,
+            );
+            debug.recovering
+        };
+
+        assert!(update_encoders(10));
+        assert!(update_encoders(15));
+        assert!(!update_encoders(15));
+    }
+}
+
+#[cfg(test)]
+mod localize_front_wall_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{Localize, LocalizeConfig};
+    use crate::config::{mouse_2019, LOCALIZE, MAZE};
+    use crate::fast::motion_queue::Motion;
+    use crate::fast::path::PathMotion;
+    use crate::fast::{Orientation, Vector, DIRECTION_0};
+    use crate::mouse::DistanceReading;
+
+    // The encoders think the mouse is 10mm further along than it really is
+    const DRIFTED: Orientation = Orientation {
+        position: Vector { x: 100.0, y: 90.0 },
+        direction: DIRECTION_0,
+    };
+
+    // What the front sensor reads when the mouse is really centered in the cell
+    const FRONT: f32 = 84.0 - mouse_2019::MECH.front_sensor_offset_x;
+
+    fn localize_x(wall_trust: f32) -> f32 {
+        let config = LocalizeConfig {
+            wall_trust,
+            ..LOCALIZE
+        };
+
+        let mut localize = Localize::new(DRIFTED, 0, 0);
+        let (orientation, _) = localize.update(
+            &mouse_2019::MECH,
+            &MAZE,
+            &config,
+            0,
+            0,
+            None,
+            Some(DistanceReading::InRange(FRONT)),
+            None,
+            true,
+            Some(Motion::Path(PathMotion::line(
+                Vector { x: 90.0, y: 90.0 },
+                Vector { x: 270.0, y: 90.0 },
+            ))),
+            0,
+        );
+
+        orientation.position.x
+    }
+
+    #[test]
+    fn stale_distances_use_encoders() {
+        let mut localize = Localize::new(DRIFTED, 0, 0);
+        let motion = Some(Motion::Path(PathMotion::line(
+            Vector { x: 90.0, y: 90.0 },
+            Vector { x: 270.0, y: 90.0 },
+        )));
+
+        let (orientation, _) = localize.update(
+            &mouse_2019::MECH,
+            &MAZE,
+            &LOCALIZE,
+            0,
+            0,
+            None,
+            Some(DistanceReading::InRange(FRONT)),
+            None,
+            false,
+            motion,
+            0,
+        );
+        assert_close(orientation.position.x, 100.0);
+
+        let (orientation, _) = localize.update(
+            &mouse_2019::MECH,
+            &MAZE,
+            &LOCALIZE,
+            0,
+            0,
+            None,
+            Some(DistanceReading::InRange(FRONT)),
+            None,
+            true,
+            motion,
+            0,
+        );
+        assert_close(orientation.position.x, 90.0);
+    }
+
+    #[test]
+    fn full_trust() {
+        assert_close(localize_x(1.0), 90.0);
+    }
+
+    #[test]
+    fn partial_trust() {
+        assert_close(localize_x(0.5), 95.0);
+    }
+
+    #[test]
+    fn no_trust() {
+        assert_close(localize_x(0.0), 100.0);
+    }
+}
+
+#[cfg(test)]
+mod blind_trust_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{Localize, LocalizeConfig};
+    use crate::config::{mouse_2019, LOCALIZE, MAZE};
+    use crate::fast::motion_queue::Motion;
+    use crate::fast::path::PathMotion;
+    use crate::fast::{Orientation, Vector, DIRECTION_0};
+    use crate::mouse::DistanceReading;
+
+    const CONFIG: LocalizeConfig = LocalizeConfig {
+        wall_trust: 0.5,
+        blind_trust_growth: 0.1,
+        ..LOCALIZE
+    };
+
+    // What the front sensor reads when the mouse is really centered in the cell
+    const FRONT: f32 = 84.0 - mouse_2019::MECH.front_sensor_offset_x;
+
+    fn motion() -> Option<Motion> {
+        Some(Motion::Path(PathMotion::line(
+            Vector { x: 90.0, y: 90.0 },
+            Vector { x: 990.0, y: 90.0 },
+        )))
+    }
+
+    fn update(
+        localize: &mut Localize,
+        config: &LocalizeConfig,
+        mm: f32,
+        front: Option<f32>,
+    ) -> (f32, u32) {
+        let ticks = mouse_2019::MECH.mm_to_ticks(mm) as i32;
+        let (orientation, debug) = localize.update(
+            &mouse_2019::MECH,
+            &MAZE,
+            config,
+            ticks,
+            ticks,
+            None,
+            front.map(DistanceReading::InRange),
+            None,
+            true,
+            motion(),
+            0,
+        );
+
+        (orientation.position.x, debug.blind_cells)
+    }
+
+    // The encoders think the mouse is 10mm further along than it really is, in the middle of
+    // the cell at x = 3
+    fn corrected_x(config: &LocalizeConfig, blind_moves: u32) -> f32 {
+        let start = 640.0 - 180.0 * blind_moves as f32;
+        let mut localize = Localize::new(
+            Orientation {
+                position: Vector { x: start, y: 90.0 },
+                direction: DIRECTION_0,
+            },
+            0,
+            0,
+        );
+
+        update(&mut localize, config, 0.0, None);
+        for i in 1..=blind_moves {
+            let (_, blind_cells) = update(&mut localize, config, 180.0 * i as f32, None);
+            assert_eq!(blind_cells, i);
+        }
+
+        let (x, blind_cells) = update(
+            &mut localize,
+            config,
+            180.0 * blind_moves as f32,
+            Some(FRONT),
+        );
+        assert_eq!(blind_cells, 0);
+        x
+    }
+
+    #[test]
+    fn right_after_a_correction() {
+        assert!((corrected_x(&CONFIG, 0) - 635.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn after_a_blind_stretch() {
+        // Trusting the wall 0.5 + 3 * 0.1
+        assert!((corrected_x(&CONFIG, 3) - 632.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn blind_stretch_moves_more() {
+        let blind = corrected_x(&CONFIG, 3);
+        let fresh = corrected_x(&CONFIG, 0);
+        assert!((blind - 630.0).abs() < (fresh - 630.0).abs());
+    }
+
+    #[test]
+    fn trust_is_capped() {
+        let config = LocalizeConfig {
+            blind_trust_growth: 0.5,
+            ..CONFIG
+        };
+
+        assert!((corrected_x(&config, 3) - 630.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn no_growth() {
+        let config = LocalizeConfig {
+            blind_trust_growth: 0.0,
+            ..CONFIG
+        };
+
+        assert!((corrected_x(&config, 3) - 635.0).abs() < 0.1);
+    }
+}
+
+#[cfg(test)]
+mod sensor_latency_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{Localize, LocalizeConfig};
+    use crate::config::{mouse_2019, LOCALIZE, MAZE};
+    use crate::fast::motion_queue::Motion;
+    use crate::fast::path::PathMotion;
+    use crate::fast::{Orientation, Vector, DIRECTION_0};
+    use crate::mouse::DistanceReading;
+
+    const CONFIG: LocalizeConfig = LocalizeConfig {
+        wall_trust: 1.0,
+        ..LOCALIZE
+    };
+
+    const START: Orientation = Orientation {
+        position: Vector { x: 90.0, y: 90.0 },
+        direction: DIRECTION_0,
+    };
+
+    // How far the mouse moves east each update
+    const STEP_TICKS: i32 = 20;
+
+    /// What the front sensor reads with the center of the mouse at `x` in the first cell
+    fn front(x: f32) -> f32 {
+        MAZE.cell_width_x
+            - MAZE.wall_width / 2.0
+            - x
+            - mouse_2019::MECH.front_sensor_offset_x
+    }
+
+    /// Drive east for 5 updates without any readings, then get a reading taken 2 updates ago.
+    /// Gives the x where the mouse really is and the x it thinks it is at.
+    fn localize_x(sensor_latency: u8) -> (f32, f32) {
+        let config = LocalizeConfig {
+            sensor_latency,
+            ..CONFIG
+        };
+
+        let step = mouse_2019::MECH.ticks_to_mm(STEP_TICKS as f32);
+        let motion = Some(Motion::Path(PathMotion::line(
+            Vector { x: 90.0, y: 90.0 },
+            Vector { x: 990.0, y: 90.0 },
+        )));
+
+        let mut localize = Localize::new(START, 0, 0);
+
+        let update = |localize: &mut Localize, i: i32, front: Option<f32>| {
+            let (orientation, _) = localize.update(
+                &mouse_2019::MECH,
+                &MAZE,
+                &config,
+                i * STEP_TICKS,
+                i * STEP_TICKS,
+                None,
+                front.map(DistanceReading::InRange),
+                None,
+                true,
+                motion,
+                0,
+            );
+            orientation.position.x
+        };
+
+        for i in 1..5 {
+            update(&mut localize, i, None);
+        }
+
+        let x = update(&mut localize, 5, Some(front(90.0 + 3.0 * step)));
+        (90.0 + 5.0 * step, x)
+    }
+
+    #[test]
+    fn no_latency_pulls_back() {
+        let (real, x) = localize_x(0);
+        let step = mouse_2019::MECH.ticks_to_mm(STEP_TICKS as f32);
+        assert!((x - (real - 2.0 * step)).abs() < 0.001, "{} {}", real, x);
+    }
+
+    #[test]
+    fn latency_compensated() {
+        let (real, x) = localize_x(2);
+        assert!((x - real).abs() < 0.001, "{} {}", real, x);
+    }
+
+    #[test]
+    fn still_is_unchanged() {
+        // Not moving, so it doesn't matter how old the reading is
+        let mut localize = Localize::new(START, 0, 0);
+        let config = LocalizeConfig {
+            sensor_latency: 3,
+            ..CONFIG
+        };
+
+        let (orientation, _) = localize.update(
+            &mouse_2019::MECH,
+            &MAZE,
+            &config,
+            0,
+            0,
+            None,
+            Some(DistanceReading::InRange(front(95.0))),
+            None,
+            true,
+            Some(Motion::Path(PathMotion::line(
+                Vector { x: 90.0, y: 90.0 },
+                Vector { x: 990.0, y: 90.0 },
+            ))),
+            0,
+        );
+        assert_close(orientation.position.x, 95.0);
+    }
+}
+
+#[cfg(test)]
+mod heading_fusion_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{Localize, LocalizeConfig};
+    use crate::config::{mouse_2019, LOCALIZE, MAZE};
+    use crate::fast::motion_queue::Motion;
+    use crate::fast::path::PathMotion;
+    use crate::fast::{Direction, Orientation, Vector, DIRECTION_PI_2};
+    use crate::mouse::DistanceReading;
+
+    const CONFIG: LocalizeConfig = LocalizeConfig {
+        heading_fusion: 0.2,
+        heading_fusion_baseline: 20.0,
+        heading_fusion_max_correction: 0.01,
+        ..LOCALIZE
+    };
+
+    // How far the mouse moves north each update
+    const STEP_TICKS: i32 = 20;
+
+    // The encoders think the mouse is turned a little to the left of north
+    const BIAS: f32 = 0.05;
+
+    // The side sensors only have new readings every few updates
+    const FRESH_EVERY: i32 = 4;
+
+    fn motion() -> Option<Motion> {
+        Some(Motion::Path(PathMotion::line(
+            Vector { x: 90.0, y: 90.0 },
+            Vector { x: 90.0, y: 2000.0 },
+        )))
+    }
+
+    fn error(direction: Direction) -> f32 {
+        direction.centered_at(DIRECTION_PI_2) - f32::from(DIRECTION_PI_2)
+    }
+
+    fn update(
+        localize: &mut Localize,
+        config: &LocalizeConfig,
+        ticks: i32,
+        left: f32,
+        right: f32,
+        fresh: bool,
+    ) -> Orientation {
+        let (orientation, _) = localize.update(
+            &mouse_2019::MECH,
+            &MAZE,
+            config,
+            ticks,
+            ticks,
+            Some(DistanceReading::InRange(left)),
+            None,
+            Some(DistanceReading::InRange(right)),
+            fresh,
+            motion(),
+            0,
+        );
+        orientation
+    }
+
+    /// Drive straight north up a corridor with walls on both sides, and give the heading error
+    /// at the end
+    fn heading_error(config: &LocalizeConfig, updates: i32) -> f32 {
+        let start = Orientation {
+            position: Vector { x: 90.0, y: 90.0 },
+            direction: DIRECTION_PI_2 + BIAS,
+        };
+
+        let mut localize = Localize::new(start, 0, 0);
+        let mut orientation = start;
+
+        for i in 1..updates {
+            orientation = update(
+                &mut localize,
+                config,
+                i * STEP_TICKS,
+                40.0,
+                40.0,
+                i % FRESH_EVERY == 0,
+            );
+        }
+
+        error(orientation.direction)
+    }
+
+    #[test]
+    fn disabled_keeps_bias() {
+        let config = LocalizeConfig {
+            heading_fusion: 0.0,
+            ..CONFIG
+        };
+        assert_close(heading_error(&config, 200), BIAS);
+    }
+
+    #[test]
+    fn corrects_bias() {
+        let error = heading_error(&CONFIG, 200);
+        assert!(error.abs() < BIAS / 10.0, "{}", error);
+    }
+
+    #[test]
+    fn too_short() {
+        // Not far enough for the direction to be used at all
+        let config = LocalizeConfig {
+            heading_fusion_baseline: 1000.0,
+            ..CONFIG
+        };
+        assert_close(heading_error(&config, 200), BIAS);
+    }
+
+    #[test]
+    fn wall_snap_does_not_rotate_the_heading() {
+        let start = Orientation {
+            position: Vector { x: 90.0, y: 90.0 },
+            direction: DIRECTION_PI_2,
+        };
+
+        let mut localize = Localize::new(start, 0, 0);
+
+        let mut ticks = 0;
+        let mut drive = |localize: &mut Localize, left: f32, right: f32, fresh: bool| {
+            ticks += STEP_TICKS;
+            update(localize, &CONFIG, ticks, left, right, fresh)
+        };
+
+        // Go just short of the baseline, so the snap comes on the update that gets there
+        let step = mouse_2019::MECH.ticks_to_mm(STEP_TICKS as f32);
+        for _ in 0..(CONFIG.heading_fusion_baseline / step) as usize {
+            drive(&mut localize, 40.0, 40.0, false);
+        }
+
+        // The side walls put the mouse 10mm to the left all at once
+        let snapped = drive(&mut localize, 30.0, 50.0, true);
+        assert_close(snapped.position.x, 80.0);
+        assert_close(error(snapped.direction), 0.0);
+
+        let mut orientation = snapped;
+        for _ in 0..50 {
+            orientation = drive(&mut localize, 40.0, 40.0, false);
+        }
+
+        // Once past the baseline, the snap only gets one correction, and that is held to the
+        // most allowed
+        assert_close(
+            error(orientation.direction),
+            CONFIG.heading_fusion_max_correction,
+        );
+    }
+}
+
+#[cfg(test)]
+mod localize_filtered_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{Localize, LocalizeConfig, LocalizeDebug};
+    use crate::config::{mouse_2019, LOCALIZE, MAZE};
+    use crate::fast::motion_queue::Motion;
+    use crate::fast::path::PathMotion;
+    use crate::fast::{Orientation, Vector, DIRECTION_0};
+    use crate::mouse::DistanceReading;
+
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 90.0, y: 90.0 },
+        direction: DIRECTION_0,
+    };
+
+    const FRONT_OFFSET: f32 = mouse_2019::MECH.front_sensor_offset_x;
+
+    fn update(localize: &mut Localize, left: f32, right: f32) -> LocalizeDebug {
+        let (_, debug) = localize.update(
+            &mouse_2019::MECH,
+            &MAZE,
+            &LOCALIZE,
+            0,
+            0,
+            Some(DistanceReading::InRange(left)),
+            Some(DistanceReading::InRange(100.0)),
+            Some(DistanceReading::InRange(right)),
+            true,
+            Some(Motion::Path(PathMotion::line(
+                Vector { x: 90.0, y: 90.0 },
+                Vector { x: 270.0, y: 90.0 },
+            ))),
+            0,
+        );
+
+        debug
+    }
+
+    #[test]
+    fn first_reading_is_raw() {
+        let mut localize = Localize::new(ORIENTATION, 0, 0);
+        let debug = update(&mut localize, 30.0, 34.0);
+
+        assert_eq!(debug.filtered_left, Some(30.0));
+        assert_eq!(debug.filtered_right, Some(34.0));
+        assert_close(debug.filtered_front.unwrap(), 100.0);
+    }
+
+    #[test]
+    fn filtered_differs_from_raw() {
+        let mut localize = Localize::new(ORIENTATION, 0, 0);
+        update(&mut localize, 30.0, 34.0);
+        let debug = update(&mut localize, 32.0, 36.0);
+
+        assert_close(debug.filtered_left.unwrap(), 31.0);
+        assert_close(debug.filtered_right.unwrap(), 35.0);
+    }
+
+    fn update_front(localize: &mut Localize, alpha: f32, front: f32) -> LocalizeDebug {
+        let config = LocalizeConfig {
+            front_filter_alpha: alpha,
+            ..LOCALIZE
+        };
+
+        let (_, debug) = localize.update(
+            &mouse_2019::MECH,
+            &MAZE,
+            &config,
+            0,
+            0,
+            None,
+            Some(DistanceReading::InRange(front)),
+            None,
+            true,
+            Some(Motion::Path(PathMotion::line(
+                Vector { x: 90.0, y: 90.0 },
+                Vector { x: 270.0, y: 90.0 },
+            ))),
+            0,
+        );
+
+        debug
+    }
+
+    #[test]
+    fn front_alpha_one_is_raw() {
+        let mut localize = Localize::new(ORIENTATION, 0, 0);
+        update_front(&mut localize, 1.0, 60.0);
+        let debug = update_front(&mut localize, 1.0, 70.0);
+
+        assert_close(debug.filtered_front.unwrap(), 70.0);
+    }
+
+    #[test]
+    fn front_filtered_differs_from_raw() {
+        let mut localize = Localize::new(ORIENTATION, 0, 0);
+        update_front(&mut localize, 0.5, 60.0);
+        let debug = update_front(&mut localize, 0.5, 70.0);
+
+        assert_close(debug.filtered_front.unwrap(), 65.0);
+        assert_close(
+            debug.sensor.unwrap().front_distance.unwrap(),
+            65.0 + FRONT_OFFSET,
+        );
+    }
+
+    #[test]
+    fn front_starts_over_after_losing_the_wall() {
+        let mut localize = Localize::new(ORIENTATION, 0, 0);
+        update_front(&mut localize, 0.5, 60.0);
+        update_front(&mut localize, 0.5, 500.0);
+        let debug = update_front(&mut localize, 0.5, 70.0);
+
+        assert_close(debug.filtered_front.unwrap(), 70.0);
+    }
+
+    #[test]
+    fn no_path_is_none() {
+        let mut localize = Localize::new(ORIENTATION, 0, 0);
+        let (_, debug) = localize.update(
+            &mouse_2019::MECH,
+            &MAZE,
+            &LOCALIZE,
+            0,
+            0,
+            Some(DistanceReading::InRange(30.0)),
+            Some(DistanceReading::InRange(100.0)),
+            Some(DistanceReading::InRange(34.0)),
+            true,
+            None,
+            0,
+        );
+
+        assert_eq!(debug.filtered_left, None);
+        assert_eq!(debug.filtered_front, None);
+        assert_eq!(debug.filtered_right, None);
+    }
+}
+
+#[cfg(test)]
+mod encoder_sign_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::Localize;
+    use crate::config::{mouse_2019, MechanicalConfig, LOCALIZE, MAZE};
+    use crate::fast::{Orientation, Vector, DIRECTION_0};
+
+    const START: Orientation = Orientation {
+        position: Vector { x: 90.0, y: 90.0 },
+        direction: DIRECTION_0,
+    };
+
+    fn moved(mech: &MechanicalConfig, left: i32, right: i32) -> Orientation {
+        let mut localize = Localize::new(START, 0, 0);
+        let (orientation, _) = localize.update(
+            mech, &MAZE, &LOCALIZE, left, right, None, None, None, true, None, 0,
+        );
+        orientation
+    }
+
+    #[test]
+    fn forward() {
+        let orientation = moved(&mouse_2019::MECH, 100, 100);
+        assert!(orientation.position.x > START.position.x);
+    }
+
+    #[test]
+    fn both_reversed_goes_backward() {
+        let mech = MechanicalConfig {
+            left_encoder_sign: -1,
+            right_encoder_sign: -1,
+            ..mouse_2019::MECH
+        };
+
+        let forward = moved(&mouse_2019::MECH, 100, 100);
+        let backward = moved(&mech, 100, 100);
+
+        assert_close(
+            backward.position.x - START.position.x,
+            START.position.x - forward.position.x,
+        );
+        assert_close(backward.position.y, START.position.y);
+    }
+
+    #[test]
+    fn one_reversed_turns() {
+        // With the left encoder reversed, both counting up is the left wheel going backwards
+        let mech = MechanicalConfig {
+            left_encoder_sign: -1,
+            ..mouse_2019::MECH
+        };
+
+        let turned = moved(&mech, 100, 100);
+        let expected = moved(&mouse_2019::MECH, -100, 100);
+
+        assert_close_orientation(turned, expected);
+        assert!(f32::from(turned.direction) > 0.0);
+    }
+}
+
+#[cfg(test)]
+mod extra_sensor_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{Localize, LocalizeDebug};
+    use crate::config::{
+        mouse_2019, DistanceSensorConfig, MechanicalConfig, SensorFacing, FRONT_SENSOR,
+        LEFT_SENSOR, LOCALIZE, MAZE, RIGHT_SENSOR,
+    };
+    use crate::fast::motion_queue::Motion;
+    use crate::fast::path::PathMotion;
+    use crate::fast::{Direction, Orientation, Vector, DIRECTION_0};
+    use crate::mouse::{DistanceReading, DistanceReadings};
+    use core::f32::consts::FRAC_PI_4;
+
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 90.0, y: 90.0 },
+        direction: DIRECTION_0,
+    };
+
+    // How far a diagonal sensor is from the center, both forward and to the side
+    const DIAGONAL_OFFSET: f32 = 20.0;
+
+    /// The 2019 mouse with a diagonal sensor on each side
+    fn mech() -> MechanicalConfig {
+        let diagonal = |direction: f32, side: f32| {
+            Some(DistanceSensorConfig {
+                mount: Orientation {
+                    position: Vector {
+                        x: DIAGONAL_OFFSET,
+                        y: side * DIAGONAL_OFFSET,
+                    },
+                    direction: Direction::from(direction),
+                },
+                limit: 150.0,
+            })
+        };
+
+        MechanicalConfig {
+            extra_sensors: [diagonal(FRAC_PI_4, 1.0), diagonal(-FRAC_PI_4, -1.0)],
+            ..mouse_2019::MECH
+        }
+    }
+
+    /// What a diagonal sensor reads when the wall beside it is `wall` from the center
+    fn diagonal_reading(wall: f32) -> Option<DistanceReading> {
+        Some(DistanceReading::InRange(
+            (wall - DIAGONAL_OFFSET) / FRAC_PI_4.sin(),
+        ))
+    }
+
+    /// Readings with the left and right walls `left` and `right` from the center of the mouse,
+    /// as seen by the straight and diagonal sensors
+    fn readings(
+        left: f32,
+        right: f32,
+        left_diagonal: f32,
+        right_diagonal: f32,
+    ) -> DistanceReadings {
+        let mut readings = [None; 5];
+        readings[LEFT_SENSOR] = Some(DistanceReading::InRange(
+            left - mouse_2019::MECH.left_sensor_offset_y,
+        ));
+        readings[FRONT_SENSOR] = Some(DistanceReading::OutOfRange);
+        readings[RIGHT_SENSOR] = Some(DistanceReading::InRange(
+            right - mouse_2019::MECH.right_sensor_offset_y,
+        ));
+        readings[3] = diagonal_reading(left_diagonal);
+        readings[4] = diagonal_reading(right_diagonal);
+        readings
+    }
+
+    fn update(readings: DistanceReadings) -> (Orientation, LocalizeDebug) {
+        let mut localize = Localize::new(ORIENTATION, 0, 0);
+        localize.update_sensors(
+            &mech(),
+            &MAZE,
+            &LOCALIZE,
+            0,
+            0,
+            readings,
+            true,
+            Some(Motion::Path(PathMotion::line(
+                Vector { x: 90.0, y: 90.0 },
+                Vector { x: 270.0, y: 90.0 },
+            ))),
+            0,
+        )
+    }
+
+    #[test]
+    fn facing() {
+        let sensors = mech().distance_sensors();
+        let facings: [SensorFacing; 5] = [
+            sensors[0].unwrap().facing(),
+            sensors[1].unwrap().facing(),
+            sensors[2].unwrap().facing(),
+            sensors[3].unwrap().facing(),
+            sensors[4].unwrap().facing(),
+        ];
+
+        assert_eq!(
+            facings,
+            [
+                SensorFacing::Left,
+                SensorFacing::Front,
+                SensorFacing::Right,
+                SensorFacing::Left,
+                SensorFacing::Right,
+            ]
+        );
+    }
+
+    #[test]
+    fn all_readings_are_projected() {
+        let (_, debug) = update(readings(84.0, 84.0, 84.0, 84.0));
+
+        // The diagonal readings lose a little going through sin and back
+        for &i in [LEFT_SENSOR, RIGHT_SENSOR, 3, 4].iter() {
+            assert!((debug.projected[i].unwrap() - 84.0).abs() < 0.001);
+        }
+
+        // The front sensor does not see a wall
+        assert_eq!(debug.projected[FRONT_SENSOR], None);
+    }
+
+    #[test]
+    fn readings_on_the_same_side_are_fused() {
+        let (orientation, debug) = update(readings(80.0, 88.0, 76.0, 90.0));
+        let sensor = debug.sensor.unwrap();
+
+        assert!((sensor.left_distance.unwrap() - 78.0).abs() < 0.001);
+        assert!((sensor.right_distance.unwrap() - 89.0).abs() < 0.001);
+
+        // The walls say the mouse is 5.5mm to the left of center
+        assert!((orientation.position.y - 95.5).abs() < 0.001);
+    }
+
+    #[test]
+    fn three_sensors_are_unchanged() {
+        let mut three = readings(80.0, 88.0, 0.0, 0.0);
+        three[3] = None;
+        three[4] = None;
+
+        let mut localize = Localize::new(ORIENTATION, 0, 0);
+        let (orientation, _) = localize.update(
+            &mouse_2019::MECH,
+            &MAZE,
+            &LOCALIZE,
+            0,
+            0,
+            three[LEFT_SENSOR],
+            three[FRONT_SENSOR],
+            three[RIGHT_SENSOR],
+            true,
+            Some(Motion::Path(PathMotion::line(
+                Vector { x: 90.0, y: 90.0 },
+                Vector { x: 270.0, y: 90.0 },
+            ))),
+            0,
+        );
+
+        assert_close(orientation.position.y, 94.0);
+    }
+}
@@ -9,6 +9,7 @@ pub mod motion_queue;
 pub mod motor_control;
 pub mod path;
 pub mod turn;
+pub mod wall_follow;
 
 use core::f32::consts::{FRAC_PI_4, PI};
 
@@ -19,6 +20,7 @@ use libm::F32Ext;
 use crate::config::MechanicalConfig;
 use crate::slow::maze::MazeConfig;
 use crate::slow::{MazeDirection, MazeOrientation, MazePosition};
+use crate::units;
 
 /// A 2d vector
 #[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
@@ -63,6 +65,11 @@ impl Vector {
         }
     }
 
+    /// Whether `other` is within `tolerance` of this vector in both x and y
+    pub fn approx_eq(&self, other: Vector, tolerance: f32) -> bool {
+        (self.x - other.x).abs() <= tolerance && (self.y - other.y).abs() <= tolerance
+    }
+
     pub fn offset_x(&self, offset: f32) -> Vector {
         Vector {
             x: self.x + offset,
@@ -76,6 +83,54 @@ impl Vector {
             y: self.y + offset,
         }
     }
+
+    /// Convert a vector in cm to mm, see [crate::units]
+    pub fn cm_to_mm(&self) -> Vector {
+        Vector {
+            x: units::cm_to_mm(self.x),
+            y: units::cm_to_mm(self.y),
+        }
+    }
+
+    /// Convert a vector in mm to cm
+    pub fn mm_to_cm(&self) -> Vector {
+        Vector {
+            x: units::mm_to_cm(self.x),
+            y: units::mm_to_cm(self.y),
+        }
+    }
+
+    /// Convert a vector in m to mm
+    pub fn m_to_mm(&self) -> Vector {
+        Vector {
+            x: units::m_to_mm(self.x),
+            y: units::m_to_mm(self.y),
+        }
+    }
+
+    /// Convert a vector in mm to m
+    pub fn mm_to_m(&self) -> Vector {
+        Vector {
+            x: units::mm_to_m(self.x),
+            y: units::mm_to_m(self.y),
+        }
+    }
+
+    /// Convert a vector in cells, where (0.5, 0.5) is the middle of the first cell, to mm
+    pub fn cells_to_mm(&self, maze_config: &MazeConfig) -> Vector {
+        Vector {
+            x: self.x * maze_config.cell_width_x,
+            y: self.y * maze_config.cell_width_y,
+        }
+    }
+
+    /// Convert a vector in mm to cells
+    pub fn mm_to_cells(&self, maze_config: &MazeConfig) -> Vector {
+        Vector {
+            x: self.x / maze_config.cell_width_x,
+            y: self.y / maze_config.cell_width_y,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -88,6 +143,8 @@ mod vector_tests {
 
     use super::Vector;
     use super::{DIRECTION_0, DIRECTION_PI_2};
+    use crate::config::MAZE;
+    use crate::slow::maze::MazeConfig;
 
     #[test]
     fn vector_magnitude_test() {
@@ -147,6 +204,14 @@ mod vector_tests {
         )
     }
 
+    #[test]
+    fn vector_approx_eq() {
+        let v = Vector { x: 30.0, y: 32.0 };
+        assert!(v.approx_eq(Vector { x: 30.05, y: 31.95 }, 0.1));
+        assert!(!v.approx_eq(Vector { x: 30.2, y: 32.0 }, 0.1));
+        assert!(!v.approx_eq(Vector { x: 30.0, y: 31.8 }, 0.1));
+    }
+
     #[test]
     fn vector_rotated2() {
         assert_close2(
@@ -154,6 +219,39 @@ mod vector_tests {
             Vector { x: 30.0, y: 32.0 },
         )
     }
+
+    #[test]
+    fn vector_cm() {
+        let cm = Vector { x: 9.0, y: -27.0 };
+        assert_close2(cm.cm_to_mm(), Vector { x: 90.0, y: -270.0 });
+        assert_close2(cm.cm_to_mm().mm_to_cm(), cm);
+    }
+
+    #[test]
+    fn vector_m() {
+        let m = Vector { x: 0.09, y: -0.27 };
+        assert_close2(m.m_to_mm(), Vector { x: 90.0, y: -270.0 });
+        assert_close2(m.m_to_mm().mm_to_m(), m);
+    }
+
+    #[test]
+    fn vector_cells() {
+        let cells = Vector { x: 0.5, y: 2.5 };
+        assert_close2(cells.cells_to_mm(&MAZE), Vector { x: 90.0, y: 450.0 });
+        assert_close2(cells.cells_to_mm(&MAZE).mm_to_cells(&MAZE), cells);
+    }
+
+    #[test]
+    fn vector_cells_non_square() {
+        let maze = MazeConfig {
+            cell_width_x: 200.0,
+            cell_width_y: 160.0,
+            ..MAZE
+        };
+        let cells = Vector { x: 0.5, y: 2.5 };
+        assert_close2(cells.cells_to_mm(&maze), Vector { x: 100.0, y: 400.0 });
+        assert_close2(cells.cells_to_mm(&maze).mm_to_cells(&maze), cells);
+    }
 }
 
 impl core::ops::Sub for Vector {
@@ -351,6 +449,18 @@ impl Orientation {
         }
     }
 
+    /// Whether `other` is within `position_tolerance` mm and `direction_tolerance` radians of
+    /// this orientation. Directions on either side of 0 are compared the short way around.
+    pub fn approx_eq(
+        &self,
+        other: Orientation,
+        position_tolerance: f32,
+        direction_tolerance: f32,
+    ) -> bool {
+        self.position.approx_eq(other.position, position_tolerance)
+            && self.direction.within(other.direction, direction_tolerance)
+    }
+
     pub fn offset(self, offset: Orientation) -> Orientation {
         Orientation {
             position: self.position + offset.position.rotated(self.direction),
@@ -371,8 +481,8 @@ impl Orientation {
 
         MazeOrientation {
             position: MazePosition {
-                x: (self.position.x / maze_config.cell_width) as usize,
-                y: (self.position.y / maze_config.cell_width) as usize,
+                x: (self.position.x / maze_config.cell_width_x) as usize,
+                y: (self.position.y / maze_config.cell_width_y) as usize,
             },
             direction: maze_direction,
         }
@@ -384,7 +494,9 @@ mod orientation_tests {
     #[allow(unused_imports)]
     use crate::test::*;
 
-    use super::{Orientation, Vector, DIRECTION_0, DIRECTION_PI_2};
+    use core::f32::consts::PI;
+
+    use super::{Direction, Orientation, Vector, DIRECTION_0, DIRECTION_PI_2};
 
     #[test]
     fn offset() {
@@ -400,11 +512,13 @@ mod orientation_tests {
 
         let result_orientation = orientation.offset(offset_orientation);
 
-        assert_close2(result_orientation.position, Vector { x: 0.5, y: 0.5 });
-        assert_close(
-            f32::from(result_orientation.direction),
-            f32::from(DIRECTION_PI_2 + DIRECTION_PI_2 / 2.0),
-        )
+        assert_close_orientation(
+            result_orientation,
+            Orientation {
+                position: Vector { x: 0.5, y: 0.5 },
+                direction: DIRECTION_PI_2 + DIRECTION_PI_2 / 2.0,
+            },
+        );
     }
 
     #[test]
@@ -424,16 +538,59 @@ mod orientation_tests {
 
         let result_orientation = orientation.offset(offset_orientation);
 
-        assert_close2(
-            result_orientation.position,
-            Vector {
-                x: 1260.0 + 30.0,
-                y: 1170.0 + 32.0,
+        assert_close_orientation(
+            result_orientation,
+            Orientation {
+                position: Vector {
+                    x: 1260.0 + 30.0,
+                    y: 1170.0 + 32.0,
+                },
+                direction: DIRECTION_PI_2,
             },
         );
-        assert_close(
-            f32::from(result_orientation.direction),
-            f32::from(DIRECTION_PI_2),
-        )
+    }
+
+    #[test]
+    fn approx_eq_wraps_around() {
+        let a = Orientation {
+            position: Vector { x: 90.0, y: 90.0 },
+            direction: Direction::from(0.1),
+        };
+        let b = Orientation {
+            position: Vector { x: 90.0, y: 90.0 },
+            direction: Direction::from(0.1 + 2.0 * PI),
+        };
+
+        assert!(a.approx_eq(b, 0.01, 0.001));
+    }
+
+    #[test]
+    fn approx_eq_across_zero() {
+        let a = Orientation {
+            position: Vector { x: 90.0, y: 90.0 },
+            direction: Direction::from(0.01),
+        };
+        let b = Orientation {
+            position: Vector { x: 90.0, y: 90.0 },
+            direction: Direction::from(-0.01),
+        };
+
+        assert!(a.approx_eq(b, 0.01, 0.05));
+        assert!(!a.approx_eq(b, 0.01, 0.01));
+    }
+
+    #[test]
+    fn approx_eq_position_tolerance() {
+        let a = Orientation {
+            position: Vector { x: 90.0, y: 90.0 },
+            direction: DIRECTION_0,
+        };
+        let b = Orientation {
+            position: Vector { x: 90.4, y: 89.6 },
+            direction: DIRECTION_0,
+        };
+
+        assert!(a.approx_eq(b, 0.5, 0.001));
+        assert!(!a.approx_eq(b, 0.3, 0.001));
     }
 }
@@ -1,11 +1,25 @@
 use serde::{Deserialize, Serialize};
 
+use libm::F32Ext;
+
 use crate::config::MechanicalConfig;
+use crate::fast::curve::clamp;
 use crate::fast::motion_queue::Motion;
-use crate::fast::motor_control::{MotorControl, MotorControlConfig, MotorControlDebug};
-use crate::fast::path::{PathHandler, PathHandlerConfig, PathHandlerDebug, PathMotion};
+use crate::fast::motor_control::{
+    MotorControl, MotorControlConfig, MotorControlDebug, PidfConfig,
+};
+use crate::fast::path::{
+    curvature_to_left_right, PathHandler, PathHandlerConfig, PathHandlerDebug, PathMotion,
+};
 use crate::fast::turn::{TurnHandler, TurnHandlerConfig, TurnHandlerDebug};
-use crate::fast::{Direction, Orientation};
+use crate::fast::wall_follow::{WallFollow, WallFollowConfig};
+use crate::fast::{Direction, Orientation, Vector};
+use crate::slow::maze::MazeConfig;
+use crate::units::Millis;
+
+/// Path motions with less curvature than this where the mouse is, in 1/mm, are straight enough
+/// to center between the side walls
+const STRAIGHT_CURVATURE: f32 = 0.0001;
 
 pub enum MotionHandler {
     Turn(TurnHandler),
@@ -22,6 +36,456 @@ pub enum MotionHandlerDebug {
 pub struct MotionControlDebug {
     pub handler: Option<MotionHandlerDebug>,
     pub motor_control: MotorControlDebug,
+
+    /// The lateral (centripetal) acceleration in mm/ms^2, from the measured velocity and the
+    /// commanded curvature
+    pub lateral_acceleration: f32,
+
+    /// The curvature given to the motors after limiting how fast it can change
+    pub commanded_curvature: f32,
+
+    /// The curvature added to center between the side walls
+    pub wall_centering_curvature: f32,
+
+    /// Whether the motion queue ran out while more motions were coming, so the mouse is going
+    /// straight at [MotionControlConfig::coast_velocity] instead of stopping
+    pub coasting: bool,
+
+    /// Whether the planner couldn't plan anything, so the mouse is following the walls with
+    /// [MotionControlConfig::wall_follow] instead of stopping
+    pub wall_following: bool,
+}
+
+/// Find the curvature that the left and right wheel velocities will drive
+pub fn left_right_to_curvature(mech: &MechanicalConfig, left: f32, right: f32) -> f32 {
+    let velocity = (left + right) / 2.0;
+
+    if velocity == 0.0 {
+        0.0
+    } else {
+        (right - left) / (velocity * mech.wheelbase)
+    }
+}
+
+/// The lateral acceleration of going `velocity` along a path with `curvature`
+pub fn lateral_acceleration(velocity: f32, curvature: f32) -> f32 {
+    velocity * velocity * curvature
+}
+
+#[cfg(test)]
+mod lateral_acceleration_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{lateral_acceleration, left_right_to_curvature};
+    use crate::config::MechanicalConfig;
+
+    const MECH: MechanicalConfig = crate::config::mouse_2019::MECH;
+
+    #[test]
+    fn straight() {
+        assert_close(lateral_acceleration(0.5, 0.0), 0.0);
+    }
+
+    #[test]
+    fn circle() {
+        assert_close(lateral_acceleration(0.5, 1.0 / 90.0), 0.25 / 90.0);
+    }
+
+    #[test]
+    fn curvature_from_left_right() {
+        // From curvature_to_left_right with a velocity of 0.5 and a curvature of 1/90
+        assert_close(
+            left_right_to_curvature(&MECH, 0.294444, 0.705556),
+            1.0 / 90.0,
+        );
+    }
+
+    #[test]
+    fn curvature_from_left_right_in_place() {
+        assert_close(left_right_to_curvature(&MECH, -0.5, 0.5), 0.0);
+    }
+}
+
+/// Limit the forward velocity of the left and right wheel velocities to between `min` and `max`,
+/// keeping the same curvature
+pub fn clamp_velocity(
+    mech: &MechanicalConfig,
+    left: f32,
+    right: f32,
+    min: f32,
+    max: f32,
+) -> (f32, f32) {
+    let velocity = (left + right) / 2.0;
+    let clamped = clamp(velocity, min, max);
+
+    if clamped == velocity {
+        (left, right)
+    } else {
+        curvature_to_left_right(mech, clamped, left_right_to_curvature(mech, left, right))
+    }
+}
+
+#[cfg(test)]
+mod clamp_velocity_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{clamp_velocity, left_right_to_curvature};
+    use crate::config::MechanicalConfig;
+
+    const MECH: MechanicalConfig = crate::config::mouse_2019::MECH;
+
+    #[test]
+    fn within() {
+        let (left, right) = clamp_velocity(&MECH, 0.4, 0.6, 0.1, 1.0);
+        assert_close(left, 0.4);
+        assert_close(right, 0.6);
+    }
+
+    #[test]
+    fn below_min() {
+        let (left, right) = clamp_velocity(&MECH, 0.0, 0.0, 0.1, 1.0);
+        assert_close(left, 0.1);
+        assert_close(right, 0.1);
+    }
+
+    #[test]
+    fn above_max_keeps_curvature() {
+        let (left, right) = clamp_velocity(&MECH, 1.5, 2.5, 0.1, 1.0);
+        assert_close((left + right) / 2.0, 1.0);
+        assert_close(
+            left_right_to_curvature(&MECH, left, right),
+            left_right_to_curvature(&MECH, 1.5, 2.5),
+        );
+    }
+}
+
+/// The left and right wheel velocities in mm/ms to move by `delta_position` and turn by
+/// `delta_heading` radians over `delta_time`. `delta_position` is relative to the mouse, with x
+/// forward and y to the left. The mouse can't move sideways, so it goes along the direction
+/// halfway through the turn, the same as [Orientation::update_from_encoders] assumes, and any
+/// movement across that is ignored.
+pub fn wheel_velocities_for(
+    mech: &MechanicalConfig,
+    delta_position: Vector,
+    delta_heading: f32,
+    delta_time: Millis,
+) -> (f32, f32) {
+    if delta_time == 0 {
+        return (0.0, 0.0);
+    }
+
+    let mid_heading = delta_heading / 2.0;
+    let delta_linear = delta_position.x * F32Ext::cos(mid_heading)
+        + delta_position.y * F32Ext::sin(mid_heading);
+    let delta_angular = mech.rads_to_mm(delta_heading);
+
+    (
+        (delta_linear - delta_angular) / delta_time as f32,
+        (delta_linear + delta_angular) / delta_time as f32,
+    )
+}
+
+/// The left and right powers, as a fraction of full power from -1 to 1, to move by
+/// `delta_position` and turn by `delta_heading` over `delta_time`, from the motor model in
+/// `mech`. See [wheel_velocities_for] and [MechanicalConfig::power_for_wheel_velocity].
+pub fn inverse_kinematics(
+    mech: &MechanicalConfig,
+    delta_position: Vector,
+    delta_heading: f32,
+    delta_time: Millis,
+) -> (f32, f32) {
+    let (left, right) =
+        wheel_velocities_for(mech, delta_position, delta_heading, delta_time);
+    (
+        mech.power_for_wheel_velocity(left),
+        mech.power_for_wheel_velocity(right),
+    )
+}
+
+#[cfg(test)]
+mod inverse_kinematics_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use libm::F32Ext;
+
+    use super::{inverse_kinematics, wheel_velocities_for};
+    use crate::config::{mouse_2019, MechanicalConfig};
+    use crate::fast::{Orientation, Vector, DIRECTION_0};
+
+    const MECH: MechanicalConfig = MechanicalConfig {
+        static_friction_power: 0.1,
+        max_wheel_velocity: 2.0,
+        ..mouse_2019::MECH
+    };
+
+    const NOWHERE: Vector = Vector { x: 0.0, y: 0.0 };
+
+    #[test]
+    fn rotation_is_opposite() {
+        let (left, right) = inverse_kinematics(&MECH, NOWHERE, 0.1, 10);
+        assert!(left < 0.0 && right > 0.0, "{} {}", left, right);
+        assert_close(left, -right);
+    }
+
+    #[test]
+    fn clockwise_rotation_is_opposite() {
+        let (left, right) = inverse_kinematics(&MECH, NOWHERE, -0.1, 10);
+        assert!(left > 0.0 && right < 0.0, "{} {}", left, right);
+    }
+
+    #[test]
+    fn translation_is_equal() {
+        let (left, right) = inverse_kinematics(&MECH, Vector { x: 5.0, y: 0.0 }, 0.0, 10);
+        assert!(left > 0.0);
+        assert_close(left, right);
+    }
+
+    #[test]
+    fn backward_is_negative() {
+        let (left, right) =
+            inverse_kinematics(&MECH, Vector { x: -5.0, y: 0.0 }, 0.0, 10);
+        assert!(left < 0.0);
+        assert_close(left, right);
+    }
+
+    #[test]
+    fn sideways_is_ignored() {
+        let (left, right) = inverse_kinematics(&MECH, Vector { x: 0.0, y: 5.0 }, 0.0, 10);
+        assert_close(left, 0.0);
+        assert_close(right, 0.0);
+    }
+
+    #[test]
+    fn no_time() {
+        let (left, right) = inverse_kinematics(&MECH, Vector { x: 5.0, y: 0.0 }, 0.1, 0);
+        assert_close(left, 0.0);
+        assert_close(right, 0.0);
+    }
+
+    #[test]
+    fn round_trip() {
+        // Forward kinematics of the wheel velocities should end up where it was asked to go
+        let delta_heading = 0.2;
+        let delta_position = Vector {
+            x: 5.0 * F32Ext::cos(delta_heading / 2.0),
+            y: 5.0 * F32Ext::sin(delta_heading / 2.0),
+        };
+
+        let (left, right) =
+            wheel_velocities_for(&MECH, delta_position, delta_heading, 10);
+
+        let start = Orientation {
+            position: NOWHERE,
+            direction: DIRECTION_0,
+        };
+        let end = start.update_from_encoders(
+            &MECH,
+            MECH.mm_to_ticks(left * 10.0) as i32,
+            MECH.mm_to_ticks(right * 10.0) as i32,
+        );
+
+        assert!(
+            (end.position - delta_position).magnitude() < 0.1,
+            "{:?}",
+            end.position
+        );
+        assert!((f32::from(end.direction) - delta_heading).abs() < 0.01);
+    }
+}
+
+/// How far to the right of the center between the side walls the mouse is, in mm, from the
+/// left and right distance readings. None unless there is a wall right next to the mouse on
+/// both sides, closer than a cell width from the center of the mouse. If the cells aren't
+/// square, the narrower width is used, since which way is across depends on the heading.
+pub fn side_wall_offset(
+    mech: &MechanicalConfig,
+    maze_config: &MazeConfig,
+    left: Option<f32>,
+    right: Option<f32>,
+) -> Option<f32> {
+    let left = left? + mech.left_sensor_offset_y;
+    let right = right? + mech.right_sensor_offset_y;
+
+    let cell_width = maze_config.cell_width_x.min(maze_config.cell_width_y);
+    if left < cell_width && right < cell_width {
+        Some((left - right) / 2.0)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod side_wall_offset_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::side_wall_offset;
+    use crate::config::{mouse_2019, MAZE};
+
+    const MECH: crate::config::MechanicalConfig = mouse_2019::MECH;
+
+    #[test]
+    fn centered() {
+        let offset = side_wall_offset(&MECH, &MAZE, Some(52.0), Some(52.0));
+        assert_close(offset.unwrap(), 0.0);
+    }
+
+    #[test]
+    fn right_of_center() {
+        let offset = side_wall_offset(&MECH, &MAZE, Some(62.0), Some(42.0));
+        assert_close(offset.unwrap(), 10.0);
+    }
+
+    #[test]
+    fn missing_wall() {
+        assert_eq!(side_wall_offset(&MECH, &MAZE, None, Some(52.0)), None);
+        assert_eq!(side_wall_offset(&MECH, &MAZE, Some(52.0), None), None);
+    }
+
+    #[test]
+    fn far_wall() {
+        // Seeing through an opening to the wall of the next cell over
+        assert_eq!(
+            side_wall_offset(&MECH, &MAZE, Some(232.0), Some(52.0)),
+            None
+        );
+    }
+}
+
+/// Move `last` towards `target`, changing by at most `rate` per ms
+pub fn slew_curvature(last: f32, target: f32, rate: f32, delta_time: Millis) -> f32 {
+    if rate <= 0.0 {
+        target
+    } else {
+        let max_change = rate * delta_time as f32;
+        clamp(target, last - max_change, last + max_change)
+    }
+}
+
+#[cfg(test)]
+mod slew_curvature_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::slew_curvature;
+
+    #[test]
+    fn disabled() {
+        assert_close(slew_curvature(0.0, 0.1, 0.0, 10), 0.1);
+    }
+
+    #[test]
+    fn small_change() {
+        assert_close(slew_curvature(0.0, 0.001, 0.001, 10), 0.001);
+    }
+
+    #[test]
+    fn step_is_rate_limited() {
+        let rate = 0.0001;
+        let target = 1.0 / 90.0;
+
+        let mut last = 0.0;
+        for _ in 0..100 {
+            let curvature = slew_curvature(last, target, rate, 10);
+            assert!((curvature - last).abs() <= rate * 10.0 + 0.0000001);
+            assert!(curvature <= target);
+            last = curvature;
+        }
+
+        assert_close(last, target);
+    }
+
+    #[test]
+    fn negative_step_is_rate_limited() {
+        assert_close(slew_curvature(0.0, -0.1, 0.0001, 10), -0.001);
+    }
+}
+
+/// Move `last` towards `target` with a first order filter with a time constant of
+/// `time_constant` ms. A time constant of 0 disables the filter.
+pub fn smooth_power(
+    last: f32,
+    target: f32,
+    time_constant: f32,
+    delta_time: Millis,
+) -> f32 {
+    if time_constant <= 0.0 {
+        target
+    } else {
+        let delta_time = delta_time as f32;
+        last + (target - last) * delta_time / (time_constant + delta_time)
+    }
+}
+
+#[cfg(test)]
+mod smooth_power_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::smooth_power;
+
+    #[test]
+    fn disabled() {
+        assert_close(smooth_power(0.0, 1000.0, 0.0, 10), 1000.0);
+    }
+
+    #[test]
+    fn step_has_bounded_slope() {
+        let time_constant = 40.0;
+        let target = 1000.0;
+
+        // Each step covers at most this fraction of the way to the target
+        let max_change = target * 10.0 / (time_constant + 10.0);
+
+        let mut last = 0.0;
+        for _ in 0..100 {
+            let power = smooth_power(last, target, time_constant, 10);
+            assert!(power - last <= max_change);
+            assert!(power >= last);
+            assert!(power <= target);
+            last = power;
+        }
+
+        assert!(target - last < 1.0);
+    }
+
+    #[test]
+    fn one_time_constant() {
+        // After one time constant in small steps, about 63% of the way there
+        let mut last = 0.0;
+        for _ in 0..100 {
+            last = smooth_power(last, 1.0, 100.0, 1);
+        }
+
+        assert!((last - 0.63).abs() < 0.01);
+    }
+}
+
+/// How many points there can be in [MotionControlConfig::gain_schedule]
+pub const GAIN_SCHEDULE_SIZE: usize = 4;
+
+/// The wheel velocity gains to use at one forward velocity. See
+/// [MotionControlConfig::gain_schedule]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GainSchedulePoint {
+    /// The forward velocity in mm/ms that these gains are for
+    pub velocity: f32,
+    pub left_pidf: PidfConfig,
+    pub right_pidf: PidfConfig,
+}
+
+/// The gains `fraction` of the way from `a` to `b`
+fn lerp_pidf(a: PidfConfig, b: PidfConfig, fraction: f32) -> PidfConfig {
+    let lerp = |a: f32, b: f32| a + (b - a) * fraction;
+    PidfConfig {
+        p: lerp(a.p, b.p),
+        i: lerp(a.i, b.i),
+        d: lerp(a.d, b.d),
+        f: lerp(a.f, b.f),
+    }
 }
 
 #[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
@@ -30,18 +494,225 @@ pub struct MotionControlConfig {
     pub path: PathHandlerConfig,
     pub motor_control: MotorControlConfig,
     pub stop_distance: f32,
+
+    /// The slowest forward velocity in mm/ms to follow a path at. While stopping, anything
+    /// slower than this is stopped outright instead of raised.
+    pub min_velocity: f32,
+
+    /// The fastest forward velocity in mm/ms to follow a path at. 0 disables the limit.
+    pub max_velocity: f32,
+
+    /// How fast the commanded curvature can change while following a path, in 1/mm per ms.
+    /// 0 disables the limit.
+    pub curvature_slew_rate: f32,
+
+    /// The time constant in ms of the filter on the motor powers, to keep steps in power off of
+    /// the drivetrain. 0 disables the filter.
+    pub power_time_constant: f32,
+
+    /// The fastest the mouse can slow down, in mm/ms^2
+    pub max_acceleration: f32,
+
+    /// How many updates to keep a finished path motion around for, in case the mouse gets
+    /// moved back onto it. 0 disables picking it back up.
+    pub look_behind: u8,
+
+    /// How much to curve, in 1/mm, for each mm off center between the side walls while
+    /// following a straight path motion. 0 disables centering.
+    pub wall_centering_p: f32,
+
+    /// How much to go by the wall centering instead of the path while centering between the
+    /// side walls, from 0 to 1. The two curvatures are blended, so 0 only follows the path and 1
+    /// only centers between the walls. Without walls on both sides, only the path is followed.
+    pub wall_centering_weight: f32,
+
+    /// Wheel velocity gains to use instead of the ones in `motor_control` at different forward
+    /// velocities, in order from slowest to fastest. See
+    /// [MotionControlConfig::scheduled_motor_control]. All None disables scheduling.
+    pub gain_schedule: [Option<GainSchedulePoint>; GAIN_SCHEDULE_SIZE],
+
+    /// How fast to keep going straight, in mm/ms, if the motion queue runs out while more
+    /// motions are still being planned, like partway through a fast run. The mouse still stops
+    /// once it is `stop_distance` past the end of the last motion. 0 stops right away instead.
+    /// See [MotionControl::set_motions_pending].
+    pub coast_velocity: f32,
+
+    /// How to follow the walls from the distance sensors when the planner couldn't plan
+    /// anything, so the mouse still makes progress. See [MotionControl::set_wall_follow]. None
+    /// stops instead.
+    pub wall_follow: Option<WallFollowConfig>,
+}
+
+impl MotionControlConfig {
+    /// The motor control config to use going `velocity`, with the gains linearly interpolated
+    /// between the points in the gain schedule on either side of it. Past either end of the
+    /// schedule, the gains at that end are used. Without a schedule, this is just
+    /// `motor_control`.
+    pub fn scheduled_motor_control(&self, velocity: f32) -> MotorControlConfig {
+        let with_gains = |left_pidf, right_pidf| MotorControlConfig {
+            left_pidf,
+            right_pidf,
+            ..self.motor_control
+        };
+
+        let mut points = self.gain_schedule.iter().filter_map(|&point| point);
+
+        let mut below = match points.next() {
+            Some(first) => first,
+            None => return self.motor_control,
+        };
+
+        if velocity <= below.velocity {
+            return with_gains(below.left_pidf, below.right_pidf);
+        }
+
+        for above in points {
+            if velocity <= above.velocity {
+                let span = above.velocity - below.velocity;
+                let fraction = if span > 0.0 {
+                    (velocity - below.velocity) / span
+                } else {
+                    1.0
+                };
+
+                return with_gains(
+                    lerp_pidf(below.left_pidf, above.left_pidf, fraction),
+                    lerp_pidf(below.right_pidf, above.right_pidf, fraction),
+                );
+            }
+
+            below = above;
+        }
+
+        with_gains(below.left_pidf, below.right_pidf)
+    }
+}
+
+#[cfg(test)]
+mod gain_schedule_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{GainSchedulePoint, MotionControlConfig, GAIN_SCHEDULE_SIZE};
+    use crate::config::sim::MOTION_CONTROL;
+    use crate::fast::motor_control::PidfConfig;
+
+    fn pidf(p: f32) -> PidfConfig {
+        PidfConfig {
+            p,
+            i: p / 10.0,
+            d: p * 10.0,
+            f: 1000.0 + p,
+        }
+    }
+
+    fn point(velocity: f32, p: f32) -> Option<GainSchedulePoint> {
+        Some(GainSchedulePoint {
+            velocity,
+            left_pidf: pidf(p),
+            right_pidf: pidf(2.0 * p),
+        })
+    }
+
+    fn config() -> MotionControlConfig {
+        let mut gain_schedule = [None; GAIN_SCHEDULE_SIZE];
+        gain_schedule[0] = point(0.2, 1.0);
+        gain_schedule[1] = point(0.6, 3.0);
+        gain_schedule[2] = point(1.0, 4.0);
+
+        MotionControlConfig {
+            gain_schedule,
+            ..MOTION_CONTROL
+        }
+    }
+
+    fn assert_pidf(left: PidfConfig, right: PidfConfig) {
+        assert_close(left.p, right.p);
+        assert_close(left.i, right.i);
+        assert_close(left.d, right.d);
+        assert_close(left.f, right.f);
+    }
+
+    #[test]
+    fn no_schedule() {
+        assert_eq!(
+            MOTION_CONTROL.scheduled_motor_control(0.5),
+            MOTION_CONTROL.motor_control
+        );
+    }
+
+    #[test]
+    fn at_points() {
+        for &(velocity, p) in [(0.2, 1.0), (0.6, 3.0), (1.0, 4.0)].iter() {
+            let motor_control = config().scheduled_motor_control(velocity);
+            assert_pidf(motor_control.left_pidf, pidf(p));
+            assert_pidf(motor_control.right_pidf, pidf(2.0 * p));
+        }
+    }
+
+    #[test]
+    fn between_points() {
+        // A quarter of the way from 0.2 to 0.6
+        let motor_control = config().scheduled_motor_control(0.3);
+        assert_pidf(motor_control.left_pidf, pidf(1.5));
+        assert_pidf(motor_control.right_pidf, pidf(3.0));
+
+        // Halfway from 0.6 to 1.0
+        let motor_control = config().scheduled_motor_control(0.8);
+        assert_pidf(motor_control.left_pidf, pidf(3.5));
+        assert_pidf(motor_control.right_pidf, pidf(7.0));
+    }
+
+    #[test]
+    fn past_the_ends() {
+        assert_pidf(config().scheduled_motor_control(0.0).left_pidf, pidf(1.0));
+        assert_pidf(config().scheduled_motor_control(2.0).left_pidf, pidf(4.0));
+    }
+
+    #[test]
+    fn keeps_the_rest() {
+        let motor_control = config().scheduled_motor_control(0.5);
+        assert_eq!(
+            motor_control.left_reverse,
+            MOTION_CONTROL.motor_control.left_reverse
+        );
+        assert_eq!(
+            motor_control.right_reverse,
+            MOTION_CONTROL.motor_control.right_reverse
+        );
+    }
 }
 
 pub struct MotionControl {
     handler: Option<MotionHandler>,
     motor_control: MotorControl,
+    last_motion: Option<Motion>,
     last_orientation: Orientation,
+    commanded_curvature: f32,
+    left_power: f32,
+    right_power: f32,
+    velocity: f32,
+    time: Millis,
+
+    /// The forward velocity to follow path motions at instead of the planned one, in mm/ms
+    velocity_override: Option<f32>,
+
+    /// Whether more motions are being planned, so running out of them shouldn't stop the mouse
+    motions_pending: bool,
+
+    wall_follow: WallFollow,
+
+    /// The left, front, and right distance readings in mm to follow the walls with, if the
+    /// planner failed
+    wall_follow_distances: Option<(Option<f32>, Option<f32>, Option<f32>)>,
 }
 
 impl MotionControl {
     pub fn new(
         config: &MotionControlConfig,
-        time: u32,
+        time: Millis,
         left_encoder: i32,
         right_encoder: i32,
         orientation: Orientation,
@@ -54,21 +725,60 @@ impl MotionControl {
                 left_encoder,
                 right_encoder,
             ),
+            last_motion: None,
             last_orientation: orientation,
+            commanded_curvature: 0.0,
+            left_power: 0.0,
+            right_power: 0.0,
+            velocity: 0.0,
+            time,
+            velocity_override: None,
+            motions_pending: false,
+            wall_follow: WallFollow::new(),
+            wall_follow_distances: None,
         }
     }
 
+    /// Follow path motions at exactly `velocity_override` in mm/ms, still steering along them,
+    /// instead of the velocity from the path handler and the velocity limits. This is for tuning
+    /// the steering without the velocity changing underneath it. It does not apply while
+    /// stopping. None goes back to the planned velocity.
+    pub fn set_velocity_override(&mut self, velocity_override: Option<f32>) {
+        self.velocity_override = velocity_override;
+    }
+
+    /// Say whether more motions are still being planned. If the motion queue runs out while
+    /// they are, the mouse coasts straight at [MotionControlConfig::coast_velocity] instead of
+    /// stopping.
+    pub fn set_motions_pending(&mut self, motions_pending: bool) {
+        self.motions_pending = motions_pending;
+    }
+
+    /// Say that the planner couldn't plan anything to follow, and give the left, front, and
+    /// right distance readings in mm, None for no wall in range. Once the motion queue runs
+    /// out, the mouse follows the walls with [MotionControlConfig::wall_follow] instead of
+    /// stopping. None stops like normal.
+    pub fn set_wall_follow(
+        &mut self,
+        distances: Option<(Option<f32>, Option<f32>, Option<f32>)>,
+    ) {
+        self.wall_follow_distances = distances;
+    }
+
     pub fn update(
         &mut self,
         config: &MotionControlConfig,
         mech: &MechanicalConfig,
-        time: u32,
+        time: Millis,
         left_encoder: i32,
         right_encoder: i32,
         motion: Option<Motion>,
         orientation: Orientation,
+        side_wall_offset: Option<f32>,
     ) -> (i32, i32, MotionControlDebug) {
-        let handler = self.handler.take();
+        let delta_time = time - self.time;
+
+        let stopping = motion.is_none();
 
         let motion = if let Some(motion) = motion {
             self.last_orientation = orientation;
@@ -82,8 +792,34 @@ impl MotionControl {
             ))
         };
 
-        let (left_target, right_target, handler_debug) = match motion {
-            Motion::Path(motion) => {
+        // Start each motion fresh so the last one doesn't kick it
+        if self.last_motion != Some(motion) {
+            self.reset_state();
+        }
+        self.last_motion = Some(motion);
+
+        let handler = self.handler.take();
+
+        let mut wall_centering_curvature = 0.0;
+        let mut coasting = false;
+
+        // With nothing planned, keep making progress along the walls instead of stopping
+        let wall_follow = match (config.wall_follow, self.wall_follow_distances) {
+            (Some(wall_follow), Some((left, front, right))) if stopping => Some(
+                self.wall_follow
+                    .update(&wall_follow, mech, left, front, right),
+            ),
+            _ => None,
+        };
+
+        let (left_target, right_target, handler_debug) = match (wall_follow, motion) {
+            (Some((left, right)), _) => {
+                self.commanded_curvature = 0.0;
+                self.handler = handler;
+
+                (left, right, None)
+            }
+            (None, Motion::Path(motion)) => {
                 let mut handler = if let Some(MotionHandler::Path(handler)) = handler {
                     handler
                 } else {
@@ -93,11 +829,87 @@ impl MotionControl {
                 let (left, right, debug) =
                     handler.update(&config.path, mech, time, orientation, motion);
 
+                let path_curvature = left_right_to_curvature(mech, left, right);
+
+                // Steer back towards the center between the side walls on straights
+                let straight = debug
+                    .closest_point
+                    .map(|(t, _)| motion.curvature(t).abs() < STRAIGHT_CURVATURE)
+                    .unwrap_or(false);
+                let mut weight = 0.0;
+                if let Some(offset) = side_wall_offset {
+                    if straight && !stopping && config.wall_centering_p != 0.0 {
+                        wall_centering_curvature = config.wall_centering_p * offset;
+                        weight = config.wall_centering_weight.max(0.0).min(1.0);
+                    }
+                }
+
+                let target_curvature =
+                    (1.0 - weight) * path_curvature + weight * wall_centering_curvature;
+                let curvature = slew_curvature(
+                    self.commanded_curvature,
+                    target_curvature,
+                    config.curvature_slew_rate,
+                    delta_time,
+                );
+                self.commanded_curvature = curvature;
+
+                let (left, right) = if curvature == path_curvature {
+                    (left, right)
+                } else {
+                    curvature_to_left_right(mech, (left + right) / 2.0, curvature)
+                };
+
+                let max_velocity = if config.max_velocity > 0.0 {
+                    config.max_velocity
+                } else {
+                    core::f32::INFINITY
+                };
+                let max_velocity = motion.limit_velocity(max_velocity);
+
+                // Don't keep the mouse moving if it is supposed to stop
+                let min_velocity = if stopping { 0.0 } else { config.min_velocity };
+                let (left, right) = clamp_velocity(
+                    mech,
+                    left,
+                    right,
+                    min_velocity.min(max_velocity),
+                    max_velocity,
+                );
+
+                // Anything under the floor while stopping would only creep, so stop outright
+                let (left, right) =
+                    if stopping && (left + right) / 2.0 < config.min_velocity {
+                        (0.0, 0.0)
+                    } else {
+                        (left, right)
+                    };
+
+                let (left, right) = match self.velocity_override {
+                    Some(velocity) if !stopping => {
+                        curvature_to_left_right(mech, velocity, curvature)
+                    }
+                    _ => (left, right),
+                };
+
+                // Keep going while the rest of the motions are planned, but not forever
+                coasting = stopping
+                    && self.motions_pending
+                    && config.coast_velocity > 0.0
+                    && !motion.done(orientation);
+
+                let (left, right) = if coasting {
+                    self.commanded_curvature = 0.0;
+                    curvature_to_left_right(mech, config.coast_velocity, 0.0)
+                } else {
+                    (left, right)
+                };
+
                 self.handler = Some(MotionHandler::Path(handler));
 
                 (left, right, Some(MotionHandlerDebug::Path(debug)))
             }
-            Motion::Turn(motion) => {
+            (None, Motion::Turn(motion)) => {
                 let mut handler = if let Some(MotionHandler::Turn(handler)) = handler {
                     handler
                 } else {
@@ -107,14 +919,22 @@ impl MotionControl {
                 let (left, right, debug) =
                     handler.update(&config.turn, mech, time, orientation, motion);
 
+                // Turns are in place, so start the next path from straight
+                self.commanded_curvature = 0.0;
+
                 self.handler = Some(MotionHandler::Turn(handler));
 
                 (left, right, Some(MotionHandlerDebug::Turn(debug)))
             }
+            (None, Motion::Stop(_)) => {
+                self.commanded_curvature = 0.0;
+                (0.0, 0.0, None)
+            }
         };
 
+        // Use the gains for how fast the mouse was last going
         let (left_power, right_power, motor_debug) = self.motor_control.update(
-            &config.motor_control,
+            &config.scheduled_motor_control(self.velocity),
             mech,
             time,
             left_encoder,
@@ -123,11 +943,1031 @@ impl MotionControl {
             right_target,
         );
 
+        let (left_power, right_power) = mech.trim_powers(left_power, right_power);
+
+        self.left_power = smooth_power(
+            self.left_power,
+            left_power as f32,
+            config.power_time_constant,
+            delta_time,
+        );
+        self.right_power = smooth_power(
+            self.right_power,
+            right_power as f32,
+            config.power_time_constant,
+            delta_time,
+        );
+
+        let (left_power, right_power) =
+            mech.quantize_powers(self.left_power as i32, self.right_power as i32);
+
+        let velocity = mech.ticks_to_mm(
+            ((motor_debug.left_velocity + motor_debug.right_velocity) / 2.0) as f32,
+        );
+
+        if velocity.is_finite() {
+            self.velocity = velocity;
+        }
+
+        let lateral_acceleration = if velocity.is_finite() {
+            lateral_acceleration(
+                velocity,
+                left_right_to_curvature(mech, left_target, right_target),
+            )
+        } else {
+            0.0
+        };
+
         let debug = MotionControlDebug {
             handler: handler_debug,
             motor_control: motor_debug,
+            lateral_acceleration,
+            commanded_curvature: self.commanded_curvature,
+            wall_centering_curvature,
+            coasting,
+            wall_following: wall_follow.is_some(),
         };
 
+        self.time = time;
+
         (left_power, right_power, debug)
     }
+
+    /// Pick back up at `time` after not being updated for a while, like while the mouse was
+    /// disarmed, so the first update doesn't see all of that time and encoder movement at once.
+    /// The velocity override and whether motions are pending are kept.
+    pub fn restart(
+        &mut self,
+        config: &MotionControlConfig,
+        time: Millis,
+        left_encoder: i32,
+        right_encoder: i32,
+        orientation: Orientation,
+    ) {
+        *self = MotionControl {
+            velocity_override: self.velocity_override,
+            motions_pending: self.motions_pending,
+            ..MotionControl::new(config, time, left_encoder, right_encoder, orientation)
+        };
+    }
+
+    /// Clear the integral and derivative state of the motion handler, so it starts the next
+    /// motion like it was new. The wheel velocity loops are left alone so the wheels don't
+    /// lurch. This is done whenever the motion changes.
+    pub fn reset_state(&mut self) {
+        match &mut self.handler {
+            Some(MotionHandler::Path(handler)) => handler.reset(),
+            Some(MotionHandler::Turn(handler)) => handler.reset(),
+            None => {}
+        }
+        self.wall_follow.reset();
+    }
+
+    /// How far the mouse will go before it can stop from the last measured velocity, slowing
+    /// down at `max_acceleration`
+    pub fn stopping_distance(&self, config: &MotionControlConfig) -> f32 {
+        if config.max_acceleration <= 0.0 {
+            f32::INFINITY
+        } else {
+            self.velocity * self.velocity / (2.0 * config.max_acceleration)
+        }
+    }
+}
+
+#[cfg(test)]
+mod stopping_distance_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{MotionControl, MotionControlConfig};
+    use crate::config::sim;
+    use crate::fast::Orientation;
+
+    const CONFIG: MotionControlConfig = MotionControlConfig {
+        max_acceleration: 0.005,
+        ..sim::MOTION_CONTROL
+    };
+
+    fn stopping_distance(config: &MotionControlConfig, velocity: f32) -> f32 {
+        let mut motion_control =
+            MotionControl::new(config, 0, 0, 0, Orientation::default());
+        motion_control.velocity = velocity;
+        motion_control.stopping_distance(config)
+    }
+
+    #[test]
+    fn stopped() {
+        assert_close(stopping_distance(&CONFIG, 0.0), 0.0);
+    }
+
+    #[test]
+    fn closed_form() {
+        for &velocity in [0.1, 0.5, 1.0, -0.5].iter() {
+            assert_close(
+                stopping_distance(&CONFIG, velocity),
+                velocity * velocity / (2.0 * 0.005),
+            );
+        }
+    }
+
+    #[test]
+    fn full_speed() {
+        // 1 m/s at 5 m/s^2 takes 10 cm to stop
+        assert_close(stopping_distance(&CONFIG, 1.0), 100.0);
+    }
+
+    #[test]
+    fn no_acceleration_never_stops() {
+        let config = MotionControlConfig {
+            max_acceleration: 0.0,
+            ..CONFIG
+        };
+        assert_eq!(stopping_distance(&config, 0.5), f32::INFINITY);
+    }
+}
+
+#[cfg(test)]
+mod velocity_limit_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{MotionControl, MotionControlConfig};
+    use crate::config::{mouse_2019, sim};
+    use crate::fast::motion_queue::Motion;
+    use crate::fast::path::{PathHandlerConfig, PathMotion};
+    use crate::fast::{Orientation, Vector, DIRECTION_0};
+
+    const CONFIG: MotionControlConfig = MotionControlConfig {
+        path: PathHandlerConfig {
+            velocity: 0.001,
+            ..sim::MOTION_CONTROL.path
+        },
+        min_velocity: 0.1,
+        max_velocity: 1.0,
+        ..sim::MOTION_CONTROL
+    };
+
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 0.0, y: 0.0 },
+        direction: DIRECTION_0,
+    };
+
+    // The average target velocity the motors were given, in mm/ms
+    fn target_velocity(config: &MotionControlConfig, motion: Option<Motion>) -> f32 {
+        let mut motion_control = MotionControl::new(config, 0, 0, 0, ORIENTATION);
+        let (_, _, debug) = motion_control.update(
+            config,
+            &mouse_2019::MECH,
+            10,
+            0,
+            0,
+            motion,
+            ORIENTATION,
+            None,
+        );
+
+        mouse_2019::MECH.ticks_to_mm(
+            ((debug.motor_control.target_left_velocity
+                + debug.motor_control.target_right_velocity)
+                / 2.0) as f32,
+        )
+    }
+
+    #[test]
+    fn tiny_velocity_raised_while_moving() {
+        let motion = Motion::Path(PathMotion::line(
+            Vector { x: 0.0, y: 0.0 },
+            Vector { x: 180.0, y: 0.0 },
+        ));
+        assert_close(target_velocity(&CONFIG, Some(motion)), 0.1);
+    }
+
+    #[test]
+    fn tiny_velocity_zero_while_stopping() {
+        assert_eq!(target_velocity(&CONFIG, None), 0.0);
+    }
+
+    #[test]
+    fn zero_max_velocity_is_no_limit() {
+        let config = MotionControlConfig {
+            path: PathHandlerConfig {
+                velocity: 0.5,
+                ..CONFIG.path
+            },
+            min_velocity: 0.0,
+            max_velocity: 0.0,
+            ..CONFIG
+        };
+
+        assert_close(target_velocity(&config, Some(Motion::Path(line()))), 0.5);
+    }
+
+    fn line() -> PathMotion {
+        PathMotion::line(Vector { x: 0.0, y: 0.0 }, Vector { x: 180.0, y: 0.0 })
+    }
+
+    // The path handler would go 0.5 mm/ms on its own
+    fn fast_target_velocity(motion: PathMotion) -> f32 {
+        let config = MotionControlConfig {
+            path: PathHandlerConfig {
+                velocity: 0.5,
+                ..CONFIG.path
+            },
+            ..CONFIG
+        };
+
+        let mut motion_control = MotionControl::new(&config, 0, 0, 0, ORIENTATION);
+        let (_, _, debug) = motion_control.update(
+            &config,
+            &mouse_2019::MECH,
+            10,
+            0,
+            0,
+            Some(Motion::Path(motion)),
+            ORIENTATION,
+            None,
+        );
+
+        mouse_2019::MECH.ticks_to_mm(
+            ((debug.motor_control.target_left_velocity
+                + debug.motor_control.target_right_velocity)
+                / 2.0) as f32,
+        )
+    }
+
+    #[test]
+    fn no_override() {
+        assert_close(fast_target_velocity(line()), 0.5);
+    }
+
+    #[test]
+    fn override_slows_down() {
+        assert_close(fast_target_velocity(line().with_max_velocity(0.2)), 0.2);
+    }
+
+    #[test]
+    fn override_below_min_velocity() {
+        assert_close(fast_target_velocity(line().with_max_velocity(0.05)), 0.05);
+    }
+
+    #[test]
+    fn override_does_not_speed_up() {
+        assert_close(fast_target_velocity(line().with_max_velocity(0.8)), 0.5);
+    }
+}
+
+#[cfg(test)]
+mod velocity_override_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{left_right_to_curvature, MotionControl, MotionControlDebug};
+    use crate::config::{mouse_2019, sim};
+    use crate::fast::motion_queue::Motion;
+    use crate::fast::path::PathMotion;
+    use crate::fast::{Orientation, Vector, DIRECTION_0};
+
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 0.0, y: 0.0 },
+        direction: DIRECTION_0,
+    };
+
+    fn update(
+        velocity_override: Option<f32>,
+        motion: Option<Motion>,
+        orientation: Orientation,
+    ) -> MotionControlDebug {
+        let config = sim::MOTION_CONTROL;
+        let mut motion_control = MotionControl::new(&config, 0, 0, 0, ORIENTATION);
+        motion_control.set_velocity_override(velocity_override);
+
+        let (_, _, debug) = motion_control.update(
+            &config,
+            &mouse_2019::MECH,
+            10,
+            0,
+            0,
+            motion,
+            orientation,
+            None,
+        );
+
+        debug
+    }
+
+    // The target wheel velocities the motors were given, in mm/ms
+    fn targets(debug: &MotionControlDebug) -> (f32, f32) {
+        (
+            mouse_2019::MECH.ticks_to_mm(debug.motor_control.target_left_velocity as f32),
+            mouse_2019::MECH
+                .ticks_to_mm(debug.motor_control.target_right_velocity as f32),
+        )
+    }
+
+    fn line() -> Option<Motion> {
+        Some(Motion::Path(PathMotion::line(
+            Vector { x: 0.0, y: 0.0 },
+            Vector { x: 1000.0, y: 0.0 },
+        )))
+    }
+
+    #[test]
+    fn forces_velocity() {
+        for &velocity in [0.1, 0.5, 1.5].iter() {
+            let (left, right) = targets(&update(Some(velocity), line(), ORIENTATION));
+            assert_close((left + right) / 2.0, velocity);
+        }
+    }
+
+    #[test]
+    fn still_steers() {
+        // Off to the left of the path, so it should turn back to the right
+        let off_path = Orientation {
+            position: Vector { x: 0.0, y: 20.0 },
+            direction: DIRECTION_0,
+        };
+
+        let without = update(None, line(), off_path);
+        let with = update(Some(0.2), line(), off_path);
+
+        let (left, right) = targets(&with);
+        assert_close((left + right) / 2.0, 0.2);
+
+        let curvature = left_right_to_curvature(&mouse_2019::MECH, left, right);
+        assert!(curvature < 0.0, "{}", curvature);
+        assert_close(curvature, with.commanded_curvature);
+        assert_close(with.commanded_curvature, without.commanded_curvature);
+    }
+
+    #[test]
+    fn not_while_stopping() {
+        let (left, right) = targets(&update(Some(1.5), None, ORIENTATION));
+        let (planned_left, planned_right) = targets(&update(None, None, ORIENTATION));
+        assert_close(left, planned_left);
+        assert_close(right, planned_right);
+    }
+}
+
+#[cfg(test)]
+mod coast_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{
+        left_right_to_curvature, MotionControl, MotionControlConfig, MotionControlDebug,
+    };
+    use crate::config::{mouse_2019, sim};
+    use crate::fast::motion_queue::Motion;
+    use crate::fast::path::PathMotion;
+    use crate::fast::{Orientation, Vector, DIRECTION_0};
+
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 0.0, y: 0.0 },
+        direction: DIRECTION_0,
+    };
+
+    const COAST: MotionControlConfig = MotionControlConfig {
+        coast_velocity: 0.1,
+        ..sim::MOTION_CONTROL
+    };
+
+    fn update(
+        config: &MotionControlConfig,
+        motions_pending: bool,
+        motion: Option<Motion>,
+        orientation: Orientation,
+    ) -> MotionControlDebug {
+        let mut motion_control = MotionControl::new(config, 0, 0, 0, ORIENTATION);
+        motion_control.set_motions_pending(motions_pending);
+
+        let (_, _, debug) = motion_control.update(
+            config,
+            &mouse_2019::MECH,
+            10,
+            0,
+            0,
+            motion,
+            orientation,
+            None,
+        );
+
+        debug
+    }
+
+    // The target wheel velocities the motors were given, in mm/ms
+    fn targets(debug: &MotionControlDebug) -> (f32, f32) {
+        (
+            mouse_2019::MECH.ticks_to_mm(debug.motor_control.target_left_velocity as f32),
+            mouse_2019::MECH
+                .ticks_to_mm(debug.motor_control.target_right_velocity as f32),
+        )
+    }
+
+    #[test]
+    fn coasts_straight() {
+        // Off to the side, where stopping would steer back to the line
+        let off_path = Orientation {
+            position: Vector { x: 10.0, y: 20.0 },
+            direction: DIRECTION_0,
+        };
+
+        let debug = update(&COAST, true, None, off_path);
+        assert!(debug.coasting);
+
+        let (left, right) = targets(&debug);
+        assert_close((left + right) / 2.0, 0.1);
+        assert_close(left_right_to_curvature(&mouse_2019::MECH, left, right), 0.0);
+        assert_close(debug.commanded_curvature, 0.0);
+    }
+
+    #[test]
+    fn stop_mode() {
+        let config = MotionControlConfig {
+            coast_velocity: 0.0,
+            ..COAST
+        };
+
+        let debug = update(&config, true, None, ORIENTATION);
+        assert!(!debug.coasting);
+        assert_eq!(
+            targets(&debug),
+            targets(&update(&config, false, None, ORIENTATION))
+        );
+    }
+
+    #[test]
+    fn nothing_pending() {
+        assert!(!update(&COAST, false, None, ORIENTATION).coasting);
+    }
+
+    #[test]
+    fn only_when_out_of_motions() {
+        let line = Motion::Path(PathMotion::line(
+            Vector { x: 0.0, y: 0.0 },
+            Vector { x: 1000.0, y: 0.0 },
+        ));
+
+        assert!(!update(&COAST, true, Some(line), ORIENTATION).coasting);
+    }
+
+    #[test]
+    fn stops_past_the_stop_distance() {
+        let past = Orientation {
+            position: Vector {
+                x: COAST.stop_distance + 10.0,
+                y: 0.0,
+            },
+            direction: DIRECTION_0,
+        };
+
+        assert!(!update(&COAST, true, None, past).coasting);
+    }
+}
+
+#[cfg(test)]
+mod wall_follow_fallback_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{
+        left_right_to_curvature, MotionControl, MotionControlConfig, MotionControlDebug,
+    };
+    use crate::config::{mouse_2019, sim};
+    use crate::fast::motion_queue::Motion;
+    use crate::fast::path::PathMotion;
+    use crate::fast::wall_follow::{WallFollowConfig, WallFollowSide};
+    use crate::fast::{Orientation, Vector, DIRECTION_0};
+
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 0.0, y: 0.0 },
+        direction: DIRECTION_0,
+    };
+
+    const WALL_FOLLOW: WallFollowConfig = WallFollowConfig {
+        side: WallFollowSide::Left,
+        velocity: 0.2,
+        turn_velocity: 0.1,
+        p: 0.001,
+        side_distance: 50.0,
+        front_threshold: 60.0,
+    };
+
+    const CONFIG: MotionControlConfig = MotionControlConfig {
+        wall_follow: Some(WALL_FOLLOW),
+        ..sim::MOTION_CONTROL
+    };
+
+    // Walls on both sides, and open in front
+    const CORRIDOR: Option<(Option<f32>, Option<f32>, Option<f32>)> =
+        Some((Some(40.0), None, Some(40.0)));
+
+    // A wall in front, and only the left open
+    const DEAD_END_LEFT_OPEN: Option<(Option<f32>, Option<f32>, Option<f32>)> =
+        Some((None, Some(30.0), Some(40.0)));
+
+    fn update(
+        config: &MotionControlConfig,
+        motion: Option<Motion>,
+        wall_follow: Option<(Option<f32>, Option<f32>, Option<f32>)>,
+    ) -> MotionControlDebug {
+        let mut motion_control = MotionControl::new(config, 0, 0, 0, ORIENTATION);
+        motion_control.set_wall_follow(wall_follow);
+
+        let (_, _, debug) = motion_control.update(
+            config,
+            &mouse_2019::MECH,
+            10,
+            0,
+            0,
+            motion,
+            ORIENTATION,
+            None,
+        );
+
+        debug
+    }
+
+    // The target wheel velocities the motors were given, in mm/ms
+    fn targets(debug: &MotionControlDebug) -> (f32, f32) {
+        (
+            mouse_2019::MECH.ticks_to_mm(debug.motor_control.target_left_velocity as f32),
+            mouse_2019::MECH
+                .ticks_to_mm(debug.motor_control.target_right_velocity as f32),
+        )
+    }
+
+    #[test]
+    fn follows_the_walls() {
+        let debug = update(&CONFIG, None, CORRIDOR);
+        assert!(debug.wall_following);
+
+        let (left, right) = targets(&debug);
+        assert_close((left + right) / 2.0, WALL_FOLLOW.velocity);
+        assert_close(left_right_to_curvature(&mouse_2019::MECH, left, right), 0.0);
+    }
+
+    #[test]
+    fn turns_away_from_the_front_wall() {
+        let debug = update(&CONFIG, None, DEAD_END_LEFT_OPEN);
+        assert!(debug.wall_following);
+
+        let (left, right) = targets(&debug);
+        assert_close(left, -WALL_FOLLOW.turn_velocity);
+        assert_close(right, WALL_FOLLOW.turn_velocity);
+    }
+
+    #[test]
+    fn stops_without_it() {
+        let config = MotionControlConfig {
+            wall_follow: None,
+            ..CONFIG
+        };
+
+        // Following the walls would turn in place away from the front wall
+        let debug = update(&config, None, DEAD_END_LEFT_OPEN);
+        assert!(!debug.wall_following);
+
+        let (left, right) = targets(&debug);
+        assert!(left >= 0.0);
+        assert!(right >= 0.0);
+    }
+
+    #[test]
+    fn stops_unless_the_planner_failed() {
+        let debug = update(&CONFIG, None, None);
+        assert!(!debug.wall_following);
+        assert_eq!(
+            targets(&debug),
+            targets(&update(&sim::MOTION_CONTROL, None, None))
+        );
+    }
+
+    #[test]
+    fn only_when_out_of_motions() {
+        let line = Motion::Path(PathMotion::line(
+            Vector { x: 0.0, y: 0.0 },
+            Vector { x: 1000.0, y: 0.0 },
+        ));
+
+        let debug = update(&CONFIG, Some(line), CORRIDOR);
+        assert!(!debug.wall_following);
+    }
+}
+
+#[cfg(test)]
+mod curvature_slew_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{MotionControl, MotionControlConfig};
+    use crate::config::{mouse_2019, sim};
+    use crate::fast::motion_queue::Motion;
+    use crate::fast::path::PathMotion;
+    use crate::fast::{Orientation, Vector, DIRECTION_0};
+
+    const CONFIG: MotionControlConfig = MotionControlConfig {
+        curvature_slew_rate: 0.00001,
+        ..sim::MOTION_CONTROL
+    };
+
+    #[test]
+    fn step_is_rate_limited() {
+        // Starting off to the side of the path makes the target curvature step to turn back
+        let line =
+            PathMotion::line(Vector { x: 0.0, y: 0.0 }, Vector { x: 1000.0, y: 0.0 });
+
+        let orientation = Orientation {
+            position: Vector { x: 0.0, y: 20.0 },
+            direction: DIRECTION_0,
+        };
+
+        let mut motion_control = MotionControl::new(&CONFIG, 0, 0, 0, orientation);
+
+        let mut last_curvature = 0.0;
+        for i in 1..20 {
+            let (_, _, debug) = motion_control.update(
+                &CONFIG,
+                &mouse_2019::MECH,
+                i * 10,
+                0,
+                0,
+                Some(Motion::Path(line)),
+                orientation,
+                None,
+            );
+
+            let change = (debug.commanded_curvature - last_curvature).abs();
+            assert!(change > 0.0);
+            assert!(change <= CONFIG.curvature_slew_rate * 10.0 + 0.0000001);
+
+            last_curvature = debug.commanded_curvature;
+        }
+    }
+}
+
+#[cfg(test)]
+mod wall_centering_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use core::f32::consts::FRAC_PI_4;
+
+    use super::{MotionControl, MotionControlConfig, MotionControlDebug};
+    use crate::config::{mouse_2019, sim};
+    use crate::fast::motion_queue::Motion;
+    use crate::fast::path::PathMotion;
+    use crate::fast::{Direction, Orientation, Vector, DIRECTION_0, DIRECTION_PI_2};
+
+    const CONFIG: MotionControlConfig = MotionControlConfig {
+        wall_centering_p: 0.001,
+        ..sim::MOTION_CONTROL
+    };
+
+    // Right on the path, so the path handler goes straight
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 90.0, y: 90.0 },
+        direction: DIRECTION_0,
+    };
+
+    fn line() -> PathMotion {
+        PathMotion::line(Vector { x: 0.0, y: 90.0 }, Vector { x: 1000.0, y: 90.0 })
+    }
+
+    fn update(
+        config: &MotionControlConfig,
+        motion: PathMotion,
+        orientation: Orientation,
+        side_wall_offset: Option<f32>,
+    ) -> MotionControlDebug {
+        let mut motion_control = MotionControl::new(config, 0, 0, 0, orientation);
+        let (_, _, debug) = motion_control.update(
+            config,
+            &mouse_2019::MECH,
+            10,
+            0,
+            0,
+            Some(Motion::Path(motion)),
+            orientation,
+            side_wall_offset,
+        );
+        debug
+    }
+
+    #[test]
+    fn right_of_center_steers_left() {
+        let debug = update(&CONFIG, line(), ORIENTATION, Some(10.0));
+        assert_close(debug.wall_centering_curvature, 0.01);
+        assert!(debug.commanded_curvature > 0.0);
+    }
+
+    #[test]
+    fn left_of_center_steers_right() {
+        let debug = update(&CONFIG, line(), ORIENTATION, Some(-10.0));
+        assert_close(debug.wall_centering_curvature, -0.01);
+        assert!(debug.commanded_curvature < 0.0);
+    }
+
+    #[test]
+    fn missing_wall_disables() {
+        let debug = update(&CONFIG, line(), ORIENTATION, None);
+        assert_close(debug.wall_centering_curvature, 0.0);
+        assert_close(debug.commanded_curvature, 0.0);
+    }
+
+    #[test]
+    fn disabled() {
+        let config = MotionControlConfig {
+            wall_centering_p: 0.0,
+            ..CONFIG
+        };
+
+        let debug = update(&config, line(), ORIENTATION, Some(10.0));
+        assert_close(debug.wall_centering_curvature, 0.0);
+    }
+
+    #[test]
+    fn not_in_corners() {
+        let corner = PathMotion::corner(
+            Vector { x: 180.0, y: 90.0 },
+            DIRECTION_0,
+            DIRECTION_PI_2,
+            90.0,
+            0.0,
+        );
+
+        // Halfway around the corner
+        let orientation = Orientation {
+            position: corner.at(0.5),
+            direction: Direction::from(FRAC_PI_4),
+        };
+
+        let debug = update(&CONFIG, corner, orientation, Some(10.0));
+        assert_close(debug.wall_centering_curvature, 0.0);
+    }
+
+    // Off to the left of the path, so following the path steers right
+    const OFF_PATH: Orientation = Orientation {
+        position: Vector { x: 90.0, y: 100.0 },
+        direction: DIRECTION_0,
+    };
+
+    fn weighted(weight: f32, side_wall_offset: Option<f32>) -> f32 {
+        let config = MotionControlConfig {
+            wall_centering_weight: weight,
+            ..CONFIG
+        };
+
+        update(&config, line(), OFF_PATH, side_wall_offset).commanded_curvature
+    }
+
+    #[test]
+    fn full_weight_follows_the_walls() {
+        assert_close(weighted(1.0, Some(10.0)), 0.01);
+    }
+
+    #[test]
+    fn zero_weight_follows_the_path() {
+        let path = weighted(1.0, None);
+        assert!(path < 0.0, "{}", path);
+        assert_close(weighted(0.0, Some(10.0)), path);
+    }
+
+    #[test]
+    fn blends_linearly() {
+        let path = weighted(0.0, Some(10.0));
+        assert_close(weighted(0.25, Some(10.0)), 0.75 * path + 0.25 * 0.01);
+        assert_close(weighted(0.5, Some(10.0)), 0.5 * path + 0.5 * 0.01);
+    }
+
+    #[test]
+    fn no_walls_follows_the_path() {
+        assert_close(weighted(1.0, None), weighted(0.0, None));
+        assert_close(weighted(0.5, None), weighted(0.0, None));
+    }
+}
+
+#[cfg(test)]
+mod reset_state_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{MotionControl, MotionControlConfig, MotionHandlerDebug};
+    use crate::config::{mouse_2019, sim};
+    use crate::fast::motion_queue::Motion;
+    use crate::fast::path::{PathHandlerConfig, PathMotion};
+    use crate::fast::{Orientation, Vector, DIRECTION_0};
+
+    // Only the i term, so any adjustment comes from the integral
+    const CONFIG: MotionControlConfig = MotionControlConfig {
+        path: PathHandlerConfig {
+            p: 0.0,
+            i: 0.001,
+            d: 0.0,
+            ..sim::MOTION_CONTROL.path
+        },
+        ..sim::MOTION_CONTROL
+    };
+
+    // Off to the side of the path, so the error keeps adding up
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 100.0, y: 20.0 },
+        direction: DIRECTION_0,
+    };
+
+    fn line(end: f32) -> Motion {
+        Motion::Path(PathMotion::line(
+            Vector { x: 0.0, y: 0.0 },
+            Vector { x: end, y: 0.0 },
+        ))
+    }
+
+    fn adjust_curvature(
+        motion_control: &mut MotionControl,
+        time: u32,
+        motion: Motion,
+    ) -> f32 {
+        let (_, _, debug) = motion_control.update(
+            &CONFIG,
+            &mouse_2019::MECH,
+            time,
+            0,
+            0,
+            Some(motion),
+            ORIENTATION,
+            None,
+        );
+
+        match debug.handler {
+            Some(MotionHandlerDebug::Path(debug)) => debug.adjust_curvature.unwrap(),
+            _ => panic!("not following a path"),
+        }
+    }
+
+    /// The adjust curvature at 200 ms from a motion control that started following `motion` at
+    /// 190 ms, so it has only had one update to build up an integral
+    fn fresh_adjust_curvature(motion: Motion) -> f32 {
+        let mut motion_control = MotionControl::new(&CONFIG, 190, 0, 0, ORIENTATION);
+        adjust_curvature(&mut motion_control, 190, motion);
+        adjust_curvature(&mut motion_control, 200, motion)
+    }
+
+    /// A motion control that has been following `line(1000.0)` from the side until 190 ms
+    fn wound_up() -> MotionControl {
+        let mut motion_control = MotionControl::new(&CONFIG, 0, 0, 0, ORIENTATION);
+        for i in 1..20 {
+            adjust_curvature(&mut motion_control, i * 10, line(1000.0));
+        }
+        motion_control
+    }
+
+    #[test]
+    fn integral_builds_up() {
+        let mut motion_control = wound_up();
+        let adjust = adjust_curvature(&mut motion_control, 200, line(1000.0));
+        assert!(adjust.abs() > fresh_adjust_curvature(line(1000.0)).abs() * 2.0);
+    }
+
+    #[test]
+    fn reset_clears_integral() {
+        let mut motion_control = wound_up();
+        motion_control.reset_state();
+
+        let adjust = adjust_curvature(&mut motion_control, 200, line(1000.0));
+        assert_close(adjust, fresh_adjust_curvature(line(1000.0)));
+    }
+
+    #[test]
+    fn new_motion_clears_integral() {
+        let mut motion_control = wound_up();
+
+        let adjust = adjust_curvature(&mut motion_control, 200, line(2000.0));
+        assert_close(adjust, fresh_adjust_curvature(line(2000.0)));
+    }
+}
+
+#[cfg(test)]
+mod power_trim_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::MotionControl;
+    use crate::config::{mouse_2019, sim, MechanicalConfig};
+    use crate::fast::motion_queue::Motion;
+    use crate::fast::path::PathMotion;
+    use crate::fast::{Orientation, Vector, DIRECTION_0};
+
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 0.0, y: 0.0 },
+        direction: DIRECTION_0,
+    };
+
+    // Going straight, so the left and right powers are the same before trimming
+    fn powers(mech: &MechanicalConfig) -> (i32, i32) {
+        let config = sim::MOTION_CONTROL;
+        let mut motion_control = MotionControl::new(&config, 0, 0, 0, ORIENTATION);
+        let (left, right, _) = motion_control.update(
+            &config,
+            mech,
+            10,
+            0,
+            0,
+            Some(Motion::Path(PathMotion::line(
+                Vector { x: 0.0, y: 0.0 },
+                Vector { x: 180.0, y: 0.0 },
+            ))),
+            ORIENTATION,
+            None,
+        );
+
+        (left, right)
+    }
+
+    #[test]
+    fn no_trim() {
+        let (left, right) = powers(&mouse_2019::MECH);
+        assert!(left > 0);
+        assert_eq!(left, right);
+    }
+
+    #[test]
+    fn asymmetric_trim() {
+        let (left, right) = powers(&mouse_2019::MECH);
+
+        let (trimmed_left, trimmed_right) = powers(&MechanicalConfig {
+            left_power_trim: 1.1,
+            right_power_trim: 0.9,
+            ..mouse_2019::MECH
+        });
+
+        assert_eq!(trimmed_left, (left as f32 * 1.1) as i32);
+        assert_eq!(trimmed_right, (right as f32 * 0.9) as i32);
+        assert!(trimmed_left > trimmed_right);
+    }
+}
+
+#[cfg(test)]
+mod power_smoothing_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{MotionControl, MotionControlConfig};
+    use crate::config::{mouse_2019, sim};
+    use crate::fast::motion_queue::Motion;
+    use crate::fast::path::PathMotion;
+    use crate::fast::{Orientation, Vector, DIRECTION_0};
+
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 0.0, y: 0.0 },
+        direction: DIRECTION_0,
+    };
+
+    // Start going straight from a stop, which is a step in power. Gives the left power that is
+    // output, and the left power from the motor control before smoothing
+    fn left_powers(config: &MotionControlConfig) -> [(i32, i32); 5] {
+        let mut motion_control = MotionControl::new(config, 0, 0, 0, ORIENTATION);
+        let line =
+            PathMotion::line(Vector { x: 0.0, y: 0.0 }, Vector { x: 180.0, y: 0.0 });
+
+        let mut powers = [(0, 0); 5];
+        for (i, power) in powers.iter_mut().enumerate() {
+            let (left, _, debug) = motion_control.update(
+                config,
+                &mouse_2019::MECH,
+                (i as u32 + 1) * 10,
+                0,
+                0,
+                Some(Motion::Path(line)),
+                ORIENTATION,
+                None,
+            );
+            *power = (left, debug.motor_control.left_power);
+        }
+
+        powers
+    }
+
+    #[test]
+    fn disabled_passes_through() {
+        for &(output, raw) in left_powers(&sim::MOTION_CONTROL).iter() {
+            assert_eq!(output, raw);
+        }
+    }
+
+    #[test]
+    fn step_is_smoothed() {
+        let powers = left_powers(&MotionControlConfig {
+            power_time_constant: 40.0,
+            ..sim::MOTION_CONTROL
+        });
+
+        // The first update only gets a fifth of the way there
+        let (output, raw) = powers[0];
+        assert!(raw > 0);
+        assert!(output <= raw / 5 + 1);
+
+        for i in 1..5 {
+            assert!(powers[i].0 > powers[i - 1].0);
+            assert!(powers[i].0 < powers[i].1);
+        }
+    }
 }
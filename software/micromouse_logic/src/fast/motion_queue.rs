@@ -5,12 +5,21 @@ use serde::{Deserialize, Serialize};
 
 use crate::fast::path::PathMotion;
 use crate::fast::turn::{TurnHandlerConfig, TurnMotion};
-use crate::fast::Orientation;
+use crate::fast::{Direction, Orientation, Vector};
+
+/// Hold still with the wheels stopped for a number of updates, like to take clean sensor
+/// readings
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StopMotion {
+    /// How many updates to hold still for
+    pub updates: u8,
+}
 
 #[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Motion {
     Path(PathMotion),
     Turn(TurnMotion),
+    Stop(StopMotion),
 }
 
 impl Motion {
@@ -22,6 +31,19 @@ impl Motion {
         match self {
             Motion::Path(path_motion) => path_motion.done(orientation),
             Motion::Turn(turn_motion) => turn_motion.done(turn_config, orientation),
+            // The updates are counted by the motion queue
+            Motion::Stop(stop_motion) => stop_motion.updates == 0,
+        }
+    }
+
+    /// Rotate the motion `rotation` about the origin, then move it by `translation`
+    pub fn transformed(&self, rotation: Direction, translation: Vector) -> Motion {
+        match self {
+            Motion::Path(path_motion) => {
+                Motion::Path(path_motion.transformed(rotation, translation))
+            }
+            Motion::Turn(turn_motion) => Motion::Turn(turn_motion.rotated(rotation)),
+            Motion::Stop(stop_motion) => Motion::Stop(*stop_motion),
         }
     }
 }
@@ -29,23 +51,249 @@ impl Motion {
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct MotionQueueDebug {
     queue: MotionQueueBuffer,
+
+    /// How many motions were finished by the last update
+    pub completed_this_cycle: u8,
 }
 
 pub type MotionQueueSize = U4;
 pub type MotionQueueBuffer = Vec<Motion, MotionQueueSize>;
 
+/// Rotate all the motions in the buffer `rotation` about the origin, then move them by
+/// `translation`
+pub fn transformed(
+    motions: &MotionQueueBuffer,
+    rotation: Direction,
+    translation: Vector,
+) -> MotionQueueBuffer {
+    motions
+        .iter()
+        .map(|motion| motion.transformed(rotation, translation))
+        .collect()
+}
+
+#[cfg(test)]
+mod transformed_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{transformed, Motion, MotionQueueBuffer};
+    use crate::config::sim;
+    use crate::fast::path::PathMotion;
+    use crate::fast::turn::TurnMotion;
+    use crate::fast::{Orientation, Vector, DIRECTION_0, DIRECTION_PI, DIRECTION_PI_2};
+
+    #[test]
+    fn transform_all() {
+        let mut motions = MotionQueueBuffer::new();
+        motions
+            .push(Motion::Path(PathMotion::line(
+                Vector { x: 0.0, y: 0.0 },
+                Vector { x: 90.0, y: 0.0 },
+            )))
+            .unwrap();
+        motions
+            .push(Motion::Turn(TurnMotion::new(DIRECTION_0, DIRECTION_PI_2)))
+            .unwrap();
+
+        let result = transformed(&motions, DIRECTION_PI_2, Vector { x: 10.0, y: 0.0 });
+
+        assert_eq!(result.len(), 2);
+
+        if let Motion::Path(path_motion) = result[0] {
+            assert_close2(path_motion.start(), Vector { x: 10.0, y: 0.0 });
+            assert_close2(path_motion.end(), Vector { x: 10.0, y: 90.0 });
+        } else {
+            panic!("Expected a path motion, got {:?}", result[0]);
+        }
+
+        if let Motion::Turn(turn_motion) = result[1] {
+            assert!(turn_motion.done(
+                &sim::MOTION_CONTROL.turn,
+                Orientation {
+                    position: Vector { x: 0.0, y: 0.0 },
+                    direction: DIRECTION_PI,
+                },
+            ));
+        } else {
+            panic!("Expected a turn motion, got {:?}", result[1]);
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ConcatError {
+    /// The first buffer ends this far away from where the second buffer starts
+    Discontinuous(f32),
+
+    /// The combined motions do not fit in one buffer
+    Overflow,
+}
+
+/// The position the buffer will start moving from, ignoring any turns in place and stops
+fn start_position(motions: &MotionQueueBuffer) -> Option<Vector> {
+    motions.iter().rev().find_map(|motion| match motion {
+        Motion::Path(path_motion) => Some(path_motion.start()),
+        Motion::Turn(_) | Motion::Stop(_) => None,
+    })
+}
+
+/// The position the buffer will end at, ignoring any turns in place and stops
+fn end_position(motions: &MotionQueueBuffer) -> Option<Vector> {
+    motions.iter().find_map(|motion| match motion {
+        Motion::Path(path_motion) => Some(path_motion.end()),
+        Motion::Turn(_) | Motion::Stop(_) => None,
+    })
+}
+
+/// Combine two buffers so that the motions in `a` are done before the motions in `b`
+///
+/// Like the motion queue, the buffers are stacks with the next motion at the end. The end of `a`
+/// must be within `tolerance` of the start of `b`, but there can be turns in place between them.
+pub fn concat(
+    a: &MotionQueueBuffer,
+    b: &MotionQueueBuffer,
+    tolerance: f32,
+) -> Result<MotionQueueBuffer, ConcatError> {
+    if let (Some(end), Some(start)) = (end_position(a), start_position(b)) {
+        let distance = (start - end).magnitude();
+        if distance > tolerance {
+            return Err(ConcatError::Discontinuous(distance));
+        }
+    }
+
+    let mut out = b.clone();
+    out.extend_from_slice(a)
+        .map_err(|_| ConcatError::Overflow)?;
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod concat_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{concat, ConcatError, Motion, MotionQueueBuffer};
+    use crate::fast::path::PathMotion;
+    use crate::fast::turn::TurnMotion;
+    use crate::fast::{Vector, DIRECTION_0, DIRECTION_PI_2};
+
+    fn line(x0: f32, y0: f32, x1: f32, y1: f32) -> Motion {
+        Motion::Path(PathMotion::line(
+            Vector { x: x0, y: y0 },
+            Vector { x: x1, y: y1 },
+        ))
+    }
+
+    fn buffer(motions: &[Motion]) -> MotionQueueBuffer {
+        let mut buffer = MotionQueueBuffer::new();
+        buffer.extend_from_slice(motions).unwrap();
+        buffer
+    }
+
+    #[test]
+    fn clean_join() {
+        let a = buffer(&[line(0.0, 0.0, 90.0, 0.0)]);
+        let b = buffer(&[line(90.0, 0.0, 180.0, 0.0)]);
+
+        assert_eq!(
+            concat(&a, &b, 1.0),
+            Ok(buffer(&[
+                line(90.0, 0.0, 180.0, 0.0),
+                line(0.0, 0.0, 90.0, 0.0),
+            ]))
+        );
+    }
+
+    #[test]
+    fn join_within_tolerance() {
+        let a = buffer(&[line(0.0, 0.0, 90.0, 0.0)]);
+        let b = buffer(&[line(90.5, 0.0, 180.0, 0.0)]);
+
+        assert!(concat(&a, &b, 1.0).is_ok());
+    }
+
+    #[test]
+    fn join_with_turn() {
+        let turn = Motion::Turn(TurnMotion::new(DIRECTION_0, DIRECTION_PI_2));
+        let a = buffer(&[line(0.0, 0.0, 90.0, 0.0)]);
+        let b = buffer(&[line(90.0, 0.0, 90.0, 90.0), turn]);
+
+        assert_eq!(
+            concat(&a, &b, 1.0),
+            Ok(buffer(&[
+                line(90.0, 0.0, 90.0, 90.0),
+                turn,
+                line(0.0, 0.0, 90.0, 0.0),
+            ]))
+        );
+    }
+
+    #[test]
+    fn discontinuous_join() {
+        let a = buffer(&[line(0.0, 0.0, 90.0, 0.0)]);
+        let b = buffer(&[line(180.0, 0.0, 270.0, 0.0)]);
+
+        match concat(&a, &b, 1.0) {
+            Err(ConcatError::Discontinuous(distance)) => assert_close(distance, 90.0),
+            result => panic!("Expected discontinuous, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn overflow() {
+        let a = buffer(&[line(90.0, 0.0, 180.0, 0.0), line(0.0, 0.0, 90.0, 0.0)]);
+        let b = buffer(&[
+            line(270.0, 0.0, 360.0, 0.0),
+            line(180.0, 0.0, 270.0, 0.0),
+            Motion::Turn(TurnMotion::new(DIRECTION_0, DIRECTION_0)),
+        ]);
+
+        assert_eq!(concat(&a, &b, 1.0), Err(ConcatError::Overflow));
+    }
+}
+
 pub struct MotionQueue {
     queue: MotionQueueBuffer,
+    completed_this_cycle: u8,
+
+    /// The last path motion to be finished, and how many more updates it can be picked back up
+    just_finished: Option<(PathMotion, u8)>,
+
+    /// The finished path motion that was picked back up, so it isn't counted twice
+    reacquired: Option<PathMotion>,
+
+    /// How many updates in a row the next turn has been within tolerance
+    turn_settled: u8,
+
+    /// How many updates the next stop has been held for
+    stopped: u8,
 }
 
 // heapless::Vec is dumb and needs to be a stack
 impl MotionQueue {
     pub fn new() -> MotionQueue {
-        MotionQueue { queue: Vec::new() }
+        MotionQueue {
+            queue: Vec::new(),
+            completed_this_cycle: 0,
+            just_finished: None,
+            reacquired: None,
+            turn_settled: 0,
+            stopped: 0,
+        }
     }
 
+    /// Throw out all of the motions. They do not count as completed.
     pub fn clear(&mut self) {
         self.queue.clear();
+        self.completed_this_cycle = 0;
+        self.just_finished = None;
+        self.reacquired = None;
+        self.turn_settled = 0;
+        self.stopped = 0;
     }
 
     pub fn add_motions(&mut self, motions: &[Motion]) -> Result<usize, usize> {
@@ -58,22 +306,122 @@ impl MotionQueue {
         Ok(MotionQueueSize::to_usize() - self.queue.len())
     }
 
+    /// Add motions to be done after all of the motions already in the queue. See [concat].
+    pub fn append(
+        &mut self,
+        motions: &MotionQueueBuffer,
+        tolerance: f32,
+    ) -> Result<(), ConcatError> {
+        self.queue = concat(&self.queue, motions, tolerance)?;
+        Ok(())
+    }
+
+    /// Whether the turn has been within tolerance for enough updates in a row to be done
+    fn turn_done(
+        &mut self,
+        config: &TurnHandlerConfig,
+        turn_motion: TurnMotion,
+        orientation: Orientation,
+    ) -> bool {
+        if !turn_motion.done(config, orientation) {
+            self.turn_settled = 0;
+            return false;
+        }
+
+        self.turn_settled = self.turn_settled.saturating_add(1);
+
+        if self.turn_settled >= config.settle_updates {
+            self.turn_settled = 0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Whether the stop has been held for all of its updates
+    fn stop_done(&mut self, stop_motion: StopMotion) -> bool {
+        self.stopped = self.stopped.saturating_add(1);
+
+        if self.stopped >= stop_motion.updates {
+            self.stopped = 0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Pop off all of the motions that are done, and return how many there were
+    ///
+    /// A path motion is only done once the mouse is `completion_hysteresis` mm past its end, see
+    /// [PathMotion::done_with_hysteresis].
+    ///
+    /// The last path motion popped is kept for `look_behind` updates. If the mouse gets moved
+    /// back before the start of the next motion and is closer to the finished one, the
+    /// finished one is put back on the queue to follow instead, as long as there is room for it.
+    /// It is not counted again when it finishes the second time.
     pub fn pop_completed(
         &mut self,
         turn_config: &TurnHandlerConfig,
+        look_behind: u8,
+        completion_hysteresis: f32,
         orientation: Orientation,
     ) -> usize {
+        if let Some((finished, cycles)) = self.just_finished.take() {
+            if let Some(Motion::Path(current)) = self.queue.last() {
+                let (t, _) = current.closest_point(orientation.position);
+                let (finished_t, finished_point) =
+                    finished.closest_point(orientation.position);
+
+                // Before the start, the closest point is along the extended tangent, so
+                // compare with the start itself
+                let closer = (finished_point - orientation.position).magnitude()
+                    < (current.start() - orientation.position).magnitude();
+
+                let room = self.queue.len() < MotionQueueSize::to_usize();
+
+                if t < 0.0 && finished_t < 1.0 && closer && room {
+                    self.queue.push(Motion::Path(finished)).ok();
+                    self.reacquired = Some(finished);
+                } else if cycles > 1 {
+                    self.just_finished = Some((finished, cycles - 1));
+                }
+            }
+        }
+
         let mut i = 0;
         // Go through the buffer and pop off any moves that have been completed
         while let Some(motion) = self.queue.pop() {
-            if motion.done(turn_config, orientation) {
-                i += 1;
+            let done = match motion {
+                Motion::Turn(turn_motion) => {
+                    self.turn_done(turn_config, turn_motion, orientation)
+                }
+                Motion::Stop(stop_motion) => self.stop_done(stop_motion),
+                Motion::Path(path_motion) => {
+                    path_motion.done_with_hysteresis(completion_hysteresis, orientation)
+                }
+            };
+
+            if done {
+                if self.reacquired.map(Motion::Path) == Some(motion) {
+                    self.reacquired = None;
+                } else {
+                    i += 1;
+                }
+
+                self.just_finished = match motion {
+                    Motion::Path(path_motion) if look_behind > 0 => {
+                        Some((path_motion, look_behind))
+                    }
+                    _ => None,
+                };
             } else {
                 self.queue.push(motion).ok();
                 break;
             }
         }
 
+        self.completed_this_cycle = i as u8;
+
         i
     }
 
@@ -81,6 +429,11 @@ impl MotionQueue {
         self.queue.len()
     }
 
+    /// All of the motions in the queue, with the next motion at the end
+    pub fn motions(&self) -> &MotionQueueBuffer {
+        &self.queue
+    }
+
     pub fn next_motion(&self) -> Option<Motion> {
         self.queue.last().cloned()
     }
@@ -88,6 +441,411 @@ impl MotionQueue {
     pub fn debug(&self) -> MotionQueueDebug {
         MotionQueueDebug {
             queue: self.queue.clone(),
+            completed_this_cycle: self.completed_this_cycle,
+        }
+    }
+}
+
+#[cfg(test)]
+mod completed_this_cycle_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{Motion, MotionQueue};
+    use crate::config::sim;
+    use crate::fast::path::PathMotion;
+    use crate::fast::{Orientation, Vector, DIRECTION_0};
+
+    fn at(x: f32) -> Orientation {
+        Orientation {
+            position: Vector { x, y: 0.0 },
+            direction: DIRECTION_0,
+        }
+    }
+
+    // Two motions along the x axis, the first ending at 90 and the second at 180
+    fn queue() -> MotionQueue {
+        let mut queue = MotionQueue::new();
+        queue
+            .add_motions(&[
+                Motion::Path(PathMotion::line(
+                    Vector { x: 90.0, y: 0.0 },
+                    Vector { x: 180.0, y: 0.0 },
+                )),
+                Motion::Path(PathMotion::line(
+                    Vector { x: 0.0, y: 0.0 },
+                    Vector { x: 90.0, y: 0.0 },
+                )),
+            ])
+            .unwrap();
+        queue
+    }
+
+    #[test]
+    fn nothing_completed() {
+        let mut queue = queue();
+        queue.pop_completed(&sim::MOTION_CONTROL.turn, 0, 0.0, at(45.0));
+        assert_eq!(queue.debug().completed_this_cycle, 0);
+    }
+
+    #[test]
+    fn past_boundary_completes_one() {
+        let mut queue = queue();
+        queue.pop_completed(&sim::MOTION_CONTROL.turn, 0, 0.0, at(45.0));
+        queue.pop_completed(&sim::MOTION_CONTROL.turn, 0, 0.0, at(100.0));
+        assert_eq!(queue.debug().completed_this_cycle, 1);
+        assert_eq!(queue.motions_remaining(), 1);
+
+        // It was only completed on that cycle
+        queue.pop_completed(&sim::MOTION_CONTROL.turn, 0, 0.0, at(110.0));
+        assert_eq!(queue.debug().completed_this_cycle, 0);
+    }
+
+    #[test]
+    fn past_both_completes_two() {
+        let mut queue = queue();
+        queue.pop_completed(&sim::MOTION_CONTROL.turn, 0, 0.0, at(200.0));
+        assert_eq!(queue.debug().completed_this_cycle, 2);
+    }
+
+    #[test]
+    fn clear_is_not_completing() {
+        let mut queue = queue();
+        queue.pop_completed(&sim::MOTION_CONTROL.turn, 0, 0.0, at(100.0));
+        queue.clear();
+        assert_eq!(queue.debug().completed_this_cycle, 0);
+    }
+}
+
+#[cfg(test)]
+mod look_behind_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{Motion, MotionQueue, MotionQueueBuffer, StopMotion};
+    use crate::config::sim;
+    use crate::fast::path::PathMotion;
+    use crate::fast::{Orientation, Vector, DIRECTION_0};
+
+    fn at(x: f32) -> Orientation {
+        Orientation {
+            position: Vector { x, y: 0.0 },
+            direction: DIRECTION_0,
+        }
+    }
+
+    fn first() -> PathMotion {
+        PathMotion::line(Vector { x: 0.0, y: 0.0 }, Vector { x: 90.0, y: 0.0 })
+    }
+
+    // Two motions along the x axis, the first ending at 90 and the second at 180
+    fn queue() -> MotionQueue {
+        let mut queue = MotionQueue::new();
+        queue
+            .add_motions(&[
+                Motion::Path(PathMotion::line(
+                    Vector { x: 90.0, y: 0.0 },
+                    Vector { x: 180.0, y: 0.0 },
+                )),
+                Motion::Path(first()),
+            ])
+            .unwrap();
+        queue
+    }
+
+    fn pop(queue: &mut MotionQueue, look_behind: u8, x: f32) -> usize {
+        queue.pop_completed(&sim::MOTION_CONTROL.turn, look_behind, 0.0, at(x))
+    }
+
+    #[test]
+    fn pushed_back_picks_up_finished_motion() {
+        let mut queue = queue();
+        assert_eq!(pop(&mut queue, 2, 95.0), 1);
+        assert_eq!(queue.motions_remaining(), 1);
+
+        // A correction puts the mouse back before the end of the first motion
+        assert_eq!(pop(&mut queue, 2, 80.0), 0);
+        assert_eq!(queue.motions_remaining(), 2);
+        match queue.next_motion() {
+            Some(Motion::Path(motion)) => assert_close2(motion.end(), first().end()),
+            motion => panic!("Expected the first path motion, got {:?}", motion),
+        }
+
+        // Then it finishes it again and carries on
+        assert_eq!(pop(&mut queue, 2, 95.0), 0);
+        assert_eq!(queue.motions_remaining(), 1);
+    }
+
+    #[test]
+    fn counted_once() {
+        let mut queue = queue();
+
+        let completed: usize = [95.0, 80.0, 95.0, 80.0, 95.0, 185.0]
+            .iter()
+            .map(|&x| pop(&mut queue, 2, x))
+            .sum();
+
+        assert_eq!(completed, 2);
+        assert_eq!(queue.motions_remaining(), 0);
+    }
+
+    #[test]
+    fn not_picked_up_when_full() {
+        let mut queue = queue();
+        pop(&mut queue, 2, 95.0);
+
+        let mut stops = MotionQueueBuffer::new();
+        for _ in 0..3 {
+            stops.push(Motion::Stop(StopMotion { updates: 1 })).unwrap();
+        }
+        queue.append(&stops, 0.0).unwrap();
+
+        assert_eq!(pop(&mut queue, 2, 80.0), 0);
+        assert_eq!(queue.motions_remaining(), 4);
+        match queue.next_motion() {
+            Some(Motion::Path(motion)) => {
+                assert_close2(motion.end(), Vector { x: 180.0, y: 0.0 })
+            }
+            motion => panic!("Expected the second path motion, got {:?}", motion),
+        }
+    }
+
+    #[test]
+    fn disabled() {
+        let mut queue = queue();
+        pop(&mut queue, 0, 95.0);
+        pop(&mut queue, 0, 80.0);
+        assert_eq!(queue.motions_remaining(), 1);
+    }
+
+    #[test]
+    fn forgotten_after_look_behind() {
+        let mut queue = queue();
+        pop(&mut queue, 1, 95.0);
+        pop(&mut queue, 1, 95.0);
+        pop(&mut queue, 1, 80.0);
+        assert_eq!(queue.motions_remaining(), 1);
+    }
+
+    #[test]
+    fn not_picked_up_while_on_next_motion() {
+        let mut queue = queue();
+        pop(&mut queue, 2, 95.0);
+        pop(&mut queue, 2, 100.0);
+        assert_eq!(queue.motions_remaining(), 1);
+    }
+}
+
+#[cfg(test)]
+mod turn_settle_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{Motion, MotionQueue};
+    use crate::config::sim;
+    use crate::fast::turn::{TurnHandlerConfig, TurnMotion};
+    use crate::fast::{Direction, Orientation, Vector, DIRECTION_0, DIRECTION_PI_2};
+
+    const CONFIG: TurnHandlerConfig = TurnHandlerConfig {
+        tolerance: 0.02,
+        settle_updates: 3,
+        ..sim::MOTION_CONTROL.turn
+    };
+
+    fn facing(direction: f32) -> Orientation {
+        Orientation {
+            position: Vector { x: 90.0, y: 90.0 },
+            direction: Direction::from(direction),
         }
     }
+
+    fn queue() -> MotionQueue {
+        let mut queue = MotionQueue::new();
+        queue
+            .add_motions(&[Motion::Turn(TurnMotion::new(DIRECTION_0, DIRECTION_PI_2))])
+            .unwrap();
+        queue
+    }
+
+    fn pop(queue: &mut MotionQueue, config: &TurnHandlerConfig, direction: f32) -> usize {
+        queue.pop_completed(config, 0, 0.0, facing(direction))
+    }
+
+    #[test]
+    fn overshoot_is_not_done() {
+        let target = f32::from(DIRECTION_PI_2);
+        let mut queue = queue();
+
+        // Swing through the target and past it
+        assert_eq!(pop(&mut queue, &CONFIG, target - 0.1), 0);
+        assert_eq!(pop(&mut queue, &CONFIG, target - 0.01), 0);
+        assert_eq!(pop(&mut queue, &CONFIG, target + 0.1), 0);
+
+        // Then come back and stay there
+        assert_eq!(pop(&mut queue, &CONFIG, target + 0.01), 0);
+        assert_eq!(pop(&mut queue, &CONFIG, target), 0);
+        assert_eq!(pop(&mut queue, &CONFIG, target - 0.005), 1);
+        assert_eq!(queue.motions_remaining(), 0);
+    }
+
+    #[test]
+    fn no_settling() {
+        let config = TurnHandlerConfig {
+            settle_updates: 1,
+            ..CONFIG
+        };
+
+        let mut queue = queue();
+        assert_eq!(
+            pop(&mut queue, &config, f32::from(DIRECTION_PI_2) - 0.01),
+            1
+        );
+    }
+
+    #[test]
+    fn outside_tolerance_is_not_done() {
+        let mut queue = queue();
+        for _ in 0..10 {
+            assert_eq!(
+                pop(&mut queue, &CONFIG, f32::from(DIRECTION_PI_2) - 0.05),
+                0
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod stop_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{Motion, MotionQueue, StopMotion};
+    use crate::config::sim;
+    use crate::fast::{Orientation, Vector, DIRECTION_0};
+
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 90.0, y: 90.0 },
+        direction: DIRECTION_0,
+    };
+
+    fn pop(queue: &mut MotionQueue) -> usize {
+        queue.pop_completed(&sim::MOTION_CONTROL.turn, 0, 0.0, ORIENTATION)
+    }
+
+    #[test]
+    fn holds_for_updates() {
+        let mut queue = MotionQueue::new();
+        queue
+            .add_motions(&[Motion::Stop(StopMotion { updates: 3 })])
+            .unwrap();
+
+        assert_eq!(pop(&mut queue), 0);
+        assert_eq!(pop(&mut queue), 0);
+        assert_eq!(pop(&mut queue), 1);
+        assert_eq!(queue.motions_remaining(), 0);
+    }
+
+    #[test]
+    fn back_to_back() {
+        let mut queue = MotionQueue::new();
+        queue
+            .add_motions(&[
+                Motion::Stop(StopMotion { updates: 3 }),
+                Motion::Stop(StopMotion { updates: 1 }),
+            ])
+            .unwrap();
+
+        // The count starts over for the second stop, starting with the update the first one
+        // finished on
+        assert_eq!(pop(&mut queue), 1);
+        assert_eq!(pop(&mut queue), 0);
+        assert_eq!(pop(&mut queue), 1);
+        assert_eq!(queue.motions_remaining(), 0);
+    }
+}
+
+#[cfg(test)]
+mod completion_hysteresis_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{Motion, MotionQueue};
+    use crate::config::sim;
+    use crate::fast::path::PathMotion;
+    use crate::fast::{Orientation, Vector, DIRECTION_0};
+
+    fn at(x: f32) -> Orientation {
+        Orientation {
+            position: Vector { x, y: 0.0 },
+            direction: DIRECTION_0,
+        }
+    }
+
+    // Two motions along the x axis, the first ending at 90 and the second at 180
+    fn queue() -> MotionQueue {
+        let mut queue = MotionQueue::new();
+        queue
+            .add_motions(&[
+                Motion::Path(PathMotion::line(
+                    Vector { x: 90.0, y: 0.0 },
+                    Vector { x: 180.0, y: 0.0 },
+                )),
+                Motion::Path(PathMotion::line(
+                    Vector { x: 0.0, y: 0.0 },
+                    Vector { x: 90.0, y: 0.0 },
+                )),
+            ])
+            .unwrap();
+        queue
+    }
+
+    /// Creep up through the end of the first motion with up to 1mm of noise in the position,
+    /// picking finished motions back up. Returns how many motions got finished, and how many
+    /// times the number of motions in the queue changed.
+    fn completions(hysteresis: f32) -> (usize, usize) {
+        let mut queue = queue();
+        let mut seed: u32 = 1;
+        let mut completed = 0;
+        let mut changes = 0;
+        let mut remaining = queue.motions_remaining();
+
+        for i in 0..100 {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let noise = ((seed >> 16) % 2001) as f32 / 1000.0 - 1.0;
+            let x = 85.0 + i as f32 * 0.1 + noise;
+
+            completed +=
+                queue.pop_completed(&sim::MOTION_CONTROL.turn, 5, hysteresis, at(x));
+
+            if queue.motions_remaining() != remaining {
+                changes += 1;
+                remaining = queue.motions_remaining();
+            }
+        }
+
+        (completed, changes)
+    }
+
+    #[test]
+    fn pops_once() {
+        assert_eq!(completions(2.5), (1, 1));
+    }
+
+    #[test]
+    fn thrashes_without_hysteresis() {
+        let (completed, changes) = completions(0.0);
+
+        // Picking the motion back up doesn't finish it again
+        assert_eq!(completed, 1);
+        assert!(changes > 1);
+    }
+
+    #[test]
+    fn not_done_until_past_hysteresis() {
+        let mut queue = queue();
+        let turn = &sim::MOTION_CONTROL.turn;
+
+        assert_eq!(queue.pop_completed(turn, 0, 2.0, at(91.0)), 0);
+        assert_eq!(queue.pop_completed(turn, 0, 2.0, at(92.5)), 1);
+    }
 }
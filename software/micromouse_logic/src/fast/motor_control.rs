@@ -5,9 +5,13 @@ use pid_control::Controller;
 use pid_control::PIDController;
 
 use crate::config::MechanicalConfig;
+use crate::units::Millis;
 use serde::Deserialize;
 use serde::Serialize;
 
+/// The motor power that drives a motor all the way
+pub const FULL_POWER: i32 = 10000;
+
 #[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct PidfConfig {
     pub p: f32,
@@ -45,7 +49,7 @@ pub struct MotorControlDebug {
 pub struct MotorControl {
     left_pid: PIDController,
     right_pid: PIDController,
-    last_time: u32,
+    last_time: Millis,
     last_left_encoder: i32,
     last_right_encoder: i32,
 }
@@ -58,7 +62,7 @@ impl MotorControl {
     /// until the update function is called.
     pub fn new(
         config: &MotorControlConfig,
-        time: u32,
+        time: Millis,
         left_encoder: i32,
         right_encoder: i32,
     ) -> MotorControl {
@@ -68,7 +72,7 @@ impl MotorControl {
             config.left_pidf.d as f64,
         );
 
-        left_pid.set_limits(-10000.0, 10000.0);
+        left_pid.set_limits(-FULL_POWER as f64, FULL_POWER as f64);
 
         let mut right_pid = PIDController::new(
             config.right_pidf.p as f64,
@@ -76,7 +80,7 @@ impl MotorControl {
             config.right_pidf.d as f64,
         );
 
-        right_pid.set_limits(-10000.0, 10000.0);
+        right_pid.set_limits(-FULL_POWER as f64, FULL_POWER as f64);
 
         MotorControl {
             left_pid,
@@ -92,7 +96,7 @@ impl MotorControl {
         &mut self,
         config: &MotorControlConfig,
         mech: &MechanicalConfig,
-        time: u32,
+        time: Millis,
         left_encoder: i32,
         right_encoder: i32,
         target_left_velocity: f32,
@@ -108,11 +112,33 @@ impl MotorControl {
 
         let delta_time = time - self.last_time;
 
+        // What the motor model says it should take to get to the target velocity. Without a
+        // model, the f gains feed the velocity forward instead.
+        let model_feedforward = |velocity: f32| {
+            (mech.power_for_wheel_velocity(velocity) * FULL_POWER as f32) as i32
+        };
+        let gain_feedforward =
+            |velocity: f32, f: f32| (mech.mm_to_ticks(velocity) as f64 * f as f64) as i32;
+
+        let (left_feedforward, right_feedforward) = if mech.max_wheel_velocity > 0.0 {
+            (
+                model_feedforward(target_left_velocity),
+                model_feedforward(target_right_velocity),
+            )
+        } else {
+            (
+                gain_feedforward(target_left_velocity, config.left_pidf.f),
+                gain_feedforward(target_right_velocity, config.right_pidf.f),
+            )
+        };
+
         let target_left_velocity = mech.mm_to_ticks(target_left_velocity) as f64;
         let target_right_velocity = mech.mm_to_ticks(target_right_velocity) as f64;
 
-        let delta_left = left_encoder - self.last_left_encoder;
-        let delta_right = right_encoder - self.last_right_encoder;
+        let (delta_left, delta_right) = mech.signed_encoder_deltas(
+            left_encoder - self.last_left_encoder,
+            right_encoder - self.last_right_encoder,
+        );
 
         let left_velocity = delta_left as f64 / delta_time as f64;
         let right_velocity = delta_right as f64 / delta_time as f64;
@@ -121,16 +147,14 @@ impl MotorControl {
             self.left_pid.set_target(target_left_velocity);
             self.right_pid.set_target(target_right_velocity);
 
-            let mut left_power = (target_left_velocity * config.left_pidf.f as f64)
-                as i32
+            let mut left_power = left_feedforward
                 + self.left_pid.update(left_velocity, delta_time as f64) as i32;
 
             if config.left_reverse {
                 left_power *= -1;
             }
 
-            let mut right_power = (target_right_velocity * config.right_pidf.f as f64)
-                as i32
+            let mut right_power = right_feedforward
                 + self.right_pid.update(right_velocity, delta_time as f64) as i32;
 
             if config.right_reverse {
@@ -158,3 +182,82 @@ impl MotorControl {
         (left_power, right_power, debug)
     }
 }
+
+#[cfg(test)]
+mod feedforward_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{MotorControl, MotorControlConfig, PidfConfig, FULL_POWER};
+    use crate::config::{mouse_2019, MechanicalConfig};
+
+    const NO_PID: PidfConfig = PidfConfig {
+        p: 0.0,
+        i: 0.0,
+        d: 0.0,
+        f: 0.0,
+    };
+
+    const CONFIG: MotorControlConfig = MotorControlConfig {
+        left_pidf: NO_PID,
+        left_reverse: false,
+        right_pidf: NO_PID,
+        right_reverse: false,
+    };
+
+    const MECH: MechanicalConfig = MechanicalConfig {
+        static_friction_power: 0.1,
+        max_wheel_velocity: 2.0,
+        ..mouse_2019::MECH
+    };
+
+    #[test]
+    fn powers_from_motor_model() {
+        let mut motor_control = MotorControl::new(&CONFIG, 0, 0, 0);
+        let (left, right, _) = motor_control.update(&CONFIG, &MECH, 10, 0, 0, 1.0, -2.0);
+
+        assert_eq!(left, (0.55 * FULL_POWER as f32) as i32);
+        assert_eq!(right, -FULL_POWER);
+    }
+
+    #[test]
+    fn model_replaces_f() {
+        let with_f = PidfConfig { f: 100.0, ..NO_PID };
+        let config = MotorControlConfig {
+            left_pidf: with_f,
+            right_pidf: with_f,
+            ..CONFIG
+        };
+
+        let mut motor_control = MotorControl::new(&config, 0, 0, 0);
+        let (left, right, _) = motor_control.update(&config, &MECH, 10, 0, 0, 1.0, -2.0);
+
+        assert_eq!(left, (0.55 * FULL_POWER as f32) as i32);
+        assert_eq!(right, -FULL_POWER);
+    }
+
+    #[test]
+    fn f_without_model() {
+        let with_f = PidfConfig { f: 0.01, ..NO_PID };
+        let config = MotorControlConfig {
+            left_pidf: with_f,
+            right_pidf: with_f,
+            ..CONFIG
+        };
+
+        let mech = mouse_2019::MECH;
+        let mut motor_control = MotorControl::new(&config, 0, 0, 0);
+        let (left, _, _) = motor_control.update(&config, &mech, 10, 0, 0, 1.0, 1.0);
+
+        assert_eq!(left, (mech.mm_to_ticks(1.0) as f64 * 0.01) as i32);
+    }
+
+    #[test]
+    fn no_model_no_power() {
+        let mut motor_control = MotorControl::new(&CONFIG, 0, 0, 0);
+        let (left, right, _) =
+            motor_control.update(&CONFIG, &mouse_2019::MECH, 10, 0, 0, 1.0, -2.0);
+
+        assert_eq!((left, right), (0, 0));
+    }
+}
@@ -8,12 +8,36 @@ use core::f32::consts::FRAC_PI_2;
 
 use serde::{Deserialize, Serialize};
 
+use heapless::Vec;
+use typenum::U256;
+
+use libm::F32Ext;
+
 use pid_control::{Controller, PIDController};
 
-use super::{Direction, Orientation, Vector};
+use super::{Direction, Orientation, Vector, DIRECTION_PI_2};
 
 use super::curve::{Bezier5, Curve};
+use super::motion_control::MotionControlConfig;
+use super::motion_queue::{Motion, MotionQueueBuffer};
 use crate::config::MechanicalConfig;
+use crate::slow::maze::{Maze, MazeConfig, Wall, WallDirection, WallIndex};
+use crate::units::Millis;
+
+/// How many points to check along a path motion when checking if it is in bounds
+const BOUNDS_STEPS: u32 = 32;
+
+/// How much to shrink the control points each time when clamping to a rectangle
+const CLAMP_STEP: f32 = 0.1;
+
+/// How many steps to take along a path motion when integrating the curvature
+const CURVATURE_STEPS: u32 = 128;
+
+/// How many steps to take along a path motion when looking for walls in the way
+const WALL_STEPS: u32 = 128;
+
+/// How many steps to take along a path motion when estimating the time to follow it
+const TIME_STEPS: u32 = 32;
 
 /**
  * A segment of a larger path
@@ -29,6 +53,10 @@ use crate::config::MechanicalConfig;
 #[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct PathMotion {
     bezier: Bezier5,
+
+    /// The fastest to go along this path motion in mm/ms, on top of any other velocity limits
+    #[serde(default)]
+    max_velocity: Option<f32>,
 }
 
 impl PathMotion {
@@ -49,18 +77,50 @@ impl PathMotion {
         end: Direction,
         radius: f32,
         offset: f32,
+    ) -> PathMotion {
+        PathMotion::corner_between(center, start, end, radius, radius, offset)
+    }
+
+    /// Like [PathMotion::corner], but the entrance line is `start_radius` long and the exit
+    /// line is `end_radius` long, like turning in a cell that isn't square
+    pub fn corner_between(
+        center: Vector,
+        start: Direction,
+        end: Direction,
+        start_radius: f32,
+        end_radius: f32,
+        offset: f32,
     ) -> PathMotion {
         let start_v = start.into_unit_vector();
         let end_v = end.into_unit_vector();
         PathMotion {
-            bezier: Bezier5 {
-                start: center - (radius - offset) * start_v,
-                ctrl0: center - (radius / 2.0) * start_v,
-                ctrl1: center - (radius / 3.0) * start_v,
-                ctrl2: center + (radius / 3.0) * end_v,
-                ctrl3: center + (radius / 2.0) * end_v,
-                end: center + (radius + offset) * end_v,
-            },
+            bezier: Bezier5::new(
+                center - (start_radius - offset) * start_v,
+                center - (start_radius / 2.0) * start_v,
+                center - (start_radius / 3.0) * start_v,
+                center + (end_radius / 3.0) * end_v,
+                center + (end_radius / 2.0) * end_v,
+                center + (end_radius + offset) * end_v,
+            ),
+            max_velocity: None,
+        }
+    }
+
+    /// Generate an s-curve from `start` to `end` that leaves and arrives going in `direction`,
+    /// like changing lanes
+    pub fn s_curve(start: Vector, end: Vector, direction: Direction) -> PathMotion {
+        let reach = (end - start).magnitude() / 5.0;
+        let tangent = direction.into_unit_vector();
+        PathMotion {
+            bezier: Bezier5::new(
+                start,
+                start + reach * tangent,
+                start + 2.0 * reach * tangent,
+                end - 2.0 * reach * tangent,
+                end - reach * tangent,
+                end,
+            ),
+            max_velocity: None,
         }
     }
 
@@ -68,14 +128,8 @@ impl PathMotion {
     pub fn line(start: Vector, end: Vector) -> PathMotion {
         let mid = (end - start) * 0.5 + start;
         PathMotion {
-            bezier: Bezier5 {
-                start,
-                ctrl0: mid,
-                ctrl1: mid,
-                ctrl2: mid,
-                ctrl3: mid,
-                end,
-            },
+            bezier: Bezier5::new(start, mid, mid, mid, mid, end),
+            max_velocity: None,
         }
     }
 
@@ -84,237 +138,2603 @@ impl PathMotion {
         self.bezier.closest_point(m)
     }
 
+    /// Find the point on the segment closest to `m`, searching from `guess`. See
+    /// [Curve::closest_point_near]
+    pub fn closest_point_near(&self, m: Vector, guess: f32) -> (f32, Vector) {
+        self.bezier.closest_point_near(m, guess)
+    }
+
+    /// The point at `t`, from 0 at the start to 1 at the end
+    pub fn at(&self, t: f32) -> Vector {
+        self.bezier.at(t)
+    }
+
     /// Derivative at `t`
     pub fn derivative(&self, t: f32) -> Vector {
         self.bezier.derivative().at(t)
     }
 
+    /// How fast the point moves along the path motion at `t`, in mm per unit of `t`. This is
+    /// the magnitude of [PathMotion::derivative], and integrates over `t` to the arc length.
+    /// It is not constant, even for a line.
+    pub fn speed(&self, t: f32) -> f32 {
+        self.derivative(t).magnitude()
+    }
+
     /// Curvature at `t`
     pub fn curvature(&self, t: f32) -> f32 {
         self.bezier.curvature(t)
     }
 
+    /// How far the path motion turns from start to end, in radians, positive when it turns
+    /// counterclockwise. This only looks at the ends, so a turn of more than half a circle
+    /// comes out going the other way.
+    pub fn turn_angle(&self) -> f32 {
+        let start = self.derivative(0.0).direction();
+        let end = self.derivative(1.0).direction();
+        end.centered_at(start) - f32::from(start)
+    }
+
+    /// The signed angle from the tangent at the closest point to the mouse's heading, positive
+    /// when the mouse is turned counterclockwise from the path. None if the tangent can't be
+    /// found.
+    pub fn heading_error(&self, orientation: Orientation) -> Option<f32> {
+        let (t, _) = self.closest_point(orientation.position);
+        let v_tangent = self.derivative(t);
+        if v_tangent.magnitude() == 0.0 || !v_tangent.magnitude().is_finite() {
+            return None;
+        }
+
+        let tangent = v_tangent.direction();
+        Some(orientation.direction.centered_at(tangent) - f32::from(tangent))
+    }
+
+    /// How far the mouse is from the closest point on the path motion, in the path motion's
+    /// frame there: `(along, cross)`. `along` is how far it is ahead of the closest point
+    /// along the tangent, which is only ever as far from 0 as the closest point search is off
+    /// by. `cross` is how far it is to the side, positive to the left of the path. None if the
+    /// tangent can't be found.
+    pub fn track_error(&self, orientation: Orientation) -> Option<(f32, f32)> {
+        let (t, p) = self.closest_point(orientation.position);
+        self.track_error_at(t, p, orientation.position)
+    }
+
+    /// Like [PathMotion::track_error], but measured from the point `p` at `t` that has
+    /// already been found
+    fn track_error_at(&self, t: f32, p: Vector, position: Vector) -> Option<(f32, f32)> {
+        let v_tangent = self.derivative(t);
+        let magnitude = v_tangent.magnitude();
+        if magnitude == 0.0 || !magnitude.is_finite() {
+            return None;
+        }
+
+        let v_m = position - p;
+        Some((
+            v_tangent.dot(v_m) / magnitude,
+            v_tangent.cross(v_m) / magnitude,
+        ))
+    }
+
+    /// Whether the mouse can start following this path motion from `orientation` without a
+    /// violent correction: within `max_offset` mm of the path and within `max_angle` radians
+    /// of its heading. If not, the mouse should turn in place first.
+    pub fn is_entry_valid(
+        &self,
+        orientation: Orientation,
+        max_offset: f32,
+        max_angle: f32,
+    ) -> bool {
+        let (_, p) = self.closest_point(orientation.position);
+        let offset = (orientation.position - p).magnitude();
+
+        match self.heading_error(orientation) {
+            Some(heading_error) => {
+                offset <= max_offset && heading_error.abs() <= max_angle
+            }
+            None => false,
+        }
+    }
+
     /// Whether this path motion is done or not
     pub fn done(&self, orientation: Orientation) -> bool {
-        self.bezier.closest_point(orientation.position).0 >= 1.0
+        self.done_with_hysteresis(0.0, orientation)
+    }
+
+    /// Whether the mouse is at least `hysteresis` mm past the end of this path motion, along
+    /// the direction it ends in. Past the end the closest point is always at the same `t`, so
+    /// the distance is checked instead. This keeps noise in the orientation right at the end
+    /// from making it flip back and forth between done and not done.
+    pub fn done_with_hysteresis(
+        &self,
+        hysteresis: f32,
+        orientation: Orientation,
+    ) -> bool {
+        if self.bezier.closest_point(orientation.position).0 < 1.0 {
+            return false;
+        }
+
+        if hysteresis <= 0.0 {
+            return true;
+        }
+
+        let tangent = self.bezier.derivative().at(1.0);
+        let past = (orientation.position - self.end()).dot(tangent) / tangent.magnitude();
+
+        past >= hysteresis
+    }
+
+    pub fn start(&self) -> Vector {
+        self.bezier.start
+    }
+
+    /// Go no faster than `max_velocity` in mm/ms along this path motion
+    pub fn with_max_velocity(self, max_velocity: f32) -> PathMotion {
+        PathMotion {
+            max_velocity: Some(max_velocity),
+            ..self
+        }
+    }
+
+    pub fn max_velocity(&self) -> Option<f32> {
+        self.max_velocity
+    }
+
+    /// `velocity`, slowed down to the max velocity of this path motion if there is one
+    pub fn limit_velocity(&self, velocity: f32) -> f32 {
+        match self.max_velocity {
+            Some(max_velocity) if max_velocity < velocity => max_velocity,
+            _ => velocity,
+        }
     }
 
     pub fn end(&self) -> Vector {
         self.bezier.end
     }
-}
 
-// Adjust the curvature for the mouse not being on the path
-fn offset_curvature(curvature: f32, distance: f32) -> f32 {
-    let r = 1.0 / curvature;
+    /// Rotate the path motion `rotation` about the origin, then move it by `translation`
+    pub fn transformed(&self, rotation: Direction, translation: Vector) -> PathMotion {
+        let transform = |v: Vector| v.rotated(rotation) + translation;
+        PathMotion {
+            bezier: Bezier5::new(
+                transform(self.bezier.start),
+                transform(self.bezier.ctrl0),
+                transform(self.bezier.ctrl1),
+                transform(self.bezier.ctrl2),
+                transform(self.bezier.ctrl3),
+                transform(self.bezier.end),
+            ),
+            max_velocity: self.max_velocity,
+        }
+    }
 
-    let r2 = if curvature > 0.0 {
-        r - distance
-    } else {
-        r + distance
-    };
+    /// How much the path motion turns in total, in radians. Turning left and turning right both
+    /// count, so an s-curve turns more than a straight line even though it ends going the same way
+    pub fn total_absolute_curvature(&self) -> f32 {
+        (0..CURVATURE_STEPS)
+            .map(|i| {
+                let t = (i as f32 + 0.5) / CURVATURE_STEPS as f32;
+                self.curvature(t).abs() * self.speed(t)
+            })
+            .sum::<f32>()
+            / CURVATURE_STEPS as f32
+    }
 
-    let curvature2 = 1.0 / r2;
-    curvature2
+    /// The sharpest curvature along the path motion, in 1/mm
+    pub fn max_curvature(&self) -> f32 {
+        (0..=CURVATURE_STEPS)
+            .map(|i| self.curvature(i as f32 / CURVATURE_STEPS as f32).abs())
+            .fold(0.0, f32::max)
+    }
+
+    /// Whether all of the path motion is inside the rectangle from `min` to `max`. This checks
+    /// points along the curve, so it may miss a very small overshoot between them
+    pub(crate) fn within_rect(&self, min: Vector, max: Vector) -> bool {
+        (0..=BOUNDS_STEPS).all(|i| {
+            let p = self.bezier.at(i as f32 / BOUNDS_STEPS as f32);
+            p.x >= min.x && p.x <= max.x && p.y >= min.y && p.y <= max.y
+        })
+    }
+
+    /// Pull the path motion back inside the rectangle from `min` to `max`
+    ///
+    /// The control points are moved towards a straight line between the start and end until the
+    /// curve fits. If the start or end are outside of the rectangle, it can't fit, and the
+    /// straight line is returned.
+    pub fn clamp_to_rect(&self, min: Vector, max: Vector) -> PathMotion {
+        let start = self.bezier.start;
+        let end = self.bezier.end;
+
+        // Move a control point `k` fifths of the way along the curve towards the straight line
+        let shrink = |ctrl: Vector, k: f32, factor: f32| {
+            let straight = start + (end - start) * (k / 5.0);
+            straight + (ctrl - straight) * factor
+        };
+
+        let mut factor = 1.0;
+        loop {
+            let path = PathMotion {
+                bezier: Bezier5::new(
+                    start,
+                    shrink(self.bezier.ctrl0, 1.0, factor),
+                    shrink(self.bezier.ctrl1, 2.0, factor),
+                    shrink(self.bezier.ctrl2, 3.0, factor),
+                    shrink(self.bezier.ctrl3, 4.0, factor),
+                    end,
+                ),
+                max_velocity: self.max_velocity,
+            };
+
+            if factor <= 0.0 || path.within_rect(min, max) {
+                break path;
+            }
+
+            factor -= CLAMP_STEP;
+        }
+    }
+}
+
+/// Where an arc of `radius` meets the incoming and outgoing lines of a corner, in that order.
+/// The lines cross at `center`, coming in going `start` and going out going `end`. The further
+/// the corner turns, the further the points are from `center`. For a square corner they are
+/// `radius` away, where [PathMotion::corner] starts and ends. If the lines don't turn, both
+/// points are at `center`.
+pub fn corner_tangent_points(
+    center: Vector,
+    start: Direction,
+    end: Direction,
+    radius: f32,
+) -> (Vector, Vector) {
+    let turn = end.centered_at(start) - f32::from(start);
+    let distance = radius * F32Ext::tan(turn.abs() / 2.0);
+
+    (
+        center - distance * start.into_unit_vector(),
+        center + distance * end.into_unit_vector(),
+    )
 }
 
 #[cfg(test)]
-mod offset_curvature_tests {
-    use super::offset_curvature;
+mod corner_tangent_points_tests {
     #[allow(unused_imports)]
     use crate::test::*;
 
-    #[test]
-    fn zero_distance_positive_curvature() {
-        assert_close(offset_curvature(1.0, 0.0), 1.0)
-    }
+    use core::f32::consts::FRAC_PI_4;
 
-    #[test]
-    fn positive_distance_positive_curvature() {
-        assert_close(offset_curvature(1.0, 0.5), 2.0)
-    }
+    use libm::F32Ext;
+
+    use super::{corner_tangent_points, PathMotion};
+    use crate::fast::{Direction, Vector, DIRECTION_0, DIRECTION_PI_2};
+
+    const CENTER: Vector = Vector { x: 90.0, y: 90.0 };
 
     #[test]
-    fn negative_distance_positive_curvature() {
-        assert_close(offset_curvature(1.0, -0.5), 0.6666667)
+    fn square_left() {
+        let (incoming, outgoing) =
+            corner_tangent_points(CENTER, DIRECTION_0, DIRECTION_PI_2, 90.0);
+
+        // On the incoming line going east, and the outgoing line going north
+        assert_close2(incoming, Vector { x: 0.0, y: 90.0 });
+        assert_close2(outgoing, Vector { x: 90.0, y: 180.0 });
     }
 
     #[test]
-    fn zero_distance_negative_curvature() {
-        assert_close(offset_curvature(-1.0, 0.0), -1.0)
+    fn square_right() {
+        let (incoming, outgoing) =
+            corner_tangent_points(CENTER, DIRECTION_PI_2, DIRECTION_0, 45.0);
+
+        assert_close2(incoming, Vector { x: 90.0, y: 45.0 });
+        assert_close2(outgoing, Vector { x: 135.0, y: 90.0 });
     }
 
     #[test]
-    fn positive_distance_negative_curvature() {
-        assert_close(offset_curvature(-1.0, 0.5), -2.0)
+    fn square_matches_corner() {
+        let (incoming, outgoing) =
+            corner_tangent_points(CENTER, DIRECTION_PI_2, DIRECTION_0, 90.0);
+        let corner = PathMotion::corner(CENTER, DIRECTION_PI_2, DIRECTION_0, 90.0, 0.0);
+
+        assert_close2(incoming, corner.start());
+        assert_close2(outgoing, corner.end());
     }
 
     #[test]
-    fn negative_distance_negative_curvature() {
-        assert_close(offset_curvature(-1.0, -0.5), -0.66666667)
+    fn shallow_is_closer() {
+        let (incoming, outgoing) =
+            corner_tangent_points(CENTER, DIRECTION_0, Direction::from(FRAC_PI_4), 90.0);
+
+        let distance = 90.0 * F32Ext::tan(FRAC_PI_4 / 2.0);
+        assert_close((CENTER - incoming).magnitude(), distance);
+        assert_close((outgoing - CENTER).magnitude(), distance);
+        assert!(distance < 90.0);
+        assert_close(f32::from((CENTER - incoming).direction()), 0.0);
+        assert_close(f32::from((outgoing - CENTER).direction()), FRAC_PI_4);
     }
 
     #[test]
-    fn zero_curvature() {
-        assert_close(offset_curvature(0.0, 0.5), 0.0)
+    fn straight() {
+        let (incoming, outgoing) =
+            corner_tangent_points(CENTER, DIRECTION_0, DIRECTION_0, 90.0);
+        assert_close2(incoming, CENTER);
+        assert_close2(outgoing, CENTER);
     }
 }
 
-fn curvature_to_left_right(
-    config: &MechanicalConfig,
-    velocity: f32,
-    curvature: f32,
-) -> (f32, f32) {
-    let rotations_per_ms = velocity * curvature;
-    let angular_mm_per_ms = rotations_per_ms * config.wheelbase / 2.0;
-    let left = velocity - angular_mm_per_ms;
-    let right = velocity + angular_mm_per_ms;
-    (left, right)
+/// Two path motions that shift `lateral_offset` to the left of `direction` (negative for
+/// right) while going `length` forward from `start`, like changing lanes. This is
+/// [PathMotion::s_curve] split in the middle, so the first curves one way to the midpoint and
+/// the second curves back the same amount, and they meet tangent with no curvature. Unlike a
+/// single [PathMotion::s_curve], each half can be given its own max velocity.
+pub fn s_curve(
+    start: Vector,
+    direction: Direction,
+    lateral_offset: f32,
+    length: f32,
+) -> (PathMotion, PathMotion) {
+    let forward = direction.into_unit_vector();
+    let left = (direction + DIRECTION_PI_2).into_unit_vector();
+    let end = start + length * forward + lateral_offset * left;
+
+    let (first, second) = PathMotion::s_curve(start, end, direction).bezier.split(0.5);
+
+    (
+        PathMotion {
+            bezier: first,
+            max_velocity: None,
+        },
+        PathMotion {
+            bezier: second,
+            max_velocity: None,
+        },
+    )
 }
 
 #[cfg(test)]
-mod curvature_to_left_right_test {
+mod s_curve_tests {
     #[allow(unused_imports)]
     use crate::test::*;
 
-    use super::curvature_to_left_right;
-    use crate::config::MechanicalConfig;
+    use super::s_curve;
+    use crate::fast::{Direction, Vector, DIRECTION_0, DIRECTION_PI_2};
 
-    const CONFIG: MechanicalConfig = crate::config::mouse_2019::MECH;
+    const START: Vector = Vector { x: 90.0, y: 90.0 };
+
+    fn directions() -> [Direction; 3] {
+        [DIRECTION_0, DIRECTION_PI_2, Direction::from(0.3)]
+    }
 
     #[test]
-    fn test_curvature_to_left_right_circle() {
-        let (left, right) = curvature_to_left_right(&CONFIG, 0.5, 1.0 / 90.0);
-        assert_close(left, 0.294444);
-        assert_close(right, 0.705556);
+    fn tangent_continuous() {
+        for &direction in directions().iter() {
+            for &offset in [20.0, -20.0].iter() {
+                let (first, second) = s_curve(START, direction, offset, 180.0);
+
+                assert_close2(first.end(), second.start());
+                assert!(first
+                    .derivative(1.0)
+                    .direction()
+                    .within(second.derivative(0.0).direction(), 0.0001));
+                assert_close(first.curvature(1.0), 0.0);
+                assert_close(second.curvature(0.0), 0.0);
+            }
+        }
     }
 
     #[test]
-    fn test_curvature_to_left_right_straight() {
-        let (left, right) = curvature_to_left_right(&CONFIG, 0.5, 0.0);
-        assert_close(left, 0.5);
-        assert_close(right, 0.5);
+    fn ends_along_direction() {
+        for &direction in directions().iter() {
+            let (first, second) = s_curve(START, direction, 20.0, 180.0);
+
+            assert!(first.derivative(0.0).direction().within(direction, 0.0001));
+            assert!(second.derivative(1.0).direction().within(direction, 0.0001));
+            assert_close(first.curvature(0.0), 0.0);
+            assert_close(second.curvature(1.0), 0.0);
+        }
     }
-}
 
-#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
-pub struct PathHandlerDebug {
-    pub closest_point: Option<(f32, Vector)>,
-    pub distance_from: Option<f32>,
-    pub tangent_direction: Option<Direction>,
-    pub adjust_direction: Option<Direction>,
-    pub centered_direction: Option<f32>,
-    pub adjust_curvature: Option<f32>,
-}
+    #[test]
+    fn shifts_by_offset() {
+        for &direction in directions().iter() {
+            for &offset in [20.0, -20.0, 0.0].iter() {
+                let (_, second) = s_curve(START, direction, offset, 180.0);
 
-#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
-pub struct PathHandlerConfig {
-    pub p: f32,
-    pub i: f32,
-    pub d: f32,
-    pub offset_p: f32,
-    pub velocity: f32,
-}
+                let forward = direction.into_unit_vector();
+                let left = (direction + DIRECTION_PI_2).into_unit_vector();
+                let shift = second.end() - START;
 
-#[derive(Clone, Debug)]
-pub struct PathHandler {
-    pub direction_pid: PIDController,
-    pub time: u32,
-}
+                assert!((shift.dot(forward) - 180.0).abs() < 0.001);
+                assert!((shift.dot(left) - offset).abs() < 0.001);
+            }
+        }
+    }
 
-impl PathHandler {
-    pub fn new(config: &PathHandlerConfig, time: u32) -> PathHandler {
-        let pid = PIDController::new(config.p as f64, config.i as f64, config.d as f64);
-        PathHandler {
-            direction_pid: pid,
-            time,
+    #[test]
+    fn curvature_balanced() {
+        let (first, second) = s_curve(START, DIRECTION_0, 20.0, 180.0);
+
+        // Left then right, the same amount
+        assert!(first.curvature(0.25) > 0.0);
+        for &t in [0.25, 0.5, 0.75].iter() {
+            assert!((first.curvature(t) + second.curvature(1.0 - t)).abs() < 0.00001);
         }
+
+        assert!(
+            (first.total_absolute_curvature() - second.total_absolute_curvature()).abs()
+                < 0.0001
+        );
     }
 
-    pub fn update(
-        &mut self,
-        config: &PathHandlerConfig,
-        mech: &MechanicalConfig,
-        time: u32,
-        orientation: Orientation,
-        segment: PathMotion,
-    ) -> (f32, f32, PathHandlerDebug) {
-        let mut debug = PathHandlerDebug::default();
+    #[test]
+    fn no_length_or_offset_stays_put() {
+        let (first, second) = s_curve(START, DIRECTION_0, 0.0, 0.0);
 
-        let delta_time = time - self.time;
+        for &t in [0.0, 0.5, 1.0].iter() {
+            assert_close2(first.at(t), START);
+            assert_close2(second.at(t), START);
+        }
+    }
+}
 
-        self.direction_pid.p_gain = config.p as f64;
-        self.direction_pid.i_gain = config.i as f64;
-        self.direction_pid.d_gain = config.d as f64;
+/// How much all of the path motions in `buffer` turn, in radians. Lower is usually smoother and
+/// faster. Turns in place are not included.
+pub fn total_absolute_curvature(buffer: &MotionQueueBuffer) -> f32 {
+    buffer
+        .iter()
+        .filter_map(|motion| match motion {
+            Motion::Path(path_motion) => Some(path_motion.total_absolute_curvature()),
+            Motion::Turn(_) | Motion::Stop(_) => None,
+        })
+        .sum()
+}
 
-        // Gather some info about the segment
-        let (t, p) = segment.closest_point(orientation.position);
-        debug.closest_point = Some((t, p));
-        let v_tangent = segment.derivative(t);
-        let v_m = orientation.position - p;
-        let distance = if v_tangent.cross(v_m) > 0.0 {
-            v_m.magnitude()
-        } else {
-            -v_m.magnitude()
-        };
+/// The sharpest curvature of all of the path motions in `buffer`, in 1/mm. Turns in place are
+/// not included.
+pub fn max_curvature(buffer: &MotionQueueBuffer) -> f32 {
+    buffer
+        .iter()
+        .filter_map(|motion| match motion {
+            Motion::Path(path_motion) => Some(path_motion.max_curvature()),
+            Motion::Turn(_) | Motion::Stop(_) => None,
+        })
+        .fold(0.0, f32::max)
+}
 
-        let tangent = v_tangent.direction();
+/// Whether the path motions in `buffer` join up, in the order they will be followed. Each one
+/// has to start within `position_tolerance` mm of where the one before it ended, going within
+/// `angle_tolerance` radians of the same direction. A turn in place between two path motions
+/// lets the direction change, and stops are skipped.
+pub fn is_continuous(
+    buffer: &MotionQueueBuffer,
+    position_tolerance: f32,
+    angle_tolerance: f32,
+) -> bool {
+    let mut last: Option<PathMotion> = None;
+    let mut turned = false;
 
-        let path_curvature = segment.curvature(t);
+    // The next motion is at the end of the buffer
+    for motion in buffer.iter().rev() {
+        match motion {
+            Motion::Path(path_motion) => {
+                if let Some(last) = last {
+                    if (path_motion.start() - last.end()).magnitude() > position_tolerance
+                    {
+                        return false;
+                    }
 
-        // If there was another segment, try to follow it
-        // The curvature of the path where the mouse is
-        let offset_curvature = offset_curvature(path_curvature, distance);
+                    let end_direction = last.derivative(1.0).direction();
+                    let start_direction = path_motion.derivative(0.0).direction();
+                    if !turned && !start_direction.within(end_direction, angle_tolerance)
+                    {
+                        return false;
+                    }
+                }
 
-        let adjust_curvature = if config.offset_p != 0.0 {
-            // Need to calculate an adjustment curvature to get the mouse back on the path
-            // This gets added to the offset curvature above to get the final path curvature.
-            // As such, it should always turn the mouse towards the path, but avoid turning
-            // past the path. This is done by calculating a target direction that points towards the
-            // path far away, but along the path close up. A curvature is then calculated that
-            // should get the mouse to that direction in the next loop (assuming no physics
-            // limitations. This should probably be limited base on the mechanics).
+                last = Some(*path_motion);
+                turned = false;
+            }
+            Motion::Turn(_) => turned = true,
+            Motion::Stop(_) => {}
+        }
+    }
 
-            // This s-curve will asymptote at -pi/2 and pi/2, and cross the origin.
-            // Points the mouse directly at the path far away, but along the path
-            // close up. The offset_p determines how aggressive it is
-            let adjust_direction_offset = -distance * config.offset_p;
+    true
+}
 
-            let adjust_direction_offset = if adjust_direction_offset > FRAC_PI_2 {
-                FRAC_PI_2
-            } else if adjust_direction_offset < -FRAC_PI_2 {
-                -FRAC_PI_2
-            } else {
-                adjust_direction_offset
-            };
+#[cfg(test)]
+mod is_continuous_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
 
-            //PI / (1.0 + F32Ext::exp(config.offset_p * distance)) - FRAC_PI_2;
+    use super::{is_continuous, PathMotion};
+    use crate::config::{MAZE, MOTION_PLAN};
+    use crate::fast::motion_queue::{Motion, MotionQueueBuffer};
+    use crate::fast::turn::TurnMotion;
+    use crate::fast::{Orientation, Vector, DIRECTION_0, DIRECTION_PI_2};
+    use crate::slow::motion_plan::{from_cells, PathStyle};
+    use crate::slow::navigate::CellPath;
+    use crate::slow::MazePosition;
 
-            let adjust_direction = tangent + Direction::from(adjust_direction_offset);
-            debug.adjust_direction = Some(adjust_direction);
+    const POSITION_TOLERANCE: f32 = 1.0;
+    const ANGLE_TOLERANCE: f32 = 0.1;
 
-            let centered_direction = orientation.direction.centered_at(adjust_direction);
-            debug.centered_direction = Some(centered_direction);
+    fn line(x0: f32, y0: f32, x1: f32, y1: f32) -> Motion {
+        Motion::Path(PathMotion::line(
+            Vector { x: x0, y: y0 },
+            Vector { x: x1, y: y1 },
+        ))
+    }
 
-            self.direction_pid
-                .set_target(f32::from(adjust_direction) as f64);
-            self.direction_pid
-                .update(centered_direction as f64, delta_time as f64) as f32
-        } else {
-            0.0
-        };
+    /// The next motion is last, so `motions` is backwards from the order they are followed in
+    fn buffer(motions: &[Motion]) -> MotionQueueBuffer {
+        let mut buffer = MotionQueueBuffer::new();
+        buffer.extend_from_slice(motions).unwrap();
+        buffer
+    }
 
-        let target_curvature = offset_curvature + adjust_curvature;
+    fn continuous(buffer: &MotionQueueBuffer) -> bool {
+        is_continuous(buffer, POSITION_TOLERANCE, ANGLE_TOLERANCE)
+    }
 
-        let (target_left_velocity, target_right_velocity) =
-            curvature_to_left_right(mech, config.velocity, target_curvature);
+    #[test]
+    fn from_cells_is_continuous() {
+        let mut cells = CellPath::new();
+        cells
+            .extend_from_slice(&[
+                MazePosition { x: 0, y: 0 },
+                MazePosition { x: 0, y: 1 },
+                MazePosition { x: 1, y: 1 },
+                MazePosition { x: 1, y: 2 },
+            ])
+            .unwrap();
 
-        debug.distance_from = Some(distance);
-        debug.tangent_direction = Some(tangent);
-        debug.adjust_curvature = Some(adjust_curvature);
+        let start = Orientation {
+            position: Vector { x: 90.0, y: 90.0 },
+            direction: DIRECTION_PI_2,
+        };
 
-        self.time = time;
+        for &style in [PathStyle::CenterLine, PathStyle::RacingLine].iter() {
+            let (motions, _) = from_cells(&MOTION_PLAN, &MAZE, start, &cells, style);
+            assert!(continuous(&motions), "{:?}", style);
+        }
+    }
 
-        (target_left_velocity, target_right_velocity, debug)
+    #[test]
+    fn empty() {
+        assert!(continuous(&MotionQueueBuffer::new()));
+    }
+
+    #[test]
+    fn gap() {
+        let motions = buffer(&[line(100.0, 0.0, 200.0, 0.0), line(0.0, 0.0, 90.0, 0.0)]);
+        assert!(!continuous(&motions));
+    }
+
+    #[test]
+    fn kink() {
+        let motions = buffer(&[line(90.0, 0.0, 90.0, 90.0), line(0.0, 0.0, 90.0, 0.0)]);
+        assert!(!continuous(&motions));
+    }
+
+    #[test]
+    fn kink_with_turn() {
+        let motions = buffer(&[
+            line(90.0, 0.0, 90.0, 90.0),
+            Motion::Turn(TurnMotion::new(DIRECTION_0, DIRECTION_PI_2)),
+            line(0.0, 0.0, 90.0, 0.0),
+        ]);
+        assert!(continuous(&motions));
+    }
+
+    #[test]
+    fn gap_with_turn() {
+        let motions = buffer(&[
+            line(100.0, 0.0, 100.0, 90.0),
+            Motion::Turn(TurnMotion::new(DIRECTION_0, DIRECTION_PI_2)),
+            line(0.0, 0.0, 90.0, 0.0),
+        ]);
+        assert!(!continuous(&motions));
+    }
+}
+
+/// The most points a [CurvatureProfile] can hold
+pub type CurvatureProfileSize = U256;
+
+/// (distance along the path in mm, curvature in 1/mm) pairs, see [curvature_profile]
+pub type CurvatureProfile = Vec<(f32, f32), CurvatureProfileSize>;
+
+/// The curvature along all of the path motions in `buffer`, in the order they will be followed.
+/// Each path motion is sampled `samples_per_motion` times, evenly in `t` from its start to its
+/// end, and the distance is measured between samples. Turns in place don't go anywhere, so they
+/// are skipped. If there are too many samples to fit, the profile stops early.
+pub fn curvature_profile(
+    buffer: &MotionQueueBuffer,
+    samples_per_motion: usize,
+) -> CurvatureProfile {
+    let mut profile = CurvatureProfile::new();
+    let mut distance = 0.0;
+    let mut last_point = None;
+
+    // The next motion is at the end of the buffer
+    let path_motions = buffer.iter().rev().filter_map(|motion| match motion {
+        Motion::Path(path_motion) => Some(path_motion),
+        Motion::Turn(_) | Motion::Stop(_) => None,
+    });
+
+    for path_motion in path_motions {
+        for i in 0..samples_per_motion {
+            let t = if samples_per_motion > 1 {
+                i as f32 / (samples_per_motion - 1) as f32
+            } else {
+                0.0
+            };
+
+            let point = path_motion.bezier.at(t);
+            if let Some(last_point) = last_point {
+                distance += (point - last_point).magnitude();
+            }
+            last_point = Some(point);
+
+            if profile.push((distance, path_motion.curvature(t))).is_err() {
+                return profile;
+            }
+        }
+    }
+
+    profile
+}
+
+/// A point sampled along the path motions in a buffer, see [planned_points]
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+struct PlannedPoint {
+    /// The distance from the last point, in mm
+    step: f32,
+
+    /// The curvature at this point, in 1/mm
+    curvature: f32,
+
+    /// The fastest the mouse can be going at this point, in mm/ms
+    max_velocity: f32,
+
+    /// The velocity to go at since the last point, in mm/ms
+    velocity: f32,
+}
+
+/// Sample all of the path motions in `buffer` and work out how fast the mouse can go at each
+/// point. See [estimated_time] for the limits.
+fn planned_points(
+    buffer: &MotionQueueBuffer,
+    config: &MotionControlConfig,
+) -> Vec<PlannedPoint, U256> {
+    let mut points: Vec<PlannedPoint, U256> = Vec::new();
+    points.push(PlannedPoint::default()).ok();
+
+    // The next motion is at the end of the buffer
+    for motion in buffer.iter().rev() {
+        let path_motion = match motion {
+            Motion::Path(path_motion) => path_motion,
+            Motion::Turn(_) | Motion::Stop(_) => {
+                if let Some(last) = points.last_mut() {
+                    last.max_velocity = 0.0;
+                }
+                continue;
+            }
+        };
+
+        if points.len() == 1 {
+            points[0].curvature = path_motion.curvature(0.0);
+        }
+
+        let max_velocity = path_motion.limit_velocity(config.max_velocity);
+        let velocity = path_motion
+            .limit_velocity(config.path.velocity)
+            .max(config.min_velocity.min(max_velocity))
+            .min(max_velocity);
+
+        let mut last_point = path_motion.bezier.at(0.0);
+        for i in 1..=TIME_STEPS {
+            let t = i as f32 / TIME_STEPS as f32;
+            let point = path_motion.bezier.at(t);
+            let step = (point - last_point).magnitude();
+            last_point = point;
+
+            if let Some(last) = points.last_mut() {
+                last.max_velocity = last.max_velocity.min(velocity);
+            }
+            points
+                .push(PlannedPoint {
+                    step,
+                    curvature: path_motion.curvature(t),
+                    max_velocity: velocity,
+                    velocity,
+                })
+                .ok();
+        }
+    }
+
+    if let Some(last) = points.last_mut() {
+        last.max_velocity = 0.0;
+    }
+
+    if config.max_acceleration <= 0.0 {
+        return points;
+    }
+
+    // Speed up as fast as possible going forward, then slow down going backward, so every
+    // point is as fast as it can be while still being able to slow down for the next ones
+    for i in 1..points.len() {
+        let reachable = (points[i - 1].max_velocity * points[i - 1].max_velocity
+            + 2.0 * config.max_acceleration * points[i].step)
+            .sqrt();
+        points[i].max_velocity = points[i].max_velocity.min(reachable);
+    }
+
+    for i in (0..points.len() - 1).rev() {
+        let reachable = (points[i + 1].max_velocity * points[i + 1].max_velocity
+            + 2.0 * config.max_acceleration * points[i + 1].step)
+            .sqrt();
+        points[i].max_velocity = points[i].max_velocity.min(reachable);
+    }
+
+    points
+}
+
+/// How long it will take to follow all of the path motions in `buffer`, in ms, starting and
+/// ending stopped. Each path motion is followed at the path velocity, limited by its own max
+/// velocity and the max velocity of the motion control. The mouse speeds up and slows down at
+/// `max_acceleration`, or changes speed right away if that is 0. Turns in place are not timed,
+/// but the mouse has to stop for them. Infinite if the mouse would never get there.
+pub fn estimated_time(buffer: &MotionQueueBuffer, config: &MotionControlConfig) -> f32 {
+    let points = planned_points(buffer, config);
+
+    if config.max_acceleration <= 0.0 {
+        return points
+            .iter()
+            .map(|point| {
+                if point.step == 0.0 {
+                    0.0
+                } else if point.velocity <= 0.0 {
+                    f32::INFINITY
+                } else {
+                    point.step / point.velocity
+                }
+            })
+            .sum();
+    }
+
+    // Between points, speed up towards the planned velocity and slow down again in time
+    let acceleration = config.max_acceleration;
+    points
+        .windows(2)
+        .map(|window| {
+            let start_velocity = window[0].max_velocity;
+            let end_velocity = window[1].max_velocity;
+            let distance = window[1].step;
+            let velocity = window[1].velocity;
+
+            let speed_up = (velocity * velocity - start_velocity * start_velocity)
+                / (2.0 * acceleration);
+            let slow_down = (velocity * velocity - end_velocity * end_velocity)
+                / (2.0 * acceleration);
+
+            if distance == 0.0 {
+                0.0
+            } else if velocity <= 0.0 {
+                f32::INFINITY
+            } else if speed_up + slow_down <= distance {
+                (velocity - start_velocity) / acceleration
+                    + (velocity - end_velocity) / acceleration
+                    + (distance - speed_up - slow_down) / velocity
+            } else {
+                // Never gets up to the planned velocity
+                let peak = ((2.0 * acceleration * distance
+                    + start_velocity * start_velocity
+                    + end_velocity * end_velocity)
+                    / 2.0)
+                    .sqrt();
+                (peak - start_velocity) / acceleration
+                    + (peak - end_velocity) / acceleration
+            }
+        })
+        .sum()
+}
+
+/// (distance along the path in mm, curvature in 1/mm, velocity in mm/ms) triples, see
+/// [velocity_profile]
+pub type VelocityProfile = Vec<(f32, f32, f32), U256>;
+
+/// The velocity the mouse plans to go along all of the path motions in `buffer`, in the order
+/// they will be followed, with the same limits as [estimated_time]. Each path motion is sampled
+/// evenly in `t`, and the velocity is the fastest the mouse can be going at each point while
+/// still being able to slow down for the next ones.
+pub fn velocity_profile(
+    buffer: &MotionQueueBuffer,
+    config: &MotionControlConfig,
+) -> VelocityProfile {
+    let mut distance = 0.0;
+
+    planned_points(buffer, config)
+        .iter()
+        .map(|point| {
+            distance += point.step;
+            (distance, point.curvature, point.max_velocity)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod estimated_time_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{curvature_profile, estimated_time, PathHandlerConfig, PathMotion};
+    use crate::config::sim::MOTION_CONTROL;
+    use crate::fast::motion_control::MotionControlConfig;
+    use crate::fast::motion_queue::{Motion, MotionQueueBuffer};
+    use crate::fast::turn::TurnMotion;
+    use crate::fast::{Vector, DIRECTION_0, DIRECTION_PI_2};
+
+    const NO_ACCELERATION: MotionControlConfig = MotionControlConfig {
+        max_acceleration: 0.0,
+        ..MOTION_CONTROL
+    };
+
+    fn buffer(motions: &[Motion]) -> MotionQueueBuffer {
+        let mut buffer = MotionQueueBuffer::new();
+        buffer.extend_from_slice(motions).unwrap();
+        buffer
+    }
+
+    fn line() -> PathMotion {
+        PathMotion::line(Vector { x: 0.0, y: 90.0 }, Vector { x: 540.0, y: 90.0 })
+    }
+
+    fn corner() -> PathMotion {
+        PathMotion::corner(
+            Vector { x: 630.0, y: 90.0 },
+            DIRECTION_0,
+            DIRECTION_PI_2,
+            90.0,
+            0.0,
+        )
+    }
+
+    #[test]
+    fn empty() {
+        assert_close(
+            estimated_time(&MotionQueueBuffer::new(), &MOTION_CONTROL),
+            0.0,
+        );
+    }
+
+    #[test]
+    fn straight_is_length_over_velocity() {
+        let time = estimated_time(&buffer(&[Motion::Path(line())]), &NO_ACCELERATION);
+        let expected = 540.0 / NO_ACCELERATION.path.velocity;
+        assert!((time - expected).abs() < 0.01, "{} != {}", time, expected);
+    }
+
+    #[test]
+    fn straight_speeds_up_and_slows_down() {
+        let time = estimated_time(&buffer(&[Motion::Path(line())]), &MOTION_CONTROL);
+
+        // Getting up to speed and back down again takes twice as long as going the same
+        // distance at full speed
+        let velocity = MOTION_CONTROL.path.velocity;
+        let expected = 540.0 / velocity + velocity / MOTION_CONTROL.max_acceleration;
+        assert!((time - expected).abs() < 0.01, "{} != {}", time, expected);
+    }
+
+    #[test]
+    fn slow_corner_takes_longer() {
+        // The next motion is at the end of the buffer
+        let fast = buffer(&[Motion::Path(corner()), Motion::Path(line())]);
+        let slow = buffer(&[
+            Motion::Path(corner().with_max_velocity(0.25)),
+            Motion::Path(line()),
+        ]);
+
+        let length = curvature_profile(&buffer(&[Motion::Path(corner())]), 33)
+            .last()
+            .unwrap()
+            .0;
+        let extra = length / 0.25 - length / NO_ACCELERATION.path.velocity;
+
+        let difference = estimated_time(&slow, &NO_ACCELERATION)
+            - estimated_time(&fast, &NO_ACCELERATION);
+        assert!(
+            (difference - extra).abs() < 0.01,
+            "{} != {}",
+            difference,
+            extra
+        );
+
+        assert!(
+            estimated_time(&slow, &MOTION_CONTROL)
+                > estimated_time(&fast, &MOTION_CONTROL)
+        );
+    }
+
+    #[test]
+    fn stops_for_turns() {
+        let turn = buffer(&[
+            Motion::Path(line()),
+            Motion::Turn(TurnMotion::new(DIRECTION_0, DIRECTION_PI_2)),
+            Motion::Path(line()),
+        ]);
+
+        let one = estimated_time(&buffer(&[Motion::Path(line())]), &MOTION_CONTROL);
+        let time = estimated_time(&turn, &MOTION_CONTROL);
+        assert!((time - 2.0 * one).abs() < 0.01, "{} != {}", time, 2.0 * one);
+    }
+
+    #[test]
+    fn never_gets_there() {
+        let config = MotionControlConfig {
+            path: PathHandlerConfig {
+                velocity: 0.0,
+                ..MOTION_CONTROL.path
+            },
+            ..NO_ACCELERATION
+        };
+
+        assert_eq!(
+            estimated_time(&buffer(&[Motion::Path(line())]), &config),
+            f32::INFINITY
+        );
+    }
+}
+
+#[cfg(test)]
+mod velocity_profile_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{max_curvature, velocity_profile, PathMotion};
+    use crate::config::sim::MOTION_CONTROL;
+    use crate::fast::motion_queue::{Motion, MotionQueueBuffer};
+    use crate::fast::{Vector, DIRECTION_0, DIRECTION_PI_2};
+
+    fn buffer() -> MotionQueueBuffer {
+        let mut buffer = MotionQueueBuffer::new();
+        buffer
+            .push(Motion::Path(PathMotion::corner(
+                Vector { x: 630.0, y: 90.0 },
+                DIRECTION_0,
+                DIRECTION_PI_2,
+                90.0,
+                0.0,
+            )))
+            .unwrap();
+        buffer
+            .push(Motion::Path(PathMotion::line(
+                Vector { x: 0.0, y: 90.0 },
+                Vector { x: 540.0, y: 90.0 },
+            )))
+            .unwrap();
+        buffer
+    }
+
+    #[test]
+    fn empty() {
+        let profile = velocity_profile(&MotionQueueBuffer::new(), &MOTION_CONTROL);
+        assert_eq!(profile.len(), 1);
+        assert_close(profile[0].2, 0.0);
+    }
+
+    #[test]
+    fn starts_and_ends_stopped() {
+        let profile = velocity_profile(&buffer(), &MOTION_CONTROL);
+
+        assert_close(profile.first().unwrap().2, 0.0);
+        assert_close(profile.last().unwrap().2, 0.0);
+
+        // The line, then the corner is somewhere between a quarter circle and its two sides
+        let length = profile.last().unwrap().0;
+        assert!(length > 540.0 + core::f32::consts::FRAC_PI_2 * 90.0);
+        assert!(length < 540.0 + 2.0 * 90.0);
+    }
+
+    #[test]
+    fn within_limits() {
+        let profile = velocity_profile(&buffer(), &MOTION_CONTROL);
+
+        for window in profile.windows(2) {
+            let (last_distance, _, last_velocity) = window[0];
+            let (distance, _, velocity) = window[1];
+
+            assert!(distance > last_distance);
+            assert!(velocity <= MOTION_CONTROL.path.velocity + 0.0001);
+
+            // Never speeds up or slows down faster than it can
+            let change = (velocity * velocity - last_velocity * last_velocity).abs();
+            assert!(
+                change
+                    <= 2.0 * MOTION_CONTROL.max_acceleration * (distance - last_distance)
+                        + 0.0001
+            );
+        }
+    }
+
+    #[test]
+    fn corner_is_curved() {
+        let profile = velocity_profile(&buffer(), &MOTION_CONTROL);
+
+        // The line is straight, and the corner gets up to its sharpest in the middle
+        assert_close(profile[1].1, 0.0);
+        let sharpest = profile.iter().map(|point| point.1).fold(0.0, f32::max);
+        assert!((sharpest - max_curvature(&buffer())).abs() < 0.001);
+    }
+}
+
+/// How far the mouse can go along the path motions in `buffer` before running into a wall that
+/// is known to be closed in `maze`, in mm. This starts from the closest point to the mouse on
+/// the next path motion, and goes up to the near side of the wall, assuming the path crosses
+/// it square on. None if no known walls are in the way.
+pub fn free_distance(
+    maze_config: &MazeConfig,
+    maze: &Maze,
+    buffer: &MotionQueueBuffer,
+    orientation: Orientation,
+) -> Option<f32> {
+    let cell = |v: Vector| {
+        Orientation {
+            position: v,
+            direction: Direction::default(),
+        }
+        .to_maze_orientation(maze_config)
+        .position
+    };
+
+    let mut distance = 0.0;
+    let mut last_point: Option<Vector> = None;
+
+    // The next motion is at the end of the buffer
+    let path_motions = buffer.iter().rev().filter_map(|motion| match motion {
+        Motion::Path(path_motion) => Some(path_motion),
+        Motion::Turn(_) | Motion::Stop(_) => None,
+    });
+
+    for path_motion in path_motions {
+        // Skip over the part of the next motion that is already behind the mouse
+        let start_t = if last_point.is_none() {
+            path_motion.closest_point(orientation.position).0
+        } else {
+            0.0
+        };
+
+        for i in 0..=WALL_STEPS {
+            let t = start_t + (1.0 - start_t) * i as f32 / WALL_STEPS as f32;
+            let point = path_motion.bezier.at(t);
+
+            if let Some(last_point) = last_point {
+                let step = (point - last_point).magnitude();
+
+                let closed_wall = WallIndex::between(cell(last_point), cell(point))
+                    .filter(|&wall| maze.get_wall(wall) == Some(&Wall::Closed));
+
+                if let Some(wall) = closed_wall {
+                    // How far along this step the middle of the wall is
+                    let (boundary, from, to) = match wall.direction {
+                        WallDirection::Horizontal => (
+                            wall.y as f32 * maze_config.cell_width_y,
+                            last_point.y,
+                            point.y,
+                        ),
+                        WallDirection::Vertical => (
+                            wall.x as f32 * maze_config.cell_width_x,
+                            last_point.x,
+                            point.x,
+                        ),
+                    };
+
+                    let fraction = (boundary - from) / (to - from);
+                    let to_wall =
+                        distance + fraction * step - maze_config.wall_width / 2.0;
+
+                    return Some(if to_wall > 0.0 { to_wall } else { 0.0 });
+                }
+
+                distance += step;
+            }
+
+            last_point = Some(point);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod free_distance_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{free_distance, PathMotion};
+    use crate::config::MAZE;
+    use crate::fast::motion_queue::{Motion, MotionQueueBuffer};
+    use crate::fast::{Orientation, Vector, DIRECTION_0, DIRECTION_PI_2};
+    use crate::slow::maze::{Maze, Wall, WallDirection, WallIndex};
+
+    // Between (0, 2) and (0, 3)
+    const WALL: WallIndex = WallIndex {
+        x: 0,
+        y: 3,
+        direction: WallDirection::Horizontal,
+    };
+
+    fn at(y: f32) -> Orientation {
+        Orientation {
+            position: Vector { x: 90.0, y },
+            direction: DIRECTION_PI_2,
+        }
+    }
+
+    // From the middle of (0, 0) north to the middle of (0, 4), in two lines
+    fn buffer() -> MotionQueueBuffer {
+        let mut buffer = MotionQueueBuffer::new();
+        buffer
+            .extend_from_slice(&[
+                Motion::Path(PathMotion::line(
+                    Vector { x: 90.0, y: 360.0 },
+                    Vector { x: 90.0, y: 810.0 },
+                )),
+                Motion::Path(PathMotion::line(
+                    Vector { x: 90.0, y: 90.0 },
+                    Vector { x: 90.0, y: 360.0 },
+                )),
+            ])
+            .unwrap();
+        buffer
+    }
+
+    fn maze(wall: Wall) -> Maze {
+        let mut maze = Maze::new(Wall::Open);
+        maze.set_wall(WALL, wall);
+        maze
+    }
+
+    #[test]
+    fn nothing_in_the_way() {
+        assert_eq!(
+            free_distance(&MAZE, &maze(Wall::Open), &buffer(), at(90.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn unknown_wall_is_not_in_the_way() {
+        assert_eq!(
+            free_distance(&MAZE, &maze(Wall::Unknown), &buffer(), at(90.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn up_to_the_wall() {
+        let distance =
+            free_distance(&MAZE, &maze(Wall::Closed), &buffer(), at(90.0)).unwrap();
+        assert!(
+            (distance - (540.0 - 6.0 - 90.0)).abs() < 0.01,
+            "{}",
+            distance
+        );
+    }
+
+    #[test]
+    fn from_part_way_along() {
+        let distance =
+            free_distance(&MAZE, &maze(Wall::Closed), &buffer(), at(200.0)).unwrap();
+        assert!(
+            (distance - (540.0 - 6.0 - 200.0)).abs() < 0.01,
+            "{}",
+            distance
+        );
+    }
+
+    #[test]
+    fn wall_behind_is_not_in_the_way() {
+        let mut maze = maze(Wall::Open);
+        maze.set_wall(
+            WallIndex {
+                x: 1,
+                y: 0,
+                direction: WallDirection::Vertical,
+            },
+            Wall::Closed,
+        );
+
+        // Going east from (1, 0), the wall to the west is behind
+        let mut buffer = MotionQueueBuffer::new();
+        buffer
+            .push(Motion::Path(PathMotion::line(
+                Vector { x: 270.0, y: 90.0 },
+                Vector { x: 630.0, y: 90.0 },
+            )))
+            .unwrap();
+
+        let orientation = Orientation {
+            position: Vector { x: 270.0, y: 90.0 },
+            direction: DIRECTION_0,
+        };
+
+        assert_eq!(free_distance(&MAZE, &maze, &buffer, orientation), None);
+    }
+}
+
+#[cfg(test)]
+mod curvature_metric_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use core::f32::consts::FRAC_PI_2;
+
+    use super::{curvature_profile, max_curvature, total_absolute_curvature, PathMotion};
+    use crate::fast::motion_queue::{Motion, MotionQueueBuffer};
+    use crate::fast::turn::TurnMotion;
+    use crate::fast::{Vector, DIRECTION_0, DIRECTION_PI, DIRECTION_PI_2};
+
+    fn buffer(motions: &[Motion]) -> MotionQueueBuffer {
+        let mut buffer = MotionQueueBuffer::new();
+        buffer.extend_from_slice(motions).unwrap();
+        buffer
+    }
+
+    fn corner(radius: f32) -> Motion {
+        Motion::Path(PathMotion::corner(
+            Vector { x: 90.0, y: 90.0 },
+            DIRECTION_0,
+            DIRECTION_PI_2,
+            radius,
+            0.0,
+        ))
+    }
+
+    #[test]
+    fn straight_line() {
+        let line = buffer(&[Motion::Path(PathMotion::line(
+            Vector { x: 0.0, y: 0.0 },
+            Vector { x: 180.0, y: 0.0 },
+        ))]);
+
+        assert_close(total_absolute_curvature(&line), 0.0);
+        assert_close(max_curvature(&line), 0.0);
+    }
+
+    #[test]
+    fn corner_is_quarter_turn() {
+        for &radius in [45.0, 90.0, 180.0].iter() {
+            let total = total_absolute_curvature(&buffer(&[corner(radius)]));
+            assert!(
+                (total - FRAC_PI_2).abs() < 0.001,
+                "radius {} turned {}",
+                radius,
+                total
+            );
+        }
+    }
+
+    #[test]
+    fn tighter_corner_has_higher_max_curvature() {
+        let tight = max_curvature(&buffer(&[corner(45.0)]));
+        let wide = max_curvature(&buffer(&[corner(90.0)]));
+        assert!(tight > wide);
+    }
+
+    #[test]
+    fn adds_up_and_skips_turns() {
+        let motions = buffer(&[
+            corner(90.0),
+            Motion::Turn(TurnMotion::new(DIRECTION_0, DIRECTION_PI)),
+            corner(90.0),
+        ]);
+
+        let one = total_absolute_curvature(&buffer(&[corner(90.0)]));
+        assert_close(total_absolute_curvature(&motions), 2.0 * one);
+    }
+
+    #[test]
+    fn line_profile_is_straight() {
+        let line = buffer(&[Motion::Path(PathMotion::line(
+            Vector { x: 0.0, y: 0.0 },
+            Vector { x: 180.0, y: 0.0 },
+        ))]);
+
+        let profile = curvature_profile(&line, 5);
+        assert_eq!(profile.len(), 5);
+
+        for &(_, curvature) in profile.iter() {
+            assert_close(curvature, 0.0);
+        }
+
+        assert_close(profile[0].0, 0.0);
+        assert_close(profile[4].0, 180.0);
+        assert!(profile.windows(2).all(|pair| pair[1].0 > pair[0].0));
+    }
+
+    #[test]
+    fn corner_profile_matches_radius() {
+        let profile = curvature_profile(&buffer(&[corner(90.0)]), 9);
+
+        // Straight at the ends to match up with lines, curving the same way in between, and
+        // tightest in the middle to make up for it
+        assert_close(profile[0].1, 0.0);
+        assert_close(profile[8].1, 0.0);
+        assert!(profile[1..8].iter().all(|&(_, curvature)| curvature > 0.0));
+        let (_, middle) = profile[4];
+        assert!(middle > 1.0 / 90.0 && middle < 2.0 / 90.0, "{}", middle);
+
+        // About a quarter of a circle around
+        let (length, _) = profile[8];
+        assert!((length - 90.0 * FRAC_PI_2).abs() < 15.0, "{}", length);
+    }
+
+    #[test]
+    fn profile_follows_motion_order() {
+        // The next motion is at the end of the buffer, so the line comes first
+        let motions = buffer(&[
+            corner(90.0),
+            Motion::Turn(TurnMotion::new(DIRECTION_0, DIRECTION_PI)),
+            Motion::Path(PathMotion::line(
+                Vector { x: 0.0, y: 90.0 },
+                Vector { x: 90.0, y: 90.0 },
+            )),
+        ]);
+
+        let profile = curvature_profile(&motions, 3);
+        assert_eq!(profile.len(), 6);
+        assert_close(profile[2].0, 90.0);
+        assert_close(profile[2].1, 0.0);
+        assert!(profile[4].1.abs() > 0.0);
+    }
+
+    #[test]
+    fn profile_stops_when_full() {
+        let profile = curvature_profile(&buffer(&[corner(90.0), corner(90.0)]), 200);
+        assert_eq!(profile.len(), 256);
+    }
+}
+
+#[cfg(test)]
+mod entry_valid_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::PathMotion;
+    use crate::fast::{Direction, Orientation, Vector, DIRECTION_0, DIRECTION_PI_2};
+    use core::f32::consts::FRAC_PI_8;
+
+    const MAX_OFFSET: f32 = 10.0;
+    const MAX_ANGLE: f32 = FRAC_PI_8;
+
+    fn at(x: f32, y: f32, direction: Direction) -> Orientation {
+        Orientation {
+            position: Vector { x, y },
+            direction,
+        }
+    }
+
+    // A path heading north
+    fn line() -> PathMotion {
+        PathMotion::line(Vector { x: 0.0, y: 0.0 }, Vector { x: 0.0, y: 100.0 })
+    }
+
+    #[test]
+    fn aligned() {
+        assert!(line().is_entry_valid(
+            at(0.0, 0.0, DIRECTION_PI_2),
+            MAX_OFFSET,
+            MAX_ANGLE
+        ));
+    }
+
+    #[test]
+    fn slightly_off() {
+        assert!(line().is_entry_valid(
+            at(5.0, 2.0, DIRECTION_PI_2 + Direction::from(0.1)),
+            MAX_OFFSET,
+            MAX_ANGLE
+        ));
+    }
+
+    #[test]
+    fn turned_away() {
+        assert!(!line().is_entry_valid(at(0.0, 0.0, DIRECTION_0), MAX_OFFSET, MAX_ANGLE));
+    }
+
+    #[test]
+    fn too_far_to_the_side() {
+        assert!(!line().is_entry_valid(
+            at(30.0, 10.0, DIRECTION_PI_2),
+            MAX_OFFSET,
+            MAX_ANGLE
+        ));
+    }
+
+    #[test]
+    fn degenerate() {
+        let point =
+            PathMotion::line(Vector { x: 0.0, y: 0.0 }, Vector { x: 0.0, y: 0.0 });
+        assert!(!point.is_entry_valid(
+            at(0.0, 0.0, DIRECTION_PI_2),
+            MAX_OFFSET,
+            MAX_ANGLE
+        ));
+    }
+}
+
+#[cfg(test)]
+mod heading_error_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::PathMotion;
+    use crate::fast::{Direction, Orientation, Vector, DIRECTION_PI_2};
+    use core::f32::consts::FRAC_PI_6;
+
+    fn at(x: f32, y: f32, direction: Direction) -> Orientation {
+        Orientation {
+            position: Vector { x, y },
+            direction,
+        }
+    }
+
+    // A path heading north
+    fn line() -> PathMotion {
+        PathMotion::line(Vector { x: 0.0, y: 0.0 }, Vector { x: 0.0, y: 100.0 })
+    }
+
+    #[test]
+    fn aligned() {
+        assert_close(
+            line().heading_error(at(5.0, 40.0, DIRECTION_PI_2)).unwrap(),
+            0.0,
+        );
+    }
+
+    #[test]
+    fn turned_left() {
+        assert_close(
+            line()
+                .heading_error(at(0.0, 40.0, DIRECTION_PI_2 + Direction::from(FRAC_PI_6)))
+                .unwrap(),
+            FRAC_PI_6,
+        );
+    }
+
+    #[test]
+    fn turned_right() {
+        assert_close(
+            line()
+                .heading_error(at(0.0, 40.0, DIRECTION_PI_2 - Direction::from(FRAC_PI_6)))
+                .unwrap(),
+            -FRAC_PI_6,
+        );
+    }
+
+    #[test]
+    fn across_zero() {
+        // A path heading east, with the mouse turned just clockwise of it
+        let line =
+            PathMotion::line(Vector { x: 0.0, y: 0.0 }, Vector { x: 100.0, y: 0.0 });
+        assert_close(
+            line.heading_error(at(40.0, 0.0, Direction::from(-FRAC_PI_6)))
+                .unwrap(),
+            -FRAC_PI_6,
+        );
+    }
+
+    #[test]
+    fn degenerate() {
+        let point =
+            PathMotion::line(Vector { x: 0.0, y: 0.0 }, Vector { x: 0.0, y: 0.0 });
+        assert_eq!(point.heading_error(at(0.0, 0.0, DIRECTION_PI_2)), None);
+    }
+}
+
+#[cfg(test)]
+mod track_error_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::PathMotion;
+    use crate::fast::{Orientation, Vector, DIRECTION_0, DIRECTION_PI_2};
+
+    fn at(x: f32, y: f32) -> Orientation {
+        Orientation {
+            position: Vector { x, y },
+            direction: DIRECTION_PI_2,
+        }
+    }
+
+    /// The closest point is searched for, so it won't be exactly under the mouse
+    fn assert_near(left: f32, right: f32) {
+        assert!(
+            (left - right).abs() < 0.05,
+            "{} is not near {}",
+            left,
+            right
+        );
+    }
+
+    // A path heading north
+    fn line() -> PathMotion {
+        PathMotion::line(Vector { x: 0.0, y: 0.0 }, Vector { x: 0.0, y: 100.0 })
+    }
+
+    #[test]
+    fn on_path() {
+        let (along, cross) = line().track_error(at(0.0, 40.0)).unwrap();
+        assert_near(along, 0.0);
+        assert_near(cross, 0.0);
+    }
+
+    #[test]
+    fn left_is_positive() {
+        let (along, cross) = line().track_error(at(-5.0, 40.0)).unwrap();
+        assert_near(along, 0.0);
+        assert_near(cross, 5.0);
+    }
+
+    #[test]
+    fn right_is_negative() {
+        let (along, cross) = line().track_error(at(5.0, 40.0)).unwrap();
+        assert_near(along, 0.0);
+        assert_near(cross, -5.0);
+    }
+
+    #[test]
+    fn past_the_end() {
+        // The closest point is on the line extended past the end
+        let (along, cross) = line().track_error(at(-3.0, 110.0)).unwrap();
+        assert_near(along, 0.0);
+        assert_near(cross, 3.0);
+    }
+
+    #[test]
+    fn on_a_curve() {
+        // Turning left from heading north to heading east
+        let corner = PathMotion::corner(
+            Vector { x: 90.0, y: 90.0 },
+            DIRECTION_PI_2,
+            DIRECTION_0,
+            90.0,
+            0.0,
+        );
+
+        let (t, p) = corner.closest_point(Vector { x: 80.0, y: 100.0 });
+        let (along, cross) = corner.track_error(at(80.0, 100.0)).unwrap();
+        assert_near(along, 0.0);
+        assert_near(cross.abs(), (Vector { x: 80.0, y: 100.0 } - p).magnitude());
+
+        // The mouse is outside of the corner, which is to the left of the path
+        assert!(cross > 0.0, "{} at {}", cross, t);
+    }
+
+    #[test]
+    fn degenerate() {
+        let point =
+            PathMotion::line(Vector { x: 0.0, y: 0.0 }, Vector { x: 0.0, y: 0.0 });
+        assert_eq!(point.track_error(at(0.0, 0.0)), None);
+    }
+}
+
+#[cfg(test)]
+mod clamp_to_rect_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{PathMotion, BOUNDS_STEPS};
+    use crate::fast::curve::{Bezier5, Curve};
+    use crate::fast::{Vector, DIRECTION_0, DIRECTION_PI_2};
+
+    const MIN: Vector = Vector { x: 0.0, y: 0.0 };
+    const MAX: Vector = Vector { x: 180.0, y: 180.0 };
+
+    // Bulges up to y = 50
+    fn bulge() -> PathMotion {
+        PathMotion {
+            bezier: Bezier5::new(
+                Vector { x: 0.0, y: 0.0 },
+                Vector { x: 20.0, y: 80.0 },
+                Vector { x: 40.0, y: 80.0 },
+                Vector { x: 60.0, y: 80.0 },
+                Vector { x: 80.0, y: 80.0 },
+                Vector { x: 100.0, y: 0.0 },
+            ),
+            max_velocity: None,
+        }
+    }
+
+    fn max_y(path: &PathMotion) -> f32 {
+        (0..=BOUNDS_STEPS)
+            .map(|i| path.bezier.at(i as f32 / BOUNDS_STEPS as f32).y)
+            .fold(core::f32::MIN, f32::max)
+    }
+
+    #[test]
+    fn inside_is_unchanged() {
+        let corner = PathMotion::corner(
+            Vector { x: 90.0, y: 90.0 },
+            DIRECTION_0,
+            DIRECTION_PI_2,
+            45.0,
+            0.0,
+        );
+        assert_eq!(corner.clamp_to_rect(MIN, MAX), corner);
+    }
+
+    #[test]
+    fn overshoot_is_pulled_in() {
+        let max = Vector { x: 100.0, y: 30.0 };
+        assert!(max_y(&bulge()) > max.y);
+
+        let clamped = bulge().clamp_to_rect(MIN, max);
+        assert!(max_y(&clamped) <= max.y);
+        assert!(max_y(&clamped) > 0.0);
+        assert_eq!(clamped.start(), bulge().start());
+        assert_eq!(clamped.end(), bulge().end());
+    }
+
+    #[test]
+    fn cant_fit_is_straight() {
+        let clamped = bulge().clamp_to_rect(MIN, Vector { x: 50.0, y: 30.0 });
+        assert_close(max_y(&clamped), 0.0);
+    }
+}
+
+#[cfg(test)]
+mod transformed_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::PathMotion;
+    use crate::fast::{Vector, DIRECTION_0, DIRECTION_PI_2};
+
+    const ORIGIN: Vector = Vector { x: 0.0, y: 0.0 };
+
+    fn line() -> PathMotion {
+        PathMotion::line(Vector { x: 10.0, y: 0.0 }, Vector { x: 100.0, y: 0.0 })
+    }
+
+    #[test]
+    fn rotate_line() {
+        let path = line().transformed(DIRECTION_PI_2, ORIGIN);
+        assert_close2(path.start(), Vector { x: 0.0, y: 10.0 });
+        assert_close2(path.end(), Vector { x: 0.0, y: 100.0 });
+    }
+
+    #[test]
+    fn translate_line() {
+        let path = line().transformed(DIRECTION_0, Vector { x: 5.0, y: -3.0 });
+        assert!(path.start().approx_eq(Vector { x: 15.0, y: -3.0 }, 0.0001));
+        assert!(path.end().approx_eq(Vector { x: 105.0, y: -3.0 }, 0.0001));
+    }
+
+    #[test]
+    fn rotate_then_translate() {
+        let path = line().transformed(DIRECTION_PI_2, Vector { x: 5.0, y: -3.0 });
+        assert_close2(path.start(), Vector { x: 5.0, y: 7.0 });
+        assert_close2(path.end(), Vector { x: 5.0, y: 97.0 });
+    }
+}
+
+// Adjust the curvature for the mouse not being on the path, limited to `max_curvature` either
+// way. Not a number still comes out as not a number, so it can be caught by the caller.
+fn offset_curvature(curvature: f32, distance: f32, max_curvature: f32) -> f32 {
+    let r = 1.0 / curvature;
+
+    let r2 = if curvature > 0.0 {
+        r - distance
+    } else {
+        r + distance
+    };
+
+    // The mouse is right at the center of the turn, so any curvature would follow it
+    if r2 == 0.0 {
+        return if curvature > 0.0 {
+            max_curvature
+        } else {
+            -max_curvature
+        };
+    }
+
+    let curvature2 = 1.0 / r2;
+
+    if curvature2 > max_curvature {
+        max_curvature
+    } else if curvature2 < -max_curvature {
+        -max_curvature
+    } else {
+        curvature2
+    }
+}
+
+#[cfg(test)]
+mod offset_curvature_tests {
+    use super::offset_curvature;
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    #[test]
+    fn zero_distance_positive_curvature() {
+        assert_close(offset_curvature(1.0, 0.0, 10.0), 1.0)
+    }
+
+    #[test]
+    fn positive_distance_positive_curvature() {
+        assert_close(offset_curvature(1.0, 0.5, 10.0), 2.0)
+    }
+
+    #[test]
+    fn negative_distance_positive_curvature() {
+        assert_close(offset_curvature(1.0, -0.5, 10.0), 0.6666667)
+    }
+
+    #[test]
+    fn zero_distance_negative_curvature() {
+        assert_close(offset_curvature(-1.0, 0.0, 10.0), -1.0)
+    }
+
+    #[test]
+    fn positive_distance_negative_curvature() {
+        assert_close(offset_curvature(-1.0, 0.5, 10.0), -2.0)
+    }
+
+    #[test]
+    fn negative_distance_negative_curvature() {
+        assert_close(offset_curvature(-1.0, -0.5, 10.0), -0.66666667)
+    }
+
+    #[test]
+    fn zero_curvature() {
+        assert_close(offset_curvature(0.0, 0.5, 10.0), 0.0)
+    }
+
+    #[test]
+    fn at_center_positive_curvature() {
+        assert_close(offset_curvature(1.0, 1.0, 10.0), 10.0)
+    }
+
+    #[test]
+    fn at_center_negative_curvature() {
+        assert_close(offset_curvature(-1.0, 1.0, 10.0), -10.0)
+    }
+
+    #[test]
+    fn clamped() {
+        assert_close(offset_curvature(1.0, 0.99, 10.0), 10.0);
+        assert_close(offset_curvature(-1.0, 0.99, 10.0), -10.0);
+    }
+
+    #[test]
+    fn past_center() {
+        // Past the center the mouse needs to turn the other way, but not very hard
+        assert_close(offset_curvature(1.0, 1.5, 10.0), -2.0);
+        assert_close(offset_curvature(1.0, 1.01, 10.0), -10.0);
+    }
+
+    #[test]
+    fn not_a_number() {
+        assert!(offset_curvature(core::f32::NAN, 0.5, 10.0).is_nan());
+    }
+}
+
+// Treat curvatures smaller than the deadband as straight
+fn apply_curvature_deadband(curvature: f32, deadband: f32) -> f32 {
+    if curvature.abs() < deadband {
+        0.0
+    } else {
+        curvature
+    }
+}
+
+#[cfg(test)]
+mod curvature_deadband_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::apply_curvature_deadband;
+
+    #[test]
+    fn within_deadband() {
+        assert_close(apply_curvature_deadband(0.0005, 0.001), 0.0);
+        assert_close(apply_curvature_deadband(-0.0005, 0.001), 0.0);
+    }
+
+    #[test]
+    fn outside_deadband() {
+        assert_close(apply_curvature_deadband(0.002, 0.001), 0.002);
+        assert_close(apply_curvature_deadband(-0.002, 0.001), -0.002);
+    }
+
+    #[test]
+    fn no_deadband() {
+        assert_close(apply_curvature_deadband(0.0000001, 0.0), 0.0000001);
+    }
+}
+
+pub(crate) fn curvature_to_left_right(
+    config: &MechanicalConfig,
+    velocity: f32,
+    curvature: f32,
+) -> (f32, f32) {
+    let rotations_per_ms = velocity * curvature;
+    let angular_mm_per_ms = rotations_per_ms * config.wheelbase / 2.0;
+    let left = velocity - angular_mm_per_ms;
+    let right = velocity + angular_mm_per_ms;
+    (left, right)
+}
+
+#[cfg(test)]
+mod curvature_to_left_right_test {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::curvature_to_left_right;
+    use crate::config::MechanicalConfig;
+
+    const CONFIG: MechanicalConfig = crate::config::mouse_2019::MECH;
+
+    #[test]
+    fn test_curvature_to_left_right_circle() {
+        let (left, right) = curvature_to_left_right(&CONFIG, 0.5, 1.0 / 90.0);
+        assert_close(left, 0.294444);
+        assert_close(right, 0.705556);
+    }
+
+    #[test]
+    fn test_curvature_to_left_right_straight() {
+        let (left, right) = curvature_to_left_right(&CONFIG, 0.5, 0.0);
+        assert_close(left, 0.5);
+        assert_close(right, 0.5);
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PathHandlerDebug {
+    pub closest_point: Option<(f32, Vector)>,
+    pub distance_from: Option<f32>,
+    pub tangent_direction: Option<Direction>,
+    pub adjust_direction: Option<Direction>,
+    pub centered_direction: Option<f32>,
+    pub adjust_curvature: Option<f32>,
+    pub heading_error: Option<f32>,
+
+    /// The control points of the path motion being followed, to draw the exact curve
+    pub bezier: Option<Bezier5>,
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PathHandlerConfig {
+    pub p: f32,
+    pub i: f32,
+    pub d: f32,
+    pub offset_p: f32,
+
+    /// The forward velocity in mm/ms
+    pub velocity: f32,
+
+    /// Target curvatures smaller than this are treated as zero, so the motors don't buzz from
+    /// tiny corrections near the path. The pid still integrates while in the deadband, so the
+    /// i term will eventually push out of it to correct a steady offset.
+    pub curvature_deadband: f32,
+
+    /// Start looking for the closest point from where it was last update, instead of searching
+    /// the whole path motion every time
+    pub warm_start: bool,
+
+    /// The most the mouse will curve to get back onto the path, in 1/mm. This keeps it from
+    /// blowing up when the mouse is near the center of a turn.
+    pub max_offset_curvature: f32,
+
+    /// How far past the end of a path motion the mouse needs to be for it to be done, in mm, so
+    /// noise at the end doesn't finish it over and over. 0 is done as soon as it is past.
+    pub completion_hysteresis: f32,
+}
+
+impl PathHandlerConfig {
+    /// Check that the config makes sense to follow a path with
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let not_negative = |value: f32| value.is_finite() && value >= 0.0;
+        let positive = |value: f32| value.is_finite() && value > 0.0;
+
+        if !positive(self.velocity) {
+            Err(ConfigError::Velocity(self.velocity))
+        } else if !not_negative(self.offset_p) {
+            Err(ConfigError::OffsetP(self.offset_p))
+        } else if let Some(&gain) = [self.p, self.i, self.d]
+            .iter()
+            .find(|&&gain| !not_negative(gain))
+        {
+            Err(ConfigError::Gain(gain))
+        } else if !not_negative(self.curvature_deadband) {
+            Err(ConfigError::CurvatureDeadband(self.curvature_deadband))
+        } else if !not_negative(self.max_offset_curvature) {
+            Err(ConfigError::MaxOffsetCurvature(self.max_offset_curvature))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Which field of a [PathHandlerConfig] is out of range, and what it was
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ConfigError {
+    /// `velocity` must be positive
+    Velocity(f32),
+
+    /// `offset_p` can't be negative, or the mouse would steer away from the path
+    OffsetP(f32),
+
+    /// `p`, `i`, and `d` can't be negative
+    Gain(f32),
+
+    /// `curvature_deadband` can't be negative
+    CurvatureDeadband(f32),
+
+    /// `max_offset_curvature` can't be negative
+    MaxOffsetCurvature(f32),
+}
+
+/// Why a path motion could not be followed
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PathError {
+    /// The configured velocity is zero or not a number, so the path can't be followed
+    InvalidVelocity(f32),
+
+    /// The path motion does not go anywhere, so there is no direction to follow it in
+    DegenerateSegment,
+
+    /// The curvature to follow the path motion came out as infinite or not a number
+    InvalidCurvature(f32),
+
+    /// The config is out of range, see [PathHandlerConfig::validate]
+    InvalidConfig(ConfigError),
+}
+
+#[derive(Clone, Debug)]
+pub struct PathHandler {
+    pub direction_pid: PIDController,
+    pub time: Millis,
+
+    /// The path motion last followed and the `t` of its closest point, to warm start from
+    last_closest: Option<(PathMotion, f32)>,
+}
+
+impl PathHandler {
+    pub fn new(config: &PathHandlerConfig, time: Millis) -> PathHandler {
+        let pid = PIDController::new(config.p as f64, config.i as f64, config.d as f64);
+        PathHandler {
+            direction_pid: pid,
+            time,
+            last_closest: None,
+        }
+    }
+
+    /// Forget the integral and derivative state, and where the closest point was, from past
+    /// path motions
+    pub fn reset(&mut self) {
+        self.direction_pid.reset();
+        self.last_closest = None;
+    }
+
+    /// Follow the path motion, or stop if it can't be followed. See [PathHandler::try_update]
+    pub fn update(
+        &mut self,
+        config: &PathHandlerConfig,
+        mech: &MechanicalConfig,
+        time: Millis,
+        orientation: Orientation,
+        segment: PathMotion,
+    ) -> (f32, f32, PathHandlerDebug) {
+        self.try_update(config, mech, time, orientation, segment)
+            .unwrap_or((0.0, 0.0, PathHandlerDebug::default()))
+    }
+
+    /// Follow the path motion, returning the left and right wheel velocities. Returns an error
+    /// instead of velocities that don't make sense if the path motion can't be followed.
+    pub fn try_update(
+        &mut self,
+        config: &PathHandlerConfig,
+        mech: &MechanicalConfig,
+        time: Millis,
+        orientation: Orientation,
+        segment: PathMotion,
+    ) -> Result<(f32, f32, PathHandlerDebug), PathError> {
+        let mut debug = PathHandlerDebug::default();
+
+        let delta_time = time - self.time;
+        self.time = time;
+
+        let velocity = segment.limit_velocity(config.velocity);
+
+        if !velocity.is_finite() || velocity == 0.0 {
+            return Err(PathError::InvalidVelocity(velocity));
+        }
+
+        config.validate().map_err(PathError::InvalidConfig)?;
+
+        self.direction_pid.p_gain = config.p as f64;
+        self.direction_pid.i_gain = config.i as f64;
+        self.direction_pid.d_gain = config.d as f64;
+
+        // Gather some info about the segment
+        let (t, p) = match self.last_closest {
+            Some((last, last_t)) if config.warm_start && last == segment => {
+                segment.closest_point_near(orientation.position, last_t)
+            }
+            _ => segment.closest_point(orientation.position),
+        };
+        self.last_closest = Some((segment, t));
+        debug.closest_point = Some((t, p));
+        debug.bezier = Some(segment.bezier);
+        let (_, distance) = segment
+            .track_error_at(t, p, orientation.position)
+            .ok_or(PathError::DegenerateSegment)?;
+
+        let tangent = segment.derivative(t).direction();
+
+        let path_curvature = segment.curvature(t);
+
+        // If there was another segment, try to follow it
+        // The curvature of the path where the mouse is
+        let offset_curvature =
+            offset_curvature(path_curvature, distance, config.max_offset_curvature);
+
+        let adjust_curvature = if config.offset_p != 0.0 {
+            // Need to calculate an adjustment curvature to get the mouse back on the path
+            // This gets added to the offset curvature above to get the final path curvature.
+            // As such, it should always turn the mouse towards the path, but avoid turning
+            // past the path. This is done by calculating a target direction that points towards the
+            // path far away, but along the path close up. A curvature is then calculated that
+            // should get the mouse to that direction in the next loop (assuming no physics
+            // limitations. This should probably be limited base on the mechanics).
+
+            // This s-curve will asymptote at -pi/2 and pi/2, and cross the origin.
+            // Points the mouse directly at the path far away, but along the path
+            // close up. The offset_p determines how aggressive it is
+            let adjust_direction_offset = -distance * config.offset_p;
+
+            let adjust_direction_offset = if adjust_direction_offset > FRAC_PI_2 {
+                FRAC_PI_2
+            } else if adjust_direction_offset < -FRAC_PI_2 {
+                -FRAC_PI_2
+            } else {
+                adjust_direction_offset
+            };
+
+            //PI / (1.0 + F32Ext::exp(config.offset_p * distance)) - FRAC_PI_2;
+
+            let adjust_direction = tangent + Direction::from(adjust_direction_offset);
+            debug.adjust_direction = Some(adjust_direction);
+
+            let centered_direction = orientation.direction.centered_at(adjust_direction);
+            debug.centered_direction = Some(centered_direction);
+
+            self.direction_pid
+                .set_target(f32::from(adjust_direction) as f64);
+            self.direction_pid
+                .update(centered_direction as f64, delta_time as f64) as f32
+        } else {
+            0.0
+        };
+
+        let target_curvature = apply_curvature_deadband(
+            offset_curvature + adjust_curvature,
+            config.curvature_deadband,
+        );
+
+        // Keep the outside wheel under the fastest it can go
+        let max_curvature = mech.max_curvature_at(velocity);
+        let target_curvature = if target_curvature > max_curvature {
+            max_curvature
+        } else if target_curvature < -max_curvature {
+            -max_curvature
+        } else {
+            target_curvature
+        };
+
+        if !target_curvature.is_finite() {
+            return Err(PathError::InvalidCurvature(target_curvature));
+        }
+
+        let (target_left_velocity, target_right_velocity) =
+            curvature_to_left_right(mech, velocity, target_curvature);
+
+        debug.distance_from = Some(distance);
+        debug.tangent_direction = Some(tangent);
+        debug.heading_error =
+            Some(orientation.direction.centered_at(tangent) - f32::from(tangent));
+        debug.adjust_curvature = Some(adjust_curvature);
+
+        Ok((target_left_velocity, target_right_velocity, debug))
+    }
+}
+
+#[cfg(test)]
+mod path_handler_deadband_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{PathHandler, PathHandlerConfig, PathMotion};
+    use crate::config::mouse_2019;
+    use crate::fast::{Orientation, Vector, DIRECTION_0};
+
+    const CONFIG: PathHandlerConfig = PathHandlerConfig {
+        p: 0.0,
+        i: 0.001,
+        d: 0.0,
+        offset_p: 0.02,
+        velocity: 0.5,
+        curvature_deadband: 0.001,
+        warm_start: true,
+        max_offset_curvature: 0.1,
+        completion_hysteresis: 0.0,
+    };
+
+    // Slightly off of the path, pointing along it
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 100.0, y: 1.0 },
+        direction: DIRECTION_0,
+    };
+
+    fn segment() -> PathMotion {
+        PathMotion::line(Vector { x: 0.0, y: 0.0 }, Vector { x: 1000.0, y: 0.0 })
+    }
+
+    #[test]
+    fn small_offset_is_straight() {
+        let mut path = PathHandler::new(&CONFIG, 0);
+        let (left, right, _) =
+            path.update(&CONFIG, &mouse_2019::MECH, 10, ORIENTATION, segment());
+        assert_close(left, right);
+    }
+
+    #[test]
+    fn steady_offset_is_eventually_corrected() {
+        let mut path = PathHandler::new(&CONFIG, 0);
+
+        let corrected = (1..100).any(|i| {
+            let (left, right, _) =
+                path.update(&CONFIG, &mouse_2019::MECH, i * 10, ORIENTATION, segment());
+            left != right
+        });
+
+        assert!(corrected);
+    }
+}
+
+#[cfg(test)]
+mod path_handler_wheel_velocity_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{PathHandler, PathMotion};
+    use crate::config::{mouse_2019, sim, MechanicalConfig};
+    use crate::fast::{Orientation, Vector, DIRECTION_0};
+
+    // Far off to the side of the path, so it wants to curve hard back to it
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 100.0, y: 50.0 },
+        direction: DIRECTION_0,
+    };
+
+    fn segment() -> PathMotion {
+        PathMotion::line(Vector { x: 0.0, y: 0.0 }, Vector { x: 1000.0, y: 0.0 })
+    }
+
+    fn fastest_wheel(max_wheel_velocity: f32) -> f32 {
+        let config = sim::MOTION_CONTROL.path;
+        let mech = MechanicalConfig {
+            max_wheel_velocity,
+            ..mouse_2019::MECH
+        };
+
+        let mut path = PathHandler::new(&config, 0);
+        let (left, right, _) = path.update(&config, &mech, 10, ORIENTATION, segment());
+        left.abs().max(right.abs())
+    }
+
+    #[test]
+    fn outside_wheel_limited() {
+        assert!(fastest_wheel(0.6) <= 0.6 + 0.0001);
+    }
+
+    #[test]
+    fn curves_without_limit() {
+        assert!(fastest_wheel(10.0) > 0.6);
+    }
+
+    #[test]
+    fn zero_is_no_limit() {
+        assert_close(fastest_wheel(0.0), fastest_wheel(1000.0));
+        assert!(fastest_wheel(0.0) > 0.6);
+    }
+}
+
+#[cfg(test)]
+mod warm_start_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{PathHandler, PathHandlerConfig, PathMotion};
+    use crate::config::{mouse_2019, sim};
+    use crate::fast::curve::Bezier5;
+    use crate::fast::{Orientation, Vector, DIRECTION_PI_2};
+
+    // Up the y axis, around a hairpin, and back down 20mm over
+    fn hairpin() -> PathMotion {
+        PathMotion {
+            bezier: Bezier5::new(
+                Vector { x: 0.0, y: 0.0 },
+                Vector { x: 0.0, y: 100.0 },
+                Vector { x: 0.0, y: 160.0 },
+                Vector { x: 20.0, y: 160.0 },
+                Vector { x: 20.0, y: 100.0 },
+                Vector { x: 20.0, y: 0.0 },
+            ),
+            max_velocity: None,
+        }
+    }
+
+    fn at(x: f32) -> Orientation {
+        Orientation {
+            position: Vector { x, y: 50.0 },
+            direction: DIRECTION_PI_2,
+        }
+    }
+
+    /// The `t` of the closest point after following the hairpin from x = 8 to x = 11
+    fn closest_t(warm_start: bool) -> f32 {
+        let config = PathHandlerConfig {
+            warm_start,
+            ..sim::MOTION_CONTROL.path
+        };
+
+        let mut path = PathHandler::new(&config, 0);
+        path.update(&config, &mouse_2019::MECH, 10, at(8.0), hairpin());
+        let (_, _, debug) =
+            path.update(&config, &mouse_2019::MECH, 20, at(11.0), hairpin());
+
+        debug.closest_point.unwrap().0
+    }
+
+    #[test]
+    fn warm_start_stays_on_the_way_up() {
+        assert!(closest_t(true) < 0.5);
+    }
+
+    #[test]
+    fn cold_start_jumps_across() {
+        assert!(closest_t(false) > 0.5);
+    }
+}
+
+#[cfg(test)]
+mod path_error_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{ConfigError, PathError, PathHandler, PathHandlerConfig, PathMotion};
+    use crate::config::{mouse_2019, sim};
+    use crate::fast::{Orientation, Vector, DIRECTION_0};
+
+    const CONFIG: PathHandlerConfig = sim::MOTION_CONTROL.path;
+
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 0.0, y: 0.0 },
+        direction: DIRECTION_0,
+    };
+
+    fn line() -> PathMotion {
+        PathMotion::line(Vector { x: 0.0, y: 0.0 }, Vector { x: 180.0, y: 0.0 })
+    }
+
+    #[test]
+    fn ok() {
+        let mut path = PathHandler::new(&CONFIG, 0);
+        let result = path.try_update(&CONFIG, &mouse_2019::MECH, 10, ORIENTATION, line());
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn zero_velocity() {
+        let config = PathHandlerConfig {
+            velocity: 0.0,
+            ..CONFIG
+        };
+
+        let mut path = PathHandler::new(&config, 0);
+        let result = path.try_update(&config, &mouse_2019::MECH, 10, ORIENTATION, line());
+        assert_eq!(result, Err(PathError::InvalidVelocity(0.0)));
+    }
+
+    #[test]
+    fn negative_offset_p() {
+        let config = PathHandlerConfig {
+            offset_p: -0.02,
+            ..CONFIG
+        };
+
+        let mut path = PathHandler::new(&config, 0);
+        let result = path.try_update(&config, &mouse_2019::MECH, 10, ORIENTATION, line());
+        assert_eq!(
+            result,
+            Err(PathError::InvalidConfig(ConfigError::OffsetP(-0.02)))
+        );
+    }
+
+    #[test]
+    fn degenerate_segment() {
+        let segment =
+            PathMotion::line(Vector { x: 0.0, y: 0.0 }, Vector { x: 0.0, y: 0.0 });
+
+        let mut path = PathHandler::new(&CONFIG, 0);
+        let result =
+            path.try_update(&CONFIG, &mouse_2019::MECH, 10, ORIENTATION, segment);
+        assert_eq!(result, Err(PathError::DegenerateSegment));
+    }
+
+    #[test]
+    fn update_stops_on_error() {
+        let segment =
+            PathMotion::line(Vector { x: 0.0, y: 0.0 }, Vector { x: 0.0, y: 0.0 });
+
+        let mut path = PathHandler::new(&CONFIG, 0);
+        let (left, right, _) =
+            path.update(&CONFIG, &mouse_2019::MECH, 10, ORIENTATION, segment);
+        assert_eq!((left, right), (0.0, 0.0));
+    }
+}
+
+#[cfg(test)]
+mod validate_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{ConfigError, PathHandlerConfig};
+    use crate::config::sim;
+
+    const CONFIG: PathHandlerConfig = sim::MOTION_CONTROL.path;
+
+    #[test]
+    fn valid() {
+        assert_eq!(CONFIG.validate(), Ok(()));
+    }
+
+    #[test]
+    fn zero_velocity() {
+        let config = PathHandlerConfig {
+            velocity: 0.0,
+            ..CONFIG
+        };
+        assert_eq!(config.validate(), Err(ConfigError::Velocity(0.0)));
+    }
+
+    #[test]
+    fn negative_velocity() {
+        let config = PathHandlerConfig {
+            velocity: -0.5,
+            ..CONFIG
+        };
+        assert_eq!(config.validate(), Err(ConfigError::Velocity(-0.5)));
+    }
+
+    #[test]
+    fn nan_velocity() {
+        let config = PathHandlerConfig {
+            velocity: f32::NAN,
+            ..CONFIG
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn negative_offset_p() {
+        let config = PathHandlerConfig {
+            offset_p: -0.02,
+            ..CONFIG
+        };
+        assert_eq!(config.validate(), Err(ConfigError::OffsetP(-0.02)));
+    }
+
+    #[test]
+    fn zero_offset_p() {
+        let config = PathHandlerConfig {
+            offset_p: 0.0,
+            ..CONFIG
+        };
+        assert_eq!(config.validate(), Ok(()));
+    }
+
+    #[test]
+    fn negative_gain() {
+        let config = PathHandlerConfig { i: -0.1, ..CONFIG };
+        assert_eq!(config.validate(), Err(ConfigError::Gain(-0.1)));
+    }
+
+    #[test]
+    fn default_only_needs_a_velocity() {
+        assert_eq!(
+            PathHandlerConfig::default().validate(),
+            Err(ConfigError::Velocity(0.0))
+        );
+
+        let config = PathHandlerConfig {
+            velocity: 0.5,
+            ..PathHandlerConfig::default()
+        };
+        assert_eq!(config.validate(), Ok(()));
+    }
+}
+
+#[cfg(test)]
+mod debug_bezier_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{PathHandler, PathHandlerConfig, PathMotion};
+    use crate::config::{mouse_2019, sim};
+    use crate::fast::curve::Bezier5;
+    use crate::fast::{Orientation, Vector, DIRECTION_0};
+
+    const CONFIG: PathHandlerConfig = sim::MOTION_CONTROL.path;
+
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 10.0, y: 5.0 },
+        direction: DIRECTION_0,
+    };
+
+    #[test]
+    fn matches_segment() {
+        let segment = PathMotion::s_curve(
+            Vector { x: 0.0, y: 0.0 },
+            Vector { x: 180.0, y: 20.0 },
+            DIRECTION_0,
+        );
+
+        let mut path = PathHandler::new(&CONFIG, 0);
+        let (_, _, debug) =
+            path.update(&CONFIG, &mouse_2019::MECH, 10, ORIENTATION, segment);
+
+        let bezier: Bezier5 = debug.bezier.unwrap();
+        assert_eq!(bezier.start, segment.start());
+        assert_eq!(bezier.end, segment.end());
+        assert_eq!(bezier, segment.bezier);
+    }
+
+    #[test]
+    fn none_when_not_followed() {
+        let segment =
+            PathMotion::line(Vector { x: 0.0, y: 0.0 }, Vector { x: 0.0, y: 0.0 });
+
+        let mut path = PathHandler::new(&CONFIG, 0);
+        let (_, _, debug) =
+            path.update(&CONFIG, &mouse_2019::MECH, 10, ORIENTATION, segment);
+
+        assert_eq!(debug.bezier, None);
+    }
+}
+
+#[cfg(test)]
+mod speed_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::PathMotion;
+    use crate::fast::{Vector, DIRECTION_0, DIRECTION_PI_2};
+
+    const STEPS: usize = 1000;
+
+    fn line() -> PathMotion {
+        PathMotion::line(Vector { x: 0.0, y: 0.0 }, Vector { x: 180.0, y: 0.0 })
+    }
+
+    fn corner() -> PathMotion {
+        PathMotion::corner(
+            Vector { x: 90.0, y: 90.0 },
+            DIRECTION_0,
+            DIRECTION_PI_2,
+            90.0,
+            0.0,
+        )
+    }
+
+    /// The arc length, adding up the speed at the middle of each step
+    fn arc_length(path: &PathMotion) -> f32 {
+        (0..STEPS)
+            .map(|i| path.speed((i as f32 + 0.5) / STEPS as f32))
+            .sum::<f32>()
+            / STEPS as f32
+    }
+
+    #[test]
+    fn magnitude_of_derivative() {
+        for &t in [0.0, 0.3, 0.5, 1.0].iter() {
+            assert_close(corner().speed(t), corner().derivative(t).magnitude());
+        }
+    }
+
+    #[test]
+    fn line_integrates_to_length() {
+        assert!((arc_length(&line()) - 180.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn line_is_symmetric() {
+        // All of the middle control points are at the middle of the line, so it starts and
+        // ends fast and slows down in the middle
+        assert_close(line().speed(0.0), 450.0);
+        assert_close(line().speed(1.0), 450.0);
+        assert_close(line().speed(0.2), line().speed(0.8));
+        assert!(line().speed(0.5) < line().speed(0.0));
+    }
+
+    #[test]
+    fn corner_varies() {
+        assert!((corner().speed(0.0) - corner().speed(0.5)).abs() > 1.0);
+        assert_close(corner().speed(0.0), corner().speed(1.0));
+    }
+}
+
+#[cfg(test)]
+mod turn_angle_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use core::f32::consts::FRAC_PI_2;
+
+    use super::PathMotion;
+    use crate::fast::{
+        Direction, Vector, DIRECTION_0, DIRECTION_3_PI_2, DIRECTION_PI, DIRECTION_PI_2,
+    };
+
+    const CENTER: Vector = Vector { x: 90.0, y: 90.0 };
+
+    fn corner(start: Direction, end: Direction) -> PathMotion {
+        PathMotion::corner(CENTER, start, end, 90.0, 0.0)
+    }
+
+    #[test]
+    fn line() {
+        let line =
+            PathMotion::line(Vector { x: 0.0, y: 0.0 }, Vector { x: 180.0, y: 180.0 });
+        assert_close(line.turn_angle(), 0.0);
+    }
+
+    #[test]
+    fn left_corner() {
+        assert_close(corner(DIRECTION_0, DIRECTION_PI_2).turn_angle(), FRAC_PI_2);
+    }
+
+    #[test]
+    fn right_corner() {
+        assert_close(
+            corner(DIRECTION_0, DIRECTION_3_PI_2).turn_angle(),
+            -FRAC_PI_2,
+        );
+    }
+
+    #[test]
+    fn reversed() {
+        // Driving the left corner backwards, coming in going south and leaving going west
+        let left = corner(DIRECTION_0, DIRECTION_PI_2);
+        let reversed = corner(DIRECTION_3_PI_2, DIRECTION_PI);
+        assert_close(reversed.turn_angle(), -left.turn_angle());
+    }
+
+    #[test]
+    fn across_zero() {
+        // Going south and then east crosses where the directions wrap around
+        assert_close(
+            corner(DIRECTION_3_PI_2, DIRECTION_0).turn_angle(),
+            FRAC_PI_2,
+        );
     }
 }
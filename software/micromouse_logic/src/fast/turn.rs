@@ -2,9 +2,14 @@ use serde::{Deserialize, Serialize};
 
 use pid_control::{Controller, PIDController};
 
+use libm::F32Ext;
+
 use super::Direction;
+
 use crate::config::MechanicalConfig;
+use crate::fast::curve::clamp;
 use crate::fast::{Orientation, DIRECTION_PI_2};
+use crate::units::Millis;
 
 #[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TurnDirection {
@@ -29,9 +34,20 @@ impl TurnMotion {
         TurnMotion { target, direction }
     }
 
+    /// Whether the mouse is pointing within tolerance of the target. See
+    /// [TurnHandlerConfig::settle_updates] for how long it needs to stay there.
     pub fn done(&self, config: &TurnHandlerConfig, orientation: Orientation) -> bool {
         orientation.direction.within(self.target, config.tolerance)
     }
+
+    /// Rotate the target direction by `rotation`. Turns happen in place, so there is nothing to
+    /// translate.
+    pub fn rotated(&self, rotation: Direction) -> TurnMotion {
+        TurnMotion {
+            target: self.target + rotation,
+            direction: self.direction,
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
@@ -40,7 +56,20 @@ pub struct TurnHandlerConfig {
     pub p: f32,
     pub i: f32,
     pub d: f32,
+
+    /// How close to the target direction a turn needs to be to be done, in radians
     pub tolerance: f32,
+
+    /// How many updates in a row a turn needs to be within tolerance before it is done, so it
+    /// is not done while swinging through the target. 0 or 1 is done as soon as it is within.
+    pub settle_updates: u8,
+
+    /// The fastest to turn, in rad/ms. 0 disables the limit.
+    pub max_angular_velocity: f32,
+
+    /// How fast the turn can speed up and slow down, in rad/ms^2, so it ramps up, cruises, and
+    /// slows down into the target instead of slipping. 0 disables the limit.
+    pub angular_acceleration: f32,
 }
 
 #[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
@@ -50,22 +79,36 @@ pub struct TurnHandlerDebug {
 
 pub struct TurnHandler {
     pid: PIDController,
-    time: u32,
+    time: Millis,
+
+    /// The turn velocity from the last update, in rad/ms
+    turn_velocity: f32,
 }
 
 impl TurnHandler {
-    pub fn new(config: &TurnHandlerConfig, time: u32) -> TurnHandler {
+    pub fn new(config: &TurnHandlerConfig, time: Millis) -> TurnHandler {
         let mut pid =
             PIDController::new(config.p as f64, config.i as f64, config.d as f64);
         pid.set_limits(-config.rad_per_sec as f64, config.rad_per_sec as f64);
-        TurnHandler { pid, time }
+        TurnHandler {
+            pid,
+            time,
+            turn_velocity: 0.0,
+        }
+    }
+
+    /// Forget the integral and derivative state from past turns, and ramp the next turn up from
+    /// stopped
+    pub fn reset(&mut self) {
+        self.pid.reset();
+        self.turn_velocity = 0.0;
     }
 
     pub fn update(
         &mut self,
         config: &TurnHandlerConfig,
         mech: &MechanicalConfig,
-        time: u32,
+        time: Millis,
         orientation: Orientation,
         motion: TurnMotion,
     ) -> (f32, f32, TurnHandlerDebug) {
@@ -85,6 +128,37 @@ impl TurnHandler {
             .update(f32::from(centered_direction) as f64, delta_time as f64)
             as f32;
 
+        let turn_velocity = if config.max_angular_velocity > 0.0 {
+            clamp(
+                turn_velocity,
+                -config.max_angular_velocity,
+                config.max_angular_velocity,
+            )
+        } else {
+            turn_velocity
+        };
+
+        let turn_velocity = if config.angular_acceleration > 0.0 {
+            // Slow enough to still stop at the target
+            let remaining = (f32::from(motion.target) - centered_direction).abs();
+            let stopping_velocity =
+                (2.0 * config.angular_acceleration * remaining).sqrt();
+            let turn_velocity =
+                clamp(turn_velocity, -stopping_velocity, stopping_velocity);
+
+            // Don't change speed faster than it can
+            let max_change = config.angular_acceleration * delta_time as f32;
+            clamp(
+                turn_velocity,
+                self.turn_velocity - max_change,
+                self.turn_velocity + max_change,
+            )
+        } else {
+            turn_velocity
+        };
+
+        self.turn_velocity = turn_velocity;
+
         let left_target = -mech.rads_to_mm(turn_velocity);
         let right_target = mech.rads_to_mm(turn_velocity);
 
@@ -97,3 +171,116 @@ impl TurnHandler {
         )
     }
 }
+
+#[cfg(test)]
+mod turn_profile_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use heapless::Vec;
+    use typenum::U1024;
+
+    use super::{TurnHandler, TurnHandlerConfig, TurnMotion};
+    use crate::config::mouse_2019::MECH;
+    use crate::config::sim::MOTION_CONTROL;
+    use crate::fast::{Direction, Orientation, Vector, DIRECTION_0, DIRECTION_PI_2};
+
+    const CONFIG: TurnHandlerConfig = TurnHandlerConfig {
+        max_angular_velocity: 0.004,
+        angular_acceleration: 0.00002,
+        ..MOTION_CONTROL.turn
+    };
+
+    fn at(direction: Direction) -> Orientation {
+        Orientation {
+            position: Vector { x: 90.0, y: 90.0 },
+            direction,
+        }
+    }
+
+    /// Turn from 0 to pi/2, moving the heading by exactly the commanded velocity. Returns the
+    /// velocity of each update and the final direction.
+    fn run(config: &TurnHandlerConfig, steps: u32) -> (Vec<f32, U1024>, Direction) {
+        let motion = TurnMotion::new(DIRECTION_0, DIRECTION_PI_2);
+        let mut handler = TurnHandler::new(config, 0);
+        let mut direction = DIRECTION_0;
+        let mut velocities = Vec::new();
+
+        for time in 1..=steps {
+            let (left, right, _) =
+                handler.update(config, &MECH, time, at(direction), motion);
+            let velocity = MECH.mm_to_rads((right - left) / 2.0);
+            velocities.push(velocity).unwrap();
+            direction = direction + Direction::from(velocity);
+        }
+
+        (velocities, direction)
+    }
+
+    #[test]
+    fn ramps_up() {
+        let (velocities, _) = run(&CONFIG, 1000);
+
+        let mut last = 0.0;
+        for &velocity in &velocities {
+            assert!(
+                (velocity - last).abs() <= CONFIG.angular_acceleration * 1.01,
+                "{} to {}",
+                last,
+                velocity
+            );
+            assert!(velocity <= CONFIG.max_angular_velocity * 1.01);
+            last = velocity;
+        }
+
+        // It does get up to the max and cruise for a while
+        let cruising = velocities
+            .iter()
+            .filter(|&&velocity| (velocity - CONFIG.max_angular_velocity).abs() < 0.00001)
+            .count();
+        assert!(cruising > 10);
+    }
+
+    #[test]
+    fn settles_on_target() {
+        let (velocities, direction) = run(&CONFIG, 1000);
+
+        assert!(direction.within(DIRECTION_PI_2, CONFIG.tolerance));
+        assert!(velocities.last().unwrap().abs() < 0.0001);
+    }
+
+    #[test]
+    fn disabled_jumps() {
+        let config = TurnHandlerConfig {
+            max_angular_velocity: 0.0,
+            angular_acceleration: 0.0,
+            ..CONFIG
+        };
+
+        let (velocities, _) = run(&config, 1);
+        assert!(velocities[0] > CONFIG.angular_acceleration * 10.0);
+    }
+
+    #[test]
+    fn reset_ramps_up_again() {
+        let motion = TurnMotion::new(DIRECTION_0, DIRECTION_PI_2);
+        let mut handler = TurnHandler::new(&CONFIG, 0);
+
+        // Get up to speed partway through the turn
+        let mut direction = DIRECTION_0;
+        let mut velocity = 0.0;
+        for time in 1..=100 {
+            let (left, right, _) =
+                handler.update(&CONFIG, &MECH, time, at(direction), motion);
+            velocity = MECH.mm_to_rads((right - left) / 2.0);
+            direction = direction + Direction::from(velocity);
+        }
+        assert!(velocity > CONFIG.angular_acceleration * 10.0);
+
+        handler.reset();
+
+        let (left, right, _) = handler.update(&CONFIG, &MECH, 101, at(direction), motion);
+        let velocity = MECH.mm_to_rads((right - left) / 2.0);
+        assert!(velocity.abs() <= CONFIG.angular_acceleration * 1.01);
+    }
+}
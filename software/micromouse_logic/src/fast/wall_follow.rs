@@ -0,0 +1,188 @@
+//! A simple wall follower that steers straight from the distance sensors
+//!
+//! This does not need a map or a path, so it can be used as a fallback to always make some
+//! progress through the maze.
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::MechanicalConfig;
+use crate::fast::path::curvature_to_left_right;
+use crate::fast::turn::TurnDirection;
+
+/// Which wall to prefer following when both ways are open
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum WallFollowSide {
+    Left,
+    Right,
+}
+
+impl Default for WallFollowSide {
+    fn default() -> Self {
+        WallFollowSide::Left
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WallFollowConfig {
+    /// Which side to turn towards if both are open
+    pub side: WallFollowSide,
+
+    /// The forward velocity in mm/ms
+    pub velocity: f32,
+
+    /// The wheel velocity in mm/ms when turning in place
+    pub turn_velocity: f32,
+
+    /// How much to steer towards the center for each mm off center
+    pub p: f32,
+
+    /// The distance to keep from a side wall when there is only one
+    pub side_distance: f32,
+
+    /// Turn in place when the front wall is closer than this
+    pub front_threshold: f32,
+}
+
+pub struct WallFollow {
+    turning: Option<TurnDirection>,
+}
+
+impl Default for WallFollow {
+    fn default() -> Self {
+        WallFollow::new()
+    }
+}
+
+impl WallFollow {
+    pub fn new() -> WallFollow {
+        WallFollow { turning: None }
+    }
+
+    /// Forget which way it was turning away from a front wall
+    pub fn reset(&mut self) {
+        self.turning = None;
+    }
+
+    /// Get the left and right wheel velocities from the distance readings. A reading of None
+    /// means that there is no wall in range.
+    pub fn update(
+        &mut self,
+        config: &WallFollowConfig,
+        mech: &MechanicalConfig,
+        left: Option<f32>,
+        front: Option<f32>,
+        right: Option<f32>,
+    ) -> (f32, f32) {
+        let front_blocked = front.map(|d| d < config.front_threshold).unwrap_or(false);
+
+        if front_blocked {
+            // Keep turning the same way until the front clears so it does not wiggle
+            let turning = self.turning.unwrap_or(match (left, right) {
+                (None, Some(_)) => TurnDirection::Counterclockwise,
+                (Some(_), None) => TurnDirection::Clockwise,
+                (Some(left), Some(right)) if left > right => {
+                    TurnDirection::Counterclockwise
+                }
+                (Some(left), Some(right)) if right > left => TurnDirection::Clockwise,
+                _ => match config.side {
+                    WallFollowSide::Left => TurnDirection::Counterclockwise,
+                    WallFollowSide::Right => TurnDirection::Clockwise,
+                },
+            });
+
+            self.turning = Some(turning);
+
+            match turning {
+                TurnDirection::Counterclockwise => {
+                    (-config.turn_velocity, config.turn_velocity)
+                }
+                TurnDirection::Clockwise => (config.turn_velocity, -config.turn_velocity),
+            }
+        } else {
+            self.turning = None;
+
+            // Positive is too far right, and needs to turn left
+            let offset = match (left, right) {
+                (Some(left), Some(right)) => (left - right) / 2.0,
+                (Some(left), None) => left - config.side_distance,
+                (None, Some(right)) => config.side_distance - right,
+                (None, None) => 0.0,
+            };
+
+            curvature_to_left_right(mech, config.velocity, config.p * offset)
+        }
+    }
+}
+
+#[cfg(test)]
+mod wall_follow_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{WallFollow, WallFollowConfig, WallFollowSide};
+    use crate::config::MechanicalConfig;
+
+    const MECH: MechanicalConfig = crate::config::mouse_2019::MECH;
+
+    const CONFIG: WallFollowConfig = WallFollowConfig {
+        side: WallFollowSide::Left,
+        velocity: 0.2,
+        turn_velocity: 0.1,
+        p: 0.001,
+        side_distance: 50.0,
+        front_threshold: 60.0,
+    };
+
+    #[test]
+    fn symmetric_goes_straight() {
+        let mut wall_follow = WallFollow::new();
+        let (left, right) =
+            wall_follow.update(&CONFIG, &MECH, Some(50.0), None, Some(50.0));
+        assert_close(left, 0.2);
+        assert_close(right, 0.2);
+    }
+
+    #[test]
+    fn closer_to_right_steers_left() {
+        let mut wall_follow = WallFollow::new();
+        let (left, right) =
+            wall_follow.update(&CONFIG, &MECH, Some(70.0), None, Some(30.0));
+        assert!(right > left);
+    }
+
+    #[test]
+    fn front_wall_turns_towards_open_side() {
+        let mut wall_follow = WallFollow::new();
+
+        // Far from the front wall, keep going
+        let (left, right) =
+            wall_follow.update(&CONFIG, &MECH, Some(50.0), Some(100.0), None);
+        assert!(left > 0.0 && right > 0.0);
+
+        // Close to the front wall, turn right since the right is open
+        let (left, right) =
+            wall_follow.update(&CONFIG, &MECH, Some(50.0), Some(40.0), None);
+        assert_close(left, 0.1);
+        assert_close(right, -0.1);
+    }
+
+    #[test]
+    fn keeps_turning_the_same_way() {
+        let mut wall_follow = WallFollow::new();
+
+        let (left, right) =
+            wall_follow.update(&CONFIG, &MECH, Some(50.0), Some(40.0), None);
+        assert!(left > right);
+
+        // The left wall goes away part way through the turn
+        let (left, right) = wall_follow.update(&CONFIG, &MECH, None, Some(40.0), None);
+        assert!(left > right);
+    }
+
+    #[test]
+    fn both_open_prefers_configured_side() {
+        let mut wall_follow = WallFollow::new();
+        let (left, right) = wall_follow.update(&CONFIG, &MECH, None, Some(40.0), None);
+        assert!(right > left);
+    }
+}
@@ -5,6 +5,7 @@ pub mod config;
 pub mod fast;
 pub mod mouse;
 pub mod slow;
+pub mod units;
 
 #[cfg(test)]
 mod test;
@@ -2,30 +2,89 @@ use core::f32;
 
 use serde::{Deserialize, Serialize};
 
-use crate::config::MechanicalConfig;
+use crate::config::{
+    MechanicalConfig, FRONT_SENSOR, LEFT_SENSOR, MAX_DISTANCE_SENSORS, RIGHT_SENSOR,
+};
+use crate::units::Millis;
 
 use crate::fast::localize::{Localize, LocalizeConfig, LocalizeDebug};
-use crate::fast::motion_queue::{Motion, MotionQueue, MotionQueueDebug};
+use crate::fast::motion_queue::{
+    Motion, MotionQueue, MotionQueueBuffer, MotionQueueDebug, MotionQueueSize,
+};
 use crate::fast::{Direction, Orientation, Vector};
 
 use crate::fast::motion_control::{
-    MotionControl, MotionControlConfig, MotionControlDebug,
+    side_wall_offset, MotionControl, MotionControlConfig, MotionControlDebug,
 };
 use crate::fast::path::PathMotion;
 use crate::slow::map::{Map, MapConfig};
-use crate::slow::maze::{Maze, MazeConfig};
-use crate::slow::motion_plan::{motion_plan, MotionPlanConfig};
-use crate::slow::navigate::TwelvePartitionNavigate;
-use crate::slow::{MazeDirection, MazeOrientation, SlowDebug};
+use crate::slow::maze::{Maze, MazeConfig, Wall, WallIndex};
+use crate::slow::motion_plan::{
+    explore_plan, intersection_stop, FastRunPlan, MotionPlanConfig,
+};
+use crate::slow::navigate::{
+    DecisionLog, NavigateConfig, NavigateDecision, TwelvePartitionNavigate,
+};
+use crate::slow::{Goal, MazeDirection, MazeOrientation, MazePosition, SlowDebug};
 use core::cmp::Ordering;
+use heapless::Vec;
+use typenum::{Unsigned, U64};
+
+/// How far apart motions can be when adding them to the end of the queue, in mm
+const APPEND_TOLERANCE: f32 = 1.0;
+
+/// What the mouse is trying to do
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MouseMode {
+    /// Move one cell at a time, sensing walls and re-planning in every cell
+    Explore,
+
+    /// Follow the shortest known path to the goal cell without stopping. The path is planned
+    /// once from the map, and only abandoned if the localization diverges.
+    FastRun(Goal),
+
+    /// Drive the motors at fixed powers, skipping all planning and motion control. The mouse
+    /// still localizes, so the odometry can be watched during hardware bring-up.
+    OpenLoop { left_power: i32, right_power: i32 },
+
+    /// Stay still without planning anything. Exploring switches to this if it can't plan a
+    /// move from where the mouse is, instead of trying again every update. Only changing the
+    /// mode, like with [Mouse::explore], leaves it. If [MotionControlConfig::wall_follow] is
+    /// set, the mouse follows the walls instead of staying still.
+    Hold,
+}
+
+impl Default for MouseMode {
+    fn default() -> MouseMode {
+        MouseMode::Explore
+    }
+}
+
+/// What to do when a fast run can't find a route to its goal. Unknown walls are already taken
+/// as open when planning it, so this means the walls seen so far shut the goal off completely.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum UnreachableGoal {
+    /// Go back to exploring, in case some of the walls were seen wrong
+    Explore,
+
+    /// Stop and hold still, so it is obvious something is wrong
+    Hold,
+}
+
+impl Default for UnreachableGoal {
+    fn default() -> UnreachableGoal {
+        UnreachableGoal::Explore
+    }
+}
 
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct HardwareDebug {
     pub left_encoder: i32,
     pub right_encoder: i32,
-    pub left_distance: Option<DistanceReading>,
-    pub front_distance: Option<DistanceReading>,
-    pub right_distance: Option<DistanceReading>,
+
+    /// The reading from each distance sensor, in the same order as
+    /// [MechanicalConfig::distance_sensors]
+    pub distances: DistanceReadings,
 }
 
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
@@ -38,8 +97,28 @@ pub struct MouseDebug {
     pub motion_queue: MotionQueueDebug,
     pub slow: Option<SlowDebug>,
     pub battery: u16,
-    pub time: u32,
-    pub delta_time: u32,
+    pub time: Millis,
+    pub delta_time: Millis,
+
+    /// Whether the start delay is over and the mouse is allowed to move
+    pub armed: bool,
+
+    /// Whether a newly seen wall blocked the planned motions, so they were thrown out
+    pub replanned: bool,
+
+    pub mode: MouseMode,
+
+    /// Whether the motor powers are fixed by [Mouse::open_loop]
+    pub open_loop: bool,
+
+    /// Whether the mouse is holding still because a plan came out empty, see [MouseMode::Hold]
+    pub holding: bool,
+
+    /// The last move the navigator picked while exploring, and why
+    pub decision: Option<NavigateDecision>,
+
+    /// Whether the last fast run couldn't find any route to its goal, see [UnreachableGoal]
+    pub unreachable: bool,
 }
 
 #[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
@@ -48,11 +127,41 @@ pub struct MouseConfig {
     pub localize: LocalizeConfig,
     pub map: MapConfig,
     pub motion_plan: MotionPlanConfig,
+    pub navigate: NavigateConfig,
     pub maze: MazeConfig,
     pub motion_control: MotionControlConfig,
     pub front_sensor_abort: f32,
     pub left_sensor_abort: f32,
     pub right_sensor_abort: f32,
+
+    /// How long to hold still after the mouse is created before moving, in ms
+    pub start_delay: Millis,
+
+    /// Watch for walls while moving, and throw out the planned motions to plan again if a new
+    /// wall is in the way
+    pub replan_on_blocked: bool,
+
+    /// The most times to go over the whole maze in one update when planning a fast run, so
+    /// planning doesn't take too long on the real mouse. The plan is picked back up in the next
+    /// update. 0 plans it all at once.
+    pub slow_plan_budget: usize,
+
+    /// The cell to measure the best known route to while exploring, see
+    /// [Mouse::best_route_cost]
+    pub route_goal: MazePosition,
+
+    /// What to do when a fast run can't find a route to its goal
+    pub unreachable_goal: UnreachableGoal,
+
+    /// How many of the most recent orientations to keep in [Mouse::trail], up to
+    /// [TrailSize]. 0 disables the trail
+    pub trail_length: usize,
+
+    /// How many cells to explore before giving up and going on a fast run to
+    /// [MouseConfig::route_goal] with whatever has been learned so far. This only happens
+    /// once, so if the mouse goes back to exploring afterwards, it keeps exploring. 0 explores
+    /// forever
+    pub max_explore_steps: usize,
 }
 
 pub trait ContainsDistanceReading {
@@ -104,6 +213,24 @@ impl PartialOrd<DistanceReading> for f32 {
     }
 }
 
+/// A reading from each of the distance sensors, in the same order as
+/// [MechanicalConfig::distance_sensors]. `None` if a sensor does not have a new reading, or is
+/// not there.
+pub type DistanceReadings = [Option<DistanceReading>; MAX_DISTANCE_SENSORS];
+
+/// Put the left, front, and right readings where they go for a mouse with just those sensors
+pub fn three_distance_readings(
+    left: Option<DistanceReading>,
+    front: Option<DistanceReading>,
+    right: Option<DistanceReading>,
+) -> DistanceReadings {
+    let mut readings = [None; MAX_DISTANCE_SENSORS];
+    readings[LEFT_SENSOR] = left;
+    readings[FRONT_SENSOR] = front;
+    readings[RIGHT_SENSOR] = right;
+    readings
+}
+
 impl ContainsDistanceReading for Option<DistanceReading> {
     /// Returns Some(value) if the distance reading is Some(InRange),
     /// None otherwise
@@ -116,25 +243,66 @@ impl ContainsDistanceReading for Option<DistanceReading> {
     }
 }
 
+pub type TrailSize = U64;
+
+/// The most recent orientations of the mouse, oldest first
+pub type Trail = Vec<Orientation, TrailSize>;
+
 pub struct Mouse {
-    last_time: u32,
+    start_time: Millis,
+    last_time: Millis,
     map: Map,
     navigate: TwelvePartitionNavigate,
     localize: Localize,
     motion_queue: MotionQueue,
     motion_control: MotionControl,
+
+    /// Whether the motion control is up to date with the last update. If it was skipped, it is
+    /// restarted before it is updated again.
+    driving: bool,
+
     moves_completed: usize,
+    mode: MouseMode,
+
+    /// How many cells have been explored since the mouse was created
+    explore_steps: usize,
+
+    /// Whether the exploring budget already started a fast run
+    explore_budget_spent: bool,
+
+    fast_run: Option<FastRunPlan>,
+
+    /// Whether the map is still planning the path for the fast run
+    planning_full_path: bool,
+
+    /// The best known route cost from the last map update
+    best_route_cost: Option<u16>,
+
+    /// The cell and [Map::changes] that `best_route_cost` was worked out for
+    best_route_cost_for: Option<(MazePosition, u32)>,
+
+    /// Whether the last fast run couldn't find a route to its goal
+    unreachable: bool,
+
+    /// The cell the mouse last stopped in to look around before picking a move
+    stopped_in: Option<MazePosition>,
+
+    /// The last reading from each distance sensor, kept until that sensor has a new one
+    distances: DistanceReadings,
+
+    trail: Trail,
 }
 
 impl Mouse {
     pub fn new(
         config: &MouseConfig,
         orientation: Orientation,
-        time: u32,
+        time: Millis,
         left_encoder: i32,
         right_encoder: i32,
     ) -> Mouse {
         Mouse {
+            start_time: time,
             last_time: time,
             map: Map::new(),
             navigate: TwelvePartitionNavigate::new(),
@@ -147,36 +315,323 @@ impl Mouse {
                 orientation,
             ),
             motion_queue: MotionQueue::new(),
+            driving: true,
             moves_completed: 0,
+            mode: MouseMode::Explore,
+            explore_steps: 0,
+            explore_budget_spent: false,
+            fast_run: None,
+            planning_full_path: false,
+            best_route_cost: None,
+            best_route_cost_for: None,
+            unreachable: false,
+            stopped_in: None,
+            distances: [None; MAX_DISTANCE_SENSORS],
+            trail: Vec::new(),
+        }
+    }
+
+    /// A mouse in the start cell from [MazeConfig::start_cell], facing
+    /// [MazeConfig::start_facing], that already knows the start cell is closed on every other
+    /// side
+    pub fn at_start(
+        config: &MouseConfig,
+        time: Millis,
+        left_encoder: i32,
+        right_encoder: i32,
+    ) -> Mouse {
+        let mut mouse = Mouse::new(
+            config,
+            config.maze.start_orientation(),
+            time,
+            left_encoder,
+            right_encoder,
+        );
+
+        let cell = config.maze.start_cell;
+        let facing = config.maze.start_facing;
+        mouse.map.apply_known_walls(&[
+            (cell, facing.left(), Wall::Closed),
+            (cell, facing.right(), Wall::Closed),
+            (cell, facing.opposite(), Wall::Closed),
+        ]);
+
+        mouse
+    }
+
+    /// Stop exploring and go to `goal` as fast as possible along the shortest path through the
+    /// maze learned so far. Any unknown walls are assumed to be open. The path is planned once
+    /// the current motions are done. If the goal has a facing, the mouse turns in place to face
+    /// it at the end.
+    pub fn start_fast_run(&mut self, goal: Goal) {
+        self.mode = MouseMode::FastRun(goal);
+        self.fast_run = None;
+        self.planning_full_path = false;
+        self.unreachable = false;
+    }
+
+    /// Go back to exploring the maze cell by cell
+    pub fn explore(&mut self) {
+        self.mode = MouseMode::Explore;
+        self.fast_run = None;
+        self.planning_full_path = false;
+    }
+
+    /// Drive the motors at `left_power` and `right_power` until the mode is changed again,
+    /// for testing the motors and encoders. Any planned motions are thrown out.
+    pub fn open_loop(&mut self, left_power: i32, right_power: i32) {
+        self.mode = MouseMode::OpenLoop {
+            left_power,
+            right_power,
+        };
+        self.fast_run = None;
+        self.planning_full_path = false;
+        self.motion_queue.clear();
+    }
+
+    /// Follow path motions at exactly `velocity_override` in mm/ms while still steering along
+    /// them, or go back to the planned velocity with None. See
+    /// [MotionControl::set_velocity_override].
+    pub fn set_velocity_override(&mut self, velocity_override: Option<f32>) {
+        self.motion_control.set_velocity_override(velocity_override);
+    }
+
+    pub fn mode(&self) -> MouseMode {
+        self.mode
+    }
+
+    /// Everything the mouse has learned about the maze so far
+    pub fn maze(&self) -> &Maze {
+        self.map.maze()
+    }
+
+    /// How many cells it is from where the mouse was at the last map update to
+    /// [MouseConfig::route_goal], only going through walls known to be open. None if no route
+    /// is known yet.
+    pub fn best_route_cost(&self) -> Option<u16> {
+        self.best_route_cost
+    }
+
+    /// The most recent moves the navigator picked while exploring, oldest first
+    pub fn decisions(&self) -> &DecisionLog {
+        self.navigate.decisions()
+    }
+
+    /// The orientation from each of the last [MouseConfig::trail_length] updates, oldest first
+    pub fn trail(&self) -> &[Orientation] {
+        &self.trail
+    }
+
+    /// Add the orientation to the trail, forgetting the oldest ones to make room
+    fn remember_orientation(&mut self, config: &MouseConfig, orientation: Orientation) {
+        let length = config.trail_length.min(TrailSize::to_usize());
+
+        if length == 0 {
+            self.trail.clear();
+            return;
+        }
+
+        while self.trail.len() >= length {
+            self.trail.rotate_left(1);
+            self.trail.pop();
+        }
+
+        self.trail.push(orientation).ok();
+    }
+
+    /// Whether the fast run has started queueing motions and has more left to queue. While it
+    /// is still being planned the mouse is meant to stop, so that isn't pending.
+    fn fast_run_pending(&self) -> bool {
+        match self.mode {
+            MouseMode::FastRun(_) => self
+                .fast_run
+                .as_ref()
+                .map(|fast_run| !fast_run.done())
+                .unwrap_or(false),
+            _ => false,
+        }
+    }
+
+    /// Whether any of the planned motions, or the rest of the fast run, go through `wall`
+    fn plan_blocked_by(&self, config: &MouseConfig, wall: WallIndex) -> bool {
+        let cell = |position: Vector| {
+            Orientation {
+                position,
+                direction: Direction::default(),
+            }
+            .to_maze_orientation(&config.maze)
+            .position
+        };
+
+        let queue_blocked =
+            self.motion_queue
+                .motions()
+                .iter()
+                .any(|motion| match motion {
+                    Motion::Path(path_motion) => {
+                        WallIndex::between(
+                            cell(path_motion.start()),
+                            cell(path_motion.end()),
+                        ) == Some(wall)
+                    }
+                    Motion::Turn(_) | Motion::Stop(_) => false,
+                });
+
+        let fast_run_blocked = self
+            .fast_run
+            .as_ref()
+            .map(|fast_run| fast_run.crosses(wall))
+            .unwrap_or(false);
+
+        queue_blocked || fast_run_blocked
+    }
+
+    /// Queue up the motions to take the next step exploring. If there aren't any, there is
+    /// nothing to follow, so hold still instead of planning again next update. See
+    /// [MouseMode::Hold].
+    fn queue_explore_plan(&mut self, path: &MotionQueueBuffer) {
+        if path.is_empty() {
+            self.mode = MouseMode::Hold;
+        } else {
+            self.motion_queue.add_motions(path).ok();
+        }
+    }
+
+    /// Plan the fast run if it has not been yet, and keep the motion queue topped up from it
+    fn update_fast_run(
+        &mut self,
+        config: &MouseConfig,
+        orientation: Orientation,
+        goal: Goal,
+    ) {
+        if self.fast_run.is_none() {
+            // Let the last motions finish so the plan starts from where the mouse stops
+            if self.motion_queue.motions_remaining() > 0 {
+                return;
+            }
+
+            let from = orientation.to_maze_orientation(&config.maze).position;
+            if !self.planning_full_path {
+                self.map.start_full_path(from, goal.position);
+                self.planning_full_path = true;
+            }
+
+            let budget = if config.slow_plan_budget == 0 {
+                usize::max_value()
+            } else {
+                config.slow_plan_budget
+            };
+
+            // Pick it back up next update
+            if !self.map.relax_full_path(&config.navigate, budget) {
+                return;
+            }
+
+            let path = self.map.full_path(&config.navigate);
+            self.planning_full_path = false;
+
+            match path {
+                Some(path) => {
+                    self.fast_run =
+                        Some(FastRunPlan::new(&path, orientation, goal.facing))
+                }
+                None => {
+                    self.unreachable = true;
+                    match config.unreachable_goal {
+                        UnreachableGoal::Explore => self.explore(),
+                        UnreachableGoal::Hold => self.mode = MouseMode::Hold,
+                    }
+                    return;
+                }
+            }
+        }
+
+        if let Some(fast_run) = &mut self.fast_run {
+            let room =
+                MotionQueueSize::to_usize() - self.motion_queue.motions_remaining();
+            let motions = fast_run.fill(&config.motion_plan, &config.maze, room);
+            self.motion_queue.append(&motions, APPEND_TOLERANCE).ok();
         }
     }
 
+    /// Run one loop of the mouse. `time` is in milliseconds, see [crate::units]
+    ///
+    /// The distance sensors may update slower than this is called, and may take turns. A reading
+    /// of None means that sensor has nothing new, and its last reading is kept for the map and
+    /// the motion control. `distances_fresh` should be true only if any of the readings are new
+    /// since the last update. Only new readings are used to correct the localization.
     pub fn update(
         &mut self,
         config: &MouseConfig,
-        time: u32,
+        time: Millis,
         battery: u16,
         left_encoder: i32,
         right_encoder: i32,
         left_distance: Option<DistanceReading>,
         front_distance: Option<DistanceReading>,
         right_distance: Option<DistanceReading>,
+        distances_fresh: bool,
+    ) -> (i32, i32, MouseDebug) {
+        self.update_sensors(
+            config,
+            time,
+            battery,
+            left_encoder,
+            right_encoder,
+            three_distance_readings(left_distance, front_distance, right_distance),
+            distances_fresh,
+        )
+    }
+
+    /// Like [Mouse::update], but with a reading from every distance sensor in
+    /// [MechanicalConfig::distance_sensors], including any extra sensors
+    pub fn update_sensors(
+        &mut self,
+        config: &MouseConfig,
+        time: Millis,
+        battery: u16,
+        left_encoder: i32,
+        right_encoder: i32,
+        distances: DistanceReadings,
+        distances_fresh: bool,
     ) -> (i32, i32, MouseDebug) {
         let delta_time = time - self.last_time;
 
-        let (orientation, localize_debug) = self.localize.update(
+        // Only correct the localization with readings that have been updated since last time
+        let fresh_distances = if distances_fresh {
+            distances
+        } else {
+            [None; MAX_DISTANCE_SENSORS]
+        };
+
+        // Everything else goes by the last reading from each sensor
+        for (last, &fresh) in self.distances.iter_mut().zip(fresh_distances.iter()) {
+            if fresh.is_some() {
+                *last = fresh;
+            }
+        }
+        let distances = self.distances;
+
+        let left_distance = distances[LEFT_SENSOR];
+        let front_distance = distances[FRONT_SENSOR];
+        let right_distance = distances[RIGHT_SENSOR];
+
+        let armed = time - self.start_time >= config.start_delay;
+
+        let (orientation, localize_debug) = self.localize.update_sensors(
             &config.mechanical,
             &config.maze,
             &config.localize,
             left_encoder,
             right_encoder,
-            left_distance,
-            front_distance,
-            right_distance,
+            fresh_distances,
+            distances_fresh,
             self.motion_queue.next_motion(),
             self.moves_completed,
         );
 
+        self.remember_orientation(config, orientation);
+
         let (motion_going_forward, motion_going_left, motion_going_right) =
             match self.motion_queue.next_motion() {
                 Some(Motion::Path(path_motion)) => {
@@ -229,42 +684,123 @@ impl Mouse {
             .map(|d| motion_going_right && d < config.right_sensor_abort)
             .unwrap_or(false);
 
-        let abort_moves = abort_front || abort_left || abort_right;
+        // A fast run trusts the map, so only give up on it if lost
+        let abort_moves = match self.mode {
+            MouseMode::Explore => {
+                abort_front || abort_left || abort_right || localize_debug.recovering
+            }
+            MouseMode::FastRun(_) => localize_debug.recovering,
+            MouseMode::OpenLoop { .. } | MouseMode::Hold => false,
+        };
+
+        if localize_debug.diverged && self.fast_run.is_some() {
+            self.explore();
+        }
 
         self.moves_completed = if abort_moves {
             let len = self.motion_queue.motions_remaining();
             self.motion_queue.clear();
             len
         } else {
-            self.motion_queue
-                .pop_completed(&config.motion_control.turn, orientation)
+            self.motion_queue.pop_completed(
+                &config.motion_control.turn,
+                config.motion_control.look_behind,
+                config.motion_control.path.completion_hysteresis,
+                orientation,
+            )
+        };
+
+        // Look for new walls while moving straight, and plan again if they are in the way
+        let replanned = match self.motion_queue.next_motion() {
+            Some(Motion::Path(_)) if config.replan_on_blocked => {
+                match self.map.sense_front(
+                    &config.mechanical,
+                    &config.maze,
+                    &config.map,
+                    orientation,
+                    front_distance,
+                ) {
+                    Some(wall) => self.plan_blocked_by(config, wall),
+                    None => false,
+                }
+            }
+            _ => false,
         };
 
-        let slow_debug = if self.motion_queue.motions_remaining() == 0 {
-            let (move_options, map_debug) = self.map.update(
+        if replanned {
+            self.motion_queue.clear();
+            self.fast_run = None;
+            self.planning_full_path = false;
+        }
+
+        // Once the exploring budget is spent, race to the goal with the map as it is
+        if self.mode == MouseMode::Explore
+            && !self.explore_budget_spent
+            && config.max_explore_steps > 0
+            && self.explore_steps >= config.max_explore_steps
+            && self.motion_queue.motions_remaining() == 0
+        {
+            self.explore_budget_spent = true;
+            self.start_fast_run(Goal {
+                position: config.route_goal,
+                facing: None,
+            });
+        }
+
+        // Don't start mapping until armed, the mouse may still be getting placed. Or while lost,
+        // the walls would end up in the wrong place
+        let explore_step = armed
+            && !localize_debug.recovering
+            && self.mode == MouseMode::Explore
+            && self.motion_queue.motions_remaining() == 0;
+
+        let slow_debug = if explore_step {
+            let (move_options, map_debug) = self.map.update_sensors(
                 &config.mechanical,
                 &config.maze,
                 &config.map,
                 orientation.to_maze_orientation(&config.maze),
-                left_distance,
-                front_distance,
-                right_distance,
+                distances,
             );
 
-            if let Some(move_options) = move_options {
+            // Only look for the route again once the mouse or the map has changed
+            let position = orientation.to_maze_orientation(&config.maze).position;
+            let route_cost_for = Some((position, self.map.changes()));
+            if self.best_route_cost_for != route_cost_for {
+                self.best_route_cost =
+                    self.map.known_route_cost(position, config.route_goal);
+                self.best_route_cost_for = route_cost_for;
+            }
+
+            let stop = move_options
+                .filter(|_| self.stopped_in != Some(position))
+                .and_then(|move_options| {
+                    intersection_stop(&config.motion_plan, move_options)
+                });
+
+            if let Some(stop) = stop {
+                // Look again once stopped, before picking where to go
+                self.motion_queue.add_motions(&[stop]).ok();
+                self.stopped_in = Some(position);
+                None
+            } else if let Some(move_options) = move_options {
+                self.stopped_in = None;
+
                 let (next_direction, navigate_debug) = self.navigate.navigate(
+                    &config.navigate,
                     orientation.to_maze_orientation(&config.maze),
                     move_options,
                 );
 
-                let path = motion_plan(
+                let path = explore_plan(
                     &config.motion_plan,
                     &config.maze,
                     orientation,
-                    &[next_direction],
+                    next_direction,
                 );
 
-                self.motion_queue.add_motions(&path).ok();
+                self.queue_explore_plan(&path);
+                self.explore_steps += 1;
 
                 // TODO: Get the move options and map debug out even if they are None
                 Some(SlowDebug {
@@ -280,22 +816,73 @@ impl Mouse {
             None
         };
 
-        let (left_power, right_power, motion_debug) = self.motion_control.update(
-            &config.motion_control,
-            &config.mechanical,
-            time,
-            left_encoder,
-            right_encoder,
-            self.motion_queue.next_motion(),
-            orientation,
-        );
+        if let MouseMode::FastRun(goal) = self.mode {
+            if armed && !localize_debug.recovering {
+                self.update_fast_run(config, orientation, goal);
+            }
+        }
+
+        // Without a plan to follow, follow the walls instead if that is set up
+        let wall_following =
+            self.mode == MouseMode::Hold && config.motion_control.wall_follow.is_some();
+        let holding_still = self.mode == MouseMode::Hold && !wall_following;
+
+        // Stay stopped until the localization has recovered, then let the new motions start
+        let (left_power, right_power, motion_debug) = if let MouseMode::OpenLoop {
+            left_power,
+            right_power,
+        } = self.mode
+        {
+            self.driving = false;
+            (left_power, right_power, MotionControlDebug::default())
+        } else if !armed || localize_debug.recovering || holding_still {
+            self.driving = false;
+            (0, 0, MotionControlDebug::default())
+        } else {
+            // Don't let the time and encoders from while it was stopped build up
+            if !self.driving {
+                self.motion_control.restart(
+                    &config.motion_control,
+                    self.last_time,
+                    left_encoder,
+                    right_encoder,
+                    orientation,
+                );
+                self.driving = true;
+            }
+
+            self.motion_control
+                .set_motions_pending(self.fast_run_pending());
+            self.motion_control.set_wall_follow(if wall_following {
+                Some((
+                    left_distance.value(),
+                    front_distance.value(),
+                    right_distance.value(),
+                ))
+            } else {
+                None
+            });
+            self.motion_control.update(
+                &config.motion_control,
+                &config.mechanical,
+                time,
+                left_encoder,
+                right_encoder,
+                self.motion_queue.next_motion(),
+                orientation,
+                side_wall_offset(
+                    &config.mechanical,
+                    &config.maze,
+                    left_distance.value(),
+                    right_distance.value(),
+                ),
+            )
+        };
 
         let hardware_debug = HardwareDebug {
             left_encoder,
             right_encoder,
-            left_distance,
-            front_distance,
-            right_distance,
+            distances,
         };
 
         let debug = MouseDebug {
@@ -309,6 +896,13 @@ impl Mouse {
             battery,
             time,
             delta_time,
+            armed,
+            replanned,
+            mode: self.mode,
+            open_loop: matches!(self.mode, MouseMode::OpenLoop { .. }),
+            holding: self.mode == MouseMode::Hold,
+            decision: self.navigate.decisions().last().copied(),
+            unreachable: self.unreachable,
         };
 
         self.last_time = time;
@@ -317,24 +911,1441 @@ impl Mouse {
     }
 }
 
-pub struct TestMouse {}
+#[cfg(test)]
+mod start_delay_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
 
-impl TestMouse {
-    pub fn new() -> TestMouse {
-        TestMouse {}
+    use super::{DistanceReading, Mouse, MouseConfig};
+    use crate::config::sim;
+    use crate::fast::{Orientation, Vector, DIRECTION_PI_2};
+
+    const CONFIG: MouseConfig = MouseConfig {
+        start_delay: 100,
+        ..sim::MOUSE_2019
+    };
+
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 90.0, y: 90.0 },
+        direction: DIRECTION_PI_2,
+    };
+
+    // In the start cell, with walls on both sides and open in front
+    fn update(mouse: &mut Mouse, time: u32) -> (i32, i32, bool) {
+        update_with(mouse, &CONFIG, time)
     }
 
-    pub fn update(
-        &mut self,
-        _config: &MouseConfig,
+    fn update_with(
+        mouse: &mut Mouse,
+        config: &MouseConfig,
         time: u32,
-        _left_encoder: i32,
-        _right_encoder: i32,
-    ) -> (f32, f32) {
-        if time % 10000 <= 5000 {
-            (0.0, 0.0)
-        } else {
-            (1.0, 1.0)
+    ) -> (i32, i32, bool) {
+        let (left, right, debug) = mouse.update(
+            config,
+            time,
+            0,
+            0,
+            0,
+            Some(DistanceReading::InRange(30.0)),
+            Some(DistanceReading::OutOfRange),
+            Some(DistanceReading::InRange(30.0)),
+            true,
+        );
+
+        (left, right, debug.armed)
+    }
+
+    #[test]
+    fn holds_still_before_delay() {
+        let mut mouse = Mouse::new(&CONFIG, ORIENTATION, 0, 0, 0);
+
+        for time in (10..100).step_by(10) {
+            assert_eq!(update(&mut mouse, time), (0, 0, false));
+        }
+    }
+
+    #[test]
+    fn moves_after_delay() {
+        let mut mouse = Mouse::new(&CONFIG, ORIENTATION, 0, 0, 0);
+
+        for time in (10..100).step_by(10) {
+            update(&mut mouse, time);
+        }
+
+        // The first motion is lining up in the cell, then it should go forward
+        let moving = (100..200).step_by(10).any(|time| {
+            let (left, right, armed) = update(&mut mouse, time);
+            assert!(armed);
+            left > 0 && right > 0
+        });
+
+        assert!(moving);
+    }
+
+    #[test]
+    fn first_armed_update_starts_fresh() {
+        let mut mouse = Mouse::new(&CONFIG, ORIENTATION, 0, 0, 0);
+
+        // The wheels get bumped while the mouse is being put down, then it sits still
+        let update = |mouse: &mut Mouse, time: u32, encoder: i32| {
+            let (_, _, debug) = mouse.update(
+                &CONFIG,
+                time,
+                0,
+                encoder,
+                encoder,
+                Some(DistanceReading::InRange(30.0)),
+                Some(DistanceReading::OutOfRange),
+                Some(DistanceReading::InRange(30.0)),
+                true,
+            );
+            debug
+        };
+
+        for time in (10..100).step_by(10) {
+            let encoder = if time < 50 { 0 } else { 20 };
+            assert!(!update(&mut mouse, time, encoder).armed);
         }
+
+        // Nothing moved since the last update, so the bump shouldn't show up as velocity
+        let debug = update(&mut mouse, 100, 20);
+        assert!(debug.armed);
+        assert_eq!(debug.motion_control.motor_control.left_velocity, 0.0);
+        assert_eq!(debug.motion_control.motor_control.right_velocity, 0.0);
+    }
+}
+
+#[cfg(test)]
+mod best_route_cost_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{DistanceReading, Mouse, MouseConfig};
+    use crate::config::sim;
+    use crate::fast::{Orientation, Vector, DIRECTION_PI_2};
+    use crate::slow::MazePosition;
+
+    const CONFIG: MouseConfig = MouseConfig {
+        route_goal: MazePosition { x: 0, y: 1 },
+        ..sim::MOUSE_2019
+    };
+
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 90.0, y: 90.0 },
+        direction: DIRECTION_PI_2,
+    };
+
+    #[test]
+    fn learns_route() {
+        let mut mouse = Mouse::new(&CONFIG, ORIENTATION, 0, 0, 0);
+        assert_eq!(mouse.best_route_cost(), None);
+
+        // Walls on both sides and open in front, so the goal is one cell ahead
+        mouse.update(
+            &CONFIG,
+            10,
+            0,
+            0,
+            0,
+            Some(DistanceReading::InRange(30.0)),
+            Some(DistanceReading::OutOfRange),
+            Some(DistanceReading::InRange(30.0)),
+            true,
+        );
+
+        assert_eq!(mouse.best_route_cost(), Some(1));
+    }
+}
+
+#[cfg(test)]
+mod velocity_override_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{DistanceReading, Mouse};
+    use crate::config::{mouse_2019, sim};
+    use crate::fast::motion_control::MotionHandlerDebug;
+    use crate::fast::{Orientation, Vector, DIRECTION_PI_2};
+
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 90.0, y: 90.0 },
+        direction: DIRECTION_PI_2,
+    };
+
+    #[test]
+    fn path_velocity_is_overridden() {
+        let config = sim::MOUSE_2019;
+        let mut mouse = Mouse::new(&config, ORIENTATION, 0, 0, 0);
+        mouse.set_velocity_override(Some(0.25));
+
+        // Walls on both sides and open in front, until it gets going along a path
+        let debug = (1..50)
+            .map(|i| {
+                let (_, _, debug) = mouse.update(
+                    &config,
+                    i * 10,
+                    0,
+                    0,
+                    0,
+                    Some(DistanceReading::InRange(30.0)),
+                    Some(DistanceReading::OutOfRange),
+                    Some(DistanceReading::InRange(30.0)),
+                    true,
+                );
+                debug
+            })
+            .find(|debug| match debug.motion_control.handler {
+                Some(MotionHandlerDebug::Path(_)) => true,
+                _ => false,
+            })
+            .expect("Never followed a path");
+
+        let motor_control = debug.motion_control.motor_control;
+        let velocity = mouse_2019::MECH.ticks_to_mm(
+            ((motor_control.target_left_velocity + motor_control.target_right_velocity)
+                / 2.0) as f32,
+        );
+        assert_close(velocity, 0.25);
+    }
+}
+
+#[cfg(test)]
+mod fast_run_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{DistanceReading, Mouse, MouseConfig, MouseMode};
+    use crate::config::sim;
+    use crate::fast::{Orientation, Vector, DIRECTION_PI_2};
+    use crate::slow::{Goal, MazePosition};
+
+    const CONFIG: MouseConfig = sim::MOUSE_2019;
+
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 90.0, y: 90.0 },
+        direction: DIRECTION_PI_2,
+    };
+
+    const GOAL: Goal = Goal {
+        position: MazePosition { x: 0, y: 6 },
+        facing: None,
+    };
+
+    fn update(mouse: &mut Mouse, time: u32) -> usize {
+        let (_, _, debug) = mouse.update(
+            &CONFIG,
+            time,
+            0,
+            0,
+            0,
+            Some(DistanceReading::InRange(30.0)),
+            Some(DistanceReading::OutOfRange),
+            Some(DistanceReading::InRange(30.0)),
+            true,
+        );
+
+        assert!(debug.slow.is_none());
+        mouse.motion_queue.motions_remaining()
+    }
+
+    #[test]
+    fn fills_the_queue_without_stopping_to_map() {
+        let mut mouse = Mouse::new(&CONFIG, ORIENTATION, 0, 0, 0);
+        mouse.start_fast_run(GOAL);
+
+        assert_eq!(update(&mut mouse, 10), 4);
+        assert_eq!(mouse.mode(), MouseMode::FastRun(GOAL));
+    }
+
+    #[test]
+    fn budget_spreads_planning_out() {
+        let config = MouseConfig {
+            slow_plan_budget: 1,
+            ..CONFIG
+        };
+
+        let mut budgeted = Mouse::new(&config, ORIENTATION, 0, 0, 0);
+        budgeted.start_fast_run(GOAL);
+
+        let mut updates = 0;
+        while budgeted.motion_queue.motions_remaining() == 0 {
+            updates += 1;
+            assert!(updates < 100);
+
+            budgeted.update(
+                &config,
+                updates * 10,
+                0,
+                0,
+                0,
+                Some(DistanceReading::InRange(30.0)),
+                Some(DistanceReading::OutOfRange),
+                Some(DistanceReading::InRange(30.0)),
+                true,
+            );
+        }
+
+        let mut all_at_once = Mouse::new(&CONFIG, ORIENTATION, 0, 0, 0);
+        all_at_once.start_fast_run(GOAL);
+        update(&mut all_at_once, 10);
+
+        assert!(updates > 1);
+        assert_eq!(
+            budgeted.motion_queue.motions(),
+            all_at_once.motion_queue.motions()
+        );
+    }
+
+    #[test]
+    fn explore_drops_the_fast_run() {
+        let mut mouse = Mouse::new(&CONFIG, ORIENTATION, 0, 0, 0);
+        mouse.start_fast_run(GOAL);
+        update(&mut mouse, 10);
+
+        mouse.explore();
+        assert_eq!(mouse.mode(), MouseMode::Explore);
+        assert!(mouse.fast_run.is_none());
+    }
+}
+
+#[cfg(test)]
+mod unreachable_goal_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{DistanceReading, Mouse, MouseConfig, MouseMode, UnreachableGoal};
+    use crate::config::sim;
+    use crate::fast::{Orientation, Vector, DIRECTION_PI_2};
+    use crate::slow::map::Map;
+    use crate::slow::maze::{Maze, Wall, WallIndex};
+    use crate::slow::{Goal, MazePosition};
+
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 90.0, y: 90.0 },
+        direction: DIRECTION_PI_2,
+    };
+
+    const GOAL: Goal = Goal {
+        position: MazePosition { x: 7, y: 7 },
+        facing: None,
+    };
+
+    /// A mouse that knows the goal is boxed in by walls, with everything else unknown
+    fn boxed_in(config: &MouseConfig) -> Mouse {
+        let mut maze = Maze::new(Wall::Unknown);
+        let goal = GOAL.position;
+        for &(x, y) in [(7, 8), (7, 6), (8, 7), (6, 7)].iter() {
+            let wall = WallIndex::between(goal, MazePosition { x, y }).unwrap();
+            maze.set_wall(wall, Wall::Closed);
+        }
+
+        let mut mouse = Mouse::new(config, ORIENTATION, 0, 0, 0);
+        mouse.map = Map::from_maze(maze);
+        mouse.start_fast_run(GOAL);
+        mouse
+    }
+
+    fn update(mouse: &mut Mouse, config: &MouseConfig) -> bool {
+        let (_, _, debug) = mouse.update(
+            config,
+            10,
+            0,
+            0,
+            0,
+            Some(DistanceReading::InRange(30.0)),
+            Some(DistanceReading::OutOfRange),
+            Some(DistanceReading::InRange(30.0)),
+            true,
+        );
+
+        debug.unreachable
+    }
+
+    #[test]
+    fn explore() {
+        let config = MouseConfig {
+            unreachable_goal: UnreachableGoal::Explore,
+            ..sim::MOUSE_2019
+        };
+
+        let mut mouse = boxed_in(&config);
+        assert!(update(&mut mouse, &config));
+        assert_eq!(mouse.mode(), MouseMode::Explore);
+    }
+
+    #[test]
+    fn hold() {
+        let config = MouseConfig {
+            unreachable_goal: UnreachableGoal::Hold,
+            ..sim::MOUSE_2019
+        };
+
+        let mut mouse = boxed_in(&config);
+        assert!(update(&mut mouse, &config));
+        assert_eq!(mouse.mode(), MouseMode::Hold);
+        assert_eq!(mouse.motion_queue.motions_remaining(), 0);
+    }
+
+    #[test]
+    fn reachable() {
+        let config = MouseConfig {
+            unreachable_goal: UnreachableGoal::Hold,
+            ..sim::MOUSE_2019
+        };
+
+        let mut mouse = Mouse::new(&config, ORIENTATION, 0, 0, 0);
+        mouse.start_fast_run(GOAL);
+        assert!(!update(&mut mouse, &config));
+        assert_eq!(mouse.mode(), MouseMode::FastRun(GOAL));
+    }
+
+    #[test]
+    fn cleared_by_next_fast_run() {
+        let config = sim::MOUSE_2019;
+
+        let mut mouse = boxed_in(&config);
+        assert!(update(&mut mouse, &config));
+
+        mouse.map = Map::new();
+        mouse.start_fast_run(GOAL);
+        assert!(!update(&mut mouse, &config));
+    }
+}
+
+#[cfg(test)]
+mod open_loop_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{DistanceReading, Mouse, MouseConfig, MouseMode};
+    use crate::config::sim;
+    use crate::fast::{Orientation, Vector, DIRECTION_PI_2};
+
+    const CONFIG: MouseConfig = sim::MOUSE_2019;
+
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 90.0, y: 90.0 },
+        direction: DIRECTION_PI_2,
+    };
+
+    fn update(
+        mouse: &mut Mouse,
+        time: u32,
+        encoder: i32,
+    ) -> (i32, i32, super::MouseDebug) {
+        mouse.update(
+            &CONFIG,
+            time,
+            0,
+            encoder,
+            encoder,
+            Some(DistanceReading::InRange(30.0)),
+            Some(DistanceReading::OutOfRange),
+            Some(DistanceReading::InRange(30.0)),
+            true,
+        )
+    }
+
+    #[test]
+    fn not_open_loop_by_default() {
+        let mut mouse = Mouse::new(&CONFIG, ORIENTATION, 0, 0, 0);
+        let (_, _, debug) = update(&mut mouse, 10, 0);
+        assert!(!debug.open_loop);
+    }
+
+    #[test]
+    fn powers_pass_through() {
+        let mut mouse = Mouse::new(&CONFIG, ORIENTATION, 0, 0, 0);
+        mouse.open_loop(1000, -500);
+
+        for time in (10..100).step_by(10) {
+            let (left, right, debug) = update(&mut mouse, time, 0);
+            assert_eq!((left, right), (1000, -500));
+            assert!(debug.open_loop);
+            assert_eq!(
+                debug.mode,
+                MouseMode::OpenLoop {
+                    left_power: 1000,
+                    right_power: -500
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn odometry_still_updates() {
+        let mut mouse = Mouse::new(&CONFIG, ORIENTATION, 0, 0, 0);
+        mouse.open_loop(1000, 1000);
+
+        let ticks = CONFIG.mechanical.mm_to_ticks(20.0) as i32;
+        let (_, _, debug) = update(&mut mouse, 10, ticks);
+
+        assert!((debug.orientation.position.y - 110.0).abs() < 0.5);
+        assert_close(debug.orientation.position.x, 90.0);
+    }
+
+    #[test]
+    fn explore_leaves_open_loop() {
+        let mut mouse = Mouse::new(&CONFIG, ORIENTATION, 0, 0, 0);
+        mouse.open_loop(1000, 1000);
+        mouse.explore();
+
+        let (_, _, debug) = update(&mut mouse, 10, 0);
+        assert!(!debug.open_loop);
+    }
+}
+
+#[cfg(test)]
+mod hold_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{DistanceReading, Mouse, MouseConfig, MouseDebug, MouseMode};
+    use crate::config::sim;
+    use crate::fast::motion_control::MotionControlConfig;
+    use crate::fast::wall_follow::{WallFollowConfig, WallFollowSide};
+    use crate::fast::{Orientation, Vector, DIRECTION_3_PI_2, DIRECTION_PI_2};
+
+    const CONFIG: MouseConfig = sim::MOUSE_2019;
+
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 90.0, y: 90.0 },
+        direction: DIRECTION_PI_2,
+    };
+
+    // In the start cell, with walls on both sides and open in front
+    fn update(mouse: &mut Mouse, time: u32) -> (i32, i32, MouseDebug) {
+        mouse.update(
+            &CONFIG,
+            time,
+            0,
+            0,
+            0,
+            Some(DistanceReading::InRange(30.0)),
+            Some(DistanceReading::OutOfRange),
+            Some(DistanceReading::InRange(30.0)),
+            true,
+        )
+    }
+
+    #[test]
+    fn not_holding_while_exploring() {
+        let mut mouse = Mouse::new(&CONFIG, ORIENTATION, 0, 0, 0);
+        let (_, _, debug) = update(&mut mouse, 10);
+        assert!(!debug.holding);
+        assert!(mouse.motion_queue.motions_remaining() > 0);
+    }
+
+    // In the corner facing out of the maze, with a wall on the left that leaves only the ways
+    // out of the maze open. The sensors have missed the edges of the maze.
+    fn update_facing_out(mouse: &mut Mouse, time: u32) -> (i32, i32, MouseDebug) {
+        mouse.update(
+            &CONFIG,
+            time,
+            0,
+            0,
+            0,
+            Some(DistanceReading::InRange(30.0)),
+            Some(DistanceReading::OutOfRange),
+            Some(DistanceReading::OutOfRange),
+            true,
+        )
+    }
+
+    const FACING_OUT: Orientation = Orientation {
+        position: Vector { x: 90.0, y: 90.0 },
+        direction: DIRECTION_3_PI_2,
+    };
+
+    #[test]
+    fn empty_plan_holds() {
+        let mut mouse = Mouse::new(&CONFIG, FACING_OUT, 0, 0, 0);
+
+        // Tries once, and finds nothing to plan
+        let (left, right, debug) = update_facing_out(&mut mouse, 10);
+        assert_eq!((left, right), (0, 0));
+        assert!(debug.slow.is_some());
+        assert!(debug.holding);
+        assert_eq!(mouse.mode(), MouseMode::Hold);
+
+        for time in (20..100).step_by(10) {
+            let (left, right, debug) = update_facing_out(&mut mouse, time);
+            assert_eq!((left, right), (0, 0));
+            assert!(debug.holding);
+            assert_eq!(debug.slow, None);
+            assert_eq!(mouse.motion_queue.motions_remaining(), 0);
+        }
+    }
+
+    const WALL_FOLLOW: MouseConfig = MouseConfig {
+        motion_control: MotionControlConfig {
+            wall_follow: Some(WallFollowConfig {
+                side: WallFollowSide::Left,
+                velocity: 0.2,
+                turn_velocity: 0.1,
+                p: 0.001,
+                side_distance: 50.0,
+                front_threshold: 60.0,
+            }),
+            ..CONFIG.motion_control
+        },
+        ..CONFIG
+    };
+
+    #[test]
+    fn follows_the_walls_while_holding() {
+        let mut mouse = Mouse::new(&WALL_FOLLOW, FACING_OUT, 0, 0, 0);
+        let update = |mouse: &mut Mouse, time| {
+            mouse.update(
+                &WALL_FOLLOW,
+                time,
+                0,
+                0,
+                0,
+                Some(DistanceReading::InRange(30.0)),
+                Some(DistanceReading::OutOfRange),
+                Some(DistanceReading::OutOfRange),
+                true,
+            )
+        };
+
+        let (_, _, debug) = update(&mut mouse, 10);
+        assert!(debug.holding);
+
+        let (left, right, debug) = update(&mut mouse, 20);
+        assert!(debug.motion_control.wall_following);
+        assert_ne!((left, right), (0, 0));
+    }
+
+    #[test]
+    fn not_following_the_walls_while_exploring() {
+        let mut mouse = Mouse::new(&WALL_FOLLOW, ORIENTATION, 0, 0, 0);
+
+        // The sensors haven't read anything yet, so nothing gets planned
+        let (_, _, debug) =
+            mouse.update(&WALL_FOLLOW, 10, 0, 0, 0, None, None, None, false);
+        assert!(!debug.holding);
+        assert!(!debug.motion_control.wall_following);
+    }
+
+    #[test]
+    fn latest_decision_in_debug() {
+        let mut mouse = Mouse::new(&CONFIG, ORIENTATION, 0, 0, 0);
+        let (_, _, debug) = update(&mut mouse, 10);
+
+        assert!(debug.decision.is_some());
+        assert_eq!(debug.decision, mouse.decisions().last().copied());
+    }
+
+    #[test]
+    fn explore_leaves_hold() {
+        let mut mouse = Mouse::new(&CONFIG, FACING_OUT, 0, 0, 0);
+        update_facing_out(&mut mouse, 10);
+        assert_eq!(mouse.mode(), MouseMode::Hold);
+
+        // Only the way into the maze is open this time
+        mouse.explore();
+        let (_, _, debug) = mouse.update(
+            &CONFIG,
+            20,
+            0,
+            0,
+            0,
+            Some(DistanceReading::OutOfRange),
+            Some(DistanceReading::InRange(30.0)),
+            Some(DistanceReading::InRange(30.0)),
+            true,
+        );
+        assert!(!debug.holding);
+        assert!(mouse.motion_queue.motions_remaining() > 0);
+    }
+}
+
+#[cfg(test)]
+mod replan_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{DistanceReading, Mouse, MouseConfig};
+    use crate::config::sim;
+    use crate::fast::motion_queue::Motion;
+    use crate::fast::{Orientation, Vector, DIRECTION_PI_2};
+
+    // Don't let the front sensor abort get in the way of seeing walls
+    const CONFIG: MouseConfig = MouseConfig {
+        front_sensor_abort: 0.0,
+        ..sim::MOUSE_2019
+    };
+
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 90.0, y: 90.0 },
+        direction: DIRECTION_PI_2,
+    };
+
+    /// What the front sensor reads from the start when there is a wall at `y`
+    fn front_reading(y: f32) -> DistanceReading {
+        DistanceReading::InRange(
+            y - ORIENTATION.position.y
+                - CONFIG.mechanical.front_sensor_offset_x
+                - CONFIG.maze.wall_width / 2.0,
+        )
+    }
+
+    fn update(
+        mouse: &mut Mouse,
+        config: &MouseConfig,
+        time: u32,
+        front: DistanceReading,
+    ) -> bool {
+        let (_, _, debug) = mouse.update(
+            config,
+            time,
+            0,
+            0,
+            0,
+            Some(DistanceReading::InRange(30.0)),
+            Some(front),
+            Some(DistanceReading::InRange(30.0)),
+            true,
+        );
+
+        debug.replanned
+    }
+
+    /// Start the mouse going north out of the first cell
+    fn going_north(config: &MouseConfig) -> Mouse {
+        let mut mouse = Mouse::new(config, ORIENTATION, 0, 0, 0);
+
+        for time in (10..50).step_by(10) {
+            assert!(!update(
+                &mut mouse,
+                config,
+                time,
+                DistanceReading::OutOfRange
+            ));
+        }
+
+        match mouse.motion_queue.next_motion() {
+            Some(Motion::Path(path_motion)) => assert!(path_motion.end().y > 180.0),
+            motion => panic!("Expected to be going north, got {:?}", motion),
+        }
+
+        mouse
+    }
+
+    #[test]
+    fn wall_on_route_replans() {
+        let mut mouse = going_north(&CONFIG);
+
+        // The wall was seen open from the start, so it has to be seen closed a few times
+        let mut time = 50;
+        for _ in 1..CONFIG.map.wall_confirm_count {
+            assert!(!update(&mut mouse, &CONFIG, time, front_reading(180.0)));
+            time += 10;
+        }
+
+        assert!(update(&mut mouse, &CONFIG, time, front_reading(180.0)));
+
+        // The new plan should not go through the wall
+        assert!(mouse
+            .motion_queue
+            .motions()
+            .iter()
+            .all(|motion| match motion {
+                Motion::Path(path_motion) => path_motion.end().y < 180.0,
+                Motion::Turn(_) | Motion::Stop(_) => true,
+            }));
+    }
+
+    #[test]
+    fn wall_past_route_keeps_plan() {
+        let mut mouse = going_north(&CONFIG);
+        let motions = mouse.motion_queue.motions().clone();
+
+        assert!(!update(&mut mouse, &CONFIG, 50, front_reading(360.0)));
+        assert_eq!(mouse.motion_queue.motions(), &motions);
+    }
+
+    #[test]
+    fn disabled() {
+        let config = MouseConfig {
+            replan_on_blocked: false,
+            ..CONFIG
+        };
+
+        let mut mouse = going_north(&config);
+        let motions = mouse.motion_queue.motions().clone();
+
+        assert!(!update(&mut mouse, &config, 50, front_reading(180.0)));
+        assert_eq!(mouse.motion_queue.motions(), &motions);
+    }
+}
+
+#[cfg(test)]
+mod maze_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{DistanceReading, Mouse};
+    use crate::config::sim;
+    use crate::fast::{Orientation, Vector, DIRECTION_PI_2};
+    use crate::slow::maze::{Wall, WallDirection, WallIndex};
+
+    #[test]
+    fn maze_has_sensed_walls() {
+        let mut mouse = Mouse::new(
+            &sim::MOUSE_2019,
+            Orientation {
+                position: Vector { x: 90.0, y: 90.0 },
+                direction: DIRECTION_PI_2,
+            },
+            0,
+            0,
+            0,
+        );
+
+        assert_eq!(
+            mouse.maze().get_wall(WallIndex {
+                x: 1,
+                y: 0,
+                direction: WallDirection::Vertical,
+            }),
+            Some(&Wall::Unknown)
+        );
+
+        // Wall on the right, open in front
+        mouse.update(
+            &sim::MOUSE_2019,
+            10,
+            0,
+            0,
+            0,
+            Some(DistanceReading::InRange(30.0)),
+            Some(DistanceReading::OutOfRange),
+            Some(DistanceReading::InRange(30.0)),
+            true,
+        );
+
+        let maze = mouse.maze().clone();
+
+        assert_eq!(
+            maze.get_wall(WallIndex {
+                x: 1,
+                y: 0,
+                direction: WallDirection::Vertical,
+            }),
+            Some(&Wall::Closed)
+        );
+
+        assert_eq!(
+            maze.get_wall(WallIndex {
+                x: 0,
+                y: 1,
+                direction: WallDirection::Horizontal,
+            }),
+            Some(&Wall::Open)
+        );
+    }
+}
+
+pub struct TestMouse {}
+
+impl TestMouse {
+    pub fn new() -> TestMouse {
+        TestMouse {}
+    }
+
+    pub fn update(
+        &mut self,
+        _config: &MouseConfig,
+        time: Millis,
+        _left_encoder: i32,
+        _right_encoder: i32,
+    ) -> (f32, f32) {
+        if time % 10000 <= 5000 {
+            (0.0, 0.0)
+        } else {
+            (1.0, 1.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod trail_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{DistanceReading, Mouse, MouseConfig};
+    use crate::config::sim;
+    use crate::fast::{Orientation, Vector, DIRECTION_PI_2};
+    use crate::units::Millis;
+
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 90.0, y: 90.0 },
+        direction: DIRECTION_PI_2,
+    };
+
+    const CONFIG: MouseConfig = MouseConfig {
+        trail_length: 3,
+        ..sim::MOUSE_2019
+    };
+
+    fn update(mouse: &mut Mouse, config: &MouseConfig, step: i32) {
+        mouse.update(
+            config,
+            step as Millis * 10,
+            0,
+            step * 20,
+            step * 20,
+            Some(DistanceReading::InRange(30.0)),
+            Some(DistanceReading::OutOfRange),
+            Some(DistanceReading::InRange(30.0)),
+            true,
+        );
+    }
+
+    #[test]
+    fn grows_to_the_length() {
+        let mut mouse = Mouse::new(&CONFIG, ORIENTATION, 0, 0, 0);
+        assert!(mouse.trail().is_empty());
+
+        for step in 1..=3 {
+            update(&mut mouse, &CONFIG, step);
+            assert_eq!(mouse.trail().len(), step as usize);
+        }
+    }
+
+    #[test]
+    fn drops_the_oldest() {
+        let mut mouse = Mouse::new(&CONFIG, ORIENTATION, 0, 0, 0);
+        for step in 1..=3 {
+            update(&mut mouse, &CONFIG, step);
+        }
+
+        let before = mouse.trail.clone();
+        update(&mut mouse, &CONFIG, 4);
+        let after = mouse.trail();
+
+        assert_eq!(after.len(), 3);
+        assert_eq!(after[..2], before[1..]);
+        assert_ne!(after[2], before[2]);
+    }
+
+    #[test]
+    fn disabled() {
+        let config = MouseConfig {
+            trail_length: 0,
+            ..CONFIG
+        };
+
+        let mut mouse = Mouse::new(&config, ORIENTATION, 0, 0, 0);
+        for step in 1..=3 {
+            update(&mut mouse, &config, step);
+        }
+
+        assert!(mouse.trail().is_empty());
+    }
+}
+
+#[cfg(test)]
+mod max_explore_steps_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{DistanceReading, Mouse, MouseConfig, MouseMode, UnreachableGoal};
+    use crate::config::sim;
+    use crate::fast::{Orientation, Vector, DIRECTION_PI_2};
+    use crate::slow::map::Map;
+    use crate::slow::maze::{Maze, Wall, WallIndex};
+    use crate::slow::{Goal, MazePosition};
+
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 90.0, y: 90.0 },
+        direction: DIRECTION_PI_2,
+    };
+
+    const CONFIG: MouseConfig = MouseConfig {
+        max_explore_steps: 2,
+        ..sim::MOUSE_2019
+    };
+
+    fn update(mouse: &mut Mouse, config: &MouseConfig) {
+        mouse.update(
+            config,
+            10,
+            0,
+            0,
+            0,
+            Some(DistanceReading::InRange(30.0)),
+            Some(DistanceReading::OutOfRange),
+            Some(DistanceReading::InRange(30.0)),
+            true,
+        );
+    }
+
+    /// Explore one cell, and pretend the mouse got through it
+    fn step(mouse: &mut Mouse, config: &MouseConfig) {
+        update(mouse, config);
+        mouse.motion_queue.clear();
+    }
+
+    #[test]
+    fn counts_steps() {
+        let mut mouse = Mouse::new(&CONFIG, ORIENTATION, 0, 0, 0);
+        step(&mut mouse, &CONFIG);
+        assert_eq!(mouse.explore_steps, 1);
+        assert_eq!(mouse.mode(), MouseMode::Explore);
+    }
+
+    #[test]
+    fn fast_run_at_the_budget() {
+        let mut mouse = Mouse::new(&CONFIG, ORIENTATION, 0, 0, 0);
+        step(&mut mouse, &CONFIG);
+        step(&mut mouse, &CONFIG);
+        assert_eq!(mouse.mode(), MouseMode::Explore);
+
+        update(&mut mouse, &CONFIG);
+        assert_eq!(
+            mouse.mode(),
+            MouseMode::FastRun(Goal {
+                position: MazePosition { x: 7, y: 7 },
+                facing: None,
+            })
+        );
+        assert_eq!(mouse.explore_steps, 2);
+
+        // Planned through the unknown walls of the partial map
+        assert!(mouse.fast_run.is_some());
+        assert!(mouse.motion_queue.motions_remaining() > 0);
+        assert!(!mouse.unreachable);
+    }
+
+    #[test]
+    fn waits_for_the_last_step() {
+        let mut mouse = Mouse::new(&CONFIG, ORIENTATION, 0, 0, 0);
+        step(&mut mouse, &CONFIG);
+        update(&mut mouse, &CONFIG);
+        assert_eq!(mouse.explore_steps, 2);
+
+        // Still moving through the last cell
+        update(&mut mouse, &CONFIG);
+        assert_eq!(mouse.mode(), MouseMode::Explore);
+    }
+
+    #[test]
+    fn only_once() {
+        let config = MouseConfig {
+            max_explore_steps: 1,
+            unreachable_goal: UnreachableGoal::Explore,
+            ..CONFIG
+        };
+
+        // The goal is walled off, so the fast run goes right back to exploring
+        let goal = config.route_goal;
+        let mut maze = Maze::new(Wall::Unknown);
+        for &(x, y) in [(7, 8), (7, 6), (8, 7), (6, 7)].iter() {
+            let wall = WallIndex::between(goal, MazePosition { x, y }).unwrap();
+            maze.set_wall(wall, Wall::Closed);
+        }
+
+        let mut mouse = Mouse::new(&config, ORIENTATION, 0, 0, 0);
+        mouse.map = Map::from_maze(maze);
+
+        step(&mut mouse, &config);
+        update(&mut mouse, &config);
+        assert!(mouse.unreachable);
+        assert_eq!(mouse.mode(), MouseMode::Explore);
+
+        for _ in 0..3 {
+            step(&mut mouse, &config);
+            assert_eq!(mouse.mode(), MouseMode::Explore);
+        }
+        assert_eq!(mouse.explore_steps, 4);
+    }
+
+    #[test]
+    fn no_budget() {
+        let config = MouseConfig {
+            max_explore_steps: 0,
+            ..CONFIG
+        };
+
+        let mut mouse = Mouse::new(&config, ORIENTATION, 0, 0, 0);
+        for _ in 0..5 {
+            step(&mut mouse, &config);
+        }
+
+        assert_eq!(mouse.mode(), MouseMode::Explore);
+        assert_eq!(mouse.explore_steps, 5);
+    }
+}
+
+#[cfg(test)]
+mod at_start_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{DistanceReading, Mouse, MouseConfig};
+    use crate::config::{sim, MAZE};
+    use crate::fast::{Orientation, Vector, DIRECTION_PI_2};
+    use crate::slow::maze::{MazeConfig, Wall, WallDirection, WallIndex};
+    use crate::slow::{MazeDirection, MazeOrientation, MazePosition};
+
+    const CONFIG: MouseConfig = MouseConfig {
+        maze: MazeConfig {
+            start_cell: MazePosition { x: 3, y: 5 },
+            start_facing: MazeDirection::East,
+            ..MAZE
+        },
+        ..sim::MOUSE_2019
+    };
+
+    fn wall(mouse: &Mouse, x: usize, y: usize, direction: WallDirection) -> Wall {
+        *mouse
+            .maze()
+            .get_wall(WallIndex { x, y, direction })
+            .unwrap()
+    }
+
+    #[test]
+    fn default_start() {
+        assert_close_orientation(
+            MAZE.start_orientation(),
+            Orientation {
+                position: Vector { x: 90.0, y: 90.0 },
+                direction: DIRECTION_PI_2,
+            },
+        );
+    }
+
+    #[test]
+    fn starts_in_the_start_cell() {
+        let mut mouse = Mouse::at_start(&CONFIG, 0, 0, 0);
+        let (_, _, debug) = mouse.update(
+            &CONFIG,
+            10,
+            0,
+            0,
+            0,
+            Some(DistanceReading::InRange(30.0)),
+            Some(DistanceReading::OutOfRange),
+            Some(DistanceReading::InRange(30.0)),
+            true,
+        );
+
+        assert_eq!(
+            debug.orientation.to_maze_orientation(&CONFIG.maze),
+            MazeOrientation {
+                position: MazePosition { x: 3, y: 5 },
+                direction: MazeDirection::East,
+            }
+        );
+    }
+
+    #[test]
+    fn knows_the_start_walls() {
+        let mouse = Mouse::at_start(&CONFIG, 0, 0, 0);
+
+        assert_eq!(wall(&mouse, 3, 6, WallDirection::Horizontal), Wall::Closed);
+        assert_eq!(wall(&mouse, 3, 5, WallDirection::Horizontal), Wall::Closed);
+        assert_eq!(wall(&mouse, 3, 5, WallDirection::Vertical), Wall::Closed);
+        assert_eq!(wall(&mouse, 4, 5, WallDirection::Vertical), Wall::Unknown);
+    }
+}
+
+#[cfg(test)]
+mod fast_run_pending_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{DistanceReading, Mouse, MouseConfig};
+    use crate::config::sim;
+    use crate::fast::{Orientation, Vector, DIRECTION_PI_2};
+    use crate::slow::{Goal, MazePosition};
+
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 90.0, y: 90.0 },
+        direction: DIRECTION_PI_2,
+    };
+
+    // Far enough away that the whole run doesn't fit in the motion queue at once
+    const GOAL: Goal = Goal {
+        position: MazePosition { x: 7, y: 7 },
+        facing: None,
+    };
+
+    #[test]
+    fn not_while_exploring() {
+        let mouse = Mouse::new(&sim::MOUSE_2019, ORIENTATION, 0, 0, 0);
+        assert!(!mouse.fast_run_pending());
+    }
+
+    fn update(mouse: &mut Mouse, config: &MouseConfig) {
+        mouse.update(
+            config,
+            10,
+            0,
+            0,
+            0,
+            Some(DistanceReading::InRange(30.0)),
+            Some(DistanceReading::OutOfRange),
+            Some(DistanceReading::InRange(30.0)),
+            true,
+        );
+    }
+
+    #[test]
+    fn not_while_planning() {
+        let mut mouse = Mouse::new(&sim::MOUSE_2019, ORIENTATION, 0, 0, 0);
+        mouse.start_fast_run(GOAL);
+        assert!(!mouse.fast_run_pending());
+
+        // Planning a little at a time, still nothing queued
+        let config = MouseConfig {
+            slow_plan_budget: 1,
+            ..sim::MOUSE_2019
+        };
+        update(&mut mouse, &config);
+        assert!(mouse.fast_run.is_none());
+        assert!(!mouse.fast_run_pending());
+    }
+
+    #[test]
+    fn once_queued() {
+        let mut mouse = Mouse::new(&sim::MOUSE_2019, ORIENTATION, 0, 0, 0);
+        mouse.start_fast_run(GOAL);
+        update(&mut mouse, &sim::MOUSE_2019);
+
+        assert!(mouse.motion_queue.motions_remaining() > 0);
+        assert!(mouse.fast_run_pending());
+    }
+}
+
+#[cfg(test)]
+mod recovery_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{DistanceReading, Mouse, MouseConfig};
+    use crate::config::{sim, LOCALIZE};
+    use crate::fast::localize::LocalizeConfig;
+    use crate::fast::{Orientation, Vector, DIRECTION_PI_2};
+
+    // Only the localization should stop the mouse
+    const CONFIG: MouseConfig = MouseConfig {
+        localize: LocalizeConfig {
+            divergence_threshold: 20.0,
+            divergence_count: 3,
+            ..LOCALIZE
+        },
+        front_sensor_abort: 0.0,
+        replan_on_blocked: false,
+        ..sim::MOUSE_2019
+    };
+
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 90.0, y: 90.0 },
+        direction: DIRECTION_PI_2,
+    };
+
+    // Returns the motor powers, and whether the localization diverged and is recovering
+    fn update(
+        mouse: &mut Mouse,
+        time: u32,
+        encoder: i32,
+        front: DistanceReading,
+    ) -> ((i32, i32), bool, bool) {
+        let (left, right, debug) = mouse.update(
+            &CONFIG,
+            time,
+            0,
+            encoder,
+            encoder,
+            Some(DistanceReading::InRange(30.0)),
+            Some(front),
+            Some(DistanceReading::InRange(30.0)),
+            true,
+        );
+
+        (
+            (left, right),
+            debug.localize.diverged,
+            debug.localize.recovering,
+        )
+    }
+
+    #[test]
+    fn stops_until_recovered() {
+        let mut mouse = Mouse::new(&CONFIG, ORIENTATION, 0, 0, 0);
+
+        // Start going forward down the corridor
+        let mut time = 0;
+        let moving = (0..10).any(|_| {
+            time += 10;
+            let (powers, _, _) = update(&mut mouse, time, 0, DistanceReading::OutOfRange);
+            powers != (0, 0)
+        });
+        assert!(moving);
+
+        // Then the front wall jumps back and forth until the localization gives up
+        let mut diverged = false;
+        while !diverged {
+            time += 10;
+            assert!(time < 200);
+
+            let front = if time % 20 == 0 { 40.0 } else { 80.0 };
+            let (powers, now_diverged, _) =
+                update(&mut mouse, time, 0, DistanceReading::InRange(front));
+            diverged = now_diverged;
+
+            if diverged {
+                assert_eq!(powers, (0, 0));
+            }
+        }
+
+        // Stays stopped while the wheels are still rolling
+        for encoder in 1..4 {
+            time += 10;
+            let (powers, diverged, recovering) =
+                update(&mut mouse, time, encoder, DistanceReading::OutOfRange);
+            assert!(!diverged);
+            assert!(recovering);
+            assert_eq!(powers, (0, 0));
+        }
+
+        // Once it has come to rest, it goes again
+        let moving = (0..10).any(|_| {
+            time += 10;
+            let (powers, _, recovering) =
+                update(&mut mouse, time, 3, DistanceReading::OutOfRange);
+            assert!(!recovering);
+            powers != (0, 0)
+        });
+        assert!(moving);
+    }
+}
+
+#[cfg(test)]
+mod stale_distance_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{DistanceReading, Mouse, MouseConfig, MouseDebug, LEFT_SENSOR};
+    use crate::config::sim;
+    use crate::fast::{Orientation, Vector, DIRECTION_PI_2};
+
+    const CONFIG: MouseConfig = sim::MOUSE_2019;
+
+    // In the start cell, with walls on both sides and open in front
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 90.0, y: 90.0 },
+        direction: DIRECTION_PI_2,
+    };
+
+    const LEFT: Option<DistanceReading> = Some(DistanceReading::InRange(30.0));
+    const FRONT: Option<DistanceReading> = Some(DistanceReading::OutOfRange);
+    const RIGHT: Option<DistanceReading> = Some(DistanceReading::InRange(30.0));
+
+    fn update(
+        mouse: &mut Mouse,
+        time: u32,
+        left: Option<DistanceReading>,
+        front: Option<DistanceReading>,
+        right: Option<DistanceReading>,
+        fresh: bool,
+    ) -> MouseDebug {
+        let (_, _, debug) =
+            mouse.update(&CONFIG, time, 0, 0, 0, left, front, right, fresh);
+        debug
+    }
+
+    #[test]
+    fn sensors_taking_turns_still_map() {
+        let mut mouse = Mouse::new(&CONFIG, ORIENTATION, 0, 0, 0);
+
+        update(&mut mouse, 10, LEFT, None, None, true);
+        assert_eq!(mouse.motion_queue.motions_remaining(), 0);
+        update(&mut mouse, 20, None, FRONT, None, true);
+        assert_eq!(mouse.motion_queue.motions_remaining(), 0);
+
+        let debug = update(&mut mouse, 30, None, None, RIGHT, true);
+        assert!(debug.slow.is_some());
+        assert!(mouse.motion_queue.motions_remaining() > 0);
+    }
+
+    #[test]
+    fn stale_cycles_keep_the_last_readings() {
+        let mut mouse = Mouse::new(&CONFIG, ORIENTATION, 0, 0, 0);
+        let fresh = update(&mut mouse, 10, LEFT, FRONT, RIGHT, true);
+
+        let stale = update(&mut mouse, 20, None, None, None, false);
+        assert_eq!(stale.hardware.distances, fresh.hardware.distances);
+        assert_eq!(stale.hardware.distances[LEFT_SENSOR], LEFT);
+    }
+}
+
+#[cfg(test)]
+mod intersection_stop_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{DistanceReading, Mouse, MouseConfig};
+    use crate::config::sim;
+    use crate::fast::motion_queue::{Motion, StopMotion};
+    use crate::fast::{Orientation, Vector, DIRECTION_PI_2};
+    use crate::slow::motion_plan::MotionPlanConfig;
+    use crate::slow::MazeDirection;
+
+    const CONFIG: MouseConfig = MouseConfig {
+        motion_plan: MotionPlanConfig {
+            stop_at_intersections: true,
+            intersection_stop_updates: 3,
+            ..sim::MOUSE_2019.motion_plan
+        },
+        front_sensor_abort: 0.0,
+        ..sim::MOUSE_2019
+    };
+
+    // Open to the left and in front, with a wall on the right
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 270.0, y: 90.0 },
+        direction: DIRECTION_PI_2,
+    };
+
+    const OPEN: Option<DistanceReading> = Some(DistanceReading::OutOfRange);
+    const WALL: Option<DistanceReading> = Some(DistanceReading::InRange(30.0));
+
+    /// Get to the intersection, then see `front` while stopped there. Returns the move picked
+    /// once the stop is done.
+    fn pick(front: Option<DistanceReading>) -> MazeDirection {
+        let mut mouse = Mouse::new(&CONFIG, ORIENTATION, 0, 0, 0);
+
+        let (_, _, debug) = mouse.update(&CONFIG, 10, 0, 0, 0, OPEN, OPEN, WALL, true);
+        assert!(debug.slow.is_none());
+        assert_eq!(
+            &mouse.motion_queue.motions()[..],
+            &[Motion::Stop(StopMotion { updates: 3 })]
+        );
+
+        for time in (20..100).step_by(10) {
+            let (_, _, debug) =
+                mouse.update(&CONFIG, time, 0, 0, 0, OPEN, front, WALL, true);
+
+            if let Some(slow) = debug.slow {
+                return slow.next_direction;
+            }
+        }
+
+        panic!("No move picked after the stop");
+    }
+
+    #[test]
+    fn goes_on_when_nothing_changes() {
+        assert_eq!(pick(OPEN), MazeDirection::North);
+    }
+
+    #[test]
+    fn wall_seen_while_stopped_changes_the_move() {
+        assert_eq!(pick(WALL), MazeDirection::West);
     }
 }
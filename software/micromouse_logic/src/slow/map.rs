@@ -1,21 +1,43 @@
 use serde::Deserialize;
 use serde::Serialize;
 
-use crate::config::MechanicalConfig;
-use crate::mouse::DistanceReading;
-use crate::slow::maze::{Maze, MazeConfig, Wall, WallIndex};
-use crate::slow::MazeOrientation;
+use heapless::Vec;
+use typenum::U256;
+
+use crate::config::{
+    DistanceSensorConfig, MechanicalConfig, SensorFacing, FRONT_SENSOR, LEFT_SENSOR,
+    MAX_DISTANCE_SENSORS, RIGHT_SENSOR,
+};
+use crate::fast::Orientation;
+use crate::mouse::{
+    three_distance_readings, ContainsDistanceReading, DistanceReading, DistanceReadings,
+};
+use crate::slow::maze::{
+    open_neighbor, Maze, MazeConfig, Wall, WallDirection, WallIndex, HEIGHT, WIDTH,
+};
+use crate::slow::navigate::{
+    CellPath, FloodFillNavigate, FullPathPlan, NavigateConfig, DIRECTIONS,
+};
+use crate::slow::{MazeDirection, MazeOrientation, MazePosition};
 
 #[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct MapConfig {
     pub front_threhold: f32,
     pub left_threshold: f32,
     pub right_threshold: f32,
+
+    /// How many times in a row a wall has to be seen the opposite way from what it is in the
+    /// map before the map is changed. Walls that are not known yet are always set right away.
+    pub wall_confirm_count: u8,
 }
 
 #[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct MapDebug {
     pub maze: Maze,
+
+    /// How many cells of diagonal are ahead of the mouse, for when the path could be driven
+    /// diagonally. See [Maze::diagonal_run_available].
+    pub diagonal_run: Option<usize>,
 }
 
 #[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
@@ -25,39 +47,153 @@ pub struct MoveOptions {
     pub right: bool,
 }
 
+/// A count for every wall in the maze, laid out the same way as [Maze]
+struct WallCounts {
+    horizontal: [[u8; HEIGHT - 1]; WIDTH],
+    vertical: [[u8; HEIGHT]; WIDTH - 1],
+}
+
+impl WallCounts {
+    fn new() -> WallCounts {
+        WallCounts {
+            horizontal: [[0; HEIGHT - 1]; WIDTH],
+            vertical: [[0; HEIGHT]; WIDTH - 1],
+        }
+    }
+
+    fn get_mut(&mut self, index: WallIndex) -> Option<&mut u8> {
+        match index.direction {
+            WallDirection::Horizontal => self
+                .horizontal
+                .get_mut(index.x)
+                .and_then(|counts| counts.get_mut(index.y.checked_sub(1)?)),
+            WallDirection::Vertical => self
+                .vertical
+                .get_mut(index.x.checked_sub(1)?)
+                .and_then(|counts| counts.get_mut(index.y)),
+        }
+    }
+}
+
 /// Figures out what the maze is. For now, it will just tell you what of the three walls around are
 /// open. Eventually, it will keep track of the entire maze.
 pub struct Map {
     maze: Maze,
+    visited: [[u16; HEIGHT]; WIDTH],
+
+    /// How many times in a row each wall has been seen the opposite way from the map
+    contradictions: WallCounts,
+
+    last_position: Option<MazePosition>,
     left_distance: Option<DistanceReading>,
     right_distance: Option<DistanceReading>,
     front_distance: Option<DistanceReading>,
+
+    /// Kept here so its costs table doesn't have to be built on the stack for every plan
+    full_path: FullPathPlan,
+
+    /// How many times a wall has changed, see [Map::changes]
+    changes: u32,
+}
+
+/// What the left, front, or right sensor would read for the wall that `sensor` sees `facing`,
+/// so the same thresholds work for sensors mounted somewhere else or at an angle
+fn main_sensor_reading(
+    mech: &MechanicalConfig,
+    sensor: &DistanceSensorConfig,
+    facing: SensorFacing,
+    distance: DistanceReading,
+) -> DistanceReading {
+    let hit = match distance {
+        DistanceReading::InRange(distance) => sensor.project(distance),
+        DistanceReading::OutOfRange => return DistanceReading::OutOfRange,
+    };
+
+    match facing {
+        SensorFacing::Front => {
+            DistanceReading::InRange(hit.x - mech.front_sensor_offset_x)
+        }
+        SensorFacing::Left => DistanceReading::InRange(hit.y - mech.left_sensor_offset_y),
+        SensorFacing::Right => {
+            DistanceReading::InRange(-hit.y - mech.right_sensor_offset_y)
+        }
+        SensorFacing::Back => distance,
+    }
 }
 
 impl Map {
     pub fn new() -> Map {
+        Map::from_maze(Maze::new(Wall::Unknown))
+    }
+
+    /// Start out already knowing the walls in `maze`, like from a maze file, instead of nothing
+    pub fn from_maze(maze: Maze) -> Map {
         Map {
-            maze: Maze::new(Wall::Unknown),
+            maze,
+            visited: [[0; HEIGHT]; WIDTH],
+            contradictions: WallCounts::new(),
+            last_position: None,
             left_distance: None,
             right_distance: None,
             front_distance: None,
+            full_path: FullPathPlan::new(
+                MazePosition::default(),
+                MazePosition::default(),
+            ),
+            changes: 0,
         }
     }
 
     pub fn update(
         &mut self,
-        _mech: &MechanicalConfig,
-        _maze: &MazeConfig,
+        mech: &MechanicalConfig,
+        maze: &MazeConfig,
         config: &MapConfig,
         maze_orientation: MazeOrientation,
         left_distance: Option<DistanceReading>,
         front_distance: Option<DistanceReading>,
         right_distance: Option<DistanceReading>,
     ) -> (Option<MoveOptions>, MapDebug) {
+        self.update_sensors(
+            mech,
+            maze,
+            config,
+            maze_orientation,
+            three_distance_readings(left_distance, front_distance, right_distance),
+        )
+    }
+
+    /// Like [Map::update], but with a reading from every distance sensor in
+    /// [MechanicalConfig::distance_sensors]. The move options only come from the left, front,
+    /// and right sensors, but the extra sensors also see the walls of the cell the mouse is in,
+    /// going by which way they face.
+    pub fn update_sensors(
+        &mut self,
+        mech: &MechanicalConfig,
+        _maze: &MazeConfig,
+        config: &MapConfig,
+        maze_orientation: MazeOrientation,
+        distances: DistanceReadings,
+    ) -> (Option<MoveOptions>, MapDebug) {
+        let left_distance = distances[LEFT_SENSOR];
+        let front_distance = distances[FRONT_SENSOR];
+        let right_distance = distances[RIGHT_SENSOR];
+
         let debug = MapDebug {
             maze: self.maze.clone(),
+            diagonal_run: self.maze.diagonal_run_available(maze_orientation),
         };
 
+        let position = maze_orientation.position;
+        if self.last_position != Some(position)
+            && position.x < WIDTH
+            && position.y < HEIGHT
+        {
+            let count = &mut self.visited[position.x][position.y];
+            *count = count.saturating_add(1);
+            self.last_position = Some(position);
+        }
+
         if left_distance != None {
             self.left_distance = left_distance
         }
@@ -84,35 +220,58 @@ impl Map {
                     right: right_distance >= config.right_threshold,
                 };
 
-                let front_index = WallIndex::from_maze_orientation(maze_orientation);
-                let front_wall = if options.front {
-                    Wall::Open
-                } else {
-                    Wall::Closed
-                };
-                self.maze.set_wall(front_index, front_wall);
-
-                let left_index = WallIndex::from_maze_orientation(MazeOrientation {
-                    direction: maze_orientation.direction.left(),
-                    ..maze_orientation
-                });
-                let left_wall = if options.left {
-                    Wall::Open
-                } else {
-                    Wall::Closed
-                };
-                self.maze.set_wall(left_index, left_wall);
-
-                let right_index = WallIndex::from_maze_orientation(MazeOrientation {
-                    direction: maze_orientation.direction.right(),
-                    ..maze_orientation
-                });
-                let right_wall = if options.right {
-                    Wall::Open
-                } else {
-                    Wall::Closed
-                };
-                self.maze.set_wall(right_index, right_wall);
+                let mut edges = [None; MAX_DISTANCE_SENSORS];
+                edges[LEFT_SENSOR] = self.reading_to_edge(
+                    config,
+                    maze_orientation,
+                    SensorFacing::Left,
+                    left_distance,
+                );
+                edges[FRONT_SENSOR] = self.reading_to_edge(
+                    config,
+                    maze_orientation,
+                    SensorFacing::Front,
+                    front_distance,
+                );
+                edges[RIGHT_SENSOR] = self.reading_to_edge(
+                    config,
+                    maze_orientation,
+                    SensorFacing::Right,
+                    right_distance,
+                );
+
+                let sensors = mech.distance_sensors();
+                for i in RIGHT_SENSOR + 1..MAX_DISTANCE_SENSORS {
+                    if let (Some(sensor), Some(distance)) = (sensors[i], distances[i]) {
+                        let facing = sensor.facing();
+                        edges[i] = self.reading_to_edge(
+                            config,
+                            maze_orientation,
+                            facing,
+                            main_sensor_reading(mech, &sensor, facing, distance),
+                        );
+                    }
+                }
+
+                // A wall is closed if any of the sensors looking at it sees it, so a wall only
+                // an extra sensor sees is still found
+                for (i, edge) in edges.iter().enumerate() {
+                    let index = match edge {
+                        Some((index, _)) => *index,
+                        None => continue,
+                    };
+
+                    if edges[..i]
+                        .iter()
+                        .any(|e| e.map(|(other, _)| other) == Some(index))
+                    {
+                        continue;
+                    }
+
+                    let closed = edges.contains(&Some((index, true)));
+                    let wall = if closed { Wall::Closed } else { Wall::Open };
+                    self.observe_wall(config, index, wall);
+                }
 
                 Some(options)
             } else {
@@ -121,4 +280,1084 @@ impl Map {
 
         (move_options, debug)
     }
+
+    /// Set walls as known without sensing them, like a few walls to set up a scenario for
+    /// testing the navigator. Each wall is given as the cell and which side of it the wall is
+    /// on. Walls on the outside of the maze are always closed, so they are skipped.
+    pub fn apply_known_walls(&mut self, walls: &[(MazePosition, MazeDirection, Wall)]) {
+        for &(position, direction, wall) in walls {
+            let index = WallIndex::from_maze_orientation(MazeOrientation {
+                position,
+                direction,
+            });
+
+            self.set_wall(index, wall);
+            if let Some(count) = self.contradictions.get_mut(index) {
+                *count = 0;
+            }
+        }
+    }
+
+    /// Which wall of the cell the mouse is in a reading from `sensor` is looking at, and
+    /// whether the reading says it is closed. None for walls on the outside of the maze, which
+    /// are always closed, and for sensors that [MapConfig] has no threshold for.
+    pub fn reading_to_edge(
+        &self,
+        config: &MapConfig,
+        maze_orientation: MazeOrientation,
+        sensor: SensorFacing,
+        distance: DistanceReading,
+    ) -> Option<(WallIndex, bool)> {
+        let (direction, threshold) = match sensor {
+            SensorFacing::Front => (maze_orientation.direction, config.front_threhold),
+            SensorFacing::Left => {
+                (maze_orientation.direction.left(), config.left_threshold)
+            }
+            SensorFacing::Right => {
+                (maze_orientation.direction.right(), config.right_threshold)
+            }
+            SensorFacing::Back => return None,
+        };
+
+        let index = WallIndex::from_maze_orientation(MazeOrientation {
+            direction,
+            ..maze_orientation
+        });
+
+        self.maze.get_wall(index)?;
+
+        Some((index, distance < threshold))
+    }
+
+    /// Set a wall that was just seen. If the map already has it the other way, it is only
+    /// changed once it has been seen this way `wall_confirm_count` times in a row, so one bad
+    /// reading doesn't undo a wall that has been seen many times.
+    fn observe_wall(&mut self, config: &MapConfig, index: WallIndex, wall: Wall) {
+        let known = match self.maze.get_wall(index) {
+            Some(&known) => known,
+            None => return,
+        };
+
+        let count = match self.contradictions.get_mut(index) {
+            Some(count) => count,
+            None => return,
+        };
+
+        if known == Wall::Unknown || known == wall {
+            *count = 0;
+            self.set_wall(index, wall);
+        } else {
+            *count = count.saturating_add(1);
+            if *count >= config.wall_confirm_count {
+                *count = 0;
+                self.set_wall(index, wall);
+            }
+        }
+    }
+
+    /// Set a wall in the maze, counting it in [Map::changes] if it is different than before
+    fn set_wall(&mut self, index: WallIndex, wall: Wall) {
+        let before = self.maze.get_wall(index).copied();
+        self.maze.set_wall(index, wall);
+
+        if self.maze.get_wall(index).copied() != before {
+            self.changes = self.changes.wrapping_add(1);
+        }
+    }
+
+    /// Look for a closed wall in front of the mouse while it is moving. The wall is found from
+    /// where the reading lands, so it may be the wall of this cell or one further ahead. Returns
+    /// the wall if it just became closed. Like any other reading, a wall known to be open is
+    /// only closed once it has been seen `wall_confirm_count` times in a row.
+    pub fn sense_front(
+        &mut self,
+        mech: &MechanicalConfig,
+        maze: &MazeConfig,
+        config: &MapConfig,
+        orientation: Orientation,
+        front_distance: Option<DistanceReading>,
+    ) -> Option<WallIndex> {
+        let distance = front_distance.value()?;
+        let maze_orientation = orientation.to_maze_orientation(maze);
+
+        // Where the reading hits, moved to the middle of the wall
+        let hit = orientation.position
+            + (mech.front_sensor_offset_x + distance + maze.wall_width / 2.0)
+                * maze_orientation
+                    .direction
+                    .into_direction()
+                    .into_unit_vector();
+
+        let along = match maze_orientation.direction {
+            MazeDirection::North | MazeDirection::South => hit.y,
+            MazeDirection::East | MazeDirection::West => hit.x,
+        };
+        let cell_width = maze.cell_width(maze_orientation.direction);
+
+        // Only trust readings that land close to where a wall could be
+        let boundary = (along / cell_width + 0.5) as usize;
+        if (along - boundary as f32 * cell_width).abs() > cell_width / 4.0 {
+            return None;
+        }
+
+        let index = match maze_orientation.direction {
+            MazeDirection::North | MazeDirection::South => WallIndex {
+                x: maze_orientation.position.x,
+                y: boundary,
+                direction: WallDirection::Horizontal,
+            },
+            MazeDirection::East | MazeDirection::West => WallIndex {
+                x: boundary,
+                y: maze_orientation.position.y,
+                direction: WallDirection::Vertical,
+            },
+        };
+
+        match self.maze.get_wall(index) {
+            Some(Wall::Closed) | None => None,
+            Some(_) => {
+                self.observe_wall(config, index, Wall::Closed);
+                match self.maze.get_wall(index) {
+                    Some(Wall::Closed) => Some(index),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// Everything that has been learned about the maze so far
+    pub fn maze(&self) -> &Maze {
+        &self.maze
+    }
+
+    /// How many times a wall in the maze has changed. Anything worked out from the maze only
+    /// needs to be worked out again when this is different.
+    pub fn changes(&self) -> u32 {
+        self.changes
+    }
+
+    /// How many times the mouse has entered the cell at (x, y)
+    pub fn visit_count(&self, x: usize, y: usize) -> u16 {
+        self.visited[x][y]
+    }
+
+    /// The fraction of the walls inside the maze that are known to be open or closed
+    pub fn coverage(&self) -> f32 {
+        let (known, total) = self.maze.walls().fold((0, 0), |(known, total), &wall| {
+            if wall == Wall::Unknown {
+                (known, total + 1)
+            } else {
+                (known + 1, total + 1)
+            }
+        });
+
+        known as f32 / total as f32
+    }
+
+    /// The number of unknown walls that could still be reached from `from`. Unknown walls are
+    /// treated as open, since they might be.
+    pub fn reachable_unknown_count(&self, from: MazePosition) -> usize {
+        let mut visited = [[false; HEIGHT]; WIDTH];
+        let mut stack: Vec<MazePosition, U256> = Vec::new();
+
+        visited[from.x][from.y] = true;
+        stack.push(from).ok();
+
+        let mut count = 0;
+
+        while let Some(position) = stack.pop() {
+            // Only look north and east so each wall is counted once. The cell on the other side
+            // of an unknown wall is always reachable, so the south and west walls will be counted
+            // from there.
+            let (north, _, east, _) = self.maze.get_cell(position.x, position.y);
+            if north == Wall::Unknown {
+                count += 1;
+            }
+            if east == Wall::Unknown {
+                count += 1;
+            }
+
+            for &direction in DIRECTIONS.iter() {
+                if let Some(next) = open_neighbor(&self.maze, position, direction) {
+                    if !visited[next.x][next.y] {
+                        visited[next.x][next.y] = true;
+                        stack.push(next).ok();
+                    }
+                }
+            }
+        }
+
+        count
+    }
+
+    /// Start planning the fastest path from `from` to `goal` with [FullPathPlan], throwing
+    /// out any plan in progress
+    pub fn start_full_path(&mut self, from: MazePosition, goal: MazePosition) {
+        self.full_path.restart(from, goal);
+    }
+
+    /// Keep planning the path from [Map::start_full_path], going over the whole maze at most
+    /// `max_sweeps` times. Returns whether it is done.
+    pub fn relax_full_path(
+        &mut self,
+        config: &NavigateConfig,
+        max_sweeps: usize,
+    ) -> bool {
+        self.full_path.relax(config, &self.maze, max_sweeps)
+    }
+
+    /// The path from [Map::start_full_path], once [Map::relax_full_path] is done. None if it
+    /// isn't done yet, or the goal can not be reached.
+    pub fn full_path(&self, config: &NavigateConfig) -> Option<CellPath> {
+        self.full_path.path(config, &self.maze)
+    }
+
+    /// Plan the fastest path from `from` to `goal` all at once. This has its own
+    /// [FullPathPlan], so a plan in progress from [Map::start_full_path] is left alone.
+    pub fn plan_full_path(
+        &self,
+        config: &NavigateConfig,
+        from: MazePosition,
+        goal: MazePosition,
+    ) -> Option<CellPath> {
+        let mut plan = FullPathPlan::new(from, goal);
+        while !plan.relax(config, &self.maze, usize::max_value()) {}
+        plan.path(config, &self.maze)
+    }
+
+    /// How many unknown walls are on the fastest path from `from` to `goal`, planned the same
+    /// way as a fast run. When this is 0, the path is known to be open and exploring more can
+    /// only find a faster one. Infinite if the goal can't be reached. Like [Map::plan_full_path],
+    /// this leaves a plan in progress alone.
+    pub fn goal_path_uncertainty(
+        &self,
+        config: &NavigateConfig,
+        from: MazePosition,
+        goal: MazePosition,
+    ) -> f32 {
+        let path = match self.plan_full_path(config, from, goal) {
+            Some(path) => path,
+            None => return f32::INFINITY,
+        };
+
+        path.windows(2)
+            .filter_map(|cells| WallIndex::between(cells[0], cells[1]))
+            .filter(|&index| self.maze.get_wall(index) == Some(&Wall::Unknown))
+            .count() as f32
+    }
+
+    /// How many cells it is from `from` to `goal`, only going through walls that are known to
+    /// be open. This goes down as shorter routes are found while exploring. None if there isn't
+    /// a known route yet.
+    pub fn known_route_cost(
+        &self,
+        from: MazePosition,
+        goal: MazePosition,
+    ) -> Option<u16> {
+        let mut known = self.maze;
+        for (index, wall) in self.maze.edges() {
+            if wall == Wall::Unknown {
+                known.set_wall(index, Wall::Closed);
+            }
+        }
+
+        FloodFillNavigate { goal }.distances(&known)[from.x][from.y]
+    }
+}
+
+#[cfg(test)]
+mod known_route_cost_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::Map;
+    use crate::slow::maze::{Wall, WallIndex};
+    use crate::slow::MazePosition;
+
+    const FROM: MazePosition = MazePosition { x: 0, y: 0 };
+    const GOAL: MazePosition = MazePosition { x: 1, y: 0 };
+
+    fn open(map: &mut Map, ax: usize, ay: usize, bx: usize, by: usize) {
+        let index = WallIndex::between(
+            MazePosition { x: ax, y: ay },
+            MazePosition { x: bx, y: by },
+        )
+        .unwrap();
+        map.maze.set_wall(index, Wall::Open);
+    }
+
+    #[test]
+    fn nothing_known() {
+        assert_eq!(Map::new().known_route_cost(FROM, GOAL), None);
+        assert_eq!(Map::new().known_route_cost(FROM, FROM), Some(0));
+    }
+
+    #[test]
+    fn shortcut_lowers_cost() {
+        // Up, over, and back down to the cell right next to the start
+        let mut map = Map::new();
+        open(&mut map, 0, 0, 0, 1);
+        open(&mut map, 0, 1, 0, 2);
+        open(&mut map, 0, 2, 1, 2);
+        open(&mut map, 1, 2, 1, 1);
+        open(&mut map, 1, 1, 1, 0);
+        assert_eq!(map.known_route_cost(FROM, GOAL), Some(5));
+
+        open(&mut map, 0, 0, 1, 0);
+        assert_eq!(map.known_route_cost(FROM, GOAL), Some(1));
+    }
+}
+
+#[cfg(test)]
+mod goal_path_uncertainty_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::Map;
+    use crate::config::NAVIGATE;
+    use crate::slow::maze::{Maze, Wall, WallIndex};
+    use crate::slow::MazePosition;
+
+    const FROM: MazePosition = MazePosition { x: 0, y: 0 };
+    const GOAL: MazePosition = MazePosition { x: 0, y: 3 };
+
+    fn map(maze: Maze) -> Map {
+        let mut map = Map::new();
+        map.maze = maze;
+        map
+    }
+
+    fn between(ax: usize, ay: usize, bx: usize, by: usize) -> WallIndex {
+        WallIndex::between(MazePosition { x: ax, y: ay }, MazePosition { x: bx, y: by })
+            .unwrap()
+    }
+
+    #[test]
+    fn fully_known() {
+        let map = map(Maze::new(Wall::Open));
+        assert_close(map.goal_path_uncertainty(&NAVIGATE, FROM, GOAL), 0.0);
+    }
+
+    #[test]
+    fn unknown_on_path() {
+        // The straight run up to the goal goes through the unknown wall
+        let mut maze = Maze::new(Wall::Open);
+        maze.set_wall(between(0, 1, 0, 2), Wall::Unknown);
+
+        let map = map(maze);
+        assert_close(map.goal_path_uncertainty(&NAVIGATE, FROM, GOAL), 1.0);
+    }
+
+    #[test]
+    fn unknown_off_path() {
+        let mut maze = Maze::new(Wall::Open);
+        maze.set_wall(between(5, 5, 5, 6), Wall::Unknown);
+
+        let map = map(maze);
+        assert_close(map.goal_path_uncertainty(&NAVIGATE, FROM, GOAL), 0.0);
+    }
+
+    #[test]
+    fn all_unknown() {
+        let map = map(Maze::new(Wall::Unknown));
+        assert_close(map.goal_path_uncertainty(&NAVIGATE, FROM, GOAL), 3.0);
+    }
+
+    #[test]
+    fn unreachable() {
+        let mut maze = Maze::new(Wall::Open);
+        maze.set_wall(between(0, 0, 0, 1), Wall::Closed);
+        maze.set_wall(between(0, 0, 1, 0), Wall::Closed);
+
+        let map = map(maze);
+        assert_eq!(
+            map.goal_path_uncertainty(&NAVIGATE, FROM, GOAL),
+            f32::INFINITY
+        );
+    }
+
+    #[test]
+    fn leaves_a_plan_in_progress_alone() {
+        let mut map = map(Maze::new(Wall::Open));
+        let goal = MazePosition { x: 7, y: 7 };
+
+        map.start_full_path(FROM, goal);
+        assert!(!map.relax_full_path(&NAVIGATE, 1));
+
+        map.goal_path_uncertainty(&NAVIGATE, FROM, GOAL);
+
+        while !map.relax_full_path(&NAVIGATE, 1) {}
+        assert_eq!(
+            map.full_path(&NAVIGATE),
+            map.plan_full_path(&NAVIGATE, FROM, goal)
+        );
+    }
+}
+
+#[cfg(test)]
+mod coverage_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::Map;
+    use crate::config::{mouse_2019, MAP, MAZE};
+    use crate::mouse::DistanceReading;
+    use crate::slow::maze::{Maze, Wall, WallDirection, WallIndex, HEIGHT, WIDTH};
+    use crate::slow::{MazeDirection, MazeOrientation, MazePosition};
+
+    const TOTAL_WALLS: usize = WIDTH * (HEIGHT - 1) + (WIDTH - 1) * HEIGHT;
+
+    // Sees the left and right walls closed and the front wall open
+    fn learn(map: &mut Map, x: usize, y: usize) {
+        map.update(
+            &mouse_2019::MECH,
+            &MAZE,
+            &MAP,
+            MazeOrientation {
+                position: MazePosition { x, y },
+                direction: MazeDirection::North,
+            },
+            Some(DistanceReading::InRange(20.0)),
+            Some(DistanceReading::OutOfRange),
+            Some(DistanceReading::InRange(20.0)),
+        );
+    }
+
+    #[test]
+    fn nothing_known() {
+        let map = Map::new();
+        assert_close(map.coverage(), 0.0);
+        assert_eq!(
+            map.reachable_unknown_count(MazePosition { x: 0, y: 0 }),
+            TOTAL_WALLS
+        );
+    }
+
+    #[test]
+    fn everything_known() {
+        let mut map = Map::new();
+        map.maze = Maze::new(Wall::Open);
+        assert_close(map.coverage(), 1.0);
+        assert_eq!(map.reachable_unknown_count(MazePosition { x: 0, y: 0 }), 0);
+    }
+
+    #[test]
+    fn coverage_increases_as_walls_are_learned() {
+        let mut map = Map::new();
+        let mut last_coverage = map.coverage();
+        let mut last_unknown = map.reachable_unknown_count(MazePosition { x: 1, y: 0 });
+
+        for y in 0..4 {
+            learn(&mut map, 1, y);
+
+            let coverage = map.coverage();
+            let unknown = map.reachable_unknown_count(MazePosition { x: 1, y: 0 });
+
+            assert!(coverage > last_coverage);
+            assert!(unknown < last_unknown);
+
+            last_coverage = coverage;
+            last_unknown = unknown;
+        }
+
+        assert_close(last_coverage, 12.0 / TOTAL_WALLS as f32);
+    }
+
+    #[test]
+    fn walled_off_walls_are_not_reachable() {
+        let mut map = Map::new();
+
+        // Close off the cell at (0, 0)
+        for &index in [
+            WallIndex {
+                x: 0,
+                y: 1,
+                direction: WallDirection::Horizontal,
+            },
+            WallIndex {
+                x: 1,
+                y: 0,
+                direction: WallDirection::Vertical,
+            },
+        ]
+        .iter()
+        {
+            map.maze.set_wall(index, Wall::Closed);
+        }
+
+        assert_eq!(map.reachable_unknown_count(MazePosition { x: 0, y: 0 }), 0);
+        assert_eq!(
+            map.reachable_unknown_count(MazePosition { x: 5, y: 5 }),
+            TOTAL_WALLS - 2
+        );
+    }
+}
+
+#[cfg(test)]
+mod visit_count_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::Map;
+    use crate::config::{mouse_2019, MAP, MAZE};
+    use crate::slow::{MazeDirection, MazeOrientation, MazePosition};
+
+    fn update(map: &mut Map, x: usize, y: usize) {
+        map.update(
+            &mouse_2019::MECH,
+            &MAZE,
+            &MAP,
+            MazeOrientation {
+                position: MazePosition { x, y },
+                direction: MazeDirection::North,
+            },
+            None,
+            None,
+            None,
+        );
+    }
+
+    #[test]
+    fn nothing_visited() {
+        let map = Map::new();
+        assert_eq!(map.visit_count(0, 0), 0);
+    }
+
+    #[test]
+    fn count_entering_cells() {
+        let mut map = Map::new();
+
+        // Staying in a cell for a few updates only counts once
+        update(&mut map, 0, 0);
+        update(&mut map, 0, 0);
+        update(&mut map, 0, 1);
+        update(&mut map, 0, 1);
+        update(&mut map, 0, 2);
+
+        // Come back the same way
+        update(&mut map, 0, 1);
+        update(&mut map, 0, 0);
+
+        assert_eq!(map.visit_count(0, 0), 2);
+        assert_eq!(map.visit_count(0, 1), 2);
+        assert_eq!(map.visit_count(0, 2), 1);
+        assert_eq!(map.visit_count(0, 3), 0);
+    }
+}
+
+#[cfg(test)]
+mod changes_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::Map;
+    use crate::config::{mouse_2019, MAP, MAZE};
+    use crate::mouse::DistanceReading;
+    use crate::slow::maze::Wall;
+    use crate::slow::{MazeDirection, MazeOrientation, MazePosition};
+
+    // In the middle of the maze, with walls on both sides and open in front
+    fn update(map: &mut Map) {
+        map.update(
+            &mouse_2019::MECH,
+            &MAZE,
+            &MAP,
+            MazeOrientation {
+                position: MazePosition { x: 4, y: 4 },
+                direction: MazeDirection::North,
+            },
+            Some(DistanceReading::InRange(30.0)),
+            Some(DistanceReading::OutOfRange),
+            Some(DistanceReading::InRange(30.0)),
+        );
+    }
+
+    #[test]
+    fn new_walls_change() {
+        let mut map = Map::new();
+        assert_eq!(map.changes(), 0);
+
+        update(&mut map);
+        assert_eq!(map.changes(), 3);
+    }
+
+    #[test]
+    fn same_walls_again_do_not() {
+        let mut map = Map::new();
+        update(&mut map);
+        update(&mut map);
+        assert_eq!(map.changes(), 3);
+
+        map.apply_known_walls(&[(
+            MazePosition { x: 4, y: 4 },
+            MazeDirection::West,
+            Wall::Closed,
+        )]);
+        assert_eq!(map.changes(), 3);
+    }
+}
+
+#[cfg(test)]
+mod diagonal_run_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::Map;
+    use crate::config::{mouse_2019, MAP, MAZE};
+    use crate::slow::maze::{Maze, Wall};
+    use crate::slow::{MazeDirection, MazeOrientation, MazePosition};
+
+    const ORIENTATION: MazeOrientation = MazeOrientation {
+        position: MazePosition { x: 0, y: 0 },
+        direction: MazeDirection::North,
+    };
+
+    fn diagonal_run(map: &mut Map) -> Option<usize> {
+        let (_, debug) = map.update(
+            &mouse_2019::MECH,
+            &MAZE,
+            &MAP,
+            ORIENTATION,
+            None,
+            None,
+            None,
+        );
+        debug.diagonal_run
+    }
+
+    #[test]
+    fn open_maze() {
+        let mut map = Map::new();
+        let run = diagonal_run(&mut map);
+
+        assert!(run.is_some());
+        assert_eq!(run, map.maze().diagonal_run_available(ORIENTATION));
+    }
+
+    #[test]
+    fn closed_maze() {
+        let mut map = Map::from_maze(Maze::new(Wall::Closed));
+        assert_eq!(diagonal_run(&mut map), None);
+    }
+}
+
+#[cfg(test)]
+mod wall_confirm_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{Map, MapConfig};
+    use crate::config::{mouse_2019, MAP, MAZE};
+    use crate::mouse::DistanceReading;
+    use crate::slow::maze::{Wall, WallDirection, WallIndex};
+    use crate::slow::{MazeDirection, MazeOrientation, MazePosition};
+
+    const CONFIG: MapConfig = MapConfig {
+        wall_confirm_count: 3,
+        ..MAP
+    };
+
+    // The wall on the left of (1, 1) facing north
+    const LEFT_WALL: WallIndex = WallIndex {
+        x: 1,
+        y: 1,
+        direction: WallDirection::Vertical,
+    };
+
+    fn see(map: &mut Map, config: &MapConfig, left_closed: bool) {
+        let left = if left_closed {
+            DistanceReading::InRange(20.0)
+        } else {
+            DistanceReading::OutOfRange
+        };
+
+        map.update(
+            &mouse_2019::MECH,
+            &MAZE,
+            config,
+            MazeOrientation {
+                position: MazePosition { x: 1, y: 1 },
+                direction: MazeDirection::North,
+            },
+            Some(left),
+            Some(DistanceReading::OutOfRange),
+            Some(DistanceReading::InRange(20.0)),
+        );
+    }
+
+    fn left_wall(map: &Map) -> Wall {
+        *map.maze().get_wall(LEFT_WALL).unwrap()
+    }
+
+    #[test]
+    fn unknown_set_right_away() {
+        let mut map = Map::new();
+        see(&mut map, &CONFIG, false);
+        assert_eq!(left_wall(&map), Wall::Open);
+    }
+
+    #[test]
+    fn one_bad_reading_does_not_flip() {
+        let mut map = Map::new();
+        for _ in 0..5 {
+            see(&mut map, &CONFIG, true);
+        }
+
+        see(&mut map, &CONFIG, false);
+        assert_eq!(left_wall(&map), Wall::Closed);
+    }
+
+    #[test]
+    fn interrupted_contradictions_do_not_flip() {
+        let mut map = Map::new();
+        see(&mut map, &CONFIG, true);
+
+        see(&mut map, &CONFIG, false);
+        see(&mut map, &CONFIG, false);
+        see(&mut map, &CONFIG, true);
+        see(&mut map, &CONFIG, false);
+        see(&mut map, &CONFIG, false);
+        assert_eq!(left_wall(&map), Wall::Closed);
+    }
+
+    #[test]
+    fn flips_after_confirm_count() {
+        let mut map = Map::new();
+        see(&mut map, &CONFIG, true);
+
+        for _ in 0..3 {
+            see(&mut map, &CONFIG, false);
+        }
+        assert_eq!(left_wall(&map), Wall::Open);
+    }
+
+    #[test]
+    fn count_of_one_flips_right_away() {
+        let config = MapConfig {
+            wall_confirm_count: 1,
+            ..MAP
+        };
+
+        let mut map = Map::new();
+        see(&mut map, &config, true);
+        see(&mut map, &config, false);
+        assert_eq!(left_wall(&map), Wall::Open);
+    }
+}
+
+#[cfg(test)]
+mod extra_sensor_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use core::f32::consts::FRAC_PI_4;
+
+    use super::Map;
+    use crate::config::{
+        mouse_2019, DistanceSensorConfig, MechanicalConfig, MAP, MAZE, RIGHT_SENSOR,
+    };
+    use crate::fast::{Direction, Orientation, Vector};
+    use crate::mouse::{three_distance_readings, DistanceReading};
+    use crate::slow::maze::{Wall, WallDirection, WallIndex};
+    use crate::slow::{MazeDirection, MazeOrientation, MazePosition};
+
+    // Up front on the left corner, looking out at 45 degrees
+    fn mech() -> MechanicalConfig {
+        MechanicalConfig {
+            extra_sensors: [
+                Some(DistanceSensorConfig {
+                    mount: Orientation {
+                        position: Vector { x: 40.0, y: 20.0 },
+                        direction: Direction::from(FRAC_PI_4),
+                    },
+                    limit: 200.0,
+                }),
+                None,
+            ],
+            ..mouse_2019::MECH
+        }
+    }
+
+    // The wall on the left of (1, 1) facing north
+    const LEFT_WALL: WallIndex = WallIndex {
+        x: 1,
+        y: 1,
+        direction: WallDirection::Vertical,
+    };
+
+    // The left sensor misses the wall, and the extra one reads `extra`
+    fn left_wall(extra: Option<DistanceReading>) -> Wall {
+        let mut distances = three_distance_readings(
+            Some(DistanceReading::OutOfRange),
+            Some(DistanceReading::OutOfRange),
+            Some(DistanceReading::InRange(20.0)),
+        );
+        distances[RIGHT_SENSOR + 1] = extra;
+
+        let mut map = Map::new();
+        map.update_sensors(
+            &mech(),
+            &MAZE,
+            &MAP,
+            MazeOrientation {
+                position: MazePosition { x: 1, y: 1 },
+                direction: MazeDirection::North,
+            },
+            distances,
+        );
+
+        *map.maze().get_wall(LEFT_WALL).unwrap()
+    }
+
+    #[test]
+    fn only_extra_sensor_sees_wall() {
+        assert_eq!(
+            left_wall(Some(DistanceReading::InRange(50.0))),
+            Wall::Closed
+        );
+    }
+
+    #[test]
+    fn extra_sensor_far_is_open() {
+        assert_eq!(left_wall(Some(DistanceReading::InRange(190.0))), Wall::Open);
+    }
+
+    #[test]
+    fn no_extra_reading() {
+        assert_eq!(left_wall(None), Wall::Open);
+    }
+}
+
+#[cfg(test)]
+mod sense_front_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{Map, MapConfig};
+    use crate::config::{mouse_2019, MAP, MAZE};
+    use crate::fast::{Orientation, Vector, DIRECTION_PI_2};
+    use crate::mouse::DistanceReading;
+    use crate::slow::maze::{Wall, WallIndex};
+    use crate::slow::MazePosition;
+
+    const CONFIG: MapConfig = MapConfig {
+        wall_confirm_count: 3,
+        ..MAP
+    };
+
+    // In the middle of (0, 0) facing north
+    const ORIENTATION: Orientation = Orientation {
+        position: Vector { x: 90.0, y: 90.0 },
+        direction: DIRECTION_PI_2,
+    };
+
+    fn front_wall() -> WallIndex {
+        WallIndex::between(MazePosition { x: 0, y: 0 }, MazePosition { x: 0, y: 1 })
+            .unwrap()
+    }
+
+    // See the wall at the top of the cell
+    fn sense(map: &mut Map) -> Option<WallIndex> {
+        let distance = MAZE.cell_width_y
+            - ORIENTATION.position.y
+            - mouse_2019::MECH.front_sensor_offset_x
+            - MAZE.wall_width / 2.0;
+
+        map.sense_front(
+            &mouse_2019::MECH,
+            &MAZE,
+            &CONFIG,
+            ORIENTATION,
+            Some(DistanceReading::InRange(distance)),
+        )
+    }
+
+    #[test]
+    fn unknown_closes_right_away() {
+        let mut map = Map::new();
+        assert_eq!(sense(&mut map), Some(front_wall()));
+        assert_eq!(map.maze().get_wall(front_wall()), Some(&Wall::Closed));
+    }
+
+    #[test]
+    fn already_closed() {
+        let mut map = Map::new();
+        sense(&mut map);
+        assert_eq!(sense(&mut map), None);
+    }
+
+    #[test]
+    fn open_closes_after_confirm_count() {
+        let mut map = Map::new();
+        map.maze.set_wall(front_wall(), Wall::Open);
+
+        assert_eq!(sense(&mut map), None);
+        assert_eq!(sense(&mut map), None);
+        assert_eq!(map.maze().get_wall(front_wall()), Some(&Wall::Open));
+
+        assert_eq!(sense(&mut map), Some(front_wall()));
+        assert_eq!(map.maze().get_wall(front_wall()), Some(&Wall::Closed));
+    }
+}
+
+#[cfg(test)]
+mod reading_to_edge_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::Map;
+    use crate::config::{SensorFacing, MAP};
+    use crate::mouse::DistanceReading;
+    use crate::slow::maze::{WallDirection, WallIndex};
+    use crate::slow::{MazeDirection, MazeOrientation, MazePosition};
+
+    const ORIENTATION: MazeOrientation = MazeOrientation {
+        position: MazePosition { x: 3, y: 4 },
+        direction: MazeDirection::East,
+    };
+
+    fn edge(
+        sensor: SensorFacing,
+        distance: DistanceReading,
+    ) -> Option<(WallIndex, bool)> {
+        Map::new().reading_to_edge(&MAP, ORIENTATION, sensor, distance)
+    }
+
+    #[test]
+    fn short_side_reading_is_closed() {
+        assert_eq!(
+            edge(SensorFacing::Left, DistanceReading::InRange(20.0)),
+            Some((
+                WallIndex {
+                    x: 3,
+                    y: 5,
+                    direction: WallDirection::Horizontal,
+                },
+                true
+            ))
+        );
+    }
+
+    #[test]
+    fn long_side_reading_is_open() {
+        assert_eq!(
+            edge(SensorFacing::Right, DistanceReading::InRange(500.0)),
+            Some((
+                WallIndex {
+                    x: 3,
+                    y: 4,
+                    direction: WallDirection::Horizontal,
+                },
+                false
+            ))
+        );
+    }
+
+    #[test]
+    fn out_of_range_is_open() {
+        assert_eq!(
+            edge(SensorFacing::Front, DistanceReading::OutOfRange),
+            Some((
+                WallIndex {
+                    x: 4,
+                    y: 4,
+                    direction: WallDirection::Vertical,
+                },
+                false
+            ))
+        );
+    }
+
+    #[test]
+    fn outside_wall() {
+        let orientation = MazeOrientation {
+            position: MazePosition { x: 0, y: 0 },
+            direction: MazeDirection::North,
+        };
+
+        assert_eq!(
+            Map::new().reading_to_edge(
+                &MAP,
+                orientation,
+                SensorFacing::Left,
+                DistanceReading::InRange(20.0),
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn no_back_threshold() {
+        assert_eq!(
+            edge(SensorFacing::Back, DistanceReading::InRange(20.0)),
+            None
+        );
+    }
+}
+
+#[cfg(test)]
+mod apply_known_walls_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::Map;
+    use crate::slow::maze::{open_neighbor, Wall, WallDirection, WallIndex};
+    use crate::slow::{MazeDirection, MazePosition};
+
+    const CELL: MazePosition = MazePosition { x: 4, y: 4 };
+
+    fn overlay() -> Map {
+        let mut map = Map::new();
+        map.apply_known_walls(&[
+            (CELL, MazeDirection::North, Wall::Closed),
+            (CELL, MazeDirection::East, Wall::Closed),
+            (CELL, MazeDirection::West, Wall::Open),
+            (
+                MazePosition { x: 0, y: 0 },
+                MazeDirection::South,
+                Wall::Open,
+            ),
+        ]);
+        map
+    }
+
+    #[test]
+    fn sets_the_walls() {
+        let map = overlay();
+        let wall = |x, y, direction| {
+            *map.maze().get_wall(WallIndex { x, y, direction }).unwrap()
+        };
+
+        assert_eq!(wall(4, 5, WallDirection::Horizontal), Wall::Closed);
+        assert_eq!(wall(5, 4, WallDirection::Vertical), Wall::Closed);
+        assert_eq!(wall(4, 4, WallDirection::Vertical), Wall::Open);
+        assert_eq!(wall(4, 4, WallDirection::Horizontal), Wall::Unknown);
+    }
+
+    #[test]
+    fn open_moves() {
+        let map = overlay();
+        let open = |direction| open_neighbor(map.maze(), CELL, direction);
+
+        assert_eq!(open(MazeDirection::North), None);
+        assert_eq!(open(MazeDirection::East), None);
+        assert_eq!(open(MazeDirection::West), Some(MazePosition { x: 3, y: 4 }));
+        assert_eq!(
+            open(MazeDirection::South),
+            Some(MazePosition { x: 4, y: 3 })
+        );
+    }
+
+    #[test]
+    fn outside_walls_stay_closed() {
+        let map = overlay();
+        assert_eq!(
+            open_neighbor(
+                map.maze(),
+                MazePosition { x: 0, y: 0 },
+                MazeDirection::South
+            ),
+            None
+        );
+    }
 }
@@ -5,21 +5,78 @@ use libm::F32Ext;
 
 use itertools::Itertools;
 
-use crate::fast::{Orientation, Vector};
-use crate::slow::{MazeDirection, MazeOrientation};
+use crate::config::MechanicalConfig;
+use crate::fast::{Orientation, Vector, DIRECTION_0, DIRECTION_3_PI_2, DIRECTION_PI_2};
+use crate::mouse::DistanceReading;
+use crate::slow::{MazeDirection, MazeOrientation, MazePosition};
 
 pub const WIDTH: usize = 16;
 pub const HEIGHT: usize = 16;
 
+/// The bits in [Maze::to_adjacency] for each way out of a cell that is open
+pub const OPEN_NORTH: u8 = 0x01;
+pub const OPEN_EAST: u8 = 0x02;
+pub const OPEN_SOUTH: u8 = 0x04;
+pub const OPEN_WEST: u8 = 0x08;
+
 #[derive(Debug, Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
 pub struct MazeConfig {
-    pub cell_width: f32,
+    /// The distance between the centers of the posts going east and west, in mm
+    pub cell_width_x: f32,
+
+    /// The distance between the centers of the posts going north and south, in mm. This is the
+    /// same as `cell_width_x` for a standard maze.
+    pub cell_width_y: f32,
+
     pub wall_width: f32,
+
+    /// The cell the mouse starts in, and which way it faces there. The start cell has walls on
+    /// every side but the one the mouse faces.
+    pub start_cell: MazePosition,
+    pub start_facing: MazeDirection,
 }
 
 impl MazeConfig {
-    pub fn center_to_wall(&self) -> f32 {
-        self.cell_width / 2.0 - self.wall_width / 2.0
+    /// Where the mouse starts, in the middle of [MazeConfig::start_cell] facing
+    /// [MazeConfig::start_facing]
+    pub fn start_orientation(&self) -> Orientation {
+        Orientation {
+            position: self.start_cell.center_position(self),
+            direction: self.start_facing.into_direction(),
+        }
+    }
+
+    /// The size of a cell in x and y, from post center to post center
+    pub fn cell_size(&self) -> Vector {
+        Vector {
+            x: self.cell_width_x,
+            y: self.cell_width_y,
+        }
+    }
+
+    /// How far it is across a cell going `direction`
+    pub fn cell_width(&self, direction: MazeDirection) -> f32 {
+        match direction {
+            MazeDirection::North | MazeDirection::South => self.cell_width_y,
+            MazeDirection::East | MazeDirection::West => self.cell_width_x,
+        }
+    }
+
+    /// How far it is from the center of a cell to the surface of the wall in `direction`
+    pub fn center_to_wall(&self, direction: MazeDirection) -> f32 {
+        self.cell_width(direction) / 2.0 - self.wall_width / 2.0
+    }
+
+    /// How far a 90 degree corner around the center of a cell starts and ends from the center,
+    /// so it comes in going `start` and goes out going `end` along the center lines of the
+    /// cells on either side. See
+    /// [crate::fast::path::PathMotion::corner_between].
+    pub fn standard_corner_radius(
+        &self,
+        start: MazeDirection,
+        end: MazeDirection,
+    ) -> (f32, f32) {
+        (self.cell_width(start) / 2.0, self.cell_width(end) / 2.0)
     }
 
     /// Projects the `from` orientation onto the nearest wall or post, and gives the index of it
@@ -27,8 +84,8 @@ impl MazeConfig {
         &self,
         from: Orientation,
     ) -> impl Iterator<Item = MazeProjectionResult> + '_ {
-        let mouse_cell_x = (from.position.x / self.cell_width) as usize;
-        let mouse_cell_y = (from.position.y / self.cell_width) as usize;
+        let mouse_cell_x = (from.position.x / self.cell_width_x) as usize;
+        let mouse_cell_y = (from.position.y / self.cell_width_y) as usize;
 
         let direction_v = from.direction.into_unit_vector();
 
@@ -40,21 +97,21 @@ impl MazeConfig {
 
         let vertical_walls = vertical_wall_range.map(move |wall_index_x| {
             let wall_x = if wall_index_x <= mouse_cell_x {
-                wall_index_x as f32 * self.cell_width + self.wall_width / 2.0
+                wall_index_x as f32 * self.cell_width_x + self.wall_width / 2.0
             } else {
-                wall_index_x as f32 * self.cell_width - self.wall_width / 2.0
+                wall_index_x as f32 * self.cell_width_x - self.wall_width / 2.0
             };
 
             let t = (wall_x - from.position.x) / direction_v.x;
 
             let wall_y = t * direction_v.y + from.position.y;
-            let wall_index_y = (wall_y / self.cell_width) as usize;
+            let wall_index_y = (wall_y / self.cell_width_y) as usize;
 
             // Figure out if we are looking at a wall or a post
-            let local_y = wall_y % self.cell_width;
+            let local_y = wall_y % self.cell_width_y;
             let maze_index = if local_y < self.wall_width / 2.0 {
                 MazeIndex::Post(wall_index_x, wall_index_y)
-            } else if self.cell_width - local_y < self.wall_width / 2.0 {
+            } else if self.cell_width_y - local_y < self.wall_width / 2.0 {
                 MazeIndex::Post(wall_index_x, wall_index_y + 1)
             } else {
                 MazeIndex::Wall(WallIndex {
@@ -83,20 +140,20 @@ impl MazeConfig {
 
         let horizontal_walls = horizontal_wall_range.map(move |wall_index_y| {
             let wall_y = if wall_index_y <= mouse_cell_y {
-                wall_index_y as f32 * self.cell_width + self.wall_width / 2.0
+                wall_index_y as f32 * self.cell_width_y + self.wall_width / 2.0
             } else {
-                wall_index_y as f32 * self.cell_width - self.wall_width / 2.0
+                wall_index_y as f32 * self.cell_width_y - self.wall_width / 2.0
             };
 
             let t = (wall_y - from.position.y) / direction_v.y;
             let wall_x = t * direction_v.x + from.position.x;
-            let wall_index_x = (wall_x / self.cell_width) as usize;
+            let wall_index_x = (wall_x / self.cell_width_x) as usize;
 
             // Figure out if we are looking at a wall or a post
-            let local_x = wall_x % self.cell_width;
+            let local_x = wall_x % self.cell_width_x;
             let maze_index = if local_x < self.wall_width / 2.0 {
                 MazeIndex::Post(wall_index_x, wall_index_y)
-            } else if self.cell_width - local_x < self.wall_width / 2.0 {
+            } else if self.cell_width_x - local_x < self.wall_width / 2.0 {
                 MazeIndex::Post(wall_index_x + 1, wall_index_y)
             } else {
                 MazeIndex::Wall(WallIndex {
@@ -122,6 +179,92 @@ impl MazeConfig {
     }
 }
 
+#[cfg(test)]
+mod standard_corner_radius_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::MazeConfig;
+    use crate::config::MAZE;
+    use crate::fast::path::PathMotion;
+    use crate::fast::{Vector, DIRECTION_0, DIRECTION_PI_2};
+    use crate::slow::{MazeDirection, MazePosition};
+
+    /// Cells that are taller than they are wide
+    const TALL: MazeConfig = MazeConfig {
+        cell_width_x: 180.0,
+        cell_width_y: 200.0,
+        ..MAZE
+    };
+
+    #[test]
+    fn half_a_cell() {
+        let (start, end) =
+            MAZE.standard_corner_radius(MazeDirection::North, MazeDirection::East);
+        assert_close(start, 90.0);
+        assert_close(end, 90.0);
+    }
+
+    #[test]
+    fn corner_ends_on_center_lines() {
+        // Come into the cell at (1, 1) from the south and leave to the east
+        let center = MazePosition { x: 1, y: 1 }.center_position(&MAZE);
+        let (start_radius, end_radius) =
+            MAZE.standard_corner_radius(MazeDirection::North, MazeDirection::East);
+        let corner = PathMotion::corner_between(
+            center,
+            DIRECTION_PI_2,
+            DIRECTION_0,
+            start_radius,
+            end_radius,
+            0.0,
+        );
+
+        // On the center line of the cell to the south, at the edge between them
+        assert_close2(corner.start(), Vector { x: 270.0, y: 180.0 });
+
+        // On the center line of the cell to the east, at the edge between them
+        assert_close2(corner.end(), Vector { x: 360.0, y: 270.0 });
+    }
+
+    #[test]
+    fn non_square_uses_each_axis() {
+        let (start, end) =
+            TALL.standard_corner_radius(MazeDirection::North, MazeDirection::East);
+        assert_close(start, 100.0);
+        assert_close(end, 90.0);
+
+        let (start, end) =
+            TALL.standard_corner_radius(MazeDirection::West, MazeDirection::South);
+        assert_close(start, 90.0);
+        assert_close(end, 100.0);
+    }
+
+    #[test]
+    fn non_square_corner_ends_on_center_lines() {
+        // Come into the cell at (1, 1) from the south and leave to the east
+        let center = MazePosition { x: 1, y: 1 }.center_position(&TALL);
+        assert_close2(center, Vector { x: 270.0, y: 300.0 });
+
+        let (start_radius, end_radius) =
+            TALL.standard_corner_radius(MazeDirection::North, MazeDirection::East);
+        let corner = PathMotion::corner_between(
+            center,
+            DIRECTION_PI_2,
+            DIRECTION_0,
+            start_radius,
+            end_radius,
+            10.0,
+        );
+
+        // On the edge with the cell to the south, pushed forward by the offset
+        assert_close2(corner.start(), Vector { x: 270.0, y: 210.0 });
+
+        // On the edge with the cell to the east, pushed forward by the offset
+        assert_close2(corner.end(), Vector { x: 370.0, y: 300.0 });
+    }
+}
+
 #[cfg(test)]
 mod wall_projection_tests {
     #[allow(unused_imports)]
@@ -329,6 +472,22 @@ impl WallIndex {
             },
         }
     }
+
+    /// The wall between two cells that are next to each other, or None if they are not
+    pub fn between(a: MazePosition, b: MazePosition) -> Option<WallIndex> {
+        let direction = match (b.x as i32 - a.x as i32, b.y as i32 - a.y as i32) {
+            (0, 1) => MazeDirection::North,
+            (0, -1) => MazeDirection::South,
+            (1, 0) => MazeDirection::East,
+            (-1, 0) => MazeDirection::West,
+            _ => return None,
+        };
+
+        Some(WallIndex::from_maze_orientation(MazeOrientation {
+            position: a,
+            direction,
+        }))
+    }
 }
 
 #[cfg(test)]
@@ -403,6 +562,38 @@ mod wall_index_test {
             }
         )
     }
+
+    #[test]
+    fn between_neighbors() {
+        assert_eq!(
+            WallIndex::between(MazePosition { x: 1, y: 1 }, MazePosition { x: 1, y: 2 }),
+            Some(WallIndex {
+                x: 1,
+                y: 2,
+                direction: WallDirection::Horizontal,
+            })
+        );
+        assert_eq!(
+            WallIndex::between(MazePosition { x: 1, y: 1 }, MazePosition { x: 0, y: 1 }),
+            Some(WallIndex {
+                x: 1,
+                y: 1,
+                direction: WallDirection::Vertical,
+            })
+        );
+    }
+
+    #[test]
+    fn between_not_neighbors() {
+        assert_eq!(
+            WallIndex::between(MazePosition { x: 1, y: 1 }, MazePosition { x: 2, y: 2 }),
+            None
+        );
+        assert_eq!(
+            WallIndex::between(MazePosition { x: 1, y: 1 }, MazePosition { x: 1, y: 1 }),
+            None
+        );
+    }
 }
 
 /// Keeps track of all the walls in a maze
@@ -511,6 +702,83 @@ impl Maze {
         }
     }
 
+    /// All the walls inside the maze, not including the perimeter
+    pub fn walls(&self) -> impl Iterator<Item = &Wall> + '_ {
+        self.horizontal_walls
+            .iter()
+            .flatten()
+            .chain(self.vertical_walls.iter().flatten())
+    }
+
+    /// All the walls inside the maze with their indexes, not including the perimeter. Each wall
+    /// is only given once, even though it is shared by two cells.
+    pub fn edges(&self) -> impl Iterator<Item = (WallIndex, Wall)> + '_ {
+        let horizontal =
+            self.horizontal_walls
+                .iter()
+                .enumerate()
+                .flat_map(|(x, walls)| {
+                    walls.iter().enumerate().map(move |(y, &wall)| {
+                        let index = WallIndex {
+                            x,
+                            y: y + 1,
+                            direction: WallDirection::Horizontal,
+                        };
+                        (index, wall)
+                    })
+                });
+
+        let vertical = self
+            .vertical_walls
+            .iter()
+            .enumerate()
+            .flat_map(|(x, walls)| {
+                walls.iter().enumerate().map(move |(y, &wall)| {
+                    let index = WallIndex {
+                        x: x + 1,
+                        y,
+                        direction: WallDirection::Vertical,
+                    };
+                    (index, wall)
+                })
+            });
+
+        horizontal.chain(vertical)
+    }
+
+    /// Which neighbors each cell is connected to, as a bitmask of [OPEN_NORTH], [OPEN_EAST],
+    /// [OPEN_SOUTH] and [OPEN_WEST], indexed by `[y][x]`. This is for trying out other solvers
+    /// without needing to know how the walls are stored here. The perimeter is always closed,
+    /// and unknown walls are open only if `treat_unknown_as_open` is set.
+    pub fn to_adjacency(&self, treat_unknown_as_open: bool) -> [[u8; WIDTH]; HEIGHT] {
+        let is_open = |wall: Wall| {
+            wall == Wall::Open || (treat_unknown_as_open && wall == Wall::Unknown)
+        };
+
+        let mut adjacency = [[0; WIDTH]; HEIGHT];
+
+        for (y, row) in adjacency.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                let (north, south, east, west) = self.get_cell(x, y);
+
+                for &(wall, bit) in [
+                    (north, OPEN_NORTH),
+                    (east, OPEN_EAST),
+                    (south, OPEN_SOUTH),
+                    (west, OPEN_WEST),
+                ]
+                .iter()
+                {
+                    if is_open(wall) {
+                        *cell |= bit;
+                    }
+                }
+            }
+        }
+
+        adjacency
+    }
+
     pub fn get_wall(&self, index: WallIndex) -> Option<&Wall> {
         match index.direction {
             WallDirection::Horizontal => {
@@ -533,4 +801,549 @@ impl Maze {
             }
         }
     }
+
+    /// How many cells of diagonal are ahead of `from`, going forward one cell and then turning
+    /// back and forth like a staircase. Both ways of turning first are tried, and the longer one
+    /// is used. Walls that are not known to be closed are taken as open, the same as when
+    /// planning a path. None if there isn't room for at least two turns, since one turn is just
+    /// a corner.
+    pub fn diagonal_run_available(&self, from: MazeOrientation) -> Option<usize> {
+        let run = |first_turn: fn(MazeDirection) -> MazeDirection| {
+            let forward = from.direction;
+            let side = first_turn(forward);
+
+            let mut position = from.position;
+            let mut cells = 0;
+
+            while let Some(next) =
+                open_neighbor(self, position, if cells % 2 == 0 { forward } else { side })
+            {
+                position = next;
+                cells += 1;
+            }
+
+            cells
+        };
+
+        let cells = run(MazeDirection::left).max(run(MazeDirection::right));
+
+        if cells >= 3 {
+            Some(cells)
+        } else {
+            None
+        }
+    }
+
+    /// Find the closest closed wall or post looking from `from`. Walls that are not in the maze
+    /// are treated as closed.
+    pub fn find_closed_wall(
+        &self,
+        config: &MazeConfig,
+        from: Orientation,
+    ) -> Option<MazeProjectionResult> {
+        config.wall_projection(from).find(|maze_projection_result| {
+            if let MazeIndex::Wall(wall_index) = maze_projection_result.maze_index {
+                self.get_wall(wall_index).unwrap_or(&Wall::Closed) == &Wall::Closed
+            } else {
+                true
+            }
+        })
+    }
+
+    /// The distances that the left, front, and right sensors should read if the mouse is at
+    /// `orientation` in this maze
+    pub fn expected_distances(
+        &self,
+        config: &MazeConfig,
+        mech: &MechanicalConfig,
+        orientation: Orientation,
+    ) -> (DistanceReading, DistanceReading, DistanceReading) {
+        let reading = |offset: Orientation, limit: f32| {
+            self.find_closed_wall(config, orientation.offset(offset))
+                .filter(|result| result.distance < limit)
+                .map_or(DistanceReading::OutOfRange, |result| {
+                    DistanceReading::InRange(result.distance)
+                })
+        };
+
+        let left = reading(
+            Orientation {
+                position: Vector {
+                    x: mech.left_sensor_offset_x,
+                    y: mech.left_sensor_offset_y,
+                },
+                direction: DIRECTION_PI_2,
+            },
+            mech.left_sensor_limit,
+        );
+
+        let front = reading(
+            Orientation {
+                position: Vector {
+                    x: mech.front_sensor_offset_x,
+                    y: 0.0,
+                },
+                direction: DIRECTION_0,
+            },
+            mech.front_sensor_limit,
+        );
+
+        let right = reading(
+            Orientation {
+                position: Vector {
+                    x: mech.right_sensor_offset_x,
+                    y: -mech.right_sensor_offset_y,
+                },
+                direction: DIRECTION_3_PI_2,
+            },
+            mech.right_sensor_limit,
+        );
+
+        (left, front, right)
+    }
+
+    /// How far the corners of the mouse body are from the closest closed wall or post when the
+    /// mouse is at `orientation`, in mm. Negative means a corner is inside a wall. Walls that
+    /// are not known to be closed are treated as open, and the edge of the maze is always
+    /// closed.
+    pub fn pose_clearance(
+        &self,
+        config: &MazeConfig,
+        mech: &MechanicalConfig,
+        orientation: Orientation,
+    ) -> f32 {
+        let front = mech.front_offset;
+        let back = mech.front_offset - mech.length;
+        let side = mech.width / 2.0;
+
+        [(front, side), (front, -side), (back, side), (back, -side)]
+            .iter()
+            .map(|&(x, y)| {
+                let corner = orientation.offset(Orientation {
+                    position: Vector { x, y },
+                    direction: DIRECTION_0,
+                });
+
+                self.point_clearance(config, corner.position)
+            })
+            .fold(core::f32::INFINITY, f32::min)
+    }
+
+    /// How far `point` is from the closest closed wall or post, negative if it is inside one.
+    /// There is a post at every corner of every cell, so only the cells right around the point
+    /// need to be checked.
+    fn point_clearance(&self, config: &MazeConfig, point: Vector) -> f32 {
+        let half_wall = config.wall_width / 2.0;
+
+        let cell_x = ((point.x / config.cell_width_x) as usize).min(WIDTH - 1);
+        let cell_y = ((point.y / config.cell_width_y) as usize).min(HEIGHT - 1);
+
+        let mut clearance = core::f32::INFINITY;
+
+        for x in cell_x.saturating_sub(1)..=(cell_x + 1).min(WIDTH - 1) {
+            for y in cell_y.saturating_sub(1)..=(cell_y + 1).min(HEIGHT - 1) {
+                let left = x as f32 * config.cell_width_x;
+                let bottom = y as f32 * config.cell_width_y;
+                let right = left + config.cell_width_x;
+                let top = bottom + config.cell_width_y;
+
+                for &(post_x, post_y) in
+                    &[(left, bottom), (right, bottom), (left, top), (right, top)]
+                {
+                    let post = Vector {
+                        x: post_x,
+                        y: post_y,
+                    };
+                    let distance = rectangle_distance(
+                        point,
+                        post,
+                        Vector {
+                            x: half_wall,
+                            y: half_wall,
+                        },
+                    );
+                    clearance = clearance.min(distance);
+                }
+
+                let walls = [
+                    (x, y, WallDirection::Horizontal),
+                    (x, y + 1, WallDirection::Horizontal),
+                    (x, y, WallDirection::Vertical),
+                    (x + 1, y, WallDirection::Vertical),
+                ];
+
+                for &(x, y, direction) in &walls {
+                    let index = WallIndex { x, y, direction };
+                    if self.get_wall(index).unwrap_or(&Wall::Closed) != &Wall::Closed {
+                        continue;
+                    }
+
+                    let (center, half_size) = match direction {
+                        WallDirection::Horizontal => (
+                            Vector {
+                                x: (x as f32 + 0.5) * config.cell_width_x,
+                                y: y as f32 * config.cell_width_y,
+                            },
+                            Vector {
+                                x: config.cell_width_x / 2.0,
+                                y: half_wall,
+                            },
+                        ),
+                        WallDirection::Vertical => (
+                            Vector {
+                                x: x as f32 * config.cell_width_x,
+                                y: (y as f32 + 0.5) * config.cell_width_y,
+                            },
+                            Vector {
+                                x: half_wall,
+                                y: config.cell_width_y / 2.0,
+                            },
+                        ),
+                    };
+
+                    clearance =
+                        clearance.min(rectangle_distance(point, center, half_size));
+                }
+            }
+        }
+
+        clearance
+    }
+}
+
+/// Get the cell next to `position` in `direction`, if it is inside the maze and the wall between
+/// them is not closed
+pub(crate) fn open_neighbor(
+    maze: &Maze,
+    position: MazePosition,
+    direction: MazeDirection,
+) -> Option<MazePosition> {
+    let (north, south, east, west) = maze.get_cell(position.x, position.y);
+
+    let wall = match direction {
+        MazeDirection::North => north,
+        MazeDirection::South => south,
+        MazeDirection::East => east,
+        MazeDirection::West => west,
+    };
+
+    if wall != Wall::Closed {
+        position.neighbor(direction)
+    } else {
+        None
+    }
+}
+
+/// The distance from `point` to the edge of the rectangle centered at `center`, negative if it
+/// is inside
+fn rectangle_distance(point: Vector, center: Vector, half_size: Vector) -> f32 {
+    let dx = (point.x - center.x).abs() - half_size.x;
+    let dy = (point.y - center.y).abs() - half_size.y;
+
+    let outside = Vector {
+        x: dx.max(0.0),
+        y: dy.max(0.0),
+    }
+    .magnitude();
+    let inside = dx.max(dy).min(0.0);
+
+    outside + inside
+}
+
+#[cfg(test)]
+mod expected_distances_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{Maze, Wall};
+    use crate::config::{mouse_2019, MAZE};
+    use crate::fast::{Orientation, Vector, DIRECTION_PI_2};
+    use crate::mouse::{ContainsDistanceReading, DistanceReading};
+
+    const CENTERED: Orientation = Orientation {
+        position: Vector { x: 90.0, y: 90.0 },
+        direction: DIRECTION_PI_2,
+    };
+
+    #[test]
+    fn closed_cell() {
+        let maze = Maze::new(Wall::Closed);
+
+        let (left, front, right) =
+            maze.expected_distances(&MAZE, &mouse_2019::MECH, CENTERED);
+
+        // The wall surface is 84mm from the center, minus the sensor offsets
+        assert_close(Some(left).value().unwrap(), 52.0);
+        assert_close(Some(front).value().unwrap(), 36.0);
+        assert_close(Some(right).value().unwrap(), 52.0);
+    }
+
+    #[test]
+    fn open_front() {
+        let maze = Maze::new(Wall::Open);
+
+        let (left, front, right) =
+            maze.expected_distances(&MAZE, &mouse_2019::MECH, CENTERED);
+
+        // The left side is the edge of the maze, which is always closed
+        assert_close(Some(left).value().unwrap(), 52.0);
+        assert_eq!(front, DistanceReading::OutOfRange);
+        assert_eq!(right, DistanceReading::OutOfRange);
+    }
+}
+
+#[cfg(test)]
+mod pose_clearance_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{Maze, Wall, WallDirection, WallIndex};
+    use crate::config::{mouse_2019, MAZE};
+    use crate::fast::{Orientation, Vector, DIRECTION_0, DIRECTION_PI_2};
+    use crate::slow::MazeDirection;
+
+    /// A corridor going north along the left edge of the maze
+    fn corridor() -> Maze {
+        let mut maze = Maze::new(Wall::Open);
+        for y in 0..4 {
+            maze.set_wall(
+                WallIndex {
+                    x: 1,
+                    y,
+                    direction: WallDirection::Vertical,
+                },
+                Wall::Closed,
+            );
+        }
+        maze
+    }
+
+    fn at(x: f32) -> Orientation {
+        Orientation {
+            position: Vector { x, y: 270.0 },
+            direction: DIRECTION_PI_2,
+        }
+    }
+
+    #[test]
+    fn centered() {
+        let clearance = corridor().pose_clearance(&MAZE, &mouse_2019::MECH, at(90.0));
+        assert_close(
+            clearance,
+            MAZE.center_to_wall(MazeDirection::East) - mouse_2019::MECH.width / 2.0,
+        );
+    }
+
+    #[test]
+    fn touching() {
+        let x = MAZE.wall_width / 2.0 + mouse_2019::MECH.width / 2.0;
+        let clearance = corridor().pose_clearance(&MAZE, &mouse_2019::MECH, at(x));
+        assert!(clearance.abs() < 0.001, "{}", clearance);
+    }
+
+    #[test]
+    fn collision() {
+        let x = MAZE.wall_width / 2.0 + mouse_2019::MECH.width / 2.0 - 5.0;
+        let clearance = corridor().pose_clearance(&MAZE, &mouse_2019::MECH, at(x));
+        assert_close(clearance, -5.0);
+    }
+
+    #[test]
+    fn front_wall() {
+        let maze = Maze::new(Wall::Closed);
+        let orientation = Orientation {
+            position: Vector { x: 90.0, y: 90.0 },
+            direction: DIRECTION_0,
+        };
+
+        // The front of the body is closer to the wall than the sides are
+        let clearance = maze.pose_clearance(&MAZE, &mouse_2019::MECH, orientation);
+        assert_close(
+            clearance,
+            MAZE.center_to_wall(MazeDirection::East) - mouse_2019::MECH.front_offset,
+        );
+    }
+}
+
+#[cfg(test)]
+mod edges_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{Maze, Wall, WallDirection, WallIndex, HEIGHT, WIDTH};
+
+    #[test]
+    fn count() {
+        let maze = Maze::new(Wall::Unknown);
+        assert_eq!(
+            maze.edges().count(),
+            WIDTH * (HEIGHT - 1) + (WIDTH - 1) * HEIGHT
+        );
+    }
+
+    #[test]
+    fn no_duplicates() {
+        let maze = Maze::new(Wall::Unknown);
+        for (i, (index1, _)) in maze.edges().enumerate() {
+            assert!(maze.edges().skip(i + 1).all(|(index2, _)| index1 != index2));
+        }
+    }
+
+    #[test]
+    fn matches_get_wall() {
+        let mut maze = Maze::new(Wall::Open);
+        let closed = WallIndex {
+            x: 3,
+            y: 4,
+            direction: WallDirection::Vertical,
+        };
+        maze.set_wall(closed, Wall::Closed);
+
+        for (index, wall) in maze.edges() {
+            assert_eq!(maze.get_wall(index), Some(&wall));
+        }
+
+        assert_eq!(
+            maze.edges()
+                .filter(|&(_, wall)| wall == Wall::Closed)
+                .count(),
+            1
+        );
+    }
+}
+
+#[cfg(test)]
+mod adjacency_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{Maze, Wall, WallIndex, OPEN_EAST, OPEN_NORTH, OPEN_SOUTH, OPEN_WEST};
+    use crate::slow::MazePosition;
+
+    /// A maze with everything unknown except around cell (2, 2), which is closed to the north,
+    /// open to the east and south, and unknown to the west
+    fn maze() -> Maze {
+        let mut maze = Maze::new(Wall::Unknown);
+        let cell = MazePosition { x: 2, y: 2 };
+        let neighbor = |x, y| WallIndex::between(cell, MazePosition { x, y }).unwrap();
+
+        maze.set_wall(neighbor(2, 3), Wall::Closed);
+        maze.set_wall(neighbor(3, 2), Wall::Open);
+        maze.set_wall(neighbor(2, 1), Wall::Open);
+        maze
+    }
+
+    #[test]
+    fn unknown_closed() {
+        let adjacency = maze().to_adjacency(false);
+
+        assert_eq!(adjacency[2][2], OPEN_EAST | OPEN_SOUTH);
+
+        // The other side of the same walls
+        assert_eq!(adjacency[2][3], OPEN_WEST);
+        assert_eq!(adjacency[1][2], OPEN_NORTH);
+        assert_eq!(adjacency[3][2], 0);
+    }
+
+    #[test]
+    fn unknown_open() {
+        let adjacency = maze().to_adjacency(true);
+
+        assert_eq!(adjacency[2][2], OPEN_EAST | OPEN_SOUTH | OPEN_WEST);
+        assert_eq!(adjacency[3][2], OPEN_EAST | OPEN_WEST | OPEN_NORTH);
+    }
+
+    #[test]
+    fn perimeter_closed() {
+        let adjacency = Maze::new(Wall::Open).to_adjacency(true);
+
+        assert_eq!(adjacency[0][0], OPEN_NORTH | OPEN_EAST);
+        assert_eq!(adjacency[15][15], OPEN_SOUTH | OPEN_WEST);
+        assert_eq!(adjacency[0][15], OPEN_NORTH | OPEN_WEST);
+        assert_eq!(adjacency[15][0], OPEN_SOUTH | OPEN_EAST);
+        assert_eq!(
+            adjacency[7][7],
+            OPEN_NORTH | OPEN_EAST | OPEN_SOUTH | OPEN_WEST
+        );
+    }
+}
+
+#[cfg(test)]
+mod diagonal_run_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{Maze, Wall, WallIndex};
+    use crate::slow::{MazeDirection, MazeOrientation, MazePosition};
+
+    // A maze with everything closed except between each of `cells` in order
+    fn corridor(cells: &[(usize, usize)]) -> Maze {
+        let mut maze = Maze::new(Wall::Closed);
+        for pair in cells.windows(2) {
+            let a = MazePosition {
+                x: pair[0].0,
+                y: pair[0].1,
+            };
+            let b = MazePosition {
+                x: pair[1].0,
+                y: pair[1].1,
+            };
+            maze.set_wall(WallIndex::between(a, b).unwrap(), Wall::Open);
+        }
+        maze
+    }
+
+    fn from(x: usize, y: usize, direction: MazeDirection) -> MazeOrientation {
+        MazeOrientation {
+            position: MazePosition { x, y },
+            direction,
+        }
+    }
+
+    #[test]
+    fn staircase_right() {
+        let maze = corridor(&[(0, 0), (0, 1), (1, 1), (1, 2), (2, 2), (2, 3)]);
+        assert_eq!(
+            maze.diagonal_run_available(from(0, 0, MazeDirection::North)),
+            Some(5)
+        );
+    }
+
+    #[test]
+    fn staircase_left() {
+        let maze = corridor(&[(5, 0), (5, 1), (4, 1), (4, 2), (3, 2)]);
+        assert_eq!(
+            maze.diagonal_run_available(from(5, 0, MazeDirection::North)),
+            Some(4)
+        );
+    }
+
+    #[test]
+    fn staircase_from_the_middle() {
+        let maze = corridor(&[(0, 0), (0, 1), (1, 1), (1, 2), (2, 2), (2, 3)]);
+        assert_eq!(
+            maze.diagonal_run_available(from(1, 1, MazeDirection::North)),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn straight_corridor() {
+        let maze = corridor(&[(0, 0), (0, 1), (0, 2), (0, 3), (0, 4)]);
+        assert_eq!(
+            maze.diagonal_run_available(from(0, 0, MazeDirection::North)),
+            None
+        );
+    }
+
+    #[test]
+    fn one_corner() {
+        let maze = corridor(&[(0, 0), (0, 1), (1, 1), (2, 1), (3, 1)]);
+        assert_eq!(
+            maze.diagonal_run_available(from(0, 0, MazeDirection::North)),
+            None
+        );
+    }
 }
@@ -14,8 +14,8 @@ use crate::fast::{
 };
 
 use crate::slow::map::{MapDebug, MoveOptions};
-use crate::slow::navigate::TwelvePartitionNavigateDebug;
-use maze::MazeConfig;
+use crate::slow::navigate::{Move, TwelvePartitionNavigateDebug};
+use maze::{MazeConfig, HEIGHT, WIDTH};
 
 #[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct SlowDebug {
@@ -86,8 +86,28 @@ pub struct MazePosition {
 impl MazePosition {
     pub fn center_position(self, config: &MazeConfig) -> Vector {
         Vector {
-            x: self.x as f32 * config.cell_width + config.cell_width / 2.0,
-            y: self.y as f32 * config.cell_width + config.cell_width / 2.0,
+            x: self.x as f32 * config.cell_width_x + config.cell_width_x / 2.0,
+            y: self.y as f32 * config.cell_width_y + config.cell_width_y / 2.0,
+        }
+    }
+
+    /// The cell next to this one going `direction`, or None if that is outside the maze
+    pub fn neighbor(self, direction: MazeDirection) -> Option<MazePosition> {
+        let (x, y) = (self.x as i32, self.y as i32);
+        let (x, y) = match direction {
+            MazeDirection::North => (x, y + 1),
+            MazeDirection::South => (x, y - 1),
+            MazeDirection::East => (x + 1, y),
+            MazeDirection::West => (x - 1, y),
+        };
+
+        if x >= 0 && x < WIDTH as i32 && y >= 0 && y < HEIGHT as i32 {
+            Some(MazePosition {
+                x: x as usize,
+                y: y as usize,
+            })
+        } else {
+            None
         }
     }
 }
@@ -97,3 +117,172 @@ pub struct MazeOrientation {
     pub position: MazePosition,
     pub direction: MazeDirection,
 }
+
+/// Where to go in the maze, and optionally which way to be facing once there
+#[derive(Debug, Copy, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Goal {
+    pub position: MazePosition,
+    pub facing: Option<MazeDirection>,
+}
+
+impl From<MazePosition> for Goal {
+    fn from(position: MazePosition) -> Goal {
+        Goal {
+            position,
+            facing: None,
+        }
+    }
+}
+
+impl MazeOrientation {
+    /// The move that will turn from this orientation to face `target`
+    pub fn turn_to(&self, target: MazeDirection) -> Move {
+        if target == self.direction {
+            Move::Forward
+        } else if target == self.direction.left() {
+            Move::Left
+        } else if target == self.direction.right() {
+            Move::Right
+        } else {
+            Move::Backward
+        }
+    }
+}
+
+#[cfg(test)]
+mod turn_to_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{MazeDirection, MazeOrientation, MazePosition, Move};
+
+    const NORTH: MazeOrientation = MazeOrientation {
+        position: MazePosition { x: 0, y: 0 },
+        direction: MazeDirection::North,
+    };
+
+    const WEST: MazeOrientation = MazeOrientation {
+        position: MazePosition { x: 0, y: 0 },
+        direction: MazeDirection::West,
+    };
+
+    #[test]
+    fn forward() {
+        assert_eq!(NORTH.turn_to(MazeDirection::North), Move::Forward);
+        assert_eq!(WEST.turn_to(MazeDirection::West), Move::Forward);
+    }
+
+    #[test]
+    fn left() {
+        assert_eq!(NORTH.turn_to(MazeDirection::West), Move::Left);
+        assert_eq!(WEST.turn_to(MazeDirection::South), Move::Left);
+    }
+
+    #[test]
+    fn right() {
+        assert_eq!(NORTH.turn_to(MazeDirection::East), Move::Right);
+        assert_eq!(WEST.turn_to(MazeDirection::North), Move::Right);
+    }
+
+    #[test]
+    fn backward() {
+        assert_eq!(NORTH.turn_to(MazeDirection::South), Move::Backward);
+        assert_eq!(WEST.turn_to(MazeDirection::East), Move::Backward);
+    }
+
+    #[test]
+    fn inverse_of_to_direction() {
+        for &direction in [
+            MazeDirection::North,
+            MazeDirection::South,
+            MazeDirection::East,
+            MazeDirection::West,
+        ]
+        .iter()
+        {
+            let m = NORTH.turn_to(direction);
+            assert_eq!(m.to_direction(NORTH.direction), direction);
+        }
+    }
+}
+
+#[cfg(test)]
+mod neighbor_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{MazeDirection, MazePosition};
+
+    #[test]
+    fn inside() {
+        let position = MazePosition { x: 3, y: 5 };
+        assert_eq!(
+            position.neighbor(MazeDirection::North),
+            Some(MazePosition { x: 3, y: 6 })
+        );
+        assert_eq!(
+            position.neighbor(MazeDirection::West),
+            Some(MazePosition { x: 2, y: 5 })
+        );
+    }
+
+    #[test]
+    fn outside() {
+        assert_eq!(
+            MazePosition { x: 0, y: 0 }.neighbor(MazeDirection::South),
+            None
+        );
+        assert_eq!(
+            MazePosition { x: 15, y: 15 }.neighbor(MazeDirection::East),
+            None
+        );
+    }
+}
+
+#[cfg(test)]
+mod center_position_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{MazeConfig, MazePosition};
+    use crate::config::MAZE;
+    use crate::fast::{Orientation, Vector, DIRECTION_0};
+
+    /// Cells that are wider than they are tall
+    const WIDE: MazeConfig = MazeConfig {
+        cell_width_x: 200.0,
+        cell_width_y: 160.0,
+        ..MAZE
+    };
+
+    #[test]
+    fn non_square_centers() {
+        assert_close2(
+            MazePosition { x: 0, y: 0 }.center_position(&WIDE),
+            Vector { x: 100.0, y: 80.0 },
+        );
+        assert_close2(
+            MazePosition { x: 3, y: 5 }.center_position(&WIDE),
+            Vector { x: 700.0, y: 880.0 },
+        );
+    }
+
+    #[test]
+    fn non_square_round_trip() {
+        for &(x, y) in [(0, 0), (1, 0), (0, 1), (3, 5), (15, 2), (7, 15)].iter() {
+            let position = MazePosition { x, y };
+            let orientation = Orientation {
+                position: position.center_position(&WIDE),
+                direction: DIRECTION_0,
+            };
+
+            assert_eq!(orientation.to_maze_orientation(&WIDE).position, position);
+        }
+    }
+}
@@ -1,37 +1,129 @@
 use serde::{Deserialize, Serialize};
 
 use heapless::Vec;
+use typenum::{Unsigned, U3};
 
-use crate::fast::motion_queue::{Motion, MotionQueueBuffer};
+use crate::fast::motion_queue::{
+    concat, Motion, MotionQueueBuffer, MotionQueueSize, StopMotion,
+};
 use crate::fast::path::PathMotion;
 use crate::fast::turn::TurnMotion;
 
-use crate::fast::Orientation;
-use crate::slow::maze::MazeConfig;
-use crate::slow::MazeDirection;
+use crate::fast::{Direction, Orientation, Vector};
+use crate::slow::map::MoveOptions;
+use crate::slow::maze::{MazeConfig, WallIndex};
+use crate::slow::navigate::{CellPath, CellPathSize, Move};
+use crate::slow::{MazeDirection, MazeOrientation, MazePosition};
 
 #[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct MotionPlanConfig {
     /// How much to offset the start of a move into the current cell and the end of a move into the
     /// next cell
     pub move_offset: f32,
+
+    /// Whether to stop in cells with more than one way to go while exploring, so the sensors get
+    /// clean readings where it matters most. See [intersection_stop].
+    pub stop_at_intersections: bool,
+
+    /// How many updates to hold still for when stopping at an intersection
+    pub intersection_stop_updates: u8,
+
+    /// The sharpest a planned path motion can curve, in 1/mm. Corners that would curve more
+    /// are done by stopping in the middle of the cell and turning in place instead, and racing
+    /// line curves that would curve more are left as separate corners. 0 disables the limit.
+    pub max_curvature: f32,
+}
+
+impl MotionPlanConfig {
+    /// Whether `path` curves no more than [MotionPlanConfig::max_curvature] anywhere along it
+    fn within_max_curvature(&self, path: &PathMotion) -> bool {
+        self.max_curvature <= 0.0 || path.max_curvature() <= self.max_curvature
+    }
+}
+
+/// How far to go straight when advancing
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Advance {
+    HalfCell,
+    FullCell,
+}
+
+impl Advance {
+    /// How far to go when going `direction`
+    pub fn distance(self, maze_config: &MazeConfig, direction: MazeDirection) -> f32 {
+        match self {
+            Advance::HalfCell => maze_config.cell_width(direction) / 2.0,
+            Advance::FullCell => maze_config.cell_width(direction),
+        }
+    }
+}
+
+/// Go straight forward from `orientation` by `advance`, lined up with the maze. This is useful
+/// for stepping through the maze slowly to sense walls.
+pub fn advance_plan(
+    maze_config: &MazeConfig,
+    orientation: Orientation,
+    advance: Advance,
+) -> MotionQueueBuffer {
+    let maze_direction = orientation.to_maze_orientation(maze_config).direction;
+    let direction = maze_direction.into_direction();
+
+    let end_position = orientation.position
+        + advance.distance(maze_config, maze_direction) * direction.into_unit_vector();
+
+    let mut out = Vec::new();
+    out.push(Motion::Path(PathMotion::line(
+        orientation.position,
+        end_position,
+    )))
+    .ok();
+    out
+}
+
+/// Where to continue planning from when not all of the directions fit in one buffer
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanResume {
+    /// How many of the directions were planned. The rest still need to be planned.
+    pub directions_planned: usize,
+
+    /// Where the mouse will be after the planned motions, to plan the rest from
+    pub orientation: Orientation,
+
+    /// The cell and direction the mouse will be in after the planned motions
+    pub maze_orientation: MazeOrientation,
 }
 
+/// Plan the motions to move in each of the `directions` in order, dropping any directions that
+/// do not fit. See [motion_plan_partial] to find out where to continue from.
 pub fn motion_plan(
     config: &MotionPlanConfig,
     maze_config: &MazeConfig,
     orientation: Orientation,
     directions: &[MazeDirection],
 ) -> MotionQueueBuffer {
-    let mut out = Vec::new();
+    motion_plan_partial(config, maze_config, orientation, directions).0
+}
+
+/// Plan the motions to move in each of the `directions` in order
+///
+/// If all the motions do not fit in the buffer, only the directions that fit completely are
+/// planned, and a [PlanResume] is returned to continue planning from later.
+pub fn motion_plan_partial(
+    config: &MotionPlanConfig,
+    maze_config: &MazeConfig,
+    orientation: Orientation,
+    directions: &[MazeDirection],
+) -> (MotionQueueBuffer, Option<PlanResume>) {
+    let mut out: MotionQueueBuffer = Vec::new();
 
     let mut current_orientation = orientation;
 
-    for next_direction in directions.iter().copied() {
+    for (i, next_direction) in directions.iter().copied().enumerate() {
         let maze_orientation = current_orientation.to_maze_orientation(maze_config);
         let cell_center = maze_orientation.position.center_position(maze_config);
 
-        let offset_distance = maze_config.cell_width / 2.0 + config.move_offset;
+        let offset_distance =
+            maze_config.cell_width(next_direction) / 2.0 + config.move_offset;
         let end_position = match next_direction {
             MazeDirection::North => cell_center.offset_y(offset_distance),
             MazeDirection::South => cell_center.offset_y(-offset_distance),
@@ -42,7 +134,7 @@ pub fn motion_plan(
         // Very dumb, but it should work.
 
         // If we are not at the edge of the cell, stop and do a turn instead of a curve
-        let center_threshold = maze_config.cell_width / 8.0;
+        let center_threshold = maze_config.cell_width(maze_orientation.direction) / 8.0;
         let do_manual_turn = match maze_orientation.direction {
             MazeDirection::North => {
                 current_orientation.position.y > cell_center.y - center_threshold
@@ -58,25 +150,63 @@ pub fn motion_plan(
             }
         };
 
-        if do_manual_turn || next_direction.opposite() == maze_orientation.direction {
-            out.push(Motion::Turn(TurnMotion::new(
-                orientation.direction,
-                next_direction.into_direction(),
-            )))
-            .ok();
-            out.push(Motion::Path(PathMotion::line(cell_center, end_position)))
+        let mut motions: Vec<Motion, U3> = Vec::new();
+
+        if do_manual_turn || maze_orientation.turn_to(next_direction) == Move::Backward {
+            motions
+                .push(Motion::Turn(TurnMotion::new(
+                    orientation.direction,
+                    next_direction.into_direction(),
+                )))
+                .ok();
+            motions
+                .push(Motion::Path(PathMotion::line(cell_center, end_position)))
                 .ok();
         } else {
-            out.push(Motion::Path(PathMotion::corner(
+            let (start_radius, end_radius) = maze_config
+                .standard_corner_radius(maze_orientation.direction, next_direction);
+            let corner = PathMotion::corner_between(
                 cell_center,
                 maze_orientation.direction.into_direction(),
                 next_direction.into_direction(),
-                maze_config.cell_width / 2.0,
+                start_radius,
+                end_radius,
                 config.move_offset,
-            )))
-            .ok();
+            );
+
+            if config.within_max_curvature(&corner) {
+                motions.push(Motion::Path(corner)).ok();
+            } else {
+                // Too sharp to follow, so go to the middle of the cell and turn there
+                motions
+                    .push(Motion::Path(PathMotion::line(corner.start(), cell_center)))
+                    .ok();
+                motions
+                    .push(Motion::Turn(TurnMotion::new(
+                        maze_orientation.direction.into_direction(),
+                        next_direction.into_direction(),
+                    )))
+                    .ok();
+                motions
+                    .push(Motion::Path(PathMotion::line(cell_center, end_position)))
+                    .ok();
+            }
         }
 
+        if out.len() + motions.len() > MotionQueueSize::to_usize() {
+            out.reverse();
+
+            let resume = PlanResume {
+                directions_planned: i,
+                orientation: current_orientation,
+                maze_orientation,
+            };
+
+            return (out, Some(resume));
+        }
+
+        out.extend_from_slice(&motions).ok();
+
         current_orientation.direction = next_direction.into_direction();
 
         current_orientation.position = end_position;
@@ -84,7 +214,374 @@ pub fn motion_plan(
 
     out.reverse();
 
-    return out;
+    (out, None)
+}
+
+/// The stop to make on getting to a cell while exploring, if any. If
+/// [MotionPlanConfig::stop_at_intersections] is set and there is more than one way to go from
+/// the cell, the mouse stops there first, and only senses and picks a move once it has.
+pub fn intersection_stop(
+    config: &MotionPlanConfig,
+    move_options: MoveOptions,
+) -> Option<Motion> {
+    let open_moves = [move_options.left, move_options.front, move_options.right]
+        .iter()
+        .filter(|&&open| open)
+        .count();
+
+    if config.stop_at_intersections && open_moves > 1 {
+        Some(Motion::Stop(StopMotion {
+            updates: config.intersection_stop_updates,
+        }))
+    } else {
+        None
+    }
+}
+
+/// Plan the motions for one exploring move out of the cell the mouse just got to. Nothing is
+/// planned for a move that would leave the maze.
+pub fn explore_plan(
+    config: &MotionPlanConfig,
+    maze_config: &MazeConfig,
+    orientation: Orientation,
+    direction: MazeDirection,
+) -> MotionQueueBuffer {
+    // A wall must have been missed
+    let position = orientation.to_maze_orientation(maze_config).position;
+    if position.neighbor(direction).is_none() {
+        return Vec::new();
+    }
+
+    motion_plan(config, maze_config, orientation, &[direction])
+}
+
+/// How to lay out a path through a sequence of cells
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PathStyle {
+    /// Go through the middle of every cell. Safe, but slow.
+    CenterLine,
+
+    /// Cut across zig zags with one smooth curve instead of turning in every cell, and widen
+    /// single corners into the straights on either side. This stays inside the cells being cut
+    /// across.
+    RacingLine,
+}
+
+/// How close the tangents need to be to count as the same direction, in radians
+const TANGENT_TOLERANCE: f32 = 0.1;
+
+/// Plan the motions to go through each of the `cells` in order, the first being the cell the
+/// mouse is in. Like [motion_plan_partial], the plan is cut short if it does not fit.
+pub fn from_cells(
+    config: &MotionPlanConfig,
+    maze_config: &MazeConfig,
+    orientation: Orientation,
+    cells: &CellPath,
+    style: PathStyle,
+) -> (MotionQueueBuffer, Option<PlanResume>) {
+    let directions: Vec<MazeDirection, CellPathSize> = cells
+        .windows(2)
+        .map(|cells| direction_between(cells[0], cells[1]))
+        .collect();
+
+    let (motions, resume) =
+        motion_plan_partial(config, maze_config, orientation, &directions);
+
+    match style {
+        PathStyle::CenterLine => (motions, resume),
+        PathStyle::RacingLine => (racing_line(config, maze_config, &motions), resume),
+    }
+}
+
+/// Whether `a` points the same way as `b`
+fn same_direction(a: Vector, b: Vector) -> bool {
+    a.direction().within(b.direction(), TANGENT_TOLERANCE)
+}
+
+/// Replace each pair of corners that turn one way and then back with an s-curve that cuts
+/// across both cells, and start and end each corner between two straights further into them
+fn racing_line(
+    config: &MotionPlanConfig,
+    maze_config: &MazeConfig,
+    motions: &MotionQueueBuffer,
+) -> MotionQueueBuffer {
+    // The bounds of the cell that `v` is in, inset by the walls
+    let cell_bounds = |v: Vector| {
+        let cell = Orientation {
+            position: v,
+            direction: Direction::default(),
+        }
+        .to_maze_orientation(maze_config)
+        .position;
+
+        let center = cell.center_position(maze_config);
+        let half = Vector {
+            x: maze_config.center_to_wall(MazeDirection::East),
+            y: maze_config.center_to_wall(MazeDirection::North),
+        };
+
+        (center - half, center + half)
+    };
+
+    // In the order they will be done
+    let mut forward = motions.clone();
+    forward.reverse();
+
+    let mut out: MotionQueueBuffer = Vec::new();
+    let mut i = 0;
+    while i < forward.len() {
+        let merged = match (forward.get(i), forward.get(i + 1)) {
+            (Some(Motion::Path(a)), Some(Motion::Path(b))) => {
+                let tangent = a.derivative(0.0);
+                let is_corner = !same_direction(tangent, a.derivative(1.0));
+                let turns_back = same_direction(tangent, b.derivative(1.0));
+                let joined = (a.end() - b.start()).magnitude() < FAST_RUN_TOLERANCE;
+
+                if is_corner && turns_back && joined {
+                    // Stay within the two cells, and the bits of the cells before and after
+                    let (a_min, a_max) = cell_bounds(a.start());
+                    let (b_min, b_max) = cell_bounds(b.start());
+                    let min = Vector {
+                        x: a_min.x.min(b_min.x).min(a.start().x).min(b.end().x),
+                        y: a_min.y.min(b_min.y).min(a.start().y).min(b.end().y),
+                    };
+                    let max = Vector {
+                        x: a_max.x.max(b_max.x).max(a.start().x).max(b.end().x),
+                        y: a_max.y.max(b_max.y).max(a.start().y).max(b.end().y),
+                    };
+
+                    Some(
+                        PathMotion::s_curve(a.start(), b.end(), tangent.direction())
+                            .clamp_to_rect(min, max),
+                    )
+                    .filter(|merged| config.within_max_curvature(merged))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+
+        if let Some(merged) = merged {
+            out.push(Motion::Path(merged)).ok();
+            i += 2;
+            continue;
+        }
+
+        let widened = match (forward.get(i), forward.get(i + 1), forward.get(i + 2)) {
+            (Some(Motion::Path(a)), Some(Motion::Path(b)), Some(Motion::Path(c))) => {
+                let straight = |path: &PathMotion| {
+                    same_direction(path.derivative(0.0), path.derivative(1.0))
+                };
+                let joined = (a.end() - b.start()).magnitude() < FAST_RUN_TOLERANCE
+                    && (b.end() - c.start()).magnitude() < FAST_RUN_TOLERANCE;
+
+                if straight(a) && !straight(b) && straight(c) && joined {
+                    widen_corner(config, a, b, c)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+
+        if let Some((a, b, c)) = widened {
+            out.push(Motion::Path(a)).ok();
+            out.push(Motion::Path(b)).ok();
+
+            // The shortened straight may lead into another corner
+            forward[i + 2] = Motion::Path(c);
+            i += 2;
+        } else {
+            out.push(forward[i]).ok();
+            i += 1;
+        }
+    }
+
+    out.reverse();
+    out
+}
+
+/// Make `corner` start further back into the `before` straight and end further into the
+/// `after` straight, by up to half of the shorter straight, but no more than the corner
+/// already reaches. Gives the shortened straights and wider corner, in that order.
+fn widen_corner(
+    config: &MotionPlanConfig,
+    before: &PathMotion,
+    corner: &PathMotion,
+    after: &PathMotion,
+) -> Option<(PathMotion, PathMotion, PathMotion)> {
+    let start = corner.derivative(0.0).direction();
+    let end = corner.derivative(1.0).direction();
+    let start_v = start.into_unit_vector();
+
+    // Where the straights would meet
+    let center = corner.start() + (corner.end() - corner.start()).dot(start_v) * start_v;
+    let start_radius = (center - corner.start()).magnitude();
+    let end_radius = (corner.end() - center).magnitude();
+
+    let before_length = (before.end() - before.start()).magnitude();
+    let after_length = (after.end() - after.start()).magnitude();
+    let widen = (before_length.min(after_length) / 2.0)
+        .min(start_radius)
+        .min(end_radius);
+
+    let wide = PathMotion::corner_between(
+        center,
+        start,
+        end,
+        start_radius + widen,
+        end_radius + widen,
+        0.0,
+    );
+
+    if widen > 0.0 && config.within_max_curvature(&wide) {
+        Some((
+            PathMotion::line(before.start(), wide.start()),
+            wide,
+            PathMotion::line(wide.end(), after.end()),
+        ))
+    } else {
+        None
+    }
+}
+
+/// How far apart the end of one piece of a fast run and the start of the next can be, in mm
+const FAST_RUN_TOLERANCE: f32 = 1.0;
+
+/// The direction to go from `from` to get to the neighboring cell `to`
+fn direction_between(from: MazePosition, to: MazePosition) -> MazeDirection {
+    if to.y > from.y {
+        MazeDirection::North
+    } else if to.y < from.y {
+        MazeDirection::South
+    } else if to.x > from.x {
+        MazeDirection::East
+    } else {
+        MazeDirection::West
+    }
+}
+
+/// A path through the whole maze that is planned once, then handed out to the motion queue a
+/// few motions at a time as there is room. Unlike exploring, the mouse does not stop to
+/// re-plan at every cell.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FastRunPlan {
+    path: CellPath,
+    next: usize,
+    orientation: Orientation,
+
+    /// The direction to turn to at the end, if it has not been handed out yet
+    facing: Option<MazeDirection>,
+}
+
+impl FastRunPlan {
+    /// Plan to follow the cells in `path`, starting at `orientation`. If `facing` is given,
+    /// the mouse will move to the center of the last cell and turn in place to face it.
+    pub fn new(
+        path: &CellPath,
+        orientation: Orientation,
+        facing: Option<MazeDirection>,
+    ) -> FastRunPlan {
+        FastRunPlan {
+            path: path.clone(),
+            next: 0,
+            orientation,
+            facing,
+        }
+    }
+
+    /// Whether all of the motions have been handed out
+    pub fn done(&self) -> bool {
+        self.cells_done() && self.facing.is_none()
+    }
+
+    fn cells_done(&self) -> bool {
+        self.next + 1 >= self.path.len()
+    }
+
+    /// Whether the part of the path that has not been handed out yet goes through `wall`
+    pub fn crosses(&self, wall: WallIndex) -> bool {
+        self.path[self.next..]
+            .windows(2)
+            .any(|cells| WallIndex::between(cells[0], cells[1]) == Some(wall))
+    }
+
+    /// Plan the next motions, up to `room` of them. They continue on from where the last
+    /// motions handed out end, and are in the same order as the motion queue.
+    pub fn fill(
+        &mut self,
+        config: &MotionPlanConfig,
+        maze_config: &MazeConfig,
+        room: usize,
+    ) -> MotionQueueBuffer {
+        let mut out: MotionQueueBuffer = Vec::new();
+
+        while !self.cells_done() {
+            let direction =
+                direction_between(self.path[self.next], self.path[self.next + 1]);
+            let (motions, _) =
+                motion_plan_partial(config, maze_config, self.orientation, &[direction]);
+
+            if out.len() + motions.len() > room {
+                break;
+            }
+
+            match concat(&out, &motions, FAST_RUN_TOLERANCE) {
+                Ok(joined) => out = joined,
+                Err(_) => break,
+            }
+
+            let end_position = match motions.first() {
+                Some(Motion::Path(path_motion)) => path_motion.end(),
+                _ => self.orientation.position,
+            };
+
+            self.orientation = Orientation {
+                position: end_position,
+                direction: direction.into_direction(),
+            };
+            self.next += 1;
+        }
+
+        if self.cells_done() {
+            if let Some(facing) = self.facing {
+                let target = facing.into_direction();
+                if self.orientation.direction == target {
+                    self.facing = None;
+                } else if let Some(goal) = self.path.last() {
+                    let center = goal.center_position(maze_config);
+
+                    let mut motions: MotionQueueBuffer = Vec::new();
+                    motions
+                        .push(Motion::Turn(TurnMotion::new(
+                            self.orientation.direction,
+                            target,
+                        )))
+                        .ok();
+                    motions
+                        .push(Motion::Path(PathMotion::line(
+                            self.orientation.position,
+                            center,
+                        )))
+                        .ok();
+
+                    if out.len() + motions.len() <= room {
+                        if let Ok(joined) = concat(&out, &motions, FAST_RUN_TOLERANCE) {
+                            out = joined;
+                            self.orientation = Orientation {
+                                position: center,
+                                direction: target,
+                            };
+                            self.facing = None;
+                        }
+                    }
+                }
+            }
+        }
+
+        out
+    }
 }
 
 #[cfg(test)]
@@ -105,7 +602,12 @@ mod test_motion_plan {
     use crate::slow::MazeDirection;
     use heapless::Vec;
 
-    const CONFIG: MotionPlanConfig = MotionPlanConfig { move_offset: 12.0 };
+    const CONFIG: MotionPlanConfig = MotionPlanConfig {
+        move_offset: 12.0,
+        stop_at_intersections: false,
+        intersection_stop_updates: 0,
+        max_curvature: 0.0,
+    };
 
     #[test]
     fn u_turn() {
@@ -140,3 +642,648 @@ mod test_motion_plan {
         )
     }
 }
+
+#[cfg(test)]
+mod test_explore_plan {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{explore_plan, intersection_stop, MotionPlanConfig};
+    use crate::config::{MAZE, MOTION_PLAN};
+    use crate::fast::motion_queue::{Motion, StopMotion};
+    use crate::fast::{Orientation, Vector, DIRECTION_PI_2};
+    use crate::slow::map::MoveOptions;
+    use crate::slow::MazeDirection;
+
+    const CONFIG: MotionPlanConfig = MotionPlanConfig {
+        stop_at_intersections: true,
+        intersection_stop_updates: 5,
+        ..MOTION_PLAN
+    };
+
+    const ENTERING: Orientation = Orientation {
+        position: Vector {
+            x: 90.0,
+            y: 180.0 + 12.0,
+        },
+        direction: DIRECTION_PI_2,
+    };
+
+    const T_INTERSECTION: MoveOptions = MoveOptions {
+        left: true,
+        front: false,
+        right: true,
+    };
+
+    const CORRIDOR: MoveOptions = MoveOptions {
+        left: false,
+        front: true,
+        right: false,
+    };
+
+    #[test]
+    fn stops_at_t_intersection() {
+        assert_eq!(
+            intersection_stop(&CONFIG, T_INTERSECTION),
+            Some(Motion::Stop(StopMotion { updates: 5 }))
+        );
+    }
+
+    #[test]
+    fn no_stop_in_corridor() {
+        assert_eq!(intersection_stop(&CONFIG, CORRIDOR), None);
+    }
+
+    #[test]
+    fn disabled() {
+        assert_eq!(intersection_stop(&MOTION_PLAN, T_INTERSECTION), None);
+    }
+
+    #[test]
+    fn only_the_move() {
+        let plan = explore_plan(&CONFIG, &MAZE, ENTERING, MazeDirection::East);
+
+        assert_eq!(
+            plan,
+            super::motion_plan(&CONFIG, &MAZE, ENTERING, &[MazeDirection::East])
+        );
+    }
+
+    #[test]
+    fn nothing_out_of_the_maze() {
+        let plan = explore_plan(&CONFIG, &MAZE, ENTERING, MazeDirection::West);
+        assert!(plan.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod test_motion_plan_partial {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{motion_plan, motion_plan_partial};
+    use crate::config::{MAZE, MOTION_PLAN};
+    use crate::fast::{Orientation, Vector, DIRECTION_PI_2};
+    use crate::slow::{MazeDirection, MazeOrientation, MazePosition};
+
+    const START: Orientation = Orientation {
+        position: Vector { x: 90.0, y: 90.0 },
+        direction: DIRECTION_PI_2,
+    };
+
+    // Lining up in the first cell takes two motions, and the rest take one each
+    const NORTH: [MazeDirection; 5] = [MazeDirection::North; 5];
+
+    #[test]
+    fn everything_fits() {
+        let (motions, resume) =
+            motion_plan_partial(&MOTION_PLAN, &MAZE, START, &NORTH[..3]);
+        assert_eq!(motions.len(), 4);
+        assert_eq!(resume, None);
+    }
+
+    #[test]
+    fn too_long_is_cut_short() {
+        let (motions, resume) = motion_plan_partial(&MOTION_PLAN, &MAZE, START, &NORTH);
+        assert_eq!(motions.len(), 4);
+        assert_eq!(
+            motions,
+            motion_plan(&MOTION_PLAN, &MAZE, START, &NORTH[..3])
+        );
+
+        let resume = resume.expect("Expected the plan to be cut short");
+        assert_eq!(resume.directions_planned, 3);
+        assert_eq!(
+            resume.maze_orientation,
+            MazeOrientation {
+                position: MazePosition { x: 0, y: 3 },
+                direction: MazeDirection::North,
+            }
+        );
+        assert_close2(
+            resume.orientation.position,
+            Vector {
+                x: 90.0,
+                y: 3.0 * MAZE.cell_width_y + MOTION_PLAN.move_offset,
+            },
+        );
+    }
+
+    #[test]
+    fn resume_plans_the_rest() {
+        let (_, resume) = motion_plan_partial(&MOTION_PLAN, &MAZE, START, &NORTH);
+        let resume = resume.unwrap();
+
+        let (motions, resume) = motion_plan_partial(
+            &MOTION_PLAN,
+            &MAZE,
+            resume.orientation,
+            &NORTH[resume.directions_planned..],
+        );
+        assert_eq!(motions.len(), 2);
+        assert_eq!(resume, None);
+    }
+}
+
+#[cfg(test)]
+mod test_advance_plan {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{advance_plan, Advance};
+    use crate::config::MAZE;
+    use crate::fast::motion_queue::Motion;
+    use crate::fast::{Direction, Orientation, Vector, DIRECTION_PI_2};
+
+    fn advance_length(orientation: Orientation, advance: Advance) -> f32 {
+        let plan = advance_plan(&MAZE, orientation, advance);
+        assert_eq!(plan.len(), 1);
+
+        if let Motion::Path(path) = plan[0] {
+            (path.end() - path.start()).magnitude()
+        } else {
+            panic!("Expected a path motion")
+        }
+    }
+
+    #[test]
+    fn half_cell() {
+        let orientation = Orientation {
+            position: Vector { x: 90.0, y: 90.0 },
+            direction: DIRECTION_PI_2,
+        };
+
+        assert_close(
+            advance_length(orientation, Advance::HalfCell),
+            MAZE.cell_width_y / 2.0,
+        );
+    }
+
+    #[test]
+    fn full_cell() {
+        let orientation = Orientation {
+            position: Vector { x: 90.0, y: 90.0 },
+            direction: DIRECTION_PI_2,
+        };
+
+        assert_close(
+            advance_length(orientation, Advance::FullCell),
+            MAZE.cell_width_y,
+        );
+    }
+
+    #[test]
+    fn half_cell_lines_up_with_maze() {
+        let orientation = Orientation {
+            position: Vector { x: 90.0, y: 90.0 },
+            direction: Direction::from(0.1),
+        };
+
+        let plan = advance_plan(&MAZE, orientation, Advance::HalfCell);
+
+        if let Motion::Path(path) = plan[0] {
+            assert_close2(path.end(), Vector { x: 180.0, y: 90.0 });
+        } else {
+            panic!("Expected a path motion")
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_fast_run_plan {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::FastRunPlan;
+    use crate::config::{MAZE, MOTION_PLAN, NAVIGATE};
+    use crate::fast::motion_queue::{Motion, MotionQueueSize};
+    use crate::fast::turn::TurnMotion;
+    use crate::fast::{Orientation, Vector, DIRECTION_0, DIRECTION_PI_2};
+    use crate::slow::map::Map;
+    use crate::slow::maze::{Maze, Wall, HEIGHT, WIDTH};
+    use crate::slow::{MazeDirection, MazePosition};
+    use heapless::Vec;
+    use typenum::{Unsigned, U32};
+
+    #[test]
+    fn known_maze_is_one_continuous_plan() {
+        let mut horizontal_walls = [[Wall::Closed; HEIGHT - 1]; WIDTH];
+        let mut vertical_walls = [[Wall::Closed; HEIGHT]; WIDTH - 1];
+
+        // North from the start to y = 3, east to x = 2, then north again to y = 5
+        horizontal_walls[0][0] = Wall::Open;
+        horizontal_walls[0][1] = Wall::Open;
+        horizontal_walls[0][2] = Wall::Open;
+        vertical_walls[0][3] = Wall::Open;
+        vertical_walls[1][3] = Wall::Open;
+        horizontal_walls[2][3] = Wall::Open;
+        horizontal_walls[2][4] = Wall::Open;
+
+        let maze = Maze::from_walls(horizontal_walls, vertical_walls);
+        let goal = MazePosition { x: 2, y: 5 };
+
+        let path = Map::from_maze(maze)
+            .plan_full_path(&NAVIGATE, MazePosition { x: 0, y: 0 }, goal)
+            .unwrap();
+
+        let start = Orientation {
+            position: Vector { x: 90.0, y: 90.0 },
+            direction: DIRECTION_PI_2,
+        };
+
+        let mut plan = FastRunPlan::new(&path, start, None);
+
+        let mut motions: Vec<Motion, U32> = Vec::new();
+        while !plan.done() {
+            let buffer = plan.fill(&MOTION_PLAN, &MAZE, MotionQueueSize::to_usize());
+            assert!(!buffer.is_empty());
+            motions.extend(buffer.iter().rev().copied());
+        }
+
+        // Only the first move needs to line up with a turn in place, the rest flow together
+        let paths = motions
+            .iter()
+            .skip(1)
+            .map(|motion| match motion {
+                Motion::Path(path_motion) => *path_motion,
+                _ => panic!("Expected the fast run to not stop and turn"),
+            })
+            .collect::<Vec<_, U32>>();
+
+        for pair in paths.windows(2) {
+            assert_close2(pair[0].end(), pair[1].start());
+        }
+
+        assert_close2(
+            paths.last().unwrap().end(),
+            Vector {
+                x: 450.0,
+                y: 5.0 * MAZE.cell_width_y + MOTION_PLAN.move_offset,
+            },
+        );
+    }
+
+    #[test]
+    fn fill_respects_room() {
+        let path = Map::from_maze(Maze::new(Wall::Open))
+            .plan_full_path(
+                &NAVIGATE,
+                MazePosition { x: 0, y: 0 },
+                MazePosition { x: 0, y: 5 },
+            )
+            .unwrap();
+
+        let mut plan = FastRunPlan::new(
+            &path,
+            Orientation {
+                position: Vector { x: 90.0, y: 90.0 },
+                direction: DIRECTION_PI_2,
+            },
+            None,
+        );
+
+        assert_eq!(plan.fill(&MOTION_PLAN, &MAZE, 1).len(), 0);
+        assert_eq!(plan.fill(&MOTION_PLAN, &MAZE, 3).len(), 3);
+        assert!(!plan.done());
+    }
+
+    fn straight_plan(facing: Option<MazeDirection>) -> Vec<Motion, U32> {
+        let path = Map::from_maze(Maze::new(Wall::Open))
+            .plan_full_path(
+                &NAVIGATE,
+                MazePosition { x: 0, y: 0 },
+                MazePosition { x: 0, y: 2 },
+            )
+            .unwrap();
+
+        let start = Orientation {
+            position: Vector { x: 90.0, y: 90.0 },
+            direction: DIRECTION_PI_2,
+        };
+
+        let mut plan = FastRunPlan::new(&path, start, facing);
+
+        let mut motions: Vec<Motion, U32> = Vec::new();
+        while !plan.done() {
+            let buffer = plan.fill(&MOTION_PLAN, &MAZE, MotionQueueSize::to_usize());
+            assert!(!buffer.is_empty());
+            motions.extend(buffer.iter().rev().copied());
+        }
+        motions
+    }
+
+    #[test]
+    fn no_facing_ends_arriving() {
+        match straight_plan(None).last() {
+            Some(Motion::Path(_)) => {}
+            motion => panic!("Expected a path motion, got {:?}", motion),
+        }
+    }
+
+    #[test]
+    fn facing_arrival_direction_adds_nothing() {
+        assert_eq!(
+            straight_plan(Some(MazeDirection::North)),
+            straight_plan(None)
+        );
+    }
+
+    #[test]
+    fn facing_ends_with_turn_in_place() {
+        let arriving = straight_plan(None);
+        let motions = straight_plan(Some(MazeDirection::East));
+
+        assert_eq!(motions.len(), arriving.len() + 2);
+        assert_eq!(motions[..arriving.len()], arriving[..]);
+
+        // Into the middle of the goal cell, then turn to face east
+        match motions[motions.len() - 2] {
+            Motion::Path(path_motion) => {
+                assert_close2(path_motion.end(), Vector { x: 90.0, y: 450.0 })
+            }
+            motion => panic!("Expected a path motion, got {:?}", motion),
+        }
+        assert_eq!(
+            motions.last(),
+            Some(&Motion::Turn(TurnMotion::new(DIRECTION_PI_2, DIRECTION_0)))
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_path_style {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{from_cells, PathStyle};
+    use crate::config::{MAZE, MOTION_PLAN};
+    use crate::fast::motion_queue::{Motion, MotionQueueBuffer};
+    use crate::fast::path::{is_continuous, max_curvature, total_absolute_curvature};
+    use crate::fast::{Orientation, Vector, DIRECTION_PI_2};
+    use crate::slow::navigate::CellPath;
+    use crate::slow::MazePosition;
+
+    const START: Orientation = Orientation {
+        position: Vector { x: 90.0, y: 90.0 },
+        direction: DIRECTION_PI_2,
+    };
+
+    // North, then jog east a cell, then north again
+    fn s_cells() -> CellPath {
+        let mut cells = CellPath::new();
+        cells
+            .extend_from_slice(&[
+                MazePosition { x: 0, y: 0 },
+                MazePosition { x: 0, y: 1 },
+                MazePosition { x: 1, y: 1 },
+                MazePosition { x: 1, y: 2 },
+            ])
+            .unwrap();
+        cells
+    }
+
+    fn plan(style: PathStyle) -> MotionQueueBuffer {
+        let (motions, resume) = from_cells(&MOTION_PLAN, &MAZE, START, &s_cells(), style);
+        assert_eq!(resume, None);
+        motions
+    }
+
+    #[test]
+    fn center_line_turns_in_every_cell() {
+        assert_eq!(plan(PathStyle::CenterLine).len(), 4);
+    }
+
+    #[test]
+    fn racing_line_turns_less() {
+        let center = total_absolute_curvature(&plan(PathStyle::CenterLine));
+        let racing = total_absolute_curvature(&plan(PathStyle::RacingLine));
+        assert!(racing < center, "racing {} center {}", racing, center);
+    }
+
+    #[test]
+    fn racing_line_ends_in_the_same_place() {
+        let end = |motions: &MotionQueueBuffer| match motions[0] {
+            Motion::Path(path_motion) => path_motion.end(),
+            _ => panic!("Expected the last motion to be a path"),
+        };
+
+        assert_close2(
+            end(&plan(PathStyle::RacingLine)),
+            end(&plan(PathStyle::CenterLine)),
+        );
+    }
+
+    #[test]
+    fn racing_line_stays_in_bounds() {
+        let racing = plan(PathStyle::RacingLine);
+        assert_eq!(racing.len(), 3);
+
+        let s_curve = match racing[0] {
+            Motion::Path(path_motion) => path_motion,
+            _ => panic!("Expected an s-curve"),
+        };
+
+        // Inside cells (0, 1) and (1, 1), plus the move offset into the cells on either side
+        let half_wall = MAZE.wall_width / 2.0;
+        let min = Vector {
+            x: half_wall,
+            y: MAZE.cell_width_y - MOTION_PLAN.move_offset,
+        };
+        let max = Vector {
+            x: 2.0 * MAZE.cell_width_x - half_wall,
+            y: 2.0 * MAZE.cell_width_y + MOTION_PLAN.move_offset,
+        };
+
+        assert!(s_curve.within_rect(min, max));
+
+        // Still leaves and arrives going north, so it was not squashed into a straight line
+        assert!(s_curve
+            .derivative(0.0)
+            .direction()
+            .within(DIRECTION_PI_2, 0.01));
+        assert!(s_curve
+            .derivative(1.0)
+            .direction()
+            .within(DIRECTION_PI_2, 0.01));
+    }
+
+    // North, then turn east once and keep going
+    fn corner_cells() -> CellPath {
+        let mut cells = CellPath::new();
+        cells
+            .extend_from_slice(&[
+                MazePosition { x: 0, y: 0 },
+                MazePosition { x: 0, y: 1 },
+                MazePosition { x: 1, y: 1 },
+                MazePosition { x: 2, y: 1 },
+            ])
+            .unwrap();
+        cells
+    }
+
+    fn plan_corner(style: PathStyle) -> MotionQueueBuffer {
+        let (motions, resume) =
+            from_cells(&MOTION_PLAN, &MAZE, START, &corner_cells(), style);
+        assert_eq!(resume, None);
+        motions
+    }
+
+    #[test]
+    fn racing_line_widens_a_single_corner() {
+        let center = plan_corner(PathStyle::CenterLine);
+        let racing = plan_corner(PathStyle::RacingLine);
+        assert_eq!(racing.len(), center.len());
+
+        // The tightest part of the corner is the tightest part of the whole path
+        let center_radius = 1.0 / max_curvature(&center);
+        let racing_radius = 1.0 / max_curvature(&racing);
+        assert!(
+            racing_radius > center_radius,
+            "racing {} center {}",
+            racing_radius,
+            center_radius
+        );
+
+        assert!(is_continuous(&racing, 0.01, 0.01));
+    }
+
+    #[test]
+    fn racing_line_corner_stays_in_bounds() {
+        let racing = plan_corner(PathStyle::RacingLine);
+
+        let corner = match racing[1] {
+            Motion::Path(path_motion) => path_motion,
+            _ => panic!("Expected a corner"),
+        };
+
+        // Inside the cells the straights go through, and clear of the post on the inside
+        let half_wall = MAZE.wall_width / 2.0;
+        let min = Vector {
+            x: half_wall,
+            y: half_wall,
+        };
+        let max = Vector {
+            x: 3.0 * MAZE.cell_width_x,
+            y: 2.0 * MAZE.cell_width_y - half_wall,
+        };
+        assert!(corner.within_rect(min, max));
+
+        let post = Vector {
+            x: MAZE.cell_width_x,
+            y: MAZE.cell_width_y,
+        };
+        assert!((0..=10).all(|i| {
+            let p = corner.at(i as f32 / 10.0);
+            !(p.x > post.x - half_wall && p.y < post.y + half_wall)
+        }));
+    }
+}
+
+#[cfg(test)]
+mod max_curvature_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{from_cells, MotionPlanConfig, PathStyle};
+    use crate::config::{MAZE, MOTION_PLAN};
+    use crate::fast::motion_queue::{Motion, MotionQueueBuffer};
+    use crate::fast::{Orientation, Vector, DIRECTION_PI_2};
+    use crate::slow::navigate::CellPath;
+    use crate::slow::MazePosition;
+
+    // Just came into cell (0, 1) from the south
+    const START: Orientation = Orientation {
+        position: Vector {
+            x: 90.0,
+            y: 180.0 + 12.0,
+        },
+        direction: DIRECTION_PI_2,
+    };
+
+    // East, then north, then east again
+    fn cells() -> CellPath {
+        let mut cells = CellPath::new();
+        cells
+            .extend_from_slice(&[
+                MazePosition { x: 0, y: 1 },
+                MazePosition { x: 1, y: 1 },
+                MazePosition { x: 1, y: 2 },
+                MazePosition { x: 2, y: 2 },
+            ])
+            .unwrap();
+        cells
+    }
+
+    fn plan(max_curvature: f32, style: PathStyle, len: usize) -> MotionQueueBuffer {
+        let config = MotionPlanConfig {
+            max_curvature,
+            ..MOTION_PLAN
+        };
+        let mut cells = cells();
+        cells.truncate(len);
+        let (motions, _) = from_cells(&config, &MAZE, START, &cells, style);
+        motions
+    }
+
+    fn sharpest(motions: &MotionQueueBuffer) -> f32 {
+        motions
+            .iter()
+            .filter_map(|motion| match motion {
+                Motion::Path(path_motion) => Some(path_motion.max_curvature()),
+                _ => None,
+            })
+            .fold(0.0, f32::max)
+    }
+
+    fn turns(motions: &MotionQueueBuffer) -> usize {
+        motions
+            .iter()
+            .filter(|motion| match motion {
+                Motion::Turn(_) => true,
+                _ => false,
+            })
+            .count()
+    }
+
+    fn end(motions: &MotionQueueBuffer) -> Vector {
+        match motions[0] {
+            Motion::Path(path_motion) => path_motion.end(),
+            _ => panic!("Expected the last motion to be a path"),
+        }
+    }
+
+    #[test]
+    fn gentle_corners_pass() {
+        let unlimited = plan(0.0, PathStyle::CenterLine, 4);
+        assert_eq!(turns(&unlimited), 0);
+        let limit = sharpest(&unlimited) * 1.01;
+
+        assert_eq!(plan(limit, PathStyle::CenterLine, 4), unlimited);
+    }
+
+    #[test]
+    fn tight_corner_turns_in_place() {
+        // Just the first corner, so the line, turn and line all fit
+        let unlimited = plan(0.0, PathStyle::CenterLine, 2);
+        let limit = sharpest(&unlimited) * 0.99;
+        let limited = plan(limit, PathStyle::CenterLine, 2);
+
+        assert_eq!(limited.len(), 3);
+        assert_eq!(turns(&limited), 1);
+        assert!(sharpest(&limited) <= limit);
+        assert_close2(end(&limited), end(&unlimited));
+    }
+
+    #[test]
+    fn racing_line_within_limit() {
+        let unlimited = plan(0.0, PathStyle::RacingLine, 4);
+        let limit = sharpest(&unlimited) * 0.99;
+        let limited = plan(limit, PathStyle::RacingLine, 4);
+
+        assert!(sharpest(&limited) <= limit);
+    }
+}
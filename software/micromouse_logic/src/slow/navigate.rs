@@ -2,8 +2,12 @@ use core::fmt::Debug;
 
 use serde::{Deserialize, Serialize};
 
+use heapless::Vec;
+use typenum::{Unsigned, U16, U256, U3};
+
 use super::map::MoveOptions;
-use super::{MazeDirection, MazeOrientation};
+use super::maze::{open_neighbor, Maze, HEIGHT, WIDTH};
+use super::{MazeDirection, MazeOrientation, MazePosition};
 
 #[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Move {
@@ -30,29 +34,180 @@ impl Move {
     }
 }
 
+pub type CellPathSize = U256;
+
+/// A sequence of cells to go through, including the start and the end
+pub type CellPath = Vec<MazePosition, CellPathSize>;
+
+/// The number of cells in a path, including the start and the end
+pub fn path_length_cells(path: &CellPath) -> usize {
+    path.len()
+}
+
+/// How many times a path through `cells` changes direction. Turns take longer than going
+/// straight, so this is a quick way to compare paths that are the same length.
+pub fn count_turns(cells: &[MazePosition]) -> usize {
+    let step = |from: MazePosition, to: MazePosition| {
+        (to.x as i32 - from.x as i32, to.y as i32 - from.y as i32)
+    };
+
+    cells
+        .windows(3)
+        .filter(|cells| step(cells[0], cells[1]) != step(cells[1], cells[2]))
+        .count()
+}
+
+#[cfg(test)]
+mod count_turns_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{count_turns, CellPath};
+    use crate::slow::MazePosition;
+
+    fn cells(positions: &[(usize, usize)]) -> CellPath {
+        positions
+            .iter()
+            .map(|&(x, y)| MazePosition { x, y })
+            .collect()
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(count_turns(&[]), 0);
+        assert_eq!(count_turns(&cells(&[(0, 0)])), 0);
+        assert_eq!(count_turns(&cells(&[(0, 0), (0, 1)])), 0);
+    }
+
+    #[test]
+    fn straight() {
+        assert_eq!(count_turns(&cells(&[(0, 0), (0, 1), (0, 2), (0, 3)])), 0);
+    }
+
+    #[test]
+    fn l_path() {
+        assert_eq!(
+            count_turns(&cells(&[(0, 0), (0, 1), (0, 2), (1, 2), (2, 2)])),
+            1
+        );
+    }
+
+    #[test]
+    fn zigzag() {
+        assert_eq!(
+            count_turns(&cells(&[(0, 0), (0, 1), (1, 1), (1, 2), (2, 2), (2, 3)])),
+            4
+        );
+    }
+}
+
+pub(crate) const DIRECTIONS: [MazeDirection; 4] = [
+    MazeDirection::North,
+    MazeDirection::South,
+    MazeDirection::East,
+    MazeDirection::West,
+];
+
 const CENTER_LEFT: [Move; 3] = [Move::Forward, Move::Left, Move::Right];
 const CENTER_RIGHT: [Move; 3] = [Move::Forward, Move::Right, Move::Left];
 const LEFT: [Move; 3] = [Move::Left, Move::Forward, Move::Right];
 const RIGHT: [Move; 3] = [Move::Right, Move::Forward, Move::Left];
 
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct NavigateConfig {
+    /// When moves are tied, prefer going forward, then turning the same way as the last turn,
+    /// to avoid wiggling back and forth
+    pub bias_turns: bool,
+
+    /// How many of the most recent cells to remember to look for loops. If the mouse ends up in
+    /// the same cell facing the same way within this many moves, it is going in a loop, and the
+    /// turn bias is ignored to try and get out of it. 0 disables loop detection
+    pub loop_window: usize,
+
+    /// The cost of going straight through a cell when planning a full path
+    pub orthogonal_cost: f32,
+
+    /// The cost of moving one cell along a diagonal, when turning the opposite way from the
+    /// last turn. There are no diagonal motions yet, so a diagonal is driven as a string of
+    /// turns, and this should be no less than `orthogonal_cost + turn_cost`
+    pub diagonal_cost: f32,
+
+    /// The extra cost of turning into a cell that is not part of a diagonal
+    pub turn_cost: f32,
+}
+
+/// Why the navigator picked the move it did
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DecisionReason {
+    /// It was the only open way to the least visited cell
+    LowestCount,
+
+    /// Several ways were tied, and going forward was preferred
+    PreferForward,
+
+    /// Several ways were tied, and turning the same way as last time was preferred
+    PreferLastTurn,
+
+    /// Several ways were tied, and the first one for this part of the maze was picked
+    PartitionOrder,
+
+    /// There was nowhere to go but back
+    DeadEnd,
+}
+
+impl Default for DecisionReason {
+    fn default() -> Self {
+        DecisionReason::LowestCount
+    }
+}
+
+/// One move picked by the navigator, to look back on after a run
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct NavigateDecision {
+    pub cell: MazePosition,
+    pub direction: MazeDirection,
+    pub reason: DecisionReason,
+}
+
 #[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct TwelvePartitionNavigateDebug {
     cells: [[u8; 16]; 16],
     next_move: Move,
     possibilities: [Move; 3],
+    pub loop_detected: bool,
+    pub decision: NavigateDecision,
 }
 
+pub type LoopHistorySize = U16;
+pub type LoopHistory = Vec<MazeOrientation, LoopHistorySize>;
+
+pub type DecisionLogSize = U16;
+
+/// The most recent decisions, oldest first
+pub type DecisionLog = Vec<NavigateDecision, DecisionLogSize>;
+
 pub struct TwelvePartitionNavigate {
     cells: [[u8; 16]; 16],
+    last_turn_direction: Option<Move>,
+    history: LoopHistory,
+    decisions: DecisionLog,
 }
 
 impl TwelvePartitionNavigate {
     pub fn new() -> TwelvePartitionNavigate {
         TwelvePartitionNavigate {
             cells: [[0; 16]; 16],
+            last_turn_direction: None,
+            history: Vec::new(),
+            decisions: Vec::new(),
         }
     }
 
+    /// The most recent decisions, oldest first
+    pub fn decisions(&self) -> &DecisionLog {
+        &self.decisions
+    }
+
     fn get_cell(&self, x: i32, y: i32) -> u8 {
         if x >= 0 && x <= 15 && y >= 0 && y <= 15 {
             self.cells[x as usize][y as usize]
@@ -63,6 +218,7 @@ impl TwelvePartitionNavigate {
 
     pub fn navigate(
         &mut self,
+        config: &NavigateConfig,
         orientation: MazeOrientation,
         move_options: MoveOptions,
     ) -> (MazeDirection, TwelvePartitionNavigateDebug) {
@@ -87,6 +243,54 @@ impl TwelvePartitionNavigate {
             self.cells[ux][uy] += 1;
         }
 
+        let loop_detected = self.remember(config, orientation);
+
+        let (next_move, possibilities, reason) =
+            self.best_move(config, orientation, move_options, loop_detected);
+
+        if next_move == Move::Left || next_move == Move::Right {
+            self.last_turn_direction = Some(next_move);
+        }
+
+        let direction = next_move.to_direction(orientation.direction);
+
+        let decision = NavigateDecision {
+            cell: orientation.position,
+            direction,
+            reason,
+        };
+
+        // Forget the oldest decision to make room
+        if self.decisions.len() >= DecisionLogSize::to_usize() {
+            self.decisions.rotate_left(1);
+            self.decisions.pop();
+        }
+        self.decisions.push(decision).ok();
+
+        (
+            direction,
+            TwelvePartitionNavigateDebug {
+                cells: self.cells,
+                next_move,
+                possibilities,
+                loop_detected,
+                decision,
+            },
+        )
+    }
+
+    /// Pick the next move from the counts of the cells around `orientation`, without changing
+    /// any state
+    fn best_move(
+        &self,
+        config: &NavigateConfig,
+        orientation: MazeOrientation,
+        move_options: MoveOptions,
+        loop_detected: bool,
+    ) -> (Move, [Move; 3], DecisionReason) {
+        let x = orientation.position.x as i32;
+        let y = orientation.position.y as i32;
+
         // win condition
         //if x >= 7 && x <= 8 && y >= 7 && y <= 8 {
         //[Some(Move::TurnLeft), Some(Move::TurnLeft)]
@@ -229,8 +433,6 @@ impl TwelvePartitionNavigate {
             (_, _) => panic!("Invalid location!"),
         };
 
-        let mut next_move = Move::Backward;
-
         // filter by walls
         let possibilities_iter = possibilities.iter().filter(|&moves| match moves {
             &Move::Forward => move_options.front,
@@ -248,30 +450,1393 @@ impl TwelvePartitionNavigate {
         .min()
         .unwrap();
 
-        for &possible_move in possibilities_iter {
-            let value = match possible_move {
-                Move::Forward => front_cell,
-                Move::Left => left_cell,
-                Move::Right => right_cell,
-                _ => 0,
-            };
+        let value = |possible_move: Move| match possible_move {
+            Move::Forward => front_cell,
+            Move::Left => left_cell,
+            Move::Right => right_cell,
+            _ => 0,
+        };
+
+        let mut tied =
+            possibilities_iter.filter(|&&possible_move| value(possible_move) == min);
+
+        let tied_count = tied.clone().count();
+
+        let is_tied = |possible_move: Move| {
+            tied.clone().any(|&tied_move| tied_move == possible_move)
+        };
 
-            if value == min {
-                next_move = possible_move;
+        let bias_turns = config.bias_turns && !loop_detected;
+
+        let next_move = if bias_turns && is_tied(Move::Forward) {
+            Move::Forward
+        } else if let Some(last_turn) = self
+            .last_turn_direction
+            .filter(|&last_turn| bias_turns && is_tied(last_turn))
+        {
+            last_turn
+        } else {
+            tied.next().cloned().unwrap_or(Move::Backward)
+        };
+
+        let reason = if next_move == Move::Backward {
+            DecisionReason::DeadEnd
+        } else if tied_count <= 1 {
+            DecisionReason::LowestCount
+        } else if bias_turns && next_move == Move::Forward {
+            DecisionReason::PreferForward
+        } else if bias_turns && self.last_turn_direction == Some(next_move) {
+            DecisionReason::PreferLastTurn
+        } else {
+            DecisionReason::PartitionOrder
+        };
+
+        (next_move, possibilities, reason)
+    }
+
+    /// Add the orientation to the history, and check if it was already there
+    fn remember(
+        &mut self,
+        config: &NavigateConfig,
+        orientation: MazeOrientation,
+    ) -> bool {
+        let window = config.loop_window.min(LoopHistorySize::to_usize());
+
+        if window == 0 {
+            self.history.clear();
+            return false;
+        }
+
+        let loop_detected = self.seen(config, orientation);
+
+        // Forget the oldest orientations to make room
+        while self.history.len() >= window {
+            self.history.rotate_left(1);
+            self.history.pop();
+        }
+
+        self.history.push(orientation).ok();
+
+        loop_detected
+    }
+
+    /// Whether the orientation is in the history, without adding it
+    fn seen(&self, config: &NavigateConfig, orientation: MazeOrientation) -> bool {
+        config.loop_window > 0 && self.history.contains(&orientation)
+    }
+}
+
+/// The cost to get to the goal from each cell, for each way of getting into that cell
+type Costs = [[[[f32; 3]; 4]; HEIGHT]; WIDTH];
+
+/// The fastest sequence of cells from `from` to `goal`, found by flood filling the maze from
+/// the goal and weighing each move by the costs in a [NavigateConfig]. Unknown walls are
+/// assumed to be open. The flood fill can be done a little at a time.
+#[derive(Clone)]
+pub struct FullPathPlan {
+    from: MazePosition,
+    goal: MazePosition,
+    costs: Costs,
+    done: bool,
+}
+
+impl FullPathPlan {
+    pub fn new(from: MazePosition, goal: MazePosition) -> FullPathPlan {
+        let mut plan = FullPathPlan {
+            from,
+            goal,
+            costs: [[[[f32::INFINITY; 3]; 4]; HEIGHT]; WIDTH],
+            done: false,
+        };
+        plan.restart(from, goal);
+        plan
+    }
+
+    /// Start over planning from `from` to `goal`, reusing the costs table in place
+    pub fn restart(&mut self, from: MazePosition, goal: MazePosition) {
+        for column in self.costs.iter_mut() {
+            for cell in column.iter_mut() {
+                *cell = [[f32::INFINITY; 3]; 4];
+            }
+        }
+        self.costs[goal.x][goal.y] = [[0.0; 3]; 4];
+
+        self.from = from;
+        self.goal = goal;
+        self.done = false;
+    }
+
+    /// Whether the costs are final and the path can be found
+    pub fn done(&self) -> bool {
+        self.done
+    }
+
+    /// The best move out of `position` after arriving facing `heading` with `last_move`
+    fn best_move(
+        &self,
+        config: &NavigateConfig,
+        maze: &Maze,
+        position: MazePosition,
+        heading: Option<(MazeDirection, Move)>,
+    ) -> Option<(f32, MazeDirection, Move, MazePosition)> {
+        DIRECTIONS
+            .iter()
+            .filter_map(|&direction| {
+                let neighbor = open_neighbor(maze, position, direction)?;
+                let (cost, next_move) = match heading {
+                    Some((heading, last_move)) => {
+                        move_cost(config, heading, last_move, direction)?
+                    }
+                    None => (config.orthogonal_cost, Move::Forward),
+                };
+                let total = cost
+                    + self.costs[neighbor.x][neighbor.y][direction_index(direction)]
+                        [move_index(next_move)];
+                Some((total, direction, next_move, neighbor))
+            })
+            .fold(
+                None,
+                |best: Option<(f32, MazeDirection, Move, MazePosition)>, next| match best
+                {
+                    Some(best) if best.0 <= next.0 => Some(best),
+                    _ => Some(next),
+                },
+            )
+    }
+
+    /// Keep relaxing the costs until nothing changes, going over the whole maze at most
+    /// `max_sweeps` times. Returns whether it is done.
+    pub fn relax(
+        &mut self,
+        config: &NavigateConfig,
+        maze: &Maze,
+        max_sweeps: usize,
+    ) -> bool {
+        for _ in 0..max_sweeps {
+            if self.done {
                 break;
             }
+
+            let mut changed = false;
+            for x in 0..WIDTH {
+                for y in 0..HEIGHT {
+                    let position = MazePosition { x, y };
+                    if position == self.goal {
+                        continue;
+                    }
+
+                    for &heading in DIRECTIONS.iter() {
+                        for &last_move in MOVES.iter() {
+                            let best = self.best_move(
+                                config,
+                                maze,
+                                position,
+                                Some((heading, last_move)),
+                            );
+                            let cost = &mut self.costs[x][y][direction_index(heading)]
+                                [move_index(last_move)];
+
+                            if let Some((total, _, _, _)) = best {
+                                if total < *cost {
+                                    *cost = total;
+                                    changed = true;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            self.done = !changed;
         }
 
-        let direction = next_move.to_direction(orientation.direction);
+        self.done
+    }
 
-        (
-            direction,
-            TwelvePartitionNavigateDebug {
-                cells: self.cells,
-                next_move,
-                possibilities,
+    /// The fastest path, once [FullPathPlan::relax] is done. None if it isn't done yet, or the
+    /// goal can not be reached.
+    pub fn path(&self, config: &NavigateConfig, maze: &Maze) -> Option<CellPath> {
+        if !self.done {
+            return None;
+        }
+
+        // Walk downhill from the start to the goal. The mouse can start facing any way.
+        let mut path = Vec::new();
+        let mut current = self.from;
+        let mut heading = None;
+        path.push(current).ok()?;
+
+        while current != self.goal {
+            let (total, direction, next_move, neighbor) =
+                self.best_move(config, maze, current, heading)?;
+
+            if !total.is_finite() {
+                return None;
+            }
+
+            current = neighbor;
+            heading = Some((direction, next_move));
+            path.push(current).ok()?;
+        }
+
+        Some(path)
+    }
+}
+
+const MOVES: [Move; 3] = [Move::Forward, Move::Left, Move::Right];
+
+/// The moves that are tied for the best, in order of preference
+pub type NavigateChoices = Vec<Move, U3>;
+
+/// Something that can pick where to go next while exploring, so navigators can be combined
+pub trait Navigate {
+    /// The moves out of `orientation` tied for the best, in order of preference. Only moves
+    /// open in `move_options` are picked. Empty if none of them are any good.
+    fn choices(
+        &mut self,
+        config: &NavigateConfig,
+        maze: &Maze,
+        orientation: MazeOrientation,
+        move_options: MoveOptions,
+    ) -> NavigateChoices;
+}
+
+/// Whether `m` is open in `move_options`
+fn move_open(move_options: MoveOptions, m: Move) -> bool {
+    match m {
+        Move::Forward => move_options.front,
+        Move::Left => move_options.left,
+        Move::Right => move_options.right,
+        Move::Backward => false,
+    }
+}
+
+impl Navigate for TwelvePartitionNavigate {
+    fn choices(
+        &mut self,
+        config: &NavigateConfig,
+        _maze: &Maze,
+        orientation: MazeOrientation,
+        move_options: MoveOptions,
+    ) -> NavigateChoices {
+        let loop_detected = self.seen(config, orientation);
+        let (next_move, _, _) =
+            self.best_move(config, orientation, move_options, loop_detected);
+
+        let mut choices = Vec::new();
+        if next_move != Move::Backward {
+            choices.push(next_move).ok();
+        }
+        choices
+    }
+}
+
+/// Goes towards `goal` along the shortest path through the maze known so far, counting cells.
+/// Unknown walls are assumed to be open.
+pub struct FloodFillNavigate {
+    pub goal: MazePosition,
+}
+
+impl FloodFillNavigate {
+    /// How many cells away from the goal each cell is, or None if it can't be reached
+    pub fn distances(&self, maze: &Maze) -> [[Option<u16>; HEIGHT]; WIDTH] {
+        let mut distances = [[None; HEIGHT]; WIDTH];
+        let mut queue: Vec<MazePosition, U256> = Vec::new();
+
+        distances[self.goal.x][self.goal.y] = Some(0);
+        queue.push(self.goal).ok();
+
+        // Breadth first, so each cell is reached the shortest way first
+        let mut next = 0;
+        while next < queue.len() {
+            let position = queue[next];
+            next += 1;
+
+            let distance = distances[position.x][position.y].unwrap_or(0);
+            for &direction in DIRECTIONS.iter() {
+                if let Some(neighbor) = open_neighbor(maze, position, direction) {
+                    if distances[neighbor.x][neighbor.y].is_none() {
+                        distances[neighbor.x][neighbor.y] = Some(distance + 1);
+                        queue.push(neighbor).ok();
+                    }
+                }
+            }
+        }
+
+        distances
+    }
+}
+
+impl Navigate for FloodFillNavigate {
+    fn choices(
+        &mut self,
+        _config: &NavigateConfig,
+        maze: &Maze,
+        orientation: MazeOrientation,
+        move_options: MoveOptions,
+    ) -> NavigateChoices {
+        let distances = self.distances(maze);
+
+        let distance = |m: Move| {
+            if !move_open(move_options, m) {
+                return None;
+            }
+
+            let neighbor = open_neighbor(
+                maze,
+                orientation.position,
+                m.to_direction(orientation.direction),
+            )?;
+            distances[neighbor.x][neighbor.y]
+        };
+
+        let best = MOVES.iter().filter_map(|&m| distance(m)).min();
+
+        MOVES
+            .iter()
+            .copied()
+            .filter(|&m| best.is_some() && distance(m) == best)
+            .collect()
+    }
+}
+
+/// When a [CompositeNavigate] asks its fallback navigator
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CompositePolicy {
+    /// When the primary navigator has more than one choice, let the fallback pick between them
+    FallbackOnTie,
+
+    /// Only when the primary navigator has no choices at all
+    FallbackOnNone,
+}
+
+/// Uses the choice of the `primary` navigator, and the `fallback` navigator when the `policy`
+/// says to
+pub struct CompositeNavigate<P: Navigate, F: Navigate> {
+    pub primary: P,
+    pub fallback: F,
+    pub policy: CompositePolicy,
+
+    /// Whether the fallback made the last choice
+    pub used_fallback: bool,
+}
+
+impl<P: Navigate, F: Navigate> CompositeNavigate<P, F> {
+    pub fn new(
+        primary: P,
+        fallback: F,
+        policy: CompositePolicy,
+    ) -> CompositeNavigate<P, F> {
+        CompositeNavigate {
+            primary,
+            fallback,
+            policy,
+            used_fallback: false,
+        }
+    }
+}
+
+impl<P: Navigate, F: Navigate> Navigate for CompositeNavigate<P, F> {
+    fn choices(
+        &mut self,
+        config: &NavigateConfig,
+        maze: &Maze,
+        orientation: MazeOrientation,
+        move_options: MoveOptions,
+    ) -> NavigateChoices {
+        let choices = self
+            .primary
+            .choices(config, maze, orientation, move_options);
+
+        let ask_fallback = match self.policy {
+            CompositePolicy::FallbackOnTie => choices.len() > 1,
+            CompositePolicy::FallbackOnNone => choices.is_empty(),
+        };
+
+        self.used_fallback = ask_fallback;
+
+        if !ask_fallback {
+            return choices;
+        }
+
+        // Only let the fallback pick from the primary's choices, if it had any
+        let move_options = if choices.is_empty() {
+            move_options
+        } else {
+            MoveOptions {
+                left: choices.contains(&Move::Left),
+                front: choices.contains(&Move::Forward),
+                right: choices.contains(&Move::Right),
+            }
+        };
+
+        self.fallback
+            .choices(config, maze, orientation, move_options)
+    }
+}
+
+/// The index of `direction` in [DIRECTIONS]
+fn direction_index(direction: MazeDirection) -> usize {
+    match direction {
+        MazeDirection::North => 0,
+        MazeDirection::South => 1,
+        MazeDirection::East => 2,
+        MazeDirection::West => 3,
+    }
+}
+
+/// The index of `m` in [MOVES]
+fn move_index(m: Move) -> usize {
+    match m {
+        Move::Forward => 0,
+        Move::Left => 1,
+        Move::Right => 2,
+        Move::Backward => 3,
+    }
+}
+
+/// The cost of moving one cell in `direction`, after getting into the current cell facing
+/// `heading` with `last_move`, and the move that it takes. Turning the opposite way from the
+/// last turn is one step of a diagonal. Going backwards is never worth it, so it returns None.
+fn move_cost(
+    config: &NavigateConfig,
+    heading: MazeDirection,
+    last_move: Move,
+    direction: MazeDirection,
+) -> Option<(f32, Move)> {
+    let next_move = MazeOrientation {
+        position: MazePosition::default(),
+        direction: heading,
+    }
+    .turn_to(direction);
+
+    let cost = match (last_move, next_move) {
+        (_, Move::Backward) => return None,
+        (_, Move::Forward) => config.orthogonal_cost,
+        (Move::Right, Move::Left) | (Move::Left, Move::Right) => config.diagonal_cost,
+        (_, Move::Left) | (_, Move::Right) => config.orthogonal_cost + config.turn_cost,
+    };
+
+    Some((cost, next_move))
+}
+
+#[cfg(test)]
+mod bias_turns_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use heapless::Vec;
+    use typenum::U64;
+
+    use super::{Move, NavigateConfig, TwelvePartitionNavigate};
+    use crate::slow::map::MoveOptions;
+    use crate::slow::{MazeDirection, MazeOrientation, MazePosition};
+
+    const BIAS: NavigateConfig = NavigateConfig {
+        bias_turns: true,
+        loop_window: 0,
+        orthogonal_cost: 1.0,
+        diagonal_cost: 1.0,
+        turn_cost: 0.0,
+    };
+    const NO_BIAS: NavigateConfig = NavigateConfig {
+        bias_turns: false,
+        loop_window: 0,
+        orthogonal_cost: 1.0,
+        diagonal_cost: 1.0,
+        turn_cost: 0.0,
+    };
+
+    // In this part of the maze facing south, the partition prefers turning left first
+    const ORIENTATION: MazeOrientation = MazeOrientation {
+        position: MazePosition { x: 2, y: 2 },
+        direction: MazeDirection::South,
+    };
+
+    const ALL_OPEN: MoveOptions = MoveOptions {
+        left: true,
+        front: true,
+        right: true,
+    };
+
+    const FRONT_CLOSED: MoveOptions = MoveOptions {
+        left: true,
+        front: false,
+        right: true,
+    };
+
+    #[test]
+    fn no_bias_turns_on_tie() {
+        let mut navigate = TwelvePartitionNavigate::new();
+        let (direction, _) = navigate.navigate(&NO_BIAS, ORIENTATION, ALL_OPEN);
+        assert_eq!(direction, MazeDirection::East);
+    }
+
+    #[test]
+    fn bias_goes_forward_on_tie() {
+        let mut navigate = TwelvePartitionNavigate::new();
+        let (direction, _) = navigate.navigate(&BIAS, ORIENTATION, ALL_OPEN);
+        assert_eq!(direction, MazeDirection::South);
+    }
+
+    #[test]
+    fn no_bias_turns_opposite_last_turn() {
+        let mut navigate = TwelvePartitionNavigate::new();
+        navigate.last_turn_direction = Some(Move::Right);
+        let (direction, _) = navigate.navigate(&NO_BIAS, ORIENTATION, FRONT_CLOSED);
+        assert_eq!(direction, MazeDirection::East);
+    }
+
+    #[test]
+    fn bias_turns_same_as_last_turn() {
+        let mut navigate = TwelvePartitionNavigate::new();
+        navigate.last_turn_direction = Some(Move::Right);
+        let (direction, _) = navigate.navigate(&BIAS, ORIENTATION, FRONT_CLOSED);
+        assert_eq!(direction, MazeDirection::West);
+    }
+
+    #[test]
+    fn bias_remembers_last_turn() {
+        let mut navigate = TwelvePartitionNavigate::new();
+
+        // Facing north, so a right turn is east
+        navigate.navigate(
+            &BIAS,
+            MazeOrientation {
+                position: MazePosition { x: 2, y: 2 },
+                direction: MazeDirection::North,
+            },
+            MoveOptions {
+                left: false,
+                front: false,
+                right: true,
             },
+        );
+
+        assert_eq!(navigate.last_turn_direction, Some(Move::Right));
+    }
+
+    /// The moves made wandering around an empty `size` by `size` room in the corner of the
+    /// maze for `steps` cells, starting from [ORIENTATION]
+    fn wander(config: &NavigateConfig, size: i32, steps: usize) -> Vec<Move, U64> {
+        let mut navigate = TwelvePartitionNavigate::new();
+        let mut orientation = ORIENTATION;
+        let mut moves = Vec::new();
+
+        let step = |position: MazePosition, direction: MazeDirection| {
+            let (x, y) = (position.x as i32, position.y as i32);
+            let (x, y) = match direction {
+                MazeDirection::North => (x, y + 1),
+                MazeDirection::South => (x, y - 1),
+                MazeDirection::East => (x + 1, y),
+                MazeDirection::West => (x - 1, y),
+            };
+
+            if x >= 0 && x < size && y >= 0 && y < size {
+                Some(MazePosition {
+                    x: x as usize,
+                    y: y as usize,
+                })
+            } else {
+                None
+            }
+        };
+
+        for _ in 0..steps {
+            let open = |m: Move| {
+                step(orientation.position, m.to_direction(orientation.direction))
+                    .is_some()
+            };
+            let move_options = MoveOptions {
+                left: open(Move::Left),
+                front: open(Move::Forward),
+                right: open(Move::Right),
+            };
+
+            let (direction, debug) = navigate.navigate(config, orientation, move_options);
+            moves.push(debug.next_move).unwrap();
+
+            orientation = MazeOrientation {
+                position: step(orientation.position, direction).unwrap(),
+                direction,
+            };
+        }
+
+        moves
+    }
+
+    /// How many times the route changes direction
+    fn turns(moves: &[Move]) -> usize {
+        moves.iter().filter(|&&m| m != Move::Forward).count()
+    }
+
+    #[test]
+    fn bias_wanders_with_fewer_turns() {
+        let biased = wander(&BIAS, 4, 40);
+        let unbiased = wander(&NO_BIAS, 4, 40);
+
+        assert!(
+            turns(&biased) < turns(&unbiased),
+            "biased {:?} unbiased {:?}",
+            biased,
+            unbiased
+        );
+    }
+
+    #[test]
+    fn bias_does_not_wiggle() {
+        // A left then a right, or a right then a left, one cell apart
+        let wiggles = |moves: &[Move]| {
+            moves
+                .windows(2)
+                .filter(|pair| match pair {
+                    [Move::Left, Move::Right] | [Move::Right, Move::Left] => true,
+                    _ => false,
+                })
+                .count()
+        };
+
+        let biased = wander(&BIAS, 4, 40);
+        let unbiased = wander(&NO_BIAS, 4, 40);
+
+        assert!(
+            wiggles(&biased) < wiggles(&unbiased),
+            "biased {:?} unbiased {:?}",
+            biased,
+            unbiased
+        );
+    }
+}
+
+#[cfg(test)]
+mod decision_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{
+        DecisionLogSize, DecisionReason, Move, Navigate, NavigateConfig,
+        TwelvePartitionNavigate,
+    };
+    use crate::config::NAVIGATE;
+    use crate::slow::map::MoveOptions;
+    use crate::slow::maze::{Maze, Wall};
+    use crate::slow::{MazeDirection, MazeOrientation, MazePosition};
+    use typenum::Unsigned;
+
+    const BIAS: NavigateConfig = NavigateConfig {
+        bias_turns: true,
+        loop_window: 0,
+        ..NAVIGATE
+    };
+
+    const NO_BIAS: NavigateConfig = NavigateConfig {
+        bias_turns: false,
+        ..BIAS
+    };
+
+    // In this part of the maze facing south, the partition prefers turning left first
+    const ORIENTATION: MazeOrientation = MazeOrientation {
+        position: MazePosition { x: 2, y: 2 },
+        direction: MazeDirection::South,
+    };
+
+    fn options(left: bool, front: bool, right: bool) -> MoveOptions {
+        MoveOptions { left, front, right }
+    }
+
+    fn reason(
+        navigate: &mut TwelvePartitionNavigate,
+        config: &NavigateConfig,
+        move_options: MoveOptions,
+    ) -> DecisionReason {
+        let (direction, debug) = navigate.navigate(config, ORIENTATION, move_options);
+        assert_eq!(debug.decision.cell, ORIENTATION.position);
+        assert_eq!(debug.decision.direction, direction);
+        assert_eq!(navigate.decisions().last(), Some(&debug.decision));
+        debug.decision.reason
+    }
+
+    #[test]
+    fn prefer_forward() {
+        let mut navigate = TwelvePartitionNavigate::new();
+        assert_eq!(
+            reason(&mut navigate, &BIAS, options(true, true, true)),
+            DecisionReason::PreferForward
+        );
+    }
+
+    #[test]
+    fn prefer_last_turn() {
+        let mut navigate = TwelvePartitionNavigate::new();
+        navigate.last_turn_direction = Some(Move::Right);
+        assert_eq!(
+            reason(&mut navigate, &BIAS, options(true, false, true)),
+            DecisionReason::PreferLastTurn
+        );
+    }
+
+    #[test]
+    fn partition_order() {
+        let mut navigate = TwelvePartitionNavigate::new();
+        assert_eq!(
+            reason(&mut navigate, &NO_BIAS, options(true, true, true)),
+            DecisionReason::PartitionOrder
+        );
+    }
+
+    #[test]
+    fn lowest_count() {
+        let mut navigate = TwelvePartitionNavigate::new();
+        assert_eq!(
+            reason(&mut navigate, &BIAS, options(false, false, true)),
+            DecisionReason::LowestCount
+        );
+    }
+
+    #[test]
+    fn dead_end() {
+        let mut navigate = TwelvePartitionNavigate::new();
+        assert_eq!(
+            reason(&mut navigate, &BIAS, options(false, false, false)),
+            DecisionReason::DeadEnd
+        );
+    }
+
+    #[test]
+    fn choices_change_nothing() {
+        let config = NavigateConfig {
+            loop_window: 4,
+            ..BIAS
+        };
+        let maze = Maze::new(Wall::Unknown);
+        let move_options = options(true, false, true);
+
+        let mut navigate = TwelvePartitionNavigate::new();
+        let first = navigate.choices(&config, &maze, ORIENTATION, move_options);
+        let second = navigate.choices(&config, &maze, ORIENTATION, move_options);
+        assert_eq!(first, second);
+
+        assert_eq!(navigate.cells, [[0; 16]; 16]);
+        assert_eq!(navigate.last_turn_direction, None);
+        assert!(navigate.history.is_empty());
+        assert!(navigate.decisions().is_empty());
+
+        // Navigating afterwards is the same as navigating from scratch
+        let (_, debug) = navigate.navigate(&config, ORIENTATION, move_options);
+        assert_eq!(&first[..], &[debug.next_move]);
+        assert!(!debug.loop_detected);
+    }
+
+    #[test]
+    fn log_keeps_the_latest() {
+        let mut navigate = TwelvePartitionNavigate::new();
+        reason(&mut navigate, &BIAS, options(true, true, true));
+        reason(&mut navigate, &BIAS, options(false, false, true));
+
+        assert_eq!(navigate.decisions().len(), 2);
+        assert_eq!(
+            navigate.decisions()[0].reason,
+            DecisionReason::PreferForward
+        );
+        assert_eq!(navigate.decisions()[1].reason, DecisionReason::LowestCount);
+
+        for _ in 0..DecisionLogSize::to_usize() {
+            reason(&mut navigate, &BIAS, options(false, false, false));
+        }
+
+        assert_eq!(navigate.decisions().len(), DecisionLogSize::to_usize());
+        assert!(navigate
+            .decisions()
+            .iter()
+            .all(|d| d.reason == DecisionReason::DeadEnd));
+    }
+}
+
+#[cfg(test)]
+mod composite_navigate_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{
+        CompositeNavigate, CompositePolicy, FloodFillNavigate, Move, Navigate,
+        TwelvePartitionNavigate,
+    };
+    use crate::config::NAVIGATE;
+    use crate::slow::map::MoveOptions;
+    use crate::slow::maze::{Maze, Wall, WallIndex};
+    use crate::slow::{MazeDirection, MazeOrientation, MazePosition};
+
+    const ALL_OPEN: MoveOptions = MoveOptions {
+        left: true,
+        front: true,
+        right: true,
+    };
+
+    // In the corner of the maze facing north, the goal is straight ahead
+    const FROM: MazeOrientation = MazeOrientation {
+        position: MazePosition { x: 0, y: 2 },
+        direction: MazeDirection::North,
+    };
+
+    fn composite(
+        goal: MazePosition,
+        policy: CompositePolicy,
+    ) -> CompositeNavigate<FloodFillNavigate, TwelvePartitionNavigate> {
+        CompositeNavigate::new(
+            FloodFillNavigate { goal },
+            TwelvePartitionNavigate::new(),
+            policy,
         )
-        //}
+    }
+
+    #[test]
+    fn flood_fill_goes_towards_goal() {
+        let mut navigate = FloodFillNavigate {
+            goal: MazePosition { x: 0, y: 5 },
+        };
+        let maze = Maze::new(Wall::Open);
+
+        let choices = navigate.choices(&NAVIGATE, &maze, FROM, ALL_OPEN);
+        assert_eq!(&choices[..], &[Move::Forward]);
+    }
+
+    #[test]
+    fn flood_fill_ties() {
+        // Diagonally ahead and to the right, so forward and right are just as far
+        let mut navigate = FloodFillNavigate {
+            goal: MazePosition { x: 3, y: 5 },
+        };
+        let maze = Maze::new(Wall::Open);
+
+        let choices = navigate.choices(&NAVIGATE, &maze, FROM, ALL_OPEN);
+        assert_eq!(&choices[..], &[Move::Forward, Move::Right]);
+    }
+
+    #[test]
+    fn flood_fill_goes_around_walls() {
+        let mut navigate = FloodFillNavigate {
+            goal: MazePosition { x: 0, y: 5 },
+        };
+
+        // Close off going north from (0, 2)
+        let mut maze = Maze::new(Wall::Open);
+        maze.set_wall(
+            WallIndex::between(FROM.position, MazePosition { x: 0, y: 3 }).unwrap(),
+            Wall::Closed,
+        );
+
+        let choices = navigate.choices(&NAVIGATE, &maze, FROM, ALL_OPEN);
+        assert_eq!(&choices[..], &[Move::Right]);
+    }
+
+    #[test]
+    fn uses_primary_when_unambiguous() {
+        let mut navigate =
+            composite(MazePosition { x: 0, y: 5 }, CompositePolicy::FallbackOnTie);
+        let maze = Maze::new(Wall::Open);
+
+        let choices = navigate.choices(&NAVIGATE, &maze, FROM, ALL_OPEN);
+        assert_eq!(&choices[..], &[Move::Forward]);
+        assert!(!navigate.used_fallback);
+    }
+
+    #[test]
+    fn uses_fallback_on_tie() {
+        let mut navigate =
+            composite(MazePosition { x: 3, y: 5 }, CompositePolicy::FallbackOnTie);
+        let maze = Maze::new(Wall::Open);
+
+        let choices = navigate.choices(&NAVIGATE, &maze, FROM, ALL_OPEN);
+        assert!(navigate.used_fallback);
+
+        // The partition navigator picks one of the tied moves
+        let expected = TwelvePartitionNavigate::new().choices(
+            &NAVIGATE,
+            &maze,
+            FROM,
+            MoveOptions {
+                left: false,
+                front: true,
+                right: true,
+            },
+        );
+        assert_eq!(choices.len(), 1);
+        assert_eq!(choices, expected);
+    }
+
+    #[test]
+    fn fallback_on_none_keeps_ties() {
+        let mut navigate =
+            composite(MazePosition { x: 3, y: 5 }, CompositePolicy::FallbackOnNone);
+        let maze = Maze::new(Wall::Open);
+
+        let choices = navigate.choices(&NAVIGATE, &maze, FROM, ALL_OPEN);
+        assert_eq!(&choices[..], &[Move::Forward, Move::Right]);
+        assert!(!navigate.used_fallback);
+    }
+
+    #[test]
+    fn fallback_on_none_when_stuck() {
+        let mut navigate =
+            composite(MazePosition { x: 0, y: 5 }, CompositePolicy::FallbackOnNone);
+
+        // The goal can't be reached at all
+        let mut maze = Maze::new(Wall::Open);
+        for &direction in [MazeDirection::North, MazeDirection::East].iter() {
+            maze.set_wall(
+                WallIndex::from_maze_orientation(MazeOrientation {
+                    position: FROM.position,
+                    direction,
+                }),
+                Wall::Closed,
+            );
+        }
+        maze.set_wall(
+            WallIndex::from_maze_orientation(MazeOrientation {
+                position: MazePosition { x: 0, y: 1 },
+                direction: MazeDirection::East,
+            }),
+            Wall::Closed,
+        );
+        maze.set_wall(
+            WallIndex::from_maze_orientation(MazeOrientation {
+                position: MazePosition { x: 0, y: 0 },
+                direction: MazeDirection::East,
+            }),
+            Wall::Closed,
+        );
+
+        let choices = navigate.choices(
+            &NAVIGATE,
+            &maze,
+            FROM,
+            MoveOptions {
+                left: false,
+                front: false,
+                right: false,
+            },
+        );
+        assert!(navigate.used_fallback);
+        assert!(choices.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod plan_full_path_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{path_length_cells, CellPath, NavigateConfig};
+    use crate::config::NAVIGATE;
+    use crate::slow::map::Map;
+    use crate::slow::maze::{Maze, Wall, HEIGHT, WIDTH};
+    use crate::slow::MazePosition;
+
+    const DISTANCE_ONLY: NavigateConfig = NavigateConfig {
+        orthogonal_cost: 1.0,
+        diagonal_cost: 1.0,
+        turn_cost: 0.0,
+        ..NAVIGATE
+    };
+
+    const FAST_DIAGONALS: NavigateConfig = NavigateConfig {
+        orthogonal_cost: 1.0,
+        diagonal_cost: 0.5,
+        turn_cost: 0.2,
+        ..NAVIGATE
+    };
+
+    const SLOW_TURNS: NavigateConfig = NavigateConfig {
+        orthogonal_cost: 1.0,
+        diagonal_cost: 5.0,
+        turn_cost: 5.0,
+        ..NAVIGATE
+    };
+
+    /// How many times the path changes direction
+    fn turns(path: &CellPath) -> usize {
+        path.windows(3)
+            .filter(|cells| {
+                let (a, b, c) = (cells[0], cells[1], cells[2]);
+                (b.x as i32 - a.x as i32, b.y as i32 - a.y as i32)
+                    != (c.x as i32 - b.x as i32, c.y as i32 - b.y as i32)
+            })
+            .count()
+    }
+
+    #[test]
+    fn open_maze() {
+        let maze = Maze::new(Wall::Open);
+
+        let path = Map::from_maze(maze)
+            .plan_full_path(
+                &NAVIGATE,
+                MazePosition { x: 0, y: 0 },
+                MazePosition { x: 7, y: 7 },
+            )
+            .unwrap();
+
+        assert_eq!(path_length_cells(&path), 15);
+        assert_eq!(path[0], MazePosition { x: 0, y: 0 });
+        assert_eq!(path[14], MazePosition { x: 7, y: 7 });
+    }
+
+    #[test]
+    fn corridor() {
+        let mut horizontal_walls = [[Wall::Closed; HEIGHT - 1]; WIDTH];
+        let mut vertical_walls = [[Wall::Closed; HEIGHT]; WIDTH - 1];
+
+        // Go east along the bottom row to x = 3, then north to y = 2
+        vertical_walls[0][0] = Wall::Open;
+        vertical_walls[1][0] = Wall::Open;
+        vertical_walls[2][0] = Wall::Open;
+        horizontal_walls[3][0] = Wall::Open;
+        horizontal_walls[3][1] = Wall::Open;
+
+        let maze = Maze::from_walls(horizontal_walls, vertical_walls);
+
+        let path = Map::from_maze(maze)
+            .plan_full_path(
+                &NAVIGATE,
+                MazePosition { x: 0, y: 0 },
+                MazePosition { x: 3, y: 2 },
+            )
+            .unwrap();
+
+        assert_eq!(path_length_cells(&path), 6);
+        assert_eq!(path[3], MazePosition { x: 3, y: 0 });
+    }
+
+    #[test]
+    fn leaves_a_plan_in_progress_alone() {
+        let from = MazePosition { x: 0, y: 0 };
+        let goal = MazePosition { x: 7, y: 7 };
+
+        let mut map = Map::from_maze(Maze::new(Wall::Open));
+        map.start_full_path(from, goal);
+        assert!(!map.relax_full_path(&NAVIGATE, 1));
+
+        // Somewhere else entirely
+        map.plan_full_path(&NAVIGATE, MazePosition { x: 15, y: 15 }, from)
+            .unwrap();
+
+        while !map.relax_full_path(&NAVIGATE, 1) {}
+        assert_eq!(
+            map.full_path(&NAVIGATE),
+            map.plan_full_path(&NAVIGATE, from, goal)
+        );
+    }
+
+    #[test]
+    fn unreachable() {
+        let maze = Maze::new(Wall::Closed);
+
+        assert_eq!(
+            Map::from_maze(maze).plan_full_path(
+                &NAVIGATE,
+                MazePosition { x: 0, y: 0 },
+                MazePosition { x: 7, y: 7 },
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn slow_turns_go_around_the_corner_once() {
+        let path = Map::from_maze(Maze::new(Wall::Open))
+            .plan_full_path(
+                &SLOW_TURNS,
+                MazePosition { x: 0, y: 0 },
+                MazePosition { x: 3, y: 3 },
+            )
+            .unwrap();
+
+        assert_eq!(path_length_cells(&path), 7);
+        assert_eq!(turns(&path), 1);
+    }
+
+    #[test]
+    fn fast_diagonals_zig_zag() {
+        let path = Map::from_maze(Maze::new(Wall::Open))
+            .plan_full_path(
+                &FAST_DIAGONALS,
+                MazePosition { x: 0, y: 0 },
+                MazePosition { x: 3, y: 3 },
+            )
+            .unwrap();
+
+        assert_eq!(path_length_cells(&path), 7);
+        assert_eq!(turns(&path), 5);
+    }
+
+    #[test]
+    fn default_does_not_zig_zag() {
+        let path = Map::from_maze(Maze::new(Wall::Open))
+            .plan_full_path(
+                &NAVIGATE,
+                MazePosition { x: 0, y: 0 },
+                MazePosition { x: 3, y: 3 },
+            )
+            .unwrap();
+
+        assert_eq!(path_length_cells(&path), 7);
+        assert_eq!(turns(&path), 1);
+    }
+
+    // A short route that zig zags east and north, and a long route north along the edge, then
+    // east and back down
+    fn two_routes() -> Maze {
+        let mut horizontal_walls = [[Wall::Closed; HEIGHT - 1]; WIDTH];
+        let mut vertical_walls = [[Wall::Closed; HEIGHT]; WIDTH - 1];
+
+        vertical_walls[0][0] = Wall::Open;
+        horizontal_walls[1][0] = Wall::Open;
+        vertical_walls[1][1] = Wall::Open;
+        horizontal_walls[2][1] = Wall::Open;
+
+        horizontal_walls[0][0] = Wall::Open;
+        horizontal_walls[0][1] = Wall::Open;
+        horizontal_walls[0][2] = Wall::Open;
+        vertical_walls[0][3] = Wall::Open;
+        vertical_walls[1][3] = Wall::Open;
+        horizontal_walls[2][2] = Wall::Open;
+
+        Maze::from_walls(horizontal_walls, vertical_walls)
+    }
+
+    #[test]
+    fn distance_takes_the_short_route() {
+        let path = Map::from_maze(two_routes())
+            .plan_full_path(
+                &DISTANCE_ONLY,
+                MazePosition { x: 0, y: 0 },
+                MazePosition { x: 2, y: 2 },
+            )
+            .unwrap();
+
+        assert_eq!(path_length_cells(&path), 5);
+        assert_eq!(path[1], MazePosition { x: 1, y: 0 });
+    }
+
+    #[test]
+    fn slow_turns_take_the_long_route() {
+        let path = Map::from_maze(two_routes())
+            .plan_full_path(
+                &SLOW_TURNS,
+                MazePosition { x: 0, y: 0 },
+                MazePosition { x: 2, y: 2 },
+            )
+            .unwrap();
+
+        assert_eq!(path_length_cells(&path), 7);
+        assert_eq!(path[1], MazePosition { x: 0, y: 1 });
+    }
+}
+
+#[cfg(test)]
+mod full_path_plan_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::FullPathPlan;
+    use crate::config::NAVIGATE;
+    use crate::slow::map::Map;
+    use crate::slow::maze::{Maze, Wall, WallDirection, WallIndex};
+    use crate::slow::MazePosition;
+
+    const FROM: MazePosition = MazePosition { x: 0, y: 0 };
+    const GOAL: MazePosition = MazePosition { x: 7, y: 7 };
+
+    // Walls across most of the maze, so the path has to wind around them
+    fn maze() -> Maze {
+        let mut maze = Maze::new(Wall::Open);
+        for x in 0..15 {
+            maze.set_wall(
+                WallIndex {
+                    x,
+                    y: 4,
+                    direction: WallDirection::Horizontal,
+                },
+                Wall::Closed,
+            );
+        }
+        for x in 1..16 {
+            maze.set_wall(
+                WallIndex {
+                    x,
+                    y: 10,
+                    direction: WallDirection::Horizontal,
+                },
+                Wall::Closed,
+            );
+        }
+        maze
+    }
+
+    #[test]
+    fn no_path_until_done() {
+        let maze = maze();
+        let mut plan = FullPathPlan::new(FROM, GOAL);
+
+        assert!(!plan.relax(&NAVIGATE, &maze, 1));
+        assert!(!plan.done());
+        assert_eq!(plan.path(&NAVIGATE, &maze), None);
+    }
+
+    #[test]
+    fn small_budget_matches_all_at_once() {
+        let maze = maze();
+        let mut plan = FullPathPlan::new(FROM, GOAL);
+
+        let mut calls = 1;
+        while !plan.relax(&NAVIGATE, &maze, 1) {
+            calls += 1;
+        }
+
+        assert!(calls > 1);
+        assert_eq!(
+            plan.path(&NAVIGATE, &maze),
+            Map::from_maze(maze).plan_full_path(&NAVIGATE, FROM, GOAL)
+        );
+        assert!(plan.path(&NAVIGATE, &maze).is_some());
+    }
+
+    #[test]
+    fn relax_after_done_does_nothing() {
+        let maze = maze();
+        let mut plan = FullPathPlan::new(FROM, GOAL);
+        while !plan.relax(&NAVIGATE, &maze, 1) {}
+
+        let path = plan.path(&NAVIGATE, &maze);
+        assert!(plan.relax(&NAVIGATE, &maze, 1));
+        assert_eq!(plan.path(&NAVIGATE, &maze), path);
+    }
+
+    #[test]
+    fn restart_forgets_the_old_goal() {
+        let maze = maze();
+        let other_goal = MazePosition { x: 12, y: 14 };
+
+        let mut plan = FullPathPlan::new(FROM, other_goal);
+        while !plan.relax(&NAVIGATE, &maze, 1) {}
+
+        plan.restart(FROM, GOAL);
+        assert!(!plan.done());
+        while !plan.relax(&NAVIGATE, &maze, 1) {}
+
+        assert_eq!(
+            plan.path(&NAVIGATE, &maze),
+            Map::from_maze(maze).plan_full_path(&NAVIGATE, FROM, GOAL)
+        );
+    }
+}
+
+#[cfg(test)]
+mod loop_detection_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::{NavigateConfig, TwelvePartitionNavigate};
+    use crate::slow::map::MoveOptions;
+    use crate::slow::{MazeDirection, MazeOrientation, MazePosition};
+
+    const CONFIG: NavigateConfig = NavigateConfig {
+        bias_turns: true,
+        loop_window: 8,
+        orthogonal_cost: 1.0,
+        diagonal_cost: 1.0,
+        turn_cost: 0.0,
+    };
+
+    const ALL_OPEN: MoveOptions = MoveOptions {
+        left: true,
+        front: true,
+        right: true,
+    };
+
+    // Driving around a 2x2 block of cells
+    const LOOP: [MazeOrientation; 4] = [
+        MazeOrientation {
+            position: MazePosition { x: 2, y: 2 },
+            direction: MazeDirection::North,
+        },
+        MazeOrientation {
+            position: MazePosition { x: 2, y: 3 },
+            direction: MazeDirection::East,
+        },
+        MazeOrientation {
+            position: MazePosition { x: 3, y: 3 },
+            direction: MazeDirection::South,
+        },
+        MazeOrientation {
+            position: MazePosition { x: 3, y: 2 },
+            direction: MazeDirection::West,
+        },
+    ];
+
+    fn drive(
+        navigate: &mut TwelvePartitionNavigate,
+        config: &NavigateConfig,
+        orientations: &[MazeOrientation],
+    ) -> bool {
+        orientations
+            .iter()
+            .map(|&orientation| {
+                let (_, debug) = navigate.navigate(config, orientation, ALL_OPEN);
+                debug.loop_detected
+            })
+            .last()
+            .unwrap()
+    }
+
+    #[test]
+    fn no_loop_first_time() {
+        let mut navigate = TwelvePartitionNavigate::new();
+        assert!(!drive(&mut navigate, &CONFIG, &LOOP));
+    }
+
+    #[test]
+    fn loop_detected_when_repeated() {
+        let mut navigate = TwelvePartitionNavigate::new();
+        drive(&mut navigate, &CONFIG, &LOOP);
+        assert!(drive(&mut navigate, &CONFIG, &LOOP[..1]));
+    }
+
+    #[test]
+    fn same_cell_different_direction_is_not_loop() {
+        let mut navigate = TwelvePartitionNavigate::new();
+        drive(&mut navigate, &CONFIG, &LOOP);
+        assert!(!drive(
+            &mut navigate,
+            &CONFIG,
+            &[MazeOrientation {
+                direction: MazeDirection::South,
+                ..LOOP[0]
+            }]
+        ));
+    }
+
+    #[test]
+    fn repeat_outside_window_is_not_loop() {
+        let config = NavigateConfig {
+            loop_window: 3,
+            ..CONFIG
+        };
+
+        let mut navigate = TwelvePartitionNavigate::new();
+        drive(&mut navigate, &config, &LOOP);
+        assert!(!drive(&mut navigate, &config, &LOOP[..1]));
+    }
+
+    #[test]
+    fn disabled() {
+        let config = NavigateConfig {
+            loop_window: 0,
+            ..CONFIG
+        };
+
+        let mut navigate = TwelvePartitionNavigate::new();
+        drive(&mut navigate, &config, &LOOP);
+        assert!(!drive(&mut navigate, &config, &LOOP));
     }
 }
@@ -1,6 +1,6 @@
 //! Various functions to help writing tests
 
-use crate::fast::Vector;
+use crate::fast::{Orientation, Vector};
 
 const MAX_DELTA: f32 = 0.00002;
 
@@ -32,6 +32,17 @@ pub fn assert_close(left: f32, right: f32) {
     );
 }
 
+/// Assert that the two orientations are close enough to be equal, going the short way around
+/// for the direction
+pub fn assert_close_orientation(left: Orientation, right: Orientation) {
+    assert!(
+        left.approx_eq(right, MAX_DELTA, MAX_DELTA),
+        "\nleft: {:?}\nright: {:?}\n",
+        left,
+        right,
+    );
+}
+
 /// Assert that the two float vectors are close enough to be equal
 pub fn assert_close2(left: Vector, right: Vector) {
     let delta0 = (left.x - right.x).abs();
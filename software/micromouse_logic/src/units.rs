@@ -0,0 +1,147 @@
+//! Units used throughout the logic
+//!
+//! All times are in milliseconds, all distances are in millimeters, and so all velocities are in
+//! millimeters per millisecond (which happens to be the same as meters per second). Angles are in
+//! radians.
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// A time or a change in time, in milliseconds
+pub type Millis = u32;
+
+pub const MS_PER_SEC: Millis = 1000;
+
+/// Convert seconds to milliseconds
+pub fn secs_to_ms(secs: f32) -> Millis {
+    (secs * MS_PER_SEC as f32) as Millis
+}
+
+/// Convert milliseconds to seconds
+pub fn ms_to_secs(ms: Millis) -> f32 {
+    ms as f32 / MS_PER_SEC as f32
+}
+
+pub const MM_PER_CM: f32 = 10.0;
+pub const MM_PER_M: f32 = 1000.0;
+
+/// Convert centimeters to millimeters
+pub fn cm_to_mm(cm: f32) -> f32 {
+    cm * MM_PER_CM
+}
+
+/// Convert millimeters to centimeters
+pub fn mm_to_cm(mm: f32) -> f32 {
+    mm / MM_PER_CM
+}
+
+/// Convert meters to millimeters
+pub fn m_to_mm(m: f32) -> f32 {
+    m * MM_PER_M
+}
+
+/// Convert millimeters to meters
+pub fn mm_to_m(mm: f32) -> f32 {
+    mm / MM_PER_M
+}
+
+/// Convert a velocity in mm/s to mm/ms
+pub fn mm_per_sec_to_mm_per_ms(velocity: f32) -> f32 {
+    velocity / MS_PER_SEC as f32
+}
+
+/// Convert a velocity in mm/ms to mm/s
+pub fn mm_per_ms_to_mm_per_sec(velocity: f32) -> f32 {
+    velocity * MS_PER_SEC as f32
+}
+
+/// The distance in mm traveled going `velocity` mm/ms for `delta_time` ms
+pub fn distance_traveled(velocity: f32, delta_time: Millis) -> f32 {
+    velocity * delta_time as f32
+}
+
+/// A velocity in mm/s, for places where mm/s is easier to think about than the mm/ms used
+/// everywhere else. Converts explicitly so the time units can't get mixed up.
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct VelocityMmPerSec(pub f32);
+
+impl VelocityMmPerSec {
+    /// Convert a velocity in mm/ms
+    pub fn from_mm_per_ms(velocity: f32) -> VelocityMmPerSec {
+        VelocityMmPerSec(mm_per_ms_to_mm_per_sec(velocity))
+    }
+
+    /// This velocity in mm/ms
+    pub fn mm_per_ms(self) -> f32 {
+        mm_per_sec_to_mm_per_ms(self.0)
+    }
+
+    /// The distance in mm traveled going this velocity for `delta_time` ms
+    pub fn distance_traveled(self, delta_time: Millis) -> f32 {
+        distance_traveled(self.mm_per_ms(), delta_time)
+    }
+}
+
+#[cfg(test)]
+mod units_tests {
+    #[allow(unused_imports)]
+    use crate::test::*;
+
+    use super::*;
+
+    #[test]
+    fn secs_to_ms_test() {
+        assert_eq!(secs_to_ms(1.5), 1500);
+    }
+
+    #[test]
+    fn ms_to_secs_test() {
+        assert_close(ms_to_secs(250), 0.25);
+    }
+
+    #[test]
+    fn distance_conversions() {
+        assert_close(cm_to_mm(18.0), 180.0);
+        assert_close(mm_to_cm(180.0), 18.0);
+        assert_close(m_to_mm(0.18), 180.0);
+        assert_close(mm_to_m(180.0), 0.18);
+    }
+
+    #[test]
+    fn velocity_round_trip() {
+        assert_close(
+            mm_per_ms_to_mm_per_sec(mm_per_sec_to_mm_per_ms(300.0)),
+            300.0,
+        );
+    }
+
+    #[test]
+    fn velocity_times_time_is_mm() {
+        // 0.5 mm/ms for 10 ms
+        assert_close(distance_traveled(0.5, 10), 5.0);
+    }
+
+    #[test]
+    fn velocity_in_mm_per_sec_times_time_is_mm() {
+        // 300 mm/s for 2 s
+        let velocity = mm_per_sec_to_mm_per_ms(300.0);
+        assert_close(distance_traveled(velocity, secs_to_ms(2.0)), 600.0);
+    }
+
+    #[test]
+    fn velocity_mm_per_sec_over_delta_time() {
+        // 300 mm/s for 10 ms
+        assert_close(VelocityMmPerSec(300.0).distance_traveled(10), 3.0);
+    }
+
+    #[test]
+    fn velocity_mm_per_sec_over_one_sec() {
+        assert_close(VelocityMmPerSec(250.0).distance_traveled(MS_PER_SEC), 250.0);
+    }
+
+    #[test]
+    fn velocity_mm_per_sec_from_mm_per_ms() {
+        assert_close(VelocityMmPerSec::from_mm_per_ms(0.3).0, 300.0);
+        assert_close(VelocityMmPerSec(300.0).mm_per_ms(), 0.3);
+    }
+}
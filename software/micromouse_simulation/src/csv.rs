@@ -0,0 +1,89 @@
+use std::fmt::Write;
+
+use micromouse_logic::fast::motion_control::{lateral_acceleration, MotionControlConfig};
+use micromouse_logic::fast::motion_queue::MotionQueueBuffer;
+use micromouse_logic::fast::path::velocity_profile;
+
+/// The first row of [plan_to_csv]
+pub const PLAN_CSV_HEADER: &str = "arc_length,curvature,planned_velocity,lateral_accel";
+
+/// Write the planned velocity along the path motions in `buffer` as CSV, one row for each point
+/// of [velocity_profile], after a header row. The arc length is in mm, the curvature in 1/mm,
+/// the velocity in mm/ms, and the lateral acceleration in mm/ms^2.
+pub fn plan_to_csv(buffer: &MotionQueueBuffer, config: &MotionControlConfig) -> String {
+    let mut csv = String::new();
+    csv.push_str(PLAN_CSV_HEADER);
+    csv.push('\n');
+
+    for (arc_length, curvature, velocity) in velocity_profile(buffer, config) {
+        // Writing to a String never fails
+        writeln!(
+            csv,
+            "{},{},{},{}",
+            arc_length,
+            curvature,
+            velocity,
+            lateral_acceleration(velocity, curvature)
+        )
+        .unwrap();
+    }
+
+    csv
+}
+
+#[cfg(test)]
+mod plan_csv_tests {
+    use micromouse_logic::config::sim::MOTION_CONTROL;
+    use micromouse_logic::fast::motion_queue::{Motion, MotionQueueBuffer};
+    use micromouse_logic::fast::path::PathMotion;
+    use micromouse_logic::fast::{Vector, DIRECTION_0, DIRECTION_PI_2};
+
+    use super::{plan_to_csv, PLAN_CSV_HEADER};
+
+    #[test]
+    fn two_motions() {
+        let mut buffer = MotionQueueBuffer::new();
+        buffer
+            .push(Motion::Path(PathMotion::corner(
+                Vector { x: 270.0, y: 90.0 },
+                DIRECTION_0,
+                DIRECTION_PI_2,
+                90.0,
+                0.0,
+            )))
+            .unwrap();
+        buffer
+            .push(Motion::Path(PathMotion::line(
+                Vector { x: 90.0, y: 90.0 },
+                Vector { x: 180.0, y: 90.0 },
+            )))
+            .unwrap();
+
+        let csv = plan_to_csv(&buffer, &MOTION_CONTROL);
+        let mut lines = csv.lines();
+
+        assert_eq!(lines.next(), Some(PLAN_CSV_HEADER));
+
+        let rows = lines.collect::<Vec<_>>();
+
+        // Several rows for each motion
+        assert!(rows.len() > 20 && rows.len() < 200, "{} rows", rows.len());
+
+        for row in &rows {
+            let values = row
+                .split(',')
+                .map(|value| value.parse::<f32>().unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(values.len(), 4);
+            assert!(values.iter().all(|value| value.is_finite()));
+        }
+    }
+
+    #[test]
+    fn empty() {
+        let csv = plan_to_csv(&MotionQueueBuffer::new(), &MOTION_CONTROL);
+
+        // Only the stopped start
+        assert_eq!(csv.lines().count(), 2);
+    }
+}
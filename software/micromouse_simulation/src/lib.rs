@@ -1,5 +1,8 @@
+pub mod csv;
+pub mod metrics;
 pub mod remote;
 pub mod simulation;
+pub mod svg;
 
 use std::panic;
 
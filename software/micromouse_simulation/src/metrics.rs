@@ -0,0 +1,126 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+use micromouse_logic::fast::Orientation;
+
+/// How far off the localization was over a run
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Metrics {
+    /// How many steps were recorded
+    pub samples: u32,
+
+    /// The root mean square distance between the estimated and true positions, in mm
+    pub rms_position_error: f32,
+
+    /// The furthest the estimated position was from the true position, in mm
+    pub max_position_error: f32,
+
+    /// The furthest the estimated direction was from the true direction, in radians
+    pub max_heading_error: f32,
+}
+
+/// Compares the orientation the mouse thinks it is at to where it really is each step
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct SimMetrics {
+    samples: u32,
+    sum_squared_position_error: f32,
+    max_position_error: f32,
+    max_heading_error: f32,
+}
+
+impl SimMetrics {
+    pub fn new() -> SimMetrics {
+        SimMetrics::default()
+    }
+
+    /// Add one step where the mouse was really at `true_pose`, and thought it was at
+    /// `estimated_pose`
+    pub fn record(&mut self, true_pose: Orientation, estimated_pose: Orientation) {
+        let position_error = (estimated_pose.position - true_pose.position).magnitude();
+
+        // Compare the short way around, so 0.1 and 2pi - 0.1 are only 0.2 apart
+        let heading_error = (estimated_pose.direction.centered_at(true_pose.direction)
+            - f32::from(true_pose.direction))
+        .abs();
+
+        self.samples += 1;
+        self.sum_squared_position_error += position_error * position_error;
+        self.max_position_error = self.max_position_error.max(position_error);
+        self.max_heading_error = self.max_heading_error.max(heading_error);
+    }
+
+    pub fn summary(&self) -> Metrics {
+        let rms_position_error = if self.samples == 0 {
+            0.0
+        } else {
+            (self.sum_squared_position_error / self.samples as f32).sqrt()
+        };
+
+        Metrics {
+            samples: self.samples,
+            rms_position_error,
+            max_position_error: self.max_position_error,
+            max_heading_error: self.max_heading_error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod sim_metrics_tests {
+    use micromouse_logic::fast::{Orientation, Vector, DIRECTION_0};
+
+    use super::SimMetrics;
+
+    const ORIGIN: Orientation = Orientation {
+        position: Vector { x: 0.0, y: 0.0 },
+        direction: DIRECTION_0,
+    };
+
+    fn at(x: f32, y: f32, direction: f32) -> Orientation {
+        Orientation {
+            position: Vector { x, y },
+            direction: direction.into(),
+        }
+    }
+
+    #[test]
+    fn empty() {
+        let summary = SimMetrics::new().summary();
+        assert_eq!(summary.samples, 0);
+        assert_eq!(summary.rms_position_error, 0.0);
+        assert_eq!(summary.max_heading_error, 0.0);
+    }
+
+    #[test]
+    fn perfect() {
+        let mut metrics = SimMetrics::new();
+        metrics.record(ORIGIN, ORIGIN);
+        metrics.record(at(10.0, 20.0, 1.0), at(10.0, 20.0, 1.0));
+
+        let summary = metrics.summary();
+        assert_eq!(summary.samples, 2);
+        assert_eq!(summary.rms_position_error, 0.0);
+        assert_eq!(summary.max_heading_error, 0.0);
+    }
+
+    #[test]
+    fn rms_position() {
+        let mut metrics = SimMetrics::new();
+        metrics.record(ORIGIN, at(3.0, 4.0, 0.0));
+        metrics.record(ORIGIN, ORIGIN);
+
+        // sqrt((25 + 0) / 2)
+        let summary = metrics.summary();
+        assert!((summary.rms_position_error - 12.5f32.sqrt()).abs() < 0.0001);
+        assert!((summary.max_position_error - 5.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn heading_wraps_around() {
+        let mut metrics = SimMetrics::new();
+        metrics.record(at(0.0, 0.0, 0.1), at(0.0, 0.0, -0.1));
+
+        let summary = metrics.summary();
+        assert!((summary.max_heading_error - 0.2).abs() < 0.0001);
+    }
+}
@@ -3,13 +3,11 @@ use std::f32;
 use serde::Deserialize;
 use serde::Serialize;
 
-use micromouse_logic::fast::{
-    Orientation, Vector, DIRECTION_0, DIRECTION_3_PI_2, DIRECTION_PI_2,
-};
+use micromouse_logic::fast::Orientation;
 use micromouse_logic::mouse::{DistanceReading, Mouse, MouseConfig, MouseDebug};
-use micromouse_logic::slow::maze::{
-    Maze, MazeConfig, MazeIndex, MazeProjectionResult, Wall,
-};
+use micromouse_logic::slow::maze::Maze;
+
+use crate::metrics::{Metrics, SimMetrics};
 
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct SimulationDebug {
@@ -49,21 +47,6 @@ impl SimulationConfig {
     }
 }
 
-/// Find the closest closed wall
-fn find_closed_wall(
-    config: &MazeConfig,
-    maze: &Maze,
-    from: Orientation,
-) -> Option<MazeProjectionResult> {
-    config.wall_projection(from).find(|maze_projection_result| {
-        if let MazeIndex::Wall(wall_index) = maze_projection_result.maze_index {
-            maze.get_wall(wall_index).unwrap_or(&Wall::Closed) == &Wall::Closed
-        } else {
-            true
-        }
-    })
-}
-
 pub struct Simulation {
     mouse: Mouse,
     orientation: Orientation,
@@ -73,6 +56,7 @@ pub struct Simulation {
     right_encoder: i32,
     time: u32,
     last_sensor_update: u32,
+    metrics: SimMetrics,
 }
 
 impl Simulation {
@@ -86,6 +70,7 @@ impl Simulation {
             last_right_ground_speed: 0.0,
             time: 0,
             last_sensor_update: 0,
+            metrics: SimMetrics::new(),
         }
     }
 
@@ -93,72 +78,31 @@ impl Simulation {
         SimulationConfig::default()
     }
 
+    /// How far off the localization has been from the true orientation so far
+    pub fn metrics(&self) -> Metrics {
+        self.metrics.summary()
+    }
+
     pub fn update(&mut self, config: &SimulationConfig) -> SimulationDebug {
         let mech = config.mouse.mechanical;
 
-        let (front_distance, left_distance, right_distance) =
-            if self.time - self.last_sensor_update >= config.millis_per_sensor_update {
-                // Figure out what the sensors should read
-                let front_result = find_closed_wall(
-                    &config.mouse.maze,
-                    &config.maze,
-                    self.orientation.offset(Orientation {
-                        position: Vector {
-                            x: config.mouse.mechanical.front_sensor_offset_x,
-                            y: 0.0,
-                        },
-
-                        direction: DIRECTION_0,
-                    }),
-                );
-                let front_distance = front_result
-                    .filter(|result| result.distance < mech.front_sensor_limit as f32)
-                    .map_or(DistanceReading::OutOfRange, |result| {
-                        DistanceReading::InRange(result.distance)
-                    });
-
-                let left_result = find_closed_wall(
-                    &config.mouse.maze,
-                    &config.maze,
-                    self.orientation.offset(Orientation {
-                        position: Vector {
-                            x: config.mouse.mechanical.left_sensor_offset_x,
-                            y: config.mouse.mechanical.left_sensor_offset_y,
-                        },
-                        direction: DIRECTION_PI_2,
-                    }),
-                );
-                let left_distance = left_result
-                    .filter(|result| result.distance < mech.left_sensor_limit)
-                    .map_or(DistanceReading::OutOfRange, |result| {
-                        DistanceReading::InRange(result.distance)
-                    });
-
-                let right_result = find_closed_wall(
-                    &config.mouse.maze,
-                    &config.maze,
-                    self.orientation.offset(Orientation {
-                        position: Vector {
-                            x: config.mouse.mechanical.right_sensor_offset_x,
-                            y: -config.mouse.mechanical.right_sensor_offset_y,
-                        },
-                        direction: DIRECTION_3_PI_2,
-                    }),
-                );
-                let right_distance = right_result
-                    .filter(|result| result.distance < mech.right_sensor_limit as f32)
-                    .map_or(DistanceReading::OutOfRange, |result| {
-                        DistanceReading::InRange(result.distance)
-                    });
-
-                (
-                    Some(front_distance),
-                    Some(left_distance),
-                    Some(right_distance),
-                )
-            } else {
-                (None, None, None)
-            };
+        let distances_fresh =
+            self.time - self.last_sensor_update >= config.millis_per_sensor_update;
+
+        let (left_distance, front_distance, right_distance) = if distances_fresh {
+            // Figure out what the sensors should read
+            let (left_distance, front_distance, right_distance) = config
+                .maze
+                .expected_distances(&config.mouse.maze, &mech, self.orientation);
+
+            (
+                Some(left_distance),
+                Some(front_distance),
+                Some(right_distance),
+            )
+        } else {
+            (None, None, None)
+        };
 
         // Update the mouse for the current time
         let (raw_left_power, raw_right_power, mouse_debug) = self.mouse.update(
@@ -170,8 +114,12 @@ impl Simulation {
             left_distance,
             front_distance,
             right_distance,
+            distances_fresh,
         );
 
+        self.metrics
+            .record(self.orientation, mouse_debug.orientation);
+
         // Make sure the wheel powers are in range -1.0 to 1.0
 
         let left_power = if raw_left_power > 10000 {
@@ -268,3 +216,56 @@ impl Simulation {
         debug
     }
 }
+
+#[cfg(test)]
+mod metrics_tests {
+    use micromouse_logic::config::sim::MOUSE_2019;
+    use micromouse_logic::fast::{Orientation, Vector, DIRECTION_PI_2};
+    use micromouse_logic::slow::maze::Maze;
+
+    use super::{Simulation, SimulationConfig};
+
+    #[test]
+    fn no_noise_has_no_error() {
+        let bytes = include_bytes!("../mazes/APEC2017.maz");
+        let config = SimulationConfig {
+            mouse: MOUSE_2019,
+            millis_per_step: 10,
+            millis_per_sensor_update: 20,
+            initial_orientation: Orientation {
+                position: Vector {
+                    x: 0.5 * 180.0,
+                    y: 0.5 * 180.0,
+                },
+                direction: DIRECTION_PI_2,
+            },
+            max_wheel_accel: 1.0,
+            max_speed: 1.0,
+            maze: Maze::from_file(*bytes),
+        };
+
+        let mut simulation = Simulation::new(&config);
+        for _ in 0..500 {
+            simulation.update(&config);
+        }
+
+        // Make sure it actually went somewhere
+        let traveled = (simulation.orientation.position
+            - config.initial_orientation.position)
+            .magnitude();
+        assert!(traveled > 180.0, "only traveled {}", traveled);
+
+        let metrics = simulation.metrics();
+        assert_eq!(metrics.samples, 500);
+        assert!(
+            metrics.rms_position_error < 1.0,
+            "rms position error {}",
+            metrics.rms_position_error
+        );
+        assert!(
+            metrics.max_heading_error < 0.01,
+            "max heading error {}",
+            metrics.max_heading_error
+        );
+    }
+}
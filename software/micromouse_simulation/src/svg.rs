@@ -0,0 +1,188 @@
+use std::fmt::Write;
+
+use micromouse_logic::fast::motion_queue::{Motion, MotionQueueBuffer};
+use micromouse_logic::slow::maze::{
+    Maze, MazeConfig, Wall, WallDirection, HEIGHT, WIDTH,
+};
+
+/// How many points to draw along each path motion
+const SVG_STEPS: u32 = 32;
+
+/// Draw the path motions in `buffer` as an SVG document, one polyline each, on top of the closed
+/// walls of `maze` if there is one. The document covers the whole maze, with y pointing up like
+/// the mouse uses. Turns in place and stops don't go anywhere, so they are not drawn.
+pub fn to_svg(
+    maze_config: &MazeConfig,
+    buffer: &MotionQueueBuffer,
+    maze: Option<&Maze>,
+) -> String {
+    let width = WIDTH as f32 * maze_config.cell_width_x;
+    let height = HEIGHT as f32 * maze_config.cell_width_y;
+
+    let mut svg = String::new();
+
+    // Writing to a String never fails
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {} {}">"#,
+        width, height
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        r#"<g transform="translate(0 {}) scale(1 -1)">"#,
+        height
+    )
+    .unwrap();
+
+    if let Some(maze) = maze {
+        writeln!(
+            svg,
+            r#"<rect x="0" y="0" width="{}" height="{}" fill="none" stroke="black" stroke-width="{}"/>"#,
+            width, height, maze_config.wall_width
+        )
+        .unwrap();
+
+        for (index, wall) in maze.edges() {
+            if wall != Wall::Closed {
+                continue;
+            }
+
+            let x = index.x as f32 * maze_config.cell_width_x;
+            let y = index.y as f32 * maze_config.cell_width_y;
+            let (x2, y2) = match index.direction {
+                WallDirection::Horizontal => (x + maze_config.cell_width_x, y),
+                WallDirection::Vertical => (x, y + maze_config.cell_width_y),
+            };
+
+            writeln!(
+                svg,
+                r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="black" stroke-width="{}"/>"#,
+                x, y, x2, y2, maze_config.wall_width
+            )
+            .unwrap();
+        }
+    }
+
+    // The next motion is at the end of the buffer
+    for motion in buffer.iter().rev() {
+        let path_motion = match motion {
+            Motion::Path(path_motion) => path_motion,
+            Motion::Turn(_) | Motion::Stop(_) => continue,
+        };
+
+        svg.push_str(r#"<polyline fill="none" stroke="red" stroke-width="2" points=""#);
+        for i in 0..=SVG_STEPS {
+            let point = path_motion.at(i as f32 / SVG_STEPS as f32);
+            if i > 0 {
+                svg.push(' ');
+            }
+            write!(svg, "{},{}", point.x, point.y).unwrap();
+        }
+        svg.push_str("\"/>\n");
+    }
+
+    svg.push_str("</g>\n</svg>\n");
+
+    svg
+}
+
+#[cfg(test)]
+mod svg_tests {
+    use micromouse_logic::config::MAZE;
+    use micromouse_logic::fast::motion_queue::{Motion, MotionQueueBuffer};
+    use micromouse_logic::fast::path::PathMotion;
+    use micromouse_logic::fast::turn::TurnMotion;
+    use micromouse_logic::fast::{Vector, DIRECTION_0, DIRECTION_PI_2};
+    use micromouse_logic::slow::maze::{Maze, Wall, WallDirection, WallIndex};
+
+    use super::to_svg;
+
+    fn buffer() -> MotionQueueBuffer {
+        let mut buffer = MotionQueueBuffer::new();
+        buffer
+            .push(Motion::Path(PathMotion::corner(
+                Vector { x: 270.0, y: 90.0 },
+                DIRECTION_0,
+                DIRECTION_PI_2,
+                90.0,
+                0.0,
+            )))
+            .unwrap();
+        buffer
+            .push(Motion::Turn(TurnMotion::new(DIRECTION_0, DIRECTION_PI_2)))
+            .unwrap();
+        buffer
+            .push(Motion::Path(PathMotion::line(
+                Vector { x: 90.0, y: 90.0 },
+                Vector { x: 180.0, y: 90.0 },
+            )))
+            .unwrap();
+        buffer
+    }
+
+    /// Check that every tag is closed in the right order, and return the names of the tags
+    fn parse_tags(svg: &str) -> Vec<String> {
+        let mut open = Vec::new();
+        let mut tags = Vec::new();
+
+        let mut rest = svg;
+        while let Some(start) = rest.find('<') {
+            let end = rest[start..].find('>').expect("unclosed bracket") + start;
+            let tag = &rest[start + 1..end];
+
+            if tag.starts_with('/') {
+                assert_eq!(open.pop(), Some(tag[1..].to_string()), "mismatched tag");
+            } else {
+                let name = tag.split_whitespace().next().unwrap().to_string();
+                assert_eq!(tag.matches('"').count() % 2, 0, "unbalanced quotes");
+                tags.push(name.clone());
+                if !tag.ends_with('/') {
+                    open.push(name);
+                }
+            }
+
+            rest = &rest[end + 1..];
+        }
+
+        assert!(open.is_empty(), "unclosed tags {:?}", open);
+        tags
+    }
+
+    #[test]
+    fn path_only() {
+        let svg = to_svg(&MAZE, &buffer(), None);
+        let tags = parse_tags(&svg);
+
+        assert_eq!(tags[0], "svg");
+        assert_eq!(tags.iter().filter(|&tag| tag == "polyline").count(), 2);
+        assert_eq!(tags.iter().filter(|&tag| tag == "line").count(), 0);
+    }
+
+    #[test]
+    fn with_maze() {
+        let mut maze = Maze::new(Wall::Open);
+        maze.set_wall(
+            WallIndex {
+                x: 1,
+                y: 1,
+                direction: WallDirection::Horizontal,
+            },
+            Wall::Closed,
+        );
+
+        let svg = to_svg(&MAZE, &buffer(), Some(&maze));
+        let tags = parse_tags(&svg);
+
+        assert_eq!(tags.iter().filter(|&tag| tag == "polyline").count(), 2);
+        assert_eq!(tags.iter().filter(|&tag| tag == "line").count(), 1);
+        assert_eq!(tags.iter().filter(|&tag| tag == "rect").count(), 1);
+    }
+
+    #[test]
+    fn empty() {
+        let svg = to_svg(&MAZE, &MotionQueueBuffer::new(), None);
+        let tags = parse_tags(&svg);
+        assert_eq!(tags, vec!["svg", "g"]);
+    }
+}
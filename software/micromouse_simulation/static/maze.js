@@ -54,8 +54,8 @@ function MazeUi(parent, state) {
     function redraw(config) {
 
         const maze_config = config.mouse.maze;
-        const maze_width_mm = MAZE_WIDTH * maze_config.cell_width + maze_config.wall_width;
-        const maze_height_mm = MAZE_HEIGHT * maze_config.cell_width + maze_config.wall_width;
+        const maze_width_mm = MAZE_WIDTH * maze_config.cell_width_x + maze_config.wall_width;
+        const maze_height_mm = MAZE_HEIGHT * maze_config.cell_width_y + maze_config.wall_width;
 
         draw.size("100%");
 
@@ -87,7 +87,7 @@ function MazeUi(parent, state) {
             for (let j = 0; j < MAZE_HEIGHT + 1; j++) {
 
                 let post = maze.rect(maze_config.wall_width, maze_config.wall_width);
-                post.move(i * maze_config.cell_width - maze_config.wall_width/2.0, j * maze_config.cell_width - maze_config.wall_width/2.0);
+                post.move(i * maze_config.cell_width_x - maze_config.wall_width/2.0, j * maze_config.cell_width_y - maze_config.wall_width/2.0);
                 self.posts[i][j] = post;
 
                 if (i < MAZE_WIDTH) {
@@ -100,8 +100,8 @@ function MazeUi(parent, state) {
                     }
 
                     self.horizontal_walls[i][j] = maze
-                        .rect(maze_config.cell_width - maze_config.wall_width, maze_config.wall_width)
-                        .move(i * maze_config.cell_width + maze_config.wall_width/2.0, j * maze_config.cell_width - maze_config.wall_width/2.0)
+                        .rect(maze_config.cell_width_x - maze_config.wall_width, maze_config.wall_width)
+                        .move(i * maze_config.cell_width_x + maze_config.wall_width/2.0, j * maze_config.cell_width_y - maze_config.wall_width/2.0)
                         .fill(wall_color);
                 }
 
@@ -115,15 +115,15 @@ function MazeUi(parent, state) {
                     }
 
                     self.vertical_walls[i][j] = maze
-                        .rect(maze_config.wall_width, maze_config.cell_width - maze_config.wall_width)
-                        .move(i * maze_config.cell_width - maze_config.wall_width/2.0, j * maze_config.cell_width + maze_config.wall_width/2.0)
+                        .rect(maze_config.wall_width, maze_config.cell_width_y - maze_config.wall_width)
+                        .move(i * maze_config.cell_width_x - maze_config.wall_width/2.0, j * maze_config.cell_width_y + maze_config.wall_width/2.0)
                         .fill(wall_color);
                 }
 
                 if (i < MAZE_WIDTH && j < MAZE_HEIGHT) {
                     self.cells[i][j] = maze
-                        .rect(maze_config.cell_width - maze_config.wall_width, maze_config.cell_width - maze_config.wall_width)
-                        .move(i * maze_config.cell_width + maze_config.wall_width / 2.0, j * maze_config.cell_width + maze_config.wall_width / 2.0)
+                        .rect(maze_config.cell_width_x - maze_config.wall_width, maze_config.cell_width_y - maze_config.wall_width)
+                        .move(i * maze_config.cell_width_x + maze_config.wall_width / 2.0, j * maze_config.cell_width_y + maze_config.wall_width / 2.0)
                         .fill({color: '#ff0000', opacity: 0.0});
                 }
             }